@@ -0,0 +1,328 @@
+//! Assertion engine, ported from `src/lib/assertions/engine.ts`.
+//!
+//! An assertion picks a value out of a response (`status_code`,
+//! `response_time`, `header` or `json_body`), resolves its expected value if
+//! it is a template, and hands both to one of the [`operators`].
+
+pub mod operators;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::flow::Assertion;
+use crate::http::HttpResponse;
+use crate::template::{self, js_string, TemplateContext};
+use crate::transform;
+
+/// The outcome of one assertion, shaped like the TS `AssertionResult`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResult {
+  pub passed: bool,
+  pub actual_value: Value,
+  /// The expected value after template resolution.
+  pub expected_value: Value,
+  /// The template the expected value came from, for template assertions.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub original_expected_value: Option<Value>,
+  pub message: String,
+}
+
+/// Run the enabled assertions in order, stopping after the first failure.
+///
+/// `transformed` is this endpoint's transformation aliases, read by
+/// assertions whose `data_source` is `transformed_data`.
+pub fn run(
+  assertions: &[Assertion],
+  response: &HttpResponse,
+  body: &Value,
+  transformed: Option<&Value>,
+  context: Option<&TemplateContext>,
+) -> Vec<AssertionResult> {
+  let mut results = Vec::new();
+  for assertion in assertions.iter().filter(|assertion| assertion.enabled) {
+    let result = match extract_value(assertion, response, body, transformed) {
+      Ok(actual) => evaluate(assertion, actual, context),
+      Err(err) => AssertionResult {
+        passed: false,
+        actual_value: Value::Null,
+        expected_value: assertion.expected_value.clone(),
+        original_expected_value: original_expected_value(assertion),
+        message: format!("Error extracting assertion value: {err}"),
+      },
+    };
+    let passed = result.passed;
+    results.push(result);
+    if !passed {
+      break;
+    }
+  }
+  results
+}
+
+/// The value an assertion checks. A `json_body` assertion's `data_id` is a
+/// transformation expression, usually a plain JSONPath.
+pub fn extract_value(
+  assertion: &Assertion,
+  response: &HttpResponse,
+  body: &Value,
+  transformed: Option<&Value>,
+) -> Result<Value> {
+  match assertion.assertion_type.as_str() {
+    "status_code" => Ok(Value::from(response.status)),
+    "response_time" => Ok(Value::from(response.timing.total_ms.round())),
+    "header" => Ok(header(response, &assertion.data_id).map_or(Value::Null, Value::String)),
+    "json_body" => {
+      let source = transformed.filter(|_| assertion.data_source == "transformed_data").unwrap_or(body);
+      transform::evaluate(&assertion.data_id, source, &TemplateContext::default())
+    }
+    other => Err(Error::InvalidFlow(format!("unknown assertion type `{other}`"))),
+  }
+}
+
+/// Grade `actual` against the assertion's expected value, resolving it as a
+/// template first when the assertion says so and a context is given.
+pub fn evaluate(assertion: &Assertion, actual: Value, context: Option<&TemplateContext>) -> AssertionResult {
+  let original = &assertion.expected_value;
+  let expected = match context.filter(|_| assertion.is_template_expression) {
+    Some(context) => match resolve_expected_value(original, context) {
+      Ok(expected) => expected,
+      Err(err) => {
+        return AssertionResult {
+          passed: false,
+          actual_value: actual,
+          expected_value: original.clone(),
+          original_expected_value: Some(original.clone()),
+          message: format!("Template resolution failed: {err}"),
+        }
+      }
+    },
+    None => original.clone(),
+  };
+
+  let Some(passed) = operators::evaluate(&assertion.operator, &actual, &expected) else {
+    return AssertionResult {
+      passed: false,
+      actual_value: actual,
+      expected_value: original.clone(),
+      original_expected_value: original_expected_value(assertion),
+      message: format!("Error evaluating assertion: Unknown operator: {}", assertion.operator),
+    };
+  };
+
+  let shown = if assertion.is_template_expression {
+    format!("{} → {expected}", js_string(original))
+  } else {
+    expected.to_string()
+  };
+  let verdict = if passed { "passed" } else { "failed" };
+  let mut message = format!(
+    "Assertion {verdict}: {} {} {} {shown}",
+    assertion.assertion_type, assertion.data_id, assertion.operator
+  );
+  if !passed {
+    message.push_str(&format!(", actual value: {actual}"));
+  }
+
+  AssertionResult {
+    passed,
+    actual_value: actual,
+    expected_value: expected,
+    original_expected_value: original_expected_value(assertion),
+    message,
+  }
+}
+
+/// Only strings containing `{{...}}` are templates; anything else is
+/// compared as written.
+fn resolve_expected_value(expected: &Value, context: &TemplateContext) -> Result<Value> {
+  match expected {
+    Value::String(text) if template::has_template_expressions(text) => template::resolve_template(text, context),
+    _ => Ok(expected.clone()),
+  }
+}
+
+fn original_expected_value(assertion: &Assertion) -> Option<Value> {
+  assertion.is_template_expression.then(|| assertion.expected_value.clone())
+}
+
+/// `Headers.get`: every value of a header, case-insensitively, joined by
+/// `", "`.
+fn header(response: &HttpResponse, name: &str) -> Option<String> {
+  let values: Vec<&str> = response
+    .headers
+    .iter()
+    .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+    .map(|(_, value)| value.as_str())
+    .collect();
+  (!values.is_empty()).then(|| values.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::http::RequestTiming;
+  use serde_json::json;
+
+  fn assertion(value: Value) -> Assertion {
+    serde_json::from_value(value).unwrap()
+  }
+
+  fn response() -> HttpResponse {
+    HttpResponse {
+      status: 201,
+      status_text: "Created".to_string(),
+      url: "http://api.local/users".to_string(),
+      headers: vec![
+        ("Content-Type".to_string(), "application/json".to_string()),
+        ("Vary".to_string(), "Accept".to_string()),
+        ("vary".to_string(), "Origin".to_string()),
+      ],
+      set_cookies: Vec::new(),
+      body: Vec::new(),
+      timing: RequestTiming {
+        total_ms: 41.6,
+        ..RequestTiming::default()
+      },
+    }
+  }
+
+  #[test]
+  fn extracts_values_by_type() {
+    let body = json!({ "users": [{ "id": 1 }, { "id": 2 }] });
+    let transformed = json!({ "ids": [1, 2] });
+    let extract = |value| extract_value(&assertion(value), &response(), &body, Some(&transformed)).unwrap();
+
+    assert_eq!(extract(json!({ "assertion_type": "status_code", "operator": "equals" })), json!(201));
+    assert_eq!(extract(json!({ "assertion_type": "response_time", "operator": "less_than" })), json!(42.0));
+    assert_eq!(
+      extract(json!({ "assertion_type": "header", "data_id": "vary", "operator": "equals" })),
+      json!("Accept, Origin")
+    );
+    assert_eq!(extract(json!({ "assertion_type": "header", "data_id": "X-None", "operator": "exists" })), Value::Null);
+    assert_eq!(
+      extract(json!({ "assertion_type": "json_body", "data_id": "$.users[*].id", "operator": "equals" })),
+      json!([1, 2])
+    );
+    assert_eq!(
+      extract(json!({ "assertion_type": "json_body", "data_id": "$.users | count()", "operator": "equals" })),
+      json!(2)
+    );
+    assert_eq!(
+      extract(json!({
+        "assertion_type": "json_body",
+        "data_source": "transformed_data",
+        "data_id": "$.ids[1]",
+        "operator": "equals"
+      })),
+      json!(2)
+    );
+  }
+
+  #[test]
+  fn resolves_template_expected_values() {
+    let context: TemplateContext = serde_json::from_value(json!({
+      "responses": { "step1-0": { "user": { "name": "John", "id": 7 } } },
+      "parameters": { "ids": [7, 8] }
+    }))
+    .unwrap();
+
+    let result = evaluate(
+      &assertion(json!({
+        "assertion_type": "json_body",
+        "data_id": "$.name",
+        "operator": "equals",
+        "expected_value": "{{res:step1-0.$.user.name}}",
+        "is_template_expression": true
+      })),
+      json!("John"),
+      Some(&context),
+    );
+    assert!(result.passed, "{}", result.message);
+    assert_eq!(result.expected_value, json!("John"));
+    assert_eq!(result.original_expected_value, Some(json!("{{res:step1-0.$.user.name}}")));
+    assert_eq!(
+      result.message,
+      "Assertion passed: json_body $.name equals {{res:step1-0.$.user.name}} → \"John\""
+    );
+
+    let result = evaluate(
+      &assertion(json!({
+        "assertion_type": "json_body",
+        "data_id": "$.ids",
+        "operator": "contains_all",
+        "expected_value": "{{param:ids}}",
+        "is_template_expression": true
+      })),
+      json!([8, 7, 6]),
+      Some(&context),
+    );
+    assert!(result.passed);
+
+    let result = evaluate(
+      &assertion(json!({
+        "assertion_type": "json_body",
+        "operator": "equals",
+        "expected_value": "{{res:missing.$.id}}",
+        "is_template_expression": true
+      })),
+      json!(1),
+      Some(&context),
+    );
+    assert!(!result.passed);
+    assert!(result.message.starts_with("Template resolution failed:"), "{}", result.message);
+  }
+
+  #[test]
+  fn templates_are_literal_unless_flagged() {
+    let context: TemplateContext = serde_json::from_value(json!({ "parameters": { "x": 1 } })).unwrap();
+    let result = evaluate(
+      &assertion(json!({ "assertion_type": "json_body", "operator": "equals", "expected_value": "{{param:x}}" })),
+      json!("{{param:x}}"),
+      Some(&context),
+    );
+    assert!(result.passed);
+    assert_eq!(result.original_expected_value, None);
+  }
+
+  #[test]
+  fn run_stops_at_first_failure() {
+    let assertions: Vec<Assertion> = serde_json::from_value(json!([
+      { "assertion_type": "status_code", "operator": "equals", "expected_value": 200, "enabled": false },
+      { "assertion_type": "status_code", "operator": "between", "expected_value": [200, 299] },
+      { "assertion_type": "json_body", "data_id": "$.id", "operator": "equals", "expected_value": 2 },
+      { "assertion_type": "status_code", "operator": "equals", "expected_value": 201 }
+    ]))
+    .unwrap();
+    let results = run(&assertions, &response(), &json!({ "id": 1 }), None, None);
+
+    assert_eq!(results.len(), 2);
+    assert!(results[0].passed);
+    assert!(!results[1].passed);
+    assert_eq!(
+      results[1].message,
+      "Assertion failed: json_body $.id equals 2, actual value: 1"
+    );
+  }
+
+  #[test]
+  fn bad_types_and_operators_fail() {
+    let results = run(
+      &[assertion(json!({ "assertion_type": "cookie", "operator": "equals" }))],
+      &response(),
+      &Value::Null,
+      None,
+      None,
+    );
+    assert!(results[0].message.starts_with("Error extracting assertion value:"));
+
+    let result = evaluate(
+      &assertion(json!({ "assertion_type": "status_code", "operator": "roughly" })),
+      json!(200),
+      None,
+    );
+    assert!(!result.passed);
+    assert_eq!(result.message, "Error evaluating assertion: Unknown operator: roughly");
+  }
+}
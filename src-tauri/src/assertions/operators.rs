@@ -0,0 +1,291 @@
+//! Assertion operators, ported from `src/lib/assertions/operators.ts`.
+//!
+//! Comparisons follow JavaScript: `equals` is `==`, membership is
+//! `Array.prototype.includes`, and ordering only applies to two numbers.
+//! Objects and arrays are never equal to one another, because the TS
+//! operators compare them by reference.
+
+use regex::RegexBuilder;
+use serde_json::Value;
+
+use crate::template::js_string;
+use crate::transform::{to_number, truthy};
+
+/// Compiled size cap for `matches_regex` patterns taken from flow files.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Every operator name, in the order the editor lists them.
+pub const OPERATORS: &[&str] = &[
+  "equals",
+  "not_equals",
+  "contains",
+  "exists",
+  "greater_than",
+  "less_than",
+  "starts_with",
+  "ends_with",
+  "matches_regex",
+  "not_contains",
+  "is_empty",
+  "is_not_empty",
+  "greater_than_or_equal",
+  "less_than_or_equal",
+  "between",
+  "not_between",
+  "has_length",
+  "length_greater_than",
+  "length_less_than",
+  "contains_all",
+  "contains_any",
+  "not_contains_any",
+  "one_of",
+  "not_one_of",
+  "is_type",
+  "is_null",
+  "is_not_null",
+];
+
+pub fn is_valid_operator(operator: &str) -> bool {
+  OPERATORS.contains(&operator)
+}
+
+/// Apply `operator` to the actual and expected values, or `None` for an
+/// unknown operator.
+pub fn evaluate(operator: &str, actual: &Value, expected: &Value) -> Option<bool> {
+  let passed = match operator {
+    "equals" => loose_equals(actual, expected),
+    "not_equals" => !loose_equals(actual, expected),
+    "contains" => match actual {
+      Value::String(text) => text.contains(&js_string(expected)),
+      Value::Array(items) => includes(items, expected),
+      _ => false,
+    },
+    "not_contains" => match actual {
+      Value::String(text) => !text.contains(&js_string(expected)),
+      Value::Array(items) => !includes(items, expected),
+      _ => true,
+    },
+    "exists" | "is_not_null" => !actual.is_null(),
+    "is_null" => actual.is_null(),
+    "greater_than" => compare(actual, expected, |a, b| a > b),
+    "less_than" => compare(actual, expected, |a, b| a < b),
+    "greater_than_or_equal" => compare(actual, expected, |a, b| a >= b),
+    "less_than_or_equal" => compare(actual, expected, |a, b| a <= b),
+    "starts_with" => matches!((actual, expected), (Value::String(text), Value::String(prefix)) if text.starts_with(prefix.as_str())),
+    "ends_with" => matches!((actual, expected), (Value::String(text), Value::String(suffix)) if text.ends_with(suffix.as_str())),
+    "matches_regex" => match (actual, expected) {
+      (Value::String(text), Value::String(pattern)) => RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .is_ok_and(|regex| regex.is_match(text)),
+      _ => false,
+    },
+    "is_empty" => actual.is_null() || size(actual) == Some(0),
+    "is_not_empty" => !actual.is_null() && size(actual).map_or(true, |size| size > 0),
+    "between" => range(actual, expected).is_some_and(|(value, min, max)| value >= min && value <= max),
+    "not_between" => range(actual, expected).is_some_and(|(value, min, max)| value < min || value > max),
+    "has_length" => compare_length(actual, expected, |length, expected| length == expected),
+    "length_greater_than" => compare_length(actual, expected, |length, expected| length > expected),
+    "length_less_than" => compare_length(actual, expected, |length, expected| length < expected),
+    "contains_all" => match (actual, expected) {
+      (Value::Array(items), Value::Array(wanted)) => wanted.iter().all(|item| includes(items, item)),
+      _ => false,
+    },
+    "contains_any" => match (actual, expected) {
+      (Value::Array(items), Value::Array(wanted)) => wanted.iter().any(|item| includes(items, item)),
+      _ => false,
+    },
+    "not_contains_any" => match (actual, expected) {
+      (Value::Array(items), Value::Array(wanted)) => !wanted.iter().any(|item| includes(items, item)),
+      _ => false,
+    },
+    "one_of" => expected.as_array().is_some_and(|options| includes(options, actual)),
+    "not_one_of" => expected.as_array().is_some_and(|options| !includes(options, actual)),
+    "is_type" => match expected.as_str() {
+      Some("string") => actual.is_string(),
+      Some("number") => actual.is_number(),
+      Some("boolean") => actual.is_boolean(),
+      Some("array") => actual.is_array(),
+      Some("object") => actual.is_object(),
+      Some("null") => actual.is_null(),
+      _ => false,
+    },
+    _ => return None,
+  };
+  Some(passed)
+}
+
+/// JavaScript `==` on JSON values, with `null` standing in for both `null`
+/// and `undefined`.
+pub fn loose_equals(left: &Value, right: &Value) -> bool {
+  match (left, right) {
+    (Value::Null, Value::Null) => true,
+    (Value::Null, _) | (_, Value::Null) => false,
+    (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+    (Value::String(a), Value::String(b)) => a == b,
+    (Value::Bool(a), Value::Bool(b)) => a == b,
+    (Value::Array(_) | Value::Object(_), Value::Array(_) | Value::Object(_)) => false,
+    (Value::Number(number), Value::String(text)) | (Value::String(text), Value::Number(number)) => {
+      number.as_f64() == Some(to_number(&Value::String(text.clone())))
+    }
+    (Value::Bool(flag), other) | (other, Value::Bool(flag)) => loose_equals(&Value::from(u8::from(*flag)), other),
+    (container @ (Value::Array(_) | Value::Object(_)), other)
+    | (other, container @ (Value::Array(_) | Value::Object(_))) => {
+      loose_equals(&Value::String(js_string(container)), other)
+    }
+  }
+}
+
+/// `Array.prototype.includes`: numbers by value, containers never match.
+fn includes(items: &[Value], wanted: &Value) -> bool {
+  items.iter().any(|item| match (item, wanted) {
+    (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+    (Value::Array(_) | Value::Object(_), _) => false,
+    _ => item == wanted,
+  })
+}
+
+fn compare(actual: &Value, expected: &Value, op: fn(f64, f64) -> bool) -> bool {
+  match (actual, expected) {
+    (Value::Number(a), Value::Number(b)) => a.as_f64().zip(b.as_f64()).is_some_and(|(a, b)| op(a, b)),
+    _ => false,
+  }
+}
+
+/// `[min, max]` bounds for `between`, with the actual value.
+fn range(actual: &Value, expected: &Value) -> Option<(f64, f64, f64)> {
+  let value = actual.as_number()?.as_f64()?;
+  match expected.as_array()?.as_slice() {
+    [Value::Number(min), Value::Number(max)] => Some((value, min.as_f64()?, max.as_f64()?)),
+    _ => None,
+  }
+}
+
+/// Compare a string or array length; falsy actual values never pass.
+fn compare_length(actual: &Value, expected: &Value, op: fn(f64, f64) -> bool) -> bool {
+  if !truthy(actual) {
+    return false;
+  }
+  match (length(actual), expected) {
+    (Some(length), Value::Number(expected)) => expected.as_f64().is_some_and(|expected| op(length as f64, expected)),
+    _ => false,
+  }
+}
+
+/// `.length` of a string (in UTF-16 units, as JavaScript counts) or array.
+fn length(value: &Value) -> Option<usize> {
+  match value {
+    Value::String(text) => Some(text.encode_utf16().count()),
+    Value::Array(items) => Some(items.len()),
+    _ => None,
+  }
+}
+
+/// Length, or key count for objects; `None` for `null` and scalars.
+fn size(value: &Value) -> Option<usize> {
+  match value {
+    Value::Object(map) => Some(map.len()),
+    _ => length(value),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn check(operator: &str, actual: Value, expected: Value) -> bool {
+    evaluate(operator, &actual, &expected).unwrap()
+  }
+
+  #[test]
+  fn equality_is_loose() {
+    assert!(check("equals", json!(5), json!(5)));
+    assert!(check("equals", json!(5), json!("5")));
+    assert!(check("equals", json!(5.0), json!(5)));
+    assert!(check("equals", json!(true), json!("1")));
+    assert!(check("equals", json!([1, 2]), json!("1,2")));
+    assert!(!check("equals", json!(5), json!(6)));
+    assert!(!check("equals", json!(null), json!(0)));
+    assert!(!check("equals", json!({ "a": 1 }), json!({ "a": 1 })));
+    assert!(check("not_equals", json!("test"), json!("other")));
+    assert!(!check("not_equals", json!(5), json!(5)));
+  }
+
+  #[test]
+  fn strings_and_membership() {
+    assert!(check("contains", json!("test string"), json!("string")));
+    assert!(check("contains", json!([1, 2, 3]), json!(2)));
+    assert!(!check("contains", json!([1, 2, 3]), json!("2")));
+    assert!(!check("contains", json!(null), json!("anything")));
+    assert!(check("not_contains", json!(null), json!("anything")));
+    assert!(check("starts_with", json!("test string"), json!("test")));
+    assert!(!check("starts_with", json!(123), json!("1")));
+    assert!(check("ends_with", json!("test string"), json!("string")));
+    assert!(check("matches_regex", json!("abc-123"), json!("^[a-z]+-\\d+$")));
+    assert!(!check("matches_regex", json!("abc"), json!("(")));
+    assert!(check("one_of", json!("b"), json!(["a", "b"])));
+    assert!(!check("one_of", json!("b"), json!("abc")));
+    assert!(check("not_one_of", json!("c"), json!(["a", "b"])));
+  }
+
+  #[test]
+  fn numbers_are_compared_strictly() {
+    assert!(check("greater_than", json!(5), json!(4)));
+    assert!(!check("greater_than", json!("5"), json!(4)));
+    assert!(check("less_than_or_equal", json!(5), json!(5.0)));
+    assert!(check("between", json!(5), json!([1, 10])));
+    assert!(!check("between", json!(11), json!([1, 10])));
+    assert!(!check("between", json!(5), json!([1])));
+    assert!(check("not_between", json!(11), json!([1, 10])));
+    assert!(!check("not_between", json!("11"), json!([1, 10])));
+  }
+
+  #[test]
+  fn lengths_and_emptiness() {
+    assert!(check("has_length", json!([1, 2, 3]), json!(3)));
+    assert!(check("has_length", json!("test"), json!(4)));
+    assert!(check("has_length", json!("é😀"), json!(3)));
+    assert!(!check("has_length", json!(""), json!(0)));
+    assert!(!check("has_length", json!(null), json!(3)));
+    assert!(check("length_greater_than", json!([1, 2]), json!(1)));
+    assert!(check("length_less_than", json!("ab"), json!(3)));
+    assert!(check("is_empty", json!(null), json!(null)));
+    assert!(check("is_empty", json!({}), json!(null)));
+    assert!(!check("is_empty", json!(0), json!(null)));
+    assert!(check("is_not_empty", json!(0), json!(null)));
+    assert!(!check("is_not_empty", json!([]), json!(null)));
+  }
+
+  #[test]
+  fn array_sets() {
+    assert!(check("contains_all", json!([1, 2, 3, 4]), json!([1, 3])));
+    assert!(!check("contains_all", json!([1, 2, 3]), json!([1, 4])));
+    assert!(!check("contains_all", json!("test"), json!([1])));
+    assert!(check("contains_any", json!([1, 2]), json!([5, 2])));
+    assert!(check("not_contains_any", json!([1, 2]), json!([5, 6])));
+    assert!(!check("not_contains_any", json!("12"), json!([5, 6])));
+  }
+
+  #[test]
+  fn types_and_nulls() {
+    assert!(check("is_type", json!({}), json!("object")));
+    assert!(!check("is_type", json!([]), json!("object")));
+    assert!(!check("is_type", json!(null), json!("object")));
+    assert!(check("is_type", json!(null), json!("null")));
+    assert!(!check("is_type", json!(1), json!("integer")));
+    assert!(check("exists", json!(""), json!(null)));
+    assert!(check("exists", json!(0), json!(null)));
+    assert!(!check("exists", json!(null), json!(null)));
+    assert!(check("is_null", json!(null), json!(null)));
+  }
+
+  #[test]
+  fn every_listed_operator_is_implemented() {
+    for operator in OPERATORS {
+      assert!(evaluate(operator, &json!(null), &json!(null)).is_some(), "{operator}");
+    }
+    assert_eq!(evaluate("nope", &json!(1), &json!(1)), None);
+    assert!(!is_valid_operator("nope"));
+  }
+}
@@ -0,0 +1,304 @@
+//! Headless runner for CI.
+//!
+//! Runs a single test flow, or a flow sequence whose flows live in a
+//! directory as `<test_flow_id>.json`, against an optional environment file.
+//! Exits with 1 when anything failed and 2 when the input could not be loaded.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use serde_json::{Map, Value};
+
+use app_lib::environment::{EnvironmentConfig, ResolvedEnvironment};
+use app_lib::flow::runner::{EndpointResult, EndpointStatus, FlowRunResult, FlowRunner, RunPreferences};
+use app_lib::flow::TestFlow;
+use app_lib::http::HttpClient;
+use app_lib::report::{html, junit, ReportDetails};
+use app_lib::sequence::runner::{SequenceRunResult, SequenceRunner};
+use app_lib::sequence::FlowSequenceConfig;
+
+const USAGE: &str = "\
+Usage:
+  test-pilot-cli --flow <flow.json> [options]
+  test-pilot-cli --sequence <sequence.json> --flows-dir <dir> [options]
+
+Options:
+  --env <environment.json>   Environment config to run against
+  --sub-env <name>           Sub-environment to use (e.g. dev, sit)
+  --param <name=value>       Override a parameter of --flow; repeatable. JSON values are parsed
+  --timeout <ms>             Per-request timeout (default 30000)
+  --retries <n>              Retry requests that got no response up to n times
+  --parallel                 Run the endpoints of each step concurrently
+  --continue-on-error        Keep going after a failed endpoint or flow
+  --json                     Print the full result as JSON
+  --junit <report.xml>       Also write a JUnit XML report
+  --html <report.html>       Also write an HTML report, secrets masked
+  -h, --help                 Show this help";
+
+#[derive(Default)]
+struct Args {
+  flow: Option<PathBuf>,
+  sequence: Option<PathBuf>,
+  flows_dir: Option<PathBuf>,
+  env: Option<PathBuf>,
+  sub_env: Option<String>,
+  params: Map<String, Value>,
+  preferences: RunPreferences,
+  json: bool,
+  junit: Option<PathBuf>,
+  html: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+  let args = match parse_args(std::env::args().skip(1)) {
+    Ok(Some(args)) => args,
+    Ok(None) => {
+      println!("{USAGE}");
+      return ExitCode::SUCCESS;
+    }
+    Err(message) => {
+      eprintln!("error: {message}\n\n{USAGE}");
+      return ExitCode::from(2);
+    }
+  };
+
+  let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+    Ok(runtime) => runtime,
+    Err(err) => {
+      eprintln!("error: failed to start runtime: {err}");
+      return ExitCode::from(2);
+    }
+  };
+
+  match runtime.block_on(run(args)) {
+    Ok(true) => ExitCode::SUCCESS,
+    Ok(false) => ExitCode::FAILURE,
+    Err(message) => {
+      eprintln!("error: {message}");
+      ExitCode::from(2)
+    }
+  }
+}
+
+fn parse_args(mut raw: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+  let mut args = Args::default();
+  while let Some(arg) = raw.next() {
+    let mut value = |name: &str| raw.next().ok_or_else(|| format!("{name} needs a value"));
+    match arg.as_str() {
+      "-h" | "--help" => return Ok(None),
+      "--flow" => args.flow = Some(value("--flow")?.into()),
+      "--sequence" => args.sequence = Some(value("--sequence")?.into()),
+      "--flows-dir" => args.flows_dir = Some(value("--flows-dir")?.into()),
+      "--env" => args.env = Some(value("--env")?.into()),
+      "--sub-env" => args.sub_env = Some(value("--sub-env")?),
+      "--param" => {
+        let param = value("--param")?;
+        let (name, raw_value) = param
+          .split_once('=')
+          .ok_or_else(|| format!("--param expects name=value, got `{param}`"))?;
+        let parsed = serde_json::from_str(raw_value).unwrap_or_else(|_| Value::String(raw_value.to_string()));
+        args.params.insert(name.to_string(), parsed);
+      }
+      "--timeout" => {
+        let timeout = value("--timeout")?;
+        args.preferences.timeout_ms = timeout
+          .parse()
+          .map_err(|_| format!("--timeout expects milliseconds, got `{timeout}`"))?;
+      }
+      "--retries" => {
+        let retries = value("--retries")?;
+        args.preferences.retry_count = retries
+          .parse()
+          .map_err(|_| format!("--retries expects a count, got `{retries}`"))?;
+      }
+      "--parallel" => args.preferences.parallel_execution = true,
+      "--continue-on-error" => args.preferences.stop_on_error = false,
+      "--json" => args.json = true,
+      "--junit" => args.junit = Some(value("--junit")?.into()),
+      "--html" => args.html = Some(value("--html")?.into()),
+      other => return Err(format!("unknown argument `{other}`")),
+    }
+  }
+
+  match (&args.flow, &args.sequence) {
+    (Some(_), Some(_)) => Err("pass either --flow or --sequence, not both".to_string()),
+    (None, None) => Err("one of --flow or --sequence is required".to_string()),
+    (None, Some(_)) if args.flows_dir.is_none() => Err("--sequence needs --flows-dir".to_string()),
+    // A sequence's flows take their parameters from its mappings.
+    (None, Some(_)) if !args.params.is_empty() => Err("--param only applies to --flow".to_string()),
+    _ => Ok(Some(args)),
+  }
+}
+
+async fn run(args: Args) -> Result<bool, String> {
+  let client = HttpClient::new().map_err(|err| err.to_string())?;
+
+  if let Some(path) = &args.flow {
+    let flow = TestFlow::from_json(&read(path)?).map_err(|err| format!("{}: {err}", path.display()))?;
+    let sub_env = args.sub_env.clone().or_else(|| {
+      flow
+        .settings
+        .environment
+        .as_ref()
+        .and_then(|environment| environment.sub_environment.clone())
+    });
+    let environment = load_environment(args.env.as_deref(), sub_env.as_deref())?;
+
+    let result = FlowRunner::new(&client, &flow, environment.as_ref())
+      .preferences(args.preferences.clone())
+      .run(&args.params)
+      .await;
+    if args.json {
+      print_json(&result)?;
+    } else {
+      print_flow(&result, "");
+    }
+    if let Some(junit_path) = &args.junit {
+      write(junit_path, &junit::flow_report(&report_name(path), &result))?;
+    }
+    if let Some(html_path) = &args.html {
+      write(html_path, &html::flow_report(&report_details(&args, path, sub_env), &result))?;
+    }
+    return Ok(result.success);
+  }
+
+  let (Some(sequence_path), Some(flows_dir)) = (&args.sequence, &args.flows_dir) else {
+    unreachable!("validated by parse_args");
+  };
+  let sequence = FlowSequenceConfig::from_json(&read(sequence_path)?)
+    .map_err(|err| format!("{}: {err}", sequence_path.display()))?;
+  let mut flows = HashMap::new();
+  for step in &sequence.steps {
+    let path = flows_dir.join(format!("{}.json", step.test_flow_id));
+    let flow = TestFlow::from_json(&read(&path)?).map_err(|err| format!("{}: {err}", path.display()))?;
+    flows.insert(step.test_flow_id, flow);
+  }
+  let environment = load_environment(args.env.as_deref(), args.sub_env.as_deref())?;
+
+  let result = SequenceRunner::new(&client, &sequence, &flows, environment.as_ref())
+    .preferences(args.preferences.clone())
+    .run()
+    .await;
+  if args.json {
+    print_json(&result)?;
+  } else {
+    print_sequence(&result);
+  }
+  if let Some(junit_path) = &args.junit {
+    write(junit_path, &junit::sequence_report(&report_name(sequence_path), &result.flow_results))?;
+  }
+  if let Some(html_path) = &args.html {
+    let details = report_details(&args, sequence_path, args.sub_env.clone());
+    write(html_path, &html::sequence_report(&details, &result.flow_results))?;
+  }
+  Ok(result.success)
+}
+
+fn read(path: &Path) -> Result<String, String> {
+  std::fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+  std::fs::write(path, contents).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// Reports are named after the flow or sequence file.
+fn report_name(path: &Path) -> String {
+  path.file_stem().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
+
+fn report_details(args: &Args, path: &Path, sub_environment: Option<String>) -> ReportDetails {
+  ReportDetails {
+    name: report_name(path),
+    environment: args.env.as_deref().map(report_name),
+    sub_environment,
+  }
+}
+
+fn load_environment(path: Option<&Path>, sub_env: Option<&str>) -> Result<Option<ResolvedEnvironment>, String> {
+  let Some(path) = path else {
+    return Ok(None);
+  };
+  let config = EnvironmentConfig::from_json(&read(path)?).map_err(|err| format!("{}: {err}", path.display()))?;
+  let sub_env = match sub_env {
+    Some(name) => name.to_string(),
+    None if config.environments.len() == 1 => config.environments.keys().next().cloned().unwrap_or_default(),
+    None => return Err("--sub-env is required when the environment has several sub-environments".to_string()),
+  };
+  config.resolve(&sub_env).map(Some).map_err(|err| err.to_string())
+}
+
+fn print_json(result: &impl serde::Serialize) -> Result<(), String> {
+  let text = serde_json::to_string_pretty(result).map_err(|err| err.to_string())?;
+  println!("{text}");
+  Ok(())
+}
+
+fn print_flow(result: &FlowRunResult, indent: &str) {
+  if !result.missing_parameters.is_empty() {
+    println!("{indent}missing required parameters: {}", result.missing_parameters.join(", "));
+  }
+  for endpoint in &result.endpoints {
+    print_endpoint(endpoint, indent);
+  }
+  let passed = result.endpoints.iter().filter(|e| e.status == EndpointStatus::Completed).count();
+  println!(
+    "{indent}{} ({passed}/{} endpoints passed)",
+    if result.success { "PASSED" } else { "FAILED" },
+    result.endpoints.len()
+  );
+}
+
+fn print_endpoint(endpoint: &EndpointResult, indent: &str) {
+  let mark = if endpoint.status == EndpointStatus::Completed { "ok  " } else { "FAIL" };
+  let (method, url) = endpoint
+    .request
+    .as_ref()
+    .map(|request| (request.method.as_str(), request.url.as_str()))
+    .unwrap_or(("", ""));
+  let status = endpoint
+    .response
+    .as_ref()
+    .map(|response| response.status.to_string())
+    .unwrap_or_else(|| "---".to_string());
+  let millis = endpoint.timing.as_ref().map(|timing| timing.total_ms).unwrap_or_default();
+  println!("{indent}{mark} {} {method} {url} -> {status} ({millis:.0} ms)", endpoint.endpoint_id);
+
+  for assertion in endpoint.assertions.iter().filter(|assertion| !assertion.passed) {
+    println!("{indent}       {}", assertion.message);
+  }
+  if let Some(error) = endpoint.error.as_ref().filter(|_| endpoint.assertions.iter().all(|a| a.passed)) {
+    println!("{indent}       {error}");
+  }
+}
+
+fn print_sequence(result: &SequenceRunResult) {
+  for flow in &result.flow_results {
+    let expectation = if flow.expects_error { " (expected to fail)" } else { "" };
+    println!("flow {} [step {}]{expectation}", flow.test_flow_id, flow.step_order);
+    match (&flow.flow, &flow.loop_result) {
+      (Some(flow_result), _) => print_flow(flow_result, "  "),
+      (None, Some(loop_result)) => {
+        for iteration in &loop_result.iterations {
+          println!("  {}", iteration.label);
+          print_flow(&iteration.flow, "    ");
+        }
+        println!(
+          "  {}/{} iterations run",
+          loop_result.completed_iterations, loop_result.total_iterations
+        );
+      }
+      (None, None) => println!("  FAILED: {}", flow.error.as_deref().unwrap_or("flow did not run")),
+    }
+    if !flow.matched_expectation {
+      println!("  -> did not match expectation");
+    }
+  }
+  let matched = result.flow_results.iter().filter(|flow| flow.matched_expectation).count();
+  println!(
+    "\nSequence {} ({matched}/{} flows as expected)",
+    if result.success { "PASSED" } else { "FAILED" },
+    result.flow_results.len()
+  );
+}
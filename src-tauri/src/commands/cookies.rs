@@ -0,0 +1,39 @@
+use tauri::State;
+
+use crate::cookies::{lock_jar, Cookie, CookieJars, ExportFormat};
+use crate::error::Result;
+
+/// Create an empty cookie jar and return its run ID.
+#[tauri::command]
+pub fn create_cookie_jar(jars: State<'_, CookieJars>, run_id: Option<String>) -> String {
+  jars.create(run_id)
+}
+
+/// List the unexpired cookies held for a run.
+#[tauri::command]
+pub fn get_cookie_jar(jars: State<'_, CookieJars>, run_id: String) -> Result<Vec<Cookie>> {
+  Ok(lock_jar(&jars.get(&run_id)?).cookies())
+}
+
+/// Clear a run's cookies, optionally only those for `domain` and its subdomains.
+#[tauri::command]
+pub fn clear_cookie_jar(jars: State<'_, CookieJars>, run_id: String, domain: Option<String>) -> Result<()> {
+  lock_jar(&jars.get(&run_id)?).clear(domain.as_deref());
+  Ok(())
+}
+
+/// Export a run's cookies as JSON or a Netscape `cookies.txt` file.
+#[tauri::command]
+pub fn export_cookie_jar(
+  jars: State<'_, CookieJars>,
+  run_id: String,
+  format: Option<ExportFormat>,
+) -> Result<String> {
+  lock_jar(&jars.get(&run_id)?).export(format.unwrap_or_default())
+}
+
+/// Drop a run's jar once the run is finished.
+#[tauri::command]
+pub fn delete_cookie_jar(jars: State<'_, CookieJars>, run_id: String) -> bool {
+  jars.remove(&run_id)
+}
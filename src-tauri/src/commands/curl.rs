@@ -0,0 +1,48 @@
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::curl::{self, KnownEndpoint, ParsedCurl};
+use crate::error::Result;
+use crate::flow::runner::EndpointRequest;
+use crate::storage::Store;
+
+/// Parse a `curl` command line into a step endpoint, matched against the
+/// stored endpoints of `apiId`, or of every API when it is not given.
+#[tauri::command]
+pub fn parse_curl_command(store: State<'_, Store>, command: String, api_id: Option<i64>) -> Result<ParsedCurl> {
+  let known = known_endpoints(&store, api_id.as_slice())?;
+  curl::parse(&command, &known)
+}
+
+/// The stored endpoints of `api_ids`, or of every API when empty. Records
+/// that do not read as endpoint definitions are skipped.
+pub(super) fn known_endpoints(store: &Store, api_ids: &[i64]) -> Result<Vec<KnownEndpoint>> {
+  let records = if api_ids.is_empty() {
+    store.list("api_endpoints", &Map::new())?
+  } else {
+    let mut records = Vec::new();
+    for &api_id in api_ids {
+      records.extend(store.list("api_endpoints", &Map::from_iter([("apiId".to_string(), Value::from(api_id))]))?);
+    }
+    records
+  };
+  Ok(
+    records
+      .into_iter()
+      .filter_map(|record| match KnownEndpoint::from_record(record) {
+        Ok(known) => Some(known),
+        Err(err) => {
+          log::debug!("[known_endpoints] skipping unreadable endpoint: {err}");
+          None
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Render a request, such as the resolved `request` of an endpoint result,
+/// as a copy-pasteable `curl` command.
+#[tauri::command]
+pub fn render_curl_command(request: EndpointRequest) -> String {
+  curl::render(&request)
+}
@@ -0,0 +1,107 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::cookies::CookieJars;
+use crate::environment::{EnvironmentConfig, ResolvedEnvironment};
+use crate::error::{Error, Result};
+use crate::flow::runner::{FlowEvent, FlowRunResult, FlowRunner, RunPreferences};
+use crate::flow::TestFlow;
+use crate::history::RunEntry;
+use crate::http::HttpClient;
+use crate::runs::RunRegistry;
+use crate::storage::Store;
+
+/// Event carrying a run's [`FlowEvent`]s to the webview.
+pub const FLOW_EVENT: &str = "flow-run-event";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FlowRunEvent<'a> {
+  run_id: &'a str,
+  #[serde(flatten)]
+  event: FlowEvent,
+}
+
+/// Run a whole flow natively and return its outputs.
+///
+/// Endpoint and step progress is emitted as `flow-run-event` tagged with
+/// `runId`, which `cancel_run` also takes. Passing a `runId` runs with that
+/// run's cookie jar and keeps it afterwards; without one a throwaway jar is
+/// used. The sub-environment defaults to the one the flow selects. The run is
+/// recorded in the run history under `testFlowId`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_flow(
+  app: AppHandle,
+  client: State<'_, HttpClient>,
+  jars: State<'_, CookieJars>,
+  runs: State<'_, RunRegistry>,
+  store: State<'_, Store>,
+  flow: TestFlow,
+  test_flow_id: Option<i64>,
+  environment: Option<EnvironmentConfig>,
+  sub_environment: Option<String>,
+  parameters: Option<Map<String, Value>>,
+  preferences: Option<RunPreferences>,
+  run_id: Option<String>,
+) -> Result<FlowRunResult> {
+  let sub_environment = sub_environment.or_else(|| {
+    flow
+      .settings
+      .environment
+      .as_ref()
+      .and_then(|selection| selection.sub_environment.clone())
+  });
+  let environment = resolve_environment(environment, sub_environment)?;
+
+  let (run_id, keep_jar) = match run_id {
+    Some(run_id) => (run_id, true),
+    None => (jars.create(None), false),
+  };
+  log::debug!("[execute_flow] starting run {run_id}");
+  let cancellation = runs.register(&run_id);
+  let started = chrono::Utc::now();
+
+  let emit = |event: FlowEvent| {
+    let payload = FlowRunEvent { run_id: &run_id, event };
+    if let Err(err) = app.emit(FLOW_EVENT, payload) {
+      log::warn!("[execute_flow] failed to emit progress for run {run_id}: {err}");
+    }
+  };
+  let result = FlowRunner::new(&client, &flow, environment.as_ref())
+    .preferences(preferences.unwrap_or_default())
+    .cookie_jar(jars.get_or_create(&run_id))
+    .cancellation(cancellation)
+    .on_event(&emit)
+    .run(&parameters.unwrap_or_default())
+    .await;
+
+  runs.remove(&run_id);
+  if !keep_jar {
+    jars.remove(&run_id);
+  }
+  let recorded = RunEntry::flow(&run_id, test_flow_id, started, &result).and_then(|entry| store.record_run(&entry));
+  if let Err(err) = recorded {
+    log::warn!("[execute_flow] failed to record run {run_id} in the history: {err}");
+  }
+  Ok(result)
+}
+
+/// Resolve the chosen sub-environment, or the only one there is.
+pub(crate) fn resolve_environment(
+  environment: Option<EnvironmentConfig>,
+  sub_environment: Option<String>,
+) -> Result<Option<ResolvedEnvironment>> {
+  match (environment, sub_environment) {
+    (Some(config), Some(name)) => Ok(Some(config.resolve(&name)?)),
+    (Some(config), None) if config.environments.len() == 1 => {
+      let name = config.environments.keys().next().cloned().unwrap_or_default();
+      Ok(Some(config.resolve(&name)?))
+    }
+    (Some(_), None) => Err(Error::Environment(
+      "a sub-environment is required when the environment has several".to_string(),
+    )),
+    (None, _) => Ok(None),
+  }
+}
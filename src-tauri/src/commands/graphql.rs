@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::{json, Value};
+use tauri::State;
+
+use super::openapi::store_api;
+use crate::error::{Error, Result};
+use crate::graphql::{self, Schema};
+use crate::http::{HttpClient, HttpRequest};
+use crate::storage::Store;
+
+/// Path of the GraphQL endpoint when the URL names none.
+const DEFAULT_PATH: &str = "/graphql";
+
+/// Import a GraphQL schema file, SDL or saved introspection JSON, as an
+/// API with an endpoint per query and mutation field. `url` is the GraphQL
+/// endpoint the operations are sent to; `name` defaults to the file name.
+/// Returns the API record with `endpointCount`.
+#[tauri::command]
+pub async fn import_graphql_file(
+  store: State<'_, Store>,
+  path: String,
+  url: Option<String>,
+  name: Option<String>,
+  description: Option<String>,
+  project_id: Option<i64>,
+) -> Result<Value> {
+  let path = Path::new(&path);
+  let content = std::fs::read_to_string(path)?;
+  let schema = graphql::parse(&content)?;
+  let name = name.unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+  store_schema(&store, &schema, content, url.as_deref(), name, description, project_id)
+}
+
+/// Import the schema of the GraphQL server at `url` by running the
+/// introspection query against it, with `headers` for authentication.
+/// `name` defaults to the server's host.
+#[tauri::command]
+pub async fn import_graphql_introspection(
+  store: State<'_, Store>,
+  client: State<'_, HttpClient>,
+  url: String,
+  headers: Option<HashMap<String, String>>,
+  name: Option<String>,
+  description: Option<String>,
+  project_id: Option<i64>,
+) -> Result<Value> {
+  let mut headers = headers.unwrap_or_default();
+  if !headers.keys().any(|header| header.eq_ignore_ascii_case("content-type")) {
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+  }
+  let request = HttpRequest {
+    method: "POST".to_string(),
+    url: url.clone(),
+    headers,
+    body: Some(json!({ "query": graphql::INTROSPECTION_QUERY }).to_string()),
+    timeout_ms: None,
+    run_id: None,
+  };
+  let response = client.execute(request, None).await?;
+  let result: Value = serde_json::from_slice(&response.body).map_err(|_| {
+    Error::GraphQl(format!("{url} answered the introspection query with status {} and no JSON", response.status))
+  })?;
+  let schema = graphql::from_introspection(&result)?;
+  let name = name.unwrap_or_else(|| location(Some(url.as_str())).0.unwrap_or_else(|| url.clone()));
+  store_schema(&store, &schema, result.to_string(), Some(url.as_str()), name, description, project_id)
+}
+
+fn store_schema(
+  store: &Store,
+  schema: &Schema,
+  content: String,
+  url: Option<&str>,
+  name: String,
+  description: Option<String>,
+  project_id: Option<i64>,
+) -> Result<Value> {
+  let (host, path) = location(url);
+  let endpoints = graphql::extract_endpoints(schema, &path);
+  let api = store_api(
+    store,
+    json!({
+      "name": name,
+      "description": description,
+      "specFormat": "graphql",
+      "specContent": content,
+      "host": host,
+      "projectId": project_id,
+    }),
+    &endpoints,
+  )?;
+  log::debug!("[import_graphql] imported {} operation(s) as API {}", endpoints.len(), api["id"]);
+  Ok(api)
+}
+
+/// The `host[:port]` and path of a GraphQL endpoint URL.
+fn location(url: Option<&str>) -> (Option<String>, String) {
+  let Some(url) = url.and_then(|url| url::Url::parse(url).ok()) else {
+    return (None, DEFAULT_PATH.to_string());
+  };
+  let host = url.host_str().map(|host| match url.port() {
+    Some(port) => format!("{host}:{port}"),
+    None => host.to_string(),
+  });
+  let path = match url.path() {
+    "" | "/" => DEFAULT_PATH.to_string(),
+    path => path.to_string(),
+  };
+  (host, path)
+}
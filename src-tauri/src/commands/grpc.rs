@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+use tauri::State;
+
+use super::openapi::store_api;
+use crate::error::Result;
+use crate::grpc;
+use crate::storage::Store;
+
+/// Import `.proto` files, or descriptor sets compiled with
+/// `protoc --descriptor_set_out`, as an API with an endpoint per gRPC
+/// method. Imports of `.proto` files are looked up in `import_paths`, then
+/// next to the importing file. `host` is the `host:port` calls go to; `name`
+/// defaults to the first file's name. Returns the API record with
+/// `endpointCount`.
+#[tauri::command]
+pub async fn import_grpc_files(
+  store: State<'_, Store>,
+  paths: Vec<String>,
+  import_paths: Option<Vec<String>>,
+  host: Option<String>,
+  name: Option<String>,
+  description: Option<String>,
+  project_id: Option<i64>,
+) -> Result<Value> {
+  let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+  let import_paths: Vec<PathBuf> = import_paths.unwrap_or_default().into_iter().map(PathBuf::from).collect();
+  let pool = grpc::load(&paths, &import_paths)?;
+  let endpoints = grpc::extract_endpoints(&pool);
+  let name = name.unwrap_or_else(|| {
+    let stem = paths.iter().find_map(|path| path.file_stem());
+    stem.unwrap_or_default().to_string_lossy().into_owned()
+  });
+  let api = store_api(
+    &store,
+    json!({
+      "name": name,
+      "description": description,
+      "specFormat": "grpc",
+      "specContent": grpc::encode(&pool),
+      "host": host,
+      "projectId": project_id,
+    }),
+    &endpoints,
+  )?;
+  log::debug!("[import_grpc] imported {} method(s) as API {}", endpoints.len(), api["id"]);
+  Ok(api)
+}
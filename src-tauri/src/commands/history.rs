@@ -0,0 +1,29 @@
+use tauri::State;
+
+use crate::error::Result;
+use crate::history::{self, PruneOptions, RunComparison, RunEntry, RunQuery};
+use crate::storage::Store;
+
+/// Recorded runs, newest first and without their results.
+#[tauri::command]
+pub fn list_runs(store: State<'_, Store>, query: Option<RunQuery>) -> Result<Vec<RunEntry>> {
+  store.list_runs(&query.unwrap_or_default())
+}
+
+/// A recorded run with its full, sanitized result.
+#[tauri::command]
+pub fn get_run(store: State<'_, Store>, id: i64) -> Result<RunEntry> {
+  store.get_run(id)
+}
+
+/// What changed from run `base_id` to run `other_id`.
+#[tauri::command]
+pub fn compare_runs(store: State<'_, Store>, base_id: i64, other_id: i64) -> Result<RunComparison> {
+  Ok(history::compare(store.get_run(base_id)?, store.get_run(other_id)?))
+}
+
+/// Delete old runs and return how many were deleted.
+#[tauri::command]
+pub fn prune_runs(store: State<'_, Store>, options: PruneOptions) -> Result<usize> {
+  store.prune_runs(&options)
+}
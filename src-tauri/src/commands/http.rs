@@ -0,0 +1,20 @@
+use tauri::State;
+
+use crate::cookies::CookieJars;
+use crate::error::Result;
+use crate::http::{HttpClient, HttpRequest, HttpResponse};
+
+/// Execute a single HTTP request natively on behalf of a flow step.
+///
+/// When `request.runId` is set, cookies are read from and stored into that
+/// run's jar, which is created on first use.
+#[tauri::command]
+pub async fn execute_request(
+  client: State<'_, HttpClient>,
+  jars: State<'_, CookieJars>,
+  request: HttpRequest,
+) -> Result<HttpResponse> {
+  log::debug!("[execute_request] {} {}", request.method, request.url);
+  let jar = request.run_id.as_deref().map(|run_id| jars.get_or_create(run_id));
+  client.execute(request, jar).await
+}
@@ -0,0 +1,39 @@
+use crate::error::Result;
+use crate::import::har::{self, HarOptions};
+use crate::import::{bruno, insomnia, postman, Import};
+
+/// Convert a Postman v2.1 collection file, with any exported environment
+/// files, into flows and an environment config. Nothing is saved: the webview
+/// shows the result and its warnings before creating records.
+#[tauri::command]
+pub async fn import_postman_collection(path: String, environment_paths: Option<Vec<String>>) -> Result<Import> {
+  let collection = std::fs::read_to_string(&path)?;
+  let environments = environment_paths
+    .unwrap_or_default()
+    .iter()
+    .map(std::fs::read_to_string)
+    .collect::<std::io::Result<Vec<_>>>()?;
+  let environments: Vec<&str> = environments.iter().map(String::as_str).collect();
+  postman::import(&collection, &environments)
+}
+
+/// Convert a HAR file recorded in the browser's DevTools into a flow that
+/// replays it. Like the Postman import, nothing is saved.
+#[tauri::command]
+pub async fn import_har_file(path: String, options: Option<HarOptions>) -> Result<Import> {
+  let har = std::fs::read_to_string(&path)?;
+  har::import(&har, &options.unwrap_or_default())
+}
+
+/// Convert an Insomnia v4 export (JSON) into flows and an environment config.
+#[tauri::command]
+pub async fn import_insomnia_export(path: String) -> Result<Import> {
+  let export = std::fs::read_to_string(&path)?;
+  insomnia::import(&export)
+}
+
+/// Convert a Bruno collection folder, the one holding `bruno.json`.
+#[tauri::command]
+pub async fn import_bruno_collection(path: String) -> Result<Import> {
+  bruno::import_dir(std::path::Path::new(&path))
+}
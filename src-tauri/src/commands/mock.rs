@@ -0,0 +1,57 @@
+use tauri::State;
+
+use crate::error::{Error, Result};
+use crate::mock::{MockCall, MockServer, MockServerInfo, MockServers, Routes};
+use crate::openapi::{self, SpecFormat};
+use crate::storage::Store;
+
+/// Start a mock server on localhost for the given imported APIs, answering
+/// from their stored specifications. `port` defaults to a free one and
+/// `validate` (on by default) rejects requests the specs do not allow.
+#[tauri::command]
+pub async fn start_mock_server(
+  store: State<'_, Store>,
+  mocks: State<'_, MockServers>,
+  api_ids: Vec<i64>,
+  port: Option<u16>,
+  validate: Option<bool>,
+) -> Result<MockServerInfo> {
+  if api_ids.is_empty() {
+    return Err(Error::Mock("no APIs to mock".to_string()));
+  }
+  let mut routes = Routes::default();
+  for &api_id in &api_ids {
+    let api = store.get("apis", api_id)?;
+    let content = api["specContent"]
+      .as_str()
+      .ok_or_else(|| Error::Mock(format!("API {api_id} has no stored specification")))?;
+    let format = match api["specFormat"].as_str() {
+      Some("yaml") => SpecFormat::Yaml,
+      Some("graphql") => return Err(Error::Mock(format!("API {api_id} is a GraphQL API, which cannot be mocked"))),
+      Some("grpc") => return Err(Error::Mock(format!("API {api_id} is a gRPC API, which cannot be mocked"))),
+      _ => SpecFormat::Json,
+    };
+    let spec = openapi::parse(content, format, None)?;
+    routes.add(api_id, &spec);
+  }
+  let server = MockServer::start(routes, api_ids, port.unwrap_or(0), validate.unwrap_or(true)).await?;
+  Ok(mocks.insert(server))
+}
+
+/// Stop the mock server on `port`; `false` if none runs there.
+#[tauri::command]
+pub fn stop_mock_server(mocks: State<'_, MockServers>, port: u16) -> bool {
+  mocks.stop(port)
+}
+
+#[tauri::command]
+pub fn list_mock_servers(mocks: State<'_, MockServers>) -> Vec<MockServerInfo> {
+  mocks.list()
+}
+
+/// The requests a mock server received, oldest first, optionally clearing
+/// its log.
+#[tauri::command]
+pub fn get_mock_server_calls(mocks: State<'_, MockServers>, port: u16, clear: Option<bool>) -> Result<Vec<MockCall>> {
+  mocks.calls(port, clear.unwrap_or(false))
+}
@@ -0,0 +1,22 @@
+//! `#[tauri::command]` entry points exposed to the webview.
+//!
+//! Each submodule is a thin layer over the matching backend module and only
+//! deals with managed state and argument plumbing.
+
+pub mod cookies;
+pub mod curl;
+pub mod flow;
+pub mod graphql;
+pub mod grpc;
+pub mod history;
+pub mod http;
+pub mod import;
+pub mod mock;
+pub mod openapi;
+pub mod proxy;
+pub mod report;
+pub mod runs;
+pub mod sequence;
+pub mod storage;
+pub mod template;
+pub mod transform;
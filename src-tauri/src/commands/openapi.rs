@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tauri::State;
+
+use crate::error::Result;
+use crate::openapi::{self, ApiEndpoint, SpecFormat};
+use crate::storage::Store;
+
+/// What a specification file holds, shown before importing it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenApiPreview {
+  title: Option<String>,
+  host: Option<String>,
+  format: SpecFormat,
+  endpoints: Vec<ApiEndpoint>,
+}
+
+/// Parse a local OpenAPI or Swagger file, resolving refs to files next to it.
+#[tauri::command]
+pub async fn parse_openapi_file(path: String) -> Result<OpenApiPreview> {
+  let path = Path::new(&path);
+  let spec = openapi::load(path)?;
+  Ok(OpenApiPreview {
+    title: spec.title().map(str::to_string),
+    host: openapi::extract_host(&spec),
+    format: SpecFormat::from_path(path),
+    endpoints: openapi::extract_endpoints(&spec),
+  })
+}
+
+/// Import a local specification into the local database, as `uploadSwagger`
+/// does on the server: an `apis` record, its `api_endpoints`, and a
+/// `project_apis` link when `projectId` is given. `name` defaults to the
+/// spec's title and `host` is used when the spec names none. Returns the API
+/// record with `endpointCount`.
+#[tauri::command]
+pub async fn import_openapi_file(
+  store: State<'_, Store>,
+  path: String,
+  name: Option<String>,
+  description: Option<String>,
+  host: Option<String>,
+  project_id: Option<i64>,
+) -> Result<Value> {
+  let path = Path::new(&path);
+  let content = std::fs::read_to_string(path)?;
+  let format = SpecFormat::from_path(path);
+  let spec = openapi::parse(&content, format, Some(path))?;
+  let host = openapi::extract_host(&spec).or(host);
+  let name = name
+    .or_else(|| spec.title().map(str::to_string))
+    .unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+  let endpoints = openapi::extract_endpoints(&spec);
+
+  let api = store_api(
+    &store,
+    json!({
+      "name": name,
+      "description": description,
+      "specFormat": format.as_str(),
+      "specContent": content,
+      "host": host,
+      "projectId": project_id,
+    }),
+    &endpoints,
+  )?;
+  log::debug!("[import_openapi_file] imported {} endpoint(s) as API {}", endpoints.len(), api["id"]);
+  Ok(api)
+}
+
+/// Create the `apis` record from `fields`, its `api_endpoints`, and the
+/// `project_apis` link when `fields` has a `projectId`, in one transaction.
+/// Returns the API record with `endpointCount`.
+pub(super) fn store_api(store: &Store, fields: Value, endpoints: &[ApiEndpoint]) -> Result<Value> {
+  store.transaction(|transaction| {
+    let mut api = transaction.create("apis", &record(fields))?;
+    let api_id = api["id"].as_i64().unwrap_or_default();
+    for endpoint in endpoints {
+      let mut fields = record(serde_json::to_value(endpoint)?);
+      fields.insert("apiId".to_string(), Value::from(api_id));
+      transaction.create("api_endpoints", &fields)?;
+    }
+    if let Some(project_id) = api["projectId"].as_i64() {
+      transaction.create(
+        "project_apis",
+        &record(json!({ "projectId": project_id, "apiId": api_id, "defaultHost": api["host"] })),
+      )?;
+    }
+    api["endpointCount"] = Value::from(endpoints.len());
+    Ok(api)
+  })
+}
+
+fn record(value: Value) -> Map<String, Value> {
+  match value {
+    Value::Object(record) => record,
+    _ => Map::new(),
+  }
+}
@@ -0,0 +1,58 @@
+use serde_json::Value;
+use tauri::State;
+
+use super::curl::known_endpoints;
+use crate::error::Result;
+use crate::import::Import;
+use crate::proxy::{ProxyInfo, ProxyOptions, RecordingProxies, RecordingProxy};
+use crate::storage::Store;
+
+/// Start a recording proxy on localhost; `port` defaults to a free one.
+#[tauri::command]
+pub async fn start_recording_proxy(
+  proxies: State<'_, RecordingProxies>,
+  port: Option<u16>,
+  options: Option<ProxyOptions>,
+) -> Result<ProxyInfo> {
+  let proxy = RecordingProxy::start(port.unwrap_or(0), options.unwrap_or_default()).await?;
+  Ok(proxies.insert(proxy))
+}
+
+/// Stop the proxy on `port`, discarding its recording; `false` if none runs
+/// there.
+#[tauri::command]
+pub fn stop_recording_proxy(proxies: State<'_, RecordingProxies>, port: u16) -> bool {
+  proxies.stop(port)
+}
+
+#[tauri::command]
+pub fn list_recording_proxies(proxies: State<'_, RecordingProxies>) -> Vec<ProxyInfo> {
+  proxies.list()
+}
+
+/// The exchanges recorded so far as HAR entries, optionally clearing them.
+#[tauri::command]
+pub fn get_recorded_exchanges(proxies: State<'_, RecordingProxies>, port: u16, clear: Option<bool>) -> Result<Vec<Value>> {
+  proxies.with(port, |proxy| {
+    let entries = proxy.entries();
+    if clear.unwrap_or(false) {
+      proxy.clear();
+    }
+    Ok(entries)
+  })
+}
+
+/// Convert a proxy's recording into a flow whose requests point at the
+/// matching endpoints of the imported APIs, all of them unless `apiIds` is
+/// given. Like the importers, nothing is saved.
+#[tauri::command]
+pub fn build_recorded_flow(
+  store: State<'_, Store>,
+  proxies: State<'_, RecordingProxies>,
+  port: u16,
+  name: Option<String>,
+  api_ids: Option<Vec<i64>>,
+) -> Result<Import> {
+  let known = known_endpoints(&store, &api_ids.unwrap_or_default())?;
+  proxies.with(port, |proxy| proxy.flow(name, &known))
+}
@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::error::{Error, Result};
+use crate::report::{html, junit, ReportDetails, RunReport};
+
+/// Write a JUnit XML report of a finished run to a file picked in a save
+/// dialog. Returns the path written, or `None` if the dialog was dismissed.
+#[tauri::command]
+pub async fn save_junit_report(app: AppHandle, name: String, report: RunReport) -> Result<Option<String>> {
+  let xml = junit::render(&name, &report);
+  let Some(path) = pick_save_path(&app, &name, "JUnit XML", "xml").await? else {
+    return Ok(None);
+  };
+  std::fs::write(&path, xml)?;
+  log::debug!("[report] wrote JUnit report to {}", path.display());
+  Ok(Some(path.display().to_string()))
+}
+
+/// Write a self-contained HTML report of a finished run, secrets masked, to
+/// a file picked in a save dialog. Returns the path written, or `None` if the
+/// dialog was dismissed.
+#[tauri::command]
+pub async fn export_run_html(app: AppHandle, details: ReportDetails, report: RunReport) -> Result<Option<String>> {
+  let page = html::render(&details, &report);
+  let Some(path) = pick_save_path(&app, &details.name, "HTML", "html").await? else {
+    return Ok(None);
+  };
+  std::fs::write(&path, page)?;
+  log::debug!("[report] wrote HTML report to {}", path.display());
+  Ok(Some(path.display().to_string()))
+}
+
+/// Ask where to save a report, suggesting a file name made from `name`. The
+/// dialog answers through a callback so no runtime worker waits on it.
+async fn pick_save_path(app: &AppHandle, name: &str, filter: &str, extension: &str) -> Result<Option<PathBuf>> {
+  let stem: String = name
+    .chars()
+    .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+    .collect();
+  let stem = if stem.trim_matches('-').is_empty() { "report" } else { stem.trim_matches('-') };
+  let (sender, receiver) = tokio::sync::oneshot::channel();
+  app
+    .dialog()
+    .file()
+    .add_filter(filter, &[extension])
+    .set_file_name(format!("{stem}.{extension}"))
+    .save_file(move |file| {
+      let _ = sender.send(file);
+    });
+  let Some(file) = receiver.await.ok().flatten() else {
+    return Ok(None);
+  };
+  file
+    .into_path()
+    .map(Some)
+    .map_err(|err| Error::InvalidRequest(format!("cannot save to the chosen location: {err}")))
+}
@@ -0,0 +1,10 @@
+use tauri::State;
+
+use crate::runs::RunRegistry;
+
+/// Cancel an active flow or sequence run, aborting its in-flight requests.
+/// Returns `false` when no run with that ID is active.
+#[tauri::command]
+pub fn cancel_run(runs: State<'_, RunRegistry>, run_id: String) -> bool {
+  runs.cancel(&run_id)
+}
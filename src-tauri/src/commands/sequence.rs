@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use super::flow::resolve_environment;
+use crate::cookies::CookieJars;
+use crate::environment::EnvironmentConfig;
+use crate::error::Result;
+use crate::flow::runner::RunPreferences;
+use crate::flow::TestFlow;
+use crate::history::RunEntry;
+use crate::http::HttpClient;
+use crate::runs::RunRegistry;
+use crate::sequence::runner::{SequenceEvent, SequenceRunResult, SequenceRunner};
+use crate::sequence::FlowSequenceConfig;
+use crate::storage::Store;
+
+/// Event carrying a run's [`SequenceEvent`]s to the webview.
+pub const SEQUENCE_EVENT: &str = "sequence-run-event";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SequenceRunEvent<'a> {
+  run_id: &'a str,
+  #[serde(flatten)]
+  event: SequenceEvent,
+}
+
+/// Run a flow sequence natively, loops included.
+///
+/// `flows` holds every flow the sequence refers to, keyed by test flow id.
+/// Progress is emitted as `sequence-run-event` tagged with `runId`, which is
+/// generated when not given and is what `cancel_run` takes. Every flow uses
+/// the cookie jar of `runId`, kept afterwards when the caller supplied the
+/// ID. The run is recorded in the run history under `sequenceId`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_sequence(
+  app: AppHandle,
+  client: State<'_, HttpClient>,
+  jars: State<'_, CookieJars>,
+  runs: State<'_, RunRegistry>,
+  store: State<'_, Store>,
+  sequence: FlowSequenceConfig,
+  sequence_id: Option<i64>,
+  flows: HashMap<i64, TestFlow>,
+  environment: Option<EnvironmentConfig>,
+  sub_environment: Option<String>,
+  preferences: Option<RunPreferences>,
+  run_id: Option<String>,
+) -> Result<SequenceRunResult> {
+  let environment = resolve_environment(environment, sub_environment)?;
+  let (run_id, keep_jar) = match run_id {
+    Some(run_id) => (run_id, true),
+    None => (jars.create(None), false),
+  };
+  log::debug!("[execute_sequence] starting run {run_id} with {} step(s)", sequence.steps.len());
+  let started = chrono::Utc::now();
+
+  let emit = |event: SequenceEvent| {
+    let payload = SequenceRunEvent { run_id: &run_id, event };
+    if let Err(err) = app.emit(SEQUENCE_EVENT, payload) {
+      log::warn!("[execute_sequence] failed to emit progress for run {run_id}: {err}");
+    }
+  };
+  let result = SequenceRunner::new(&client, &sequence, &flows, environment.as_ref())
+    .preferences(preferences.unwrap_or_default())
+    .cookie_jar(jars.get_or_create(&run_id))
+    .cancellation(runs.register(&run_id))
+    .on_event(&emit)
+    .run()
+    .await;
+
+  runs.remove(&run_id);
+  if !keep_jar {
+    jars.remove(&run_id);
+  }
+  let recorded =
+    RunEntry::sequence(&run_id, sequence_id, started, &result).and_then(|entry| store.record_run(&entry));
+  if let Err(err) = recorded {
+    log::warn!("[execute_sequence] failed to record run {run_id} in the history: {err}");
+  }
+  Ok(result)
+}
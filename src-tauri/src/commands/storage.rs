@@ -0,0 +1,35 @@
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::error::Result;
+use crate::storage::Store;
+
+/// List a table's records, optionally only those matching every field of
+/// `filter`, e.g. `{ "projectId": 3 }`.
+#[tauri::command]
+pub fn storage_list(store: State<'_, Store>, table: String, filter: Option<Map<String, Value>>) -> Result<Vec<Value>> {
+  store.list(&table, &filter.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn storage_get(store: State<'_, Store>, table: String, id: i64) -> Result<Value> {
+  store.get(&table, id)
+}
+
+/// Insert a record and return it with its assigned `id` and timestamps.
+#[tauri::command]
+pub fn storage_create(store: State<'_, Store>, table: String, record: Map<String, Value>) -> Result<Value> {
+  store.create(&table, &record)
+}
+
+/// Change some fields of a record and return the updated record.
+#[tauri::command]
+pub fn storage_update(store: State<'_, Store>, table: String, id: i64, changes: Map<String, Value>) -> Result<Value> {
+  store.update(&table, id, &changes)
+}
+
+/// Delete a record, and whatever cascades from it; `false` if there was none.
+#[tauri::command]
+pub fn storage_delete(store: State<'_, Store>, table: String, id: i64) -> Result<bool> {
+  store.delete(&table, id)
+}
@@ -0,0 +1,14 @@
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::template::{self, TemplateContext};
+
+/// Resolve a template string against the given context.
+///
+/// A single unquoted or triple-brace expression keeps its value's type; any
+/// other template comes back as a string.
+#[tauri::command]
+pub fn resolve_template(template: String, context: TemplateContext) -> Result<Value> {
+  log::debug!("[resolve_template] {template}");
+  template::resolve_template(&template, &context)
+}
@@ -0,0 +1,15 @@
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::template::TemplateContext;
+use crate::transform;
+
+/// Evaluate a transformation expression against `data`, e.g. a response body.
+///
+/// `context` supplies the `{{param:...}}`, `{{res:...}}` and `{{proc:...}}`
+/// values the expression may reference.
+#[tauri::command]
+pub fn evaluate_transformation(expression: String, data: Value, context: Option<TemplateContext>) -> Result<Value> {
+  log::debug!("[evaluate_transformation] {expression}");
+  transform::evaluate(&expression, &data, &context.unwrap_or_default())
+}
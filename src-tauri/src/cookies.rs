@@ -0,0 +1,684 @@
+//! Per-run cookie jars following the RFC 6265 storage and retrieval model.
+//!
+//! Each flow or sequence run gets its own [`CookieJar`], looked up by run ID
+//! through [`CookieJars`], so sessions carry across steps of one run without
+//! leaking into others.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::error::{Error, Result};
+
+/// Expiry used for cookies whose Max-Age is zero or negative.
+const EARLIEST_EXPIRY: i64 = i64::MIN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SameSite {
+  Strict,
+  Lax,
+  None,
+}
+
+/// A stored cookie, as described by RFC 6265 section 5.3.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cookie {
+  pub name: String,
+  pub value: String,
+  pub domain: String,
+  pub path: String,
+  /// Only sent to the exact host that set it (no Domain attribute).
+  pub host_only: bool,
+  pub secure: bool,
+  pub http_only: bool,
+  pub same_site: Option<SameSite>,
+  /// Unix timestamp in seconds; `None` for session cookies.
+  pub expires_at: Option<i64>,
+  #[serde(skip)]
+  creation_index: u64,
+}
+
+impl Cookie {
+  fn is_expired(&self, now: i64) -> bool {
+    self.expires_at.is_some_and(|expires_at| expires_at <= now)
+  }
+
+  fn matches(&self, url: &Url, host: &str) -> bool {
+    let domain_ok = if self.host_only {
+      self.domain == host
+    } else {
+      domain_match(host, &self.domain)
+    };
+    domain_ok && path_match(url.path(), &self.path) && (!self.secure || is_secure_origin(url))
+  }
+}
+
+/// Export formats supported by [`CookieJar::export`].
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+  #[default]
+  Json,
+  /// Netscape `cookies.txt`, as read by curl and wget.
+  Netscape,
+}
+
+#[derive(Debug, Default)]
+pub struct CookieJar {
+  cookies: Vec<Cookie>,
+  next_index: u64,
+}
+
+impl CookieJar {
+  /// Store every `Set-Cookie` header received from `url`.
+  pub fn store_response_cookies(&mut self, url: &Url, set_cookies: &[String]) {
+    let now = unix_now();
+    for header in set_cookies {
+      self.store(url, header, now);
+    }
+  }
+
+  /// Build the `Cookie` request header for `url`, if any cookie applies.
+  pub fn cookie_header(&mut self, url: &Url) -> Option<String> {
+    let now = unix_now();
+    self.cookies.retain(|cookie| !cookie.is_expired(now));
+
+    let host = canonical_host(url)?;
+    let mut matched: Vec<&Cookie> = self.cookies.iter().filter(|cookie| cookie.matches(url, &host)).collect();
+    if matched.is_empty() {
+      return None;
+    }
+
+    // Longer paths first, then earlier creation time (section 5.4 step 2).
+    matched.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.creation_index.cmp(&b.creation_index)));
+    let header = matched
+      .iter()
+      .map(|cookie| {
+        if cookie.name.is_empty() {
+          cookie.value.clone()
+        } else {
+          format!("{}={}", cookie.name, cookie.value)
+        }
+      })
+      .collect::<Vec<_>>()
+      .join("; ");
+    Some(header)
+  }
+
+  /// Unexpired cookies, in creation order.
+  pub fn cookies(&self) -> Vec<Cookie> {
+    let now = unix_now();
+    self.cookies.iter().filter(|cookie| !cookie.is_expired(now)).cloned().collect()
+  }
+
+  /// Remove every cookie, or only those belonging to `domain` and its subdomains.
+  pub fn clear(&mut self, domain: Option<&str>) {
+    match domain {
+      Some(domain) => {
+        let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+        self.cookies.retain(|cookie| !domain_match(&cookie.domain, &domain));
+      }
+      None => self.cookies.clear(),
+    }
+  }
+
+  pub fn export(&self, format: ExportFormat) -> Result<String> {
+    let cookies = self.cookies();
+    match format {
+      ExportFormat::Json => Ok(serde_json::to_string_pretty(&cookies)?),
+      ExportFormat::Netscape => {
+        let mut out = String::from("# Netscape HTTP Cookie File\n");
+        for cookie in cookies {
+          let domain = if cookie.host_only {
+            cookie.domain.clone()
+          } else {
+            format!(".{}", cookie.domain)
+          };
+          out.push_str(&format!(
+            "{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            if cookie.http_only { "#HttpOnly_" } else { "" },
+            domain,
+            if cookie.host_only { "FALSE" } else { "TRUE" },
+            cookie.path,
+            if cookie.secure { "TRUE" } else { "FALSE" },
+            cookie.expires_at.unwrap_or(0).max(0),
+            cookie.name,
+            cookie.value
+          ));
+        }
+        Ok(out)
+      }
+    }
+  }
+
+  /// Storage model from RFC 6265 section 5.3.
+  fn store(&mut self, url: &Url, header: &str, now: i64) {
+    let Some(parsed) = parse_set_cookie(header) else {
+      return;
+    };
+    let Some(host) = canonical_host(url) else {
+      return;
+    };
+
+    let expires_at = match (parsed.max_age, parsed.expires) {
+      (Some(max_age), _) if max_age <= 0 => Some(EARLIEST_EXPIRY),
+      (Some(max_age), _) => Some(now.saturating_add(max_age)),
+      (None, Some(expires)) => Some(expires),
+      (None, None) => None,
+    };
+
+    let (domain, host_only) = match parsed.domain {
+      // RFC 6265 section 5.3 step 5, with single-label domains standing in
+      // for public suffixes as no public suffix list is bundled.
+      Some(domain) if !domain.contains('.') => {
+        if domain != host {
+          log::debug!("[cookies] rejected `{}`: domain {domain} is a public suffix", parsed.name);
+          return;
+        }
+        (host.clone(), true)
+      }
+      Some(domain) => {
+        if !domain_match(&host, &domain) {
+          log::debug!("[cookies] rejected `{}`: domain {domain} does not match {host}", parsed.name);
+          return;
+        }
+        (domain, false)
+      }
+      None => (host.clone(), true),
+    };
+
+    // Secure cookies may only be set from secure origins (RFC 6265bis).
+    if parsed.secure && !is_secure_origin(url) {
+      log::debug!("[cookies] rejected `{}`: Secure cookie from insecure origin", parsed.name);
+      return;
+    }
+    // SameSite=None requires Secure (RFC 6265bis).
+    if parsed.same_site == Some(SameSite::None) && !parsed.secure {
+      log::debug!("[cookies] rejected `{}`: SameSite=None without Secure", parsed.name);
+      return;
+    }
+
+    let path = parsed.path.unwrap_or_else(|| default_path(url.path()));
+
+    let existing = self
+      .cookies
+      .iter()
+      .position(|cookie| cookie.name == parsed.name && cookie.domain == domain && cookie.path == path);
+    let creation_index = match existing {
+      Some(index) => self.cookies.remove(index).creation_index,
+      None => {
+        self.next_index += 1;
+        self.next_index
+      }
+    };
+
+    let cookie = Cookie {
+      name: parsed.name,
+      value: parsed.value,
+      domain,
+      path,
+      host_only,
+      secure: parsed.secure,
+      http_only: parsed.http_only,
+      same_site: parsed.same_site,
+      expires_at,
+      creation_index,
+    };
+    // An already-expired cookie only serves to evict the old one.
+    if !cookie.is_expired(now) {
+      self.cookies.push(cookie);
+      self.cookies.sort_by_key(|cookie| cookie.creation_index);
+    }
+  }
+}
+
+pub type SharedCookieJar = Arc<Mutex<CookieJar>>;
+
+/// Lock a shared jar, recovering the data if a previous holder panicked.
+pub fn lock_jar(jar: &SharedCookieJar) -> MutexGuard<'_, CookieJar> {
+  jar.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Cookie jars for all active runs, held as Tauri managed state.
+#[derive(Default)]
+pub struct CookieJars {
+  jars: Mutex<HashMap<String, SharedCookieJar>>,
+  counter: AtomicU64,
+}
+
+impl CookieJars {
+  /// Create a jar for `run_id`, generating an ID when none is given.
+  /// An existing jar with the same ID is replaced by an empty one.
+  pub fn create(&self, run_id: Option<String>) -> String {
+    let run_id = run_id.unwrap_or_else(|| {
+      format!("run-{}-{}", unix_now(), self.counter.fetch_add(1, Ordering::Relaxed) + 1)
+    });
+    self.lock().insert(run_id.clone(), SharedCookieJar::default());
+    run_id
+  }
+
+  pub fn get(&self, run_id: &str) -> Result<SharedCookieJar> {
+    self
+      .lock()
+      .get(run_id)
+      .cloned()
+      .ok_or_else(|| Error::NotFound(format!("cookie jar for run `{run_id}`")))
+  }
+
+  /// Get the jar for `run_id`, creating it on first use.
+  pub fn get_or_create(&self, run_id: &str) -> SharedCookieJar {
+    self.lock().entry(run_id.to_string()).or_default().clone()
+  }
+
+  pub fn remove(&self, run_id: &str) -> bool {
+    self.lock().remove(run_id).is_some()
+  }
+
+  fn lock(&self) -> MutexGuard<'_, HashMap<String, SharedCookieJar>> {
+    self.jars.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+}
+
+struct ParsedCookie {
+  name: String,
+  value: String,
+  expires: Option<i64>,
+  max_age: Option<i64>,
+  domain: Option<String>,
+  path: Option<String>,
+  secure: bool,
+  http_only: bool,
+  same_site: Option<SameSite>,
+}
+
+/// Parse a `Set-Cookie` header value (RFC 6265 section 5.2).
+fn parse_set_cookie(header: &str) -> Option<ParsedCookie> {
+  let (name_value, attributes) = header.split_once(';').unwrap_or((header, ""));
+  let (name, value) = name_value.split_once('=')?;
+  let name = name.trim();
+  if name.is_empty() {
+    return None;
+  }
+  let value = value.trim();
+  let value = value
+    .strip_prefix('"')
+    .and_then(|v| v.strip_suffix('"'))
+    .unwrap_or(value);
+
+  let mut cookie = ParsedCookie {
+    name: name.to_string(),
+    value: value.to_string(),
+    expires: None,
+    max_age: None,
+    domain: None,
+    path: None,
+    secure: false,
+    http_only: false,
+    same_site: None,
+  };
+
+  for attribute in attributes.split(';') {
+    let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+    let value = value.trim();
+    match key.trim().to_ascii_lowercase().as_str() {
+      "expires" => {
+        if let Some(expires) = parse_cookie_date(value) {
+          cookie.expires = Some(expires);
+        }
+      }
+      "max-age" => {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+          // Out-of-range values saturate rather than being dropped.
+          let saturated = if digits.len() < value.len() { i64::MIN } else { i64::MAX };
+          cookie.max_age = Some(value.parse().unwrap_or(saturated));
+        }
+      }
+      "domain" => {
+        let domain = value.trim_start_matches('.');
+        if !domain.is_empty() {
+          cookie.domain = Some(domain.to_ascii_lowercase());
+        }
+      }
+      "path" => {
+        cookie.path = value.starts_with('/').then(|| value.to_string());
+      }
+      "secure" => cookie.secure = true,
+      "httponly" => cookie.http_only = true,
+      "samesite" => {
+        cookie.same_site = match value.to_ascii_lowercase().as_str() {
+          "strict" => Some(SameSite::Strict),
+          "lax" => Some(SameSite::Lax),
+          "none" => Some(SameSite::None),
+          _ => None,
+        };
+      }
+      _ => {}
+    }
+  }
+
+  Some(cookie)
+}
+
+/// Parse a cookie date with the algorithm from RFC 6265 section 5.1.1,
+/// returning a Unix timestamp in seconds.
+fn parse_cookie_date(input: &str) -> Option<i64> {
+  let is_delimiter = |c: char| {
+    matches!(c, '\t' | '\x20'..='\x2f' | '\x3b'..='\x40' | '\x5b'..='\x60' | '\x7b'..='\x7e')
+  };
+
+  let mut time = None;
+  let mut day = None;
+  let mut month = None;
+  let mut year = None;
+
+  for token in input.split(is_delimiter).filter(|token| !token.is_empty()) {
+    if time.is_none() {
+      if let Some(parsed) = parse_time_token(token) {
+        time = Some(parsed);
+        continue;
+      }
+    }
+    if day.is_none() {
+      if let Some(parsed) = leading_digits(token, 1, 2) {
+        day = Some(parsed);
+        continue;
+      }
+    }
+    if month.is_none() {
+      if let Some(parsed) = parse_month(token) {
+        month = Some(parsed);
+        continue;
+      }
+    }
+    if year.is_none() {
+      if let Some(parsed) = leading_digits(token, 2, 4) {
+        year = Some(parsed);
+        continue;
+      }
+    }
+  }
+
+  let (hour, minute, second) = time?;
+  let (day, month, mut year) = (day?, month?, year?);
+  if (70..=99).contains(&year) {
+    year += 1900;
+  } else if year <= 69 {
+    year += 2000;
+  }
+  if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+    return None;
+  }
+  if day > days_in_month(year, month) {
+    return None;
+  }
+
+  let days = days_from_civil(year, month, day);
+  Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+/// `1*2DIGIT ":" 1*2DIGIT ":" 1*2DIGIT ( non-digit *OCTET )`
+fn parse_time_token(token: &str) -> Option<(i64, i64, i64)> {
+  let mut parts = token.splitn(3, ':');
+  let hour = exact_digits(parts.next()?, 1, 2)?;
+  let minute = exact_digits(parts.next()?, 1, 2)?;
+  let second = leading_digits(parts.next()?, 1, 2)?;
+  Some((hour, minute, second))
+}
+
+fn exact_digits(token: &str, min: usize, max: usize) -> Option<i64> {
+  if (min..=max).contains(&token.len()) && token.bytes().all(|b| b.is_ascii_digit()) {
+    token.parse().ok()
+  } else {
+    None
+  }
+}
+
+/// `min*maxDIGIT ( non-digit *OCTET )`
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<i64> {
+  let count = token.bytes().take_while(|b| b.is_ascii_digit()).count();
+  if (min..=max).contains(&count) {
+    token[..count].parse().ok()
+  } else {
+    None
+  }
+}
+
+fn parse_month(token: &str) -> Option<i64> {
+  const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+  let prefix = token.get(..3)?.to_ascii_lowercase();
+  MONTHS.iter().position(|month| *month == prefix).map(|index| index as i64 + 1)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+  match month {
+    2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+    2 => 28,
+    4 | 6 | 9 | 11 => 30,
+    _ => 31,
+  }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let month_index = (month + 9) % 12;
+  let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  era * 146_097 + day_of_era - 719_468
+}
+
+/// Domain matching from RFC 6265 section 5.1.3.
+fn domain_match(host: &str, domain: &str) -> bool {
+  if host == domain {
+    return true;
+  }
+  host.ends_with(domain)
+    && host[..host.len() - domain.len()].ends_with('.')
+    && host.parse::<std::net::IpAddr>().is_err()
+}
+
+/// Default-path algorithm from RFC 6265 section 5.1.4.
+fn default_path(request_path: &str) -> String {
+  if !request_path.starts_with('/') {
+    return "/".to_string();
+  }
+  match request_path.rfind('/') {
+    Some(0) | None => "/".to_string(),
+    Some(index) => request_path[..index].to_string(),
+  }
+}
+
+/// Path matching from RFC 6265 section 5.1.4.
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+  let request_path = if request_path.is_empty() { "/" } else { request_path };
+  request_path == cookie_path
+    || (request_path.starts_with(cookie_path)
+      && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+fn canonical_host(url: &Url) -> Option<String> {
+  url
+    .host_str()
+    .map(|host| host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase())
+}
+
+/// HTTPS, or a loopback host which browsers also treat as potentially trustworthy.
+fn is_secure_origin(url: &Url) -> bool {
+  url.scheme() == "https"
+    || matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"))
+    || url.host_str().is_some_and(|host| host.ends_with(".localhost"))
+}
+
+fn unix_now() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs() as i64)
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn url(value: &str) -> Url {
+    Url::parse(value).unwrap()
+  }
+
+  fn store(jar: &mut CookieJar, at: &str, header: &str) {
+    jar.store_response_cookies(&url(at), &[header.to_string()]);
+  }
+
+  #[test]
+  fn parses_cookie_dates() {
+    assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"), Some(1_445_412_480));
+    assert_eq!(parse_cookie_date("Wed, 21-Oct-2015 07:28:00 GMT"), Some(1_445_412_480));
+    assert_eq!(parse_cookie_date("Wednesday, 21-Oct-15 07:28:00 GMT"), Some(1_445_412_480));
+    assert_eq!(parse_cookie_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+    assert_eq!(parse_cookie_date("Sat, 29 Feb 2025 00:00:00 GMT"), None);
+    assert_eq!(parse_cookie_date("not a date"), None);
+  }
+
+  #[test]
+  fn host_only_cookies_are_not_sent_to_subdomains() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "https://example.com/login", "session=abc; Path=/");
+
+    assert_eq!(jar.cookie_header(&url("https://example.com/api")), Some("session=abc".into()));
+    assert_eq!(jar.cookie_header(&url("https://api.example.com/")), None);
+  }
+
+  #[test]
+  fn domain_cookies_match_subdomains_only() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "https://auth.example.com/", "token=1; Domain=.example.com; Path=/");
+    store(&mut jar, "https://auth.example.com/", "evil=1; Domain=other.com");
+
+    assert_eq!(jar.cookie_header(&url("https://api.example.com/")), Some("token=1".into()));
+    assert_eq!(jar.cookie_header(&url("https://notexample.com/")), None);
+    assert_eq!(jar.cookies().len(), 1);
+  }
+
+  #[test]
+  fn single_label_domains_are_rejected() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "https://example.com/", "a=1; Domain=com; Path=/");
+    store(&mut jar, "https://example.com/", "b=1; Domain=.COM; Path=/");
+    assert!(jar.cookies().is_empty());
+
+    // Unless it is the host itself, whose cookie is then host-only.
+    store(&mut jar, "http://localhost/", "c=1; Domain=localhost; Path=/");
+    assert_eq!(jar.cookie_header(&url("http://localhost/")), Some("c=1".into()));
+    assert!(jar.cookies()[0].host_only);
+  }
+
+  #[test]
+  fn default_path_and_path_matching() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "https://example.com/api/v1/login", "a=1");
+
+    assert_eq!(jar.cookies()[0].path, "/api/v1");
+    assert!(jar.cookie_header(&url("https://example.com/api/v1/users")).is_some());
+    assert!(jar.cookie_header(&url("https://example.com/api/v10")).is_none());
+    assert!(jar.cookie_header(&url("https://example.com/")).is_none());
+  }
+
+  #[test]
+  fn longer_paths_are_sent_first() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "https://example.com/", "a=root; Path=/");
+    store(&mut jar, "https://example.com/", "b=deep; Path=/api");
+
+    assert_eq!(jar.cookie_header(&url("https://example.com/api/x")), Some("b=deep; a=root".into()));
+  }
+
+  #[test]
+  fn max_age_takes_precedence_and_evicts() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "https://example.com/", "a=1; Path=/; Max-Age=3600; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+    assert_eq!(jar.cookies().len(), 1);
+
+    store(&mut jar, "https://example.com/", "a=gone; Path=/; Max-Age=0");
+    assert!(jar.cookies().is_empty());
+
+    store(&mut jar, "https://example.com/", "b=1; Path=/; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+    assert!(jar.cookies().is_empty());
+  }
+
+  #[test]
+  fn replacing_a_cookie_keeps_its_creation_order() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "https://example.com/", "a=1; Path=/");
+    store(&mut jar, "https://example.com/", "b=1; Path=/");
+    store(&mut jar, "https://example.com/", "a=2; Path=/");
+
+    assert_eq!(jar.cookie_header(&url("https://example.com/")), Some("a=2; b=1".into()));
+  }
+
+  #[test]
+  fn secure_cookies_require_secure_origins() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "http://example.com/", "a=1; Secure");
+    assert!(jar.cookies().is_empty());
+
+    store(&mut jar, "https://example.com/", "a=1; Secure; Path=/");
+    assert!(jar.cookie_header(&url("http://example.com/")).is_none());
+    assert!(jar.cookie_header(&url("https://example.com/")).is_some());
+
+    store(&mut jar, "http://localhost:3000/", "dev=1; Secure; Path=/");
+    assert_eq!(jar.cookie_header(&url("http://localhost:3000/")), Some("dev=1".into()));
+  }
+
+  #[test]
+  fn same_site_none_requires_secure() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "https://example.com/", "a=1; SameSite=None");
+    store(&mut jar, "https://example.com/", "b=1; SameSite=None; Secure");
+
+    let cookies = jar.cookies();
+    assert_eq!(cookies.len(), 1);
+    assert_eq!(cookies[0].same_site, Some(SameSite::None));
+  }
+
+  #[test]
+  fn ip_hosts_never_domain_match() {
+    assert!(!domain_match("10.0.0.1", "0.0.1"));
+    assert!(domain_match("10.0.0.1", "10.0.0.1"));
+  }
+
+  #[test]
+  fn clear_by_domain_and_export() {
+    let mut jar = CookieJar::default();
+    store(&mut jar, "https://a.example.com/", "a=1; Path=/; HttpOnly");
+    store(&mut jar, "https://other.com/", "b=2; Path=/; Domain=other.com; Max-Age=60");
+
+    let netscape = jar.export(ExportFormat::Netscape).unwrap();
+    assert!(netscape.contains("#HttpOnly_a.example.com\tFALSE\t/\tFALSE\t0\ta\t1"));
+    assert!(netscape.contains(".other.com\tTRUE\t/\tFALSE\t"));
+
+    jar.clear(Some("example.com"));
+    let remaining = jar.cookies();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].name, "b");
+  }
+
+  #[test]
+  fn jars_are_isolated_per_run() {
+    let jars = CookieJars::default();
+    let first = jars.create(None);
+    let second = jars.create(Some("run-b".into()));
+    assert_ne!(first, second);
+
+    store(&mut lock_jar(&jars.get(&first).unwrap()), "https://example.com/", "a=1");
+    assert!(lock_jar(&jars.get(&second).unwrap()).cookies().is_empty());
+    assert!(jars.remove(&first));
+    assert!(jars.get(&first).is_err());
+  }
+}
@@ -0,0 +1,578 @@
+//! `curl` command lines: parsing one into a step endpoint, and rendering a
+//! resolved request as one.
+//!
+//! Parsing follows POSIX shell quoting (single, double and `$'...'` quotes,
+//! backslash escapes and line continuations), which is what "Copy as cURL"
+//! in browser DevTools and most API docs produce.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::error::{Error, Result};
+use crate::flow::runner::EndpointRequest;
+use crate::flow::{EndpointDefinition, HeaderEntry, StepEndpoint};
+use crate::template::functions::URI_COMPONENT;
+
+/// An API operation a command can be matched against, such as an
+/// `api_endpoints` record.
+#[derive(Debug, Clone)]
+pub struct KnownEndpoint {
+  pub api_id: String,
+  pub definition: EndpointDefinition,
+}
+
+impl KnownEndpoint {
+  pub fn from_record(record: Value) -> Result<Self> {
+    let api_id = match &record["apiId"] {
+      Value::String(api_id) => api_id.clone(),
+      other => other.to_string(),
+    };
+    Ok(Self {
+      api_id,
+      definition: serde_json::from_value(record)?,
+    })
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedCurl {
+  /// `endpoint_id` and `api_id` are empty when no known endpoint matched.
+  pub endpoint: StepEndpoint,
+  pub method: String,
+  pub url: String,
+  /// The URL up to the matched endpoint's path, usable as the API host.
+  pub host: String,
+  /// Options and data that could not be carried over.
+  pub warnings: Vec<String>,
+}
+
+/// What an option does with its argument.
+#[derive(Debug, Clone, Copy)]
+enum Arg {
+  Method,
+  Header,
+  Data,
+  DataUrlencode,
+  Json,
+  User,
+  Cookie,
+  Form,
+  UserAgent,
+  Referer,
+  Url,
+  Ignored,
+  Unsupported,
+}
+
+/// Options that take an argument: long name, short name and effect. Any
+/// other option is taken to be a flag.
+const OPTIONS: &[(&str, Option<char>, Arg)] = &[
+  ("request", Some('X'), Arg::Method),
+  ("header", Some('H'), Arg::Header),
+  ("data", Some('d'), Arg::Data),
+  ("data-ascii", None, Arg::Data),
+  ("data-binary", None, Arg::Data),
+  ("data-raw", None, Arg::Data),
+  ("data-urlencode", None, Arg::DataUrlencode),
+  ("json", None, Arg::Json),
+  ("user", Some('u'), Arg::User),
+  ("cookie", Some('b'), Arg::Cookie),
+  ("form", Some('F'), Arg::Form),
+  ("form-string", None, Arg::Form),
+  ("user-agent", Some('A'), Arg::UserAgent),
+  ("referer", Some('e'), Arg::Referer),
+  ("url", None, Arg::Url),
+  ("cacert", None, Arg::Ignored),
+  ("cert", Some('E'), Arg::Ignored),
+  ("connect-timeout", None, Arg::Ignored),
+  ("cookie-jar", Some('c'), Arg::Ignored),
+  ("key", None, Arg::Ignored),
+  ("max-redirs", None, Arg::Ignored),
+  ("max-time", Some('m'), Arg::Ignored),
+  ("output", Some('o'), Arg::Ignored),
+  ("proxy", Some('x'), Arg::Ignored),
+  ("proxy-user", Some('U'), Arg::Ignored),
+  ("resolve", None, Arg::Ignored),
+  ("retry", None, Arg::Ignored),
+  ("write-out", Some('w'), Arg::Ignored),
+  ("config", Some('K'), Arg::Unsupported),
+  ("range", Some('r'), Arg::Unsupported),
+  ("upload-file", Some('T'), Arg::Unsupported),
+];
+
+#[derive(Default)]
+struct Command {
+  method: Option<String>,
+  urls: Vec<String>,
+  headers: Vec<(String, String)>,
+  data: Vec<String>,
+  get: bool,
+  warnings: Vec<String>,
+}
+
+impl Command {
+  fn apply(&mut self, option: &str, arg: Arg, value: String) {
+    match arg {
+      Arg::Method => self.method = Some(value.to_uppercase()),
+      Arg::Header => match value.split_once(':') {
+        Some((name, value)) => self.headers.push((name.trim().to_string(), value.trim().to_string())),
+        // `-H 'Name;'` sends an empty header.
+        None => match value.strip_suffix(';') {
+          Some(name) => self.headers.push((name.trim().to_string(), String::new())),
+          None => self.warn(format!("ignored header `{value}` without a value")),
+        },
+      },
+      Arg::Data if value.starts_with('@') && option != "data-raw" => {
+        self.warn(format!("`--{option}` reads the body from `{}`; add it by hand", &value[1..]));
+      }
+      Arg::Data => self.data.push(value),
+      Arg::DataUrlencode => {
+        // `content`, `=content` or `name=content`; only the content is encoded.
+        let (name, content) = value.split_once('=').unwrap_or(("", &value));
+        let content = utf8_percent_encode(content, URI_COMPONENT);
+        self.data.push(if name.is_empty() { content.to_string() } else { format!("{name}={content}") });
+      }
+      Arg::Json => {
+        self.data.push(value);
+        self.default_header("Content-Type", "application/json");
+        self.default_header("Accept", "application/json");
+      }
+      Arg::User => {
+        let credentials = if value.contains(':') { value } else { format!("{value}:") };
+        self.headers.push(("Authorization".to_string(), format!("Basic {}", STANDARD.encode(credentials))));
+      }
+      Arg::Cookie if value.contains('=') => match self.headers.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case("cookie")) {
+        Some((_, cookies)) => {
+          cookies.push_str("; ");
+          cookies.push_str(&value);
+        }
+        None => self.headers.push(("Cookie".to_string(), value)),
+      },
+      Arg::Cookie => self.warn(format!("`--cookie` reads cookies from `{value}`; add them by hand")),
+      Arg::Form => {
+        let message = "multipart form fields (`-F`) are not supported; flows only send JSON bodies".to_string();
+        if !self.warnings.contains(&message) {
+          self.warn(message);
+        }
+      }
+      Arg::UserAgent => self.headers.push(("User-Agent".to_string(), value)),
+      Arg::Referer => {
+        let referer = value.strip_suffix(";auto").unwrap_or(&value).to_string();
+        self.headers.push(("Referer".to_string(), referer));
+      }
+      Arg::Url => self.urls.push(value),
+      Arg::Ignored => {}
+      Arg::Unsupported => self.warn(format!("`--{option}` is not supported and was ignored")),
+    }
+  }
+
+  fn default_header(&mut self, name: &str, value: &str) {
+    if !self.headers.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(name)) {
+      self.headers.push((name.to_string(), value.to_string()));
+    }
+  }
+
+  fn warn(&mut self, message: String) {
+    self.warnings.push(message);
+  }
+}
+
+/// Parse a `curl` command line into a step endpoint, matched against
+/// `known` by method and path.
+pub fn parse(command_line: &str, known: &[KnownEndpoint]) -> Result<ParsedCurl> {
+  let mut tokens = tokenize(command_line)?.into_iter();
+  let program = tokens.next().unwrap_or_default();
+  if !matches!(program.rsplit(['/', '\\']).next(), Some("curl" | "curl.exe")) {
+    return Err(Error::Import("not a curl command".to_string()));
+  }
+
+  let mut command = Command::default();
+  let mut positional_only = false;
+  while let Some(token) = tokens.next() {
+    if positional_only || token == "-" || !token.starts_with('-') {
+      command.urls.push(token);
+    } else if token == "--" {
+      positional_only = true;
+    } else if let Some(long) = token.strip_prefix("--") {
+      match OPTIONS.iter().find(|(name, _, _)| *name == long) {
+        Some(&(name, _, arg)) => {
+          let value = tokens.next().ok_or_else(|| missing_argument(&token))?;
+          command.apply(name, arg, value);
+        }
+        None => match long {
+          "get" => command.get = true,
+          "head" => command.method = Some("HEAD".to_string()),
+          _ => {}
+        },
+      }
+    } else {
+      // Short options cluster (`-sSL`), and the last one may carry its
+      // argument attached (`-XPOST`).
+      for (index, short) in token.char_indices().skip(1) {
+        match OPTIONS.iter().find(|(_, option, _)| *option == Some(short)) {
+          Some(&(name, _, arg)) => {
+            let attached = &token[index + short.len_utf8()..];
+            let value = match attached.is_empty() {
+              true => tokens.next().ok_or_else(|| missing_argument(&token))?,
+              false => attached.to_string(),
+            };
+            command.apply(name, arg, value);
+            break;
+          }
+          None => match short {
+            'G' => command.get = true,
+            'I' => command.method = Some("HEAD".to_string()),
+            _ => {}
+          },
+        }
+      }
+    }
+  }
+
+  let mut urls = std::mem::take(&mut command.urls).into_iter();
+  let raw_url = urls.next().ok_or_else(|| Error::Import("the curl command has no URL".to_string()))?;
+  for extra in urls {
+    command.warn(format!("ignored extra argument `{extra}`"));
+  }
+  // curl assumes `http://` when the scheme is left out; `localhost:8080/x`
+  // would otherwise parse with `localhost` as its scheme.
+  let url = Url::parse(&raw_url)
+    .ok()
+    .filter(|url| matches!(url.scheme(), "http" | "https"))
+    .or_else(|| Url::parse(&format!("http://{raw_url}")).ok())
+    .ok_or_else(|| Error::Import(format!("invalid URL `{raw_url}`")))?;
+
+  let data = (!command.data.is_empty()).then(|| command.data.join("&"));
+  let method = command.method.clone().unwrap_or_else(|| match (&data, command.get) {
+    (Some(_), false) => "POST".to_string(),
+    _ => "GET".to_string(),
+  });
+
+  let mut query: Vec<(String, String)> = url.query_pairs().map(|(key, value)| (key.into_owned(), value.into_owned())).collect();
+  let mut body = None;
+  match data {
+    Some(data) if command.get => {
+      query.extend(url::form_urlencoded::parse(data.as_bytes()).map(|(key, value)| (key.into_owned(), value.into_owned())));
+    }
+    Some(data) => match serde_json::from_str::<Value>(&data) {
+      Ok(json) => {
+        // curl labels `-d` data as a form unless told otherwise.
+        let form = "application/x-www-form-urlencoded";
+        command.headers.retain(|(name, value)| !(name.eq_ignore_ascii_case("content-type") && value == form));
+        command.default_header("Content-Type", "application/json");
+        body = Some(json);
+      }
+      Err(_) => command.warn("the body is not JSON and was dropped; flows only send JSON bodies".to_string()),
+    },
+    None => {}
+  }
+
+  let mut query_params = Map::new();
+  for (key, value) in query {
+    match query_params.get_mut(&key) {
+      Some(Value::Array(values)) => values.push(Value::String(value)),
+      Some(existing) => *existing = Value::Array(vec![existing.take(), Value::String(value)]),
+      None => {
+        query_params.insert(key, Value::String(value));
+      }
+    }
+  }
+
+  let raw_segments: Vec<&str> = url.path().split('/').filter(|segment| !segment.is_empty()).collect();
+  let segments: Vec<String> = raw_segments
+    .iter()
+    .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+    .collect();
+  let origin = url.origin().ascii_serialization();
+  let (endpoint_id, api_id, path_params, host) = match match_endpoint(known, &method, &segments) {
+    Some((endpoint, path_params, prefix)) => {
+      let host = std::iter::once(origin.as_str()).chain(raw_segments[..prefix].iter().copied()).collect::<Vec<_>>();
+      (endpoint.definition.id.clone(), endpoint.api_id.clone(), path_params, host.join("/"))
+    }
+    None => {
+      command.warn(format!("no known endpoint matches {method} {}", url.path()));
+      (String::new(), String::new(), Map::new(), origin)
+    }
+  };
+
+  let headers = command
+    .headers
+    .into_iter()
+    .map(|(name, value)| HeaderEntry { name, value, enabled: true })
+    .collect();
+  Ok(ParsedCurl {
+    endpoint: StepEndpoint {
+      endpoint_id,
+      api_id,
+      path_params,
+      query_params,
+      body,
+      headers,
+      transformations: Vec::new(),
+      assertions: Vec::new(),
+      skip_default_status_check: false,
+      graphql: None,
+      websocket: None,
+      event_stream: None,
+      grpc: None,
+    },
+    method,
+    url: url.to_string(),
+    host,
+    warnings: command.warnings,
+  })
+}
+
+fn missing_argument(option: &str) -> Error {
+  Error::Import(format!("`{option}` needs an argument"))
+}
+
+/// The known endpoint whose path template matches the end of the URL path,
+/// with its path params and the number of leading segments that belong to
+/// the host. The template with the most literal segments wins.
+pub(crate) fn match_endpoint<'k>(
+  known: &'k [KnownEndpoint],
+  method: &str,
+  segments: &[String],
+) -> Option<(&'k KnownEndpoint, Map<String, Value>, usize)> {
+  let mut best: Option<(&KnownEndpoint, Map<String, Value>, usize, usize)> = None;
+  for endpoint in known.iter().filter(|endpoint| endpoint.definition.method.eq_ignore_ascii_case(method)) {
+    let template: Vec<&str> = endpoint.definition.path.split('/').filter(|segment| !segment.is_empty()).collect();
+    let Some(prefix) = segments.len().checked_sub(template.len()) else {
+      continue;
+    };
+    let mut path_params = Map::new();
+    let mut literals = 0;
+    let matched = template.iter().zip(&segments[prefix..]).all(|(expected, actual)| {
+      match expected.strip_prefix('{').and_then(|name| name.strip_suffix('}')) {
+        Some(name) => {
+          path_params.insert(name.to_string(), Value::String(actual.clone()));
+          true
+        }
+        None => {
+          literals += 1;
+          expected == actual
+        }
+      }
+    });
+    if matched && best.as_ref().map_or(true, |(_, _, _, best_literals)| literals > *best_literals) {
+      best = Some((endpoint, path_params, prefix, literals));
+    }
+  }
+  best.map(|(endpoint, path_params, prefix, _)| (endpoint, path_params, prefix))
+}
+
+/// Split a command line into words as a POSIX shell would.
+fn tokenize(command: &str) -> Result<Vec<String>> {
+  let unterminated = || Error::Import("unterminated quote in the curl command".to_string());
+  let mut tokens = Vec::new();
+  // `Some` once a word has started, so `''` still yields an empty word.
+  let mut word: Option<String> = None;
+  let mut chars = command.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some('\n') | None => {}
+        Some('\r') if chars.peek() == Some(&'\n') => {
+          chars.next();
+        }
+        Some(escaped) => word.get_or_insert_with(String::new).push(escaped),
+      },
+      '\'' => {
+        let word = word.get_or_insert_with(String::new);
+        loop {
+          match chars.next().ok_or_else(unterminated)? {
+            '\'' => break,
+            c => word.push(c),
+          }
+        }
+      }
+      '"' => {
+        let word = word.get_or_insert_with(String::new);
+        loop {
+          match chars.next().ok_or_else(unterminated)? {
+            '"' => break,
+            '\\' => match chars.next().ok_or_else(unterminated)? {
+              '\n' => {}
+              c @ ('"' | '\\' | '$' | '`') => word.push(c),
+              c => {
+                word.push('\\');
+                word.push(c);
+              }
+            },
+            c => word.push(c),
+          }
+        }
+      }
+      '$' if chars.peek() == Some(&'\'') => {
+        chars.next();
+        let word = word.get_or_insert_with(String::new);
+        loop {
+          match chars.next().ok_or_else(unterminated)? {
+            '\'' => break,
+            '\\' => match chars.next().ok_or_else(unterminated)? {
+              'n' => word.push('\n'),
+              't' => word.push('\t'),
+              'r' => word.push('\r'),
+              c => word.push(c),
+            },
+            c => word.push(c),
+          }
+        }
+      }
+      c if c.is_whitespace() => tokens.extend(word.take()),
+      c => word.get_or_insert_with(String::new).push(c),
+    }
+  }
+  tokens.extend(word);
+  Ok(tokens)
+}
+
+/// Render a request as a `curl` command for a POSIX shell, one option per
+/// line. Headers are sorted by name so the output is stable.
+pub fn render(request: &EndpointRequest) -> String {
+  // With data, curl would send a POST unless told the method.
+  let method = match (request.method.as_str(), &request.body) {
+    ("GET", None) | ("POST", Some(_)) => String::new(),
+    ("HEAD", None) => " --head".to_string(),
+    (method, _) => format!(" -X {method}"),
+  };
+  let mut parts = vec![format!("curl{method} {}", quote(&request.url))];
+
+  let mut headers: Vec<(&String, &String)> = request.headers.iter().collect();
+  headers.sort();
+  for (name, value) in headers {
+    parts.push(format!("-H {}", quote(&format!("{name}: {value}"))));
+  }
+  if let Some(body) = &request.body {
+    // The runner sends every body as JSON, strings included.
+    parts.push(format!("--data-raw {}", quote(&body.to_string())));
+  }
+  parts.join(" \\\n  ")
+}
+
+fn quote(text: &str) -> String {
+  format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use std::collections::HashMap;
+
+  fn known() -> Vec<KnownEndpoint> {
+    [("1", "GET", "/users/{id}"), ("2", "GET", "/users/me"), ("3", "POST", "/users/{id}/orders")]
+      .into_iter()
+      .map(|(id, method, path)| KnownEndpoint {
+        api_id: "9".to_string(),
+        definition: serde_json::from_value(json!({ "id": id, "method": method, "path": path })).unwrap(),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn parses_devtools_commands() {
+    let command = r#"curl 'https://shop.test/api/v2/users/42%20b/orders?tag=a&tag=b' \
+  -H 'accept: application/json' \
+  -H "x-note: it's \"quoted\"" \
+  -b 'sid=1' --cookie theme=dark \
+  -u ann:pw -sSL --compressed \
+  --data-raw $'{"note":"a\'b"}'"#;
+    let parsed = parse(command, &known()).unwrap();
+    assert_eq!(parsed.method, "POST");
+    assert_eq!(parsed.host, "https://shop.test/api/v2");
+    let endpoint = &parsed.endpoint;
+    assert_eq!((endpoint.endpoint_id.as_str(), endpoint.api_id.as_str()), ("3", "9"));
+    assert_eq!(endpoint.path_params["id"], json!("42 b"));
+    assert_eq!(endpoint.query_params["tag"], json!(["a", "b"]));
+    assert_eq!(endpoint.body, Some(json!({ "note": "a'b" })));
+    let headers: Vec<(&str, &str)> = endpoint.headers.iter().map(|h| (h.name.as_str(), h.value.as_str())).collect();
+    assert_eq!(
+      headers,
+      [
+        ("accept", "application/json"),
+        ("x-note", "it's \"quoted\""),
+        ("Cookie", "sid=1; theme=dark"),
+        ("Authorization", "Basic YW5uOnB3"),
+        ("Content-Type", "application/json"),
+      ]
+    );
+    assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+    let me = parse("curl -XGET http://shop.test/users/me", &known()).unwrap();
+    assert_eq!(me.endpoint.endpoint_id, "2");
+    assert!(me.endpoint.path_params.is_empty());
+  }
+
+  #[test]
+  fn handles_get_data_forms_and_unknown_paths() {
+    let parsed = parse(
+      "curl -G shop.test/search --data-urlencode 'q=a b&c' -d page=2 -F file=@a.png -F x=1 -T up.bin",
+      &known(),
+    )
+    .unwrap();
+    assert_eq!(parsed.method, "GET");
+    assert_eq!(parsed.url, "http://shop.test/search");
+    assert_eq!(parsed.endpoint.query_params, json!({ "q": "a b&c", "page": "2" }).as_object().unwrap().clone());
+    assert!(parsed.endpoint.endpoint_id.is_empty());
+    assert_eq!(parsed.host, "http://shop.test");
+    assert_eq!(parsed.warnings.len(), 3, "{:?}", parsed.warnings);
+
+    let form = parse("curl https://shop.test/users/1/orders -d 'a=1'", &known()).unwrap();
+    assert!(form.endpoint.body.is_none());
+    assert!(form.warnings[0].contains("not JSON"));
+
+    assert!(parse("wget https://shop.test", &known()).is_err());
+    assert!(parse("curl 'https://shop.test", &known()).is_err());
+    assert!(parse("curl -H", &known()).is_err());
+  }
+
+  #[test]
+  fn renders_requests_that_parse_back() {
+    let request = EndpointRequest {
+      url: "https://shop.test/users/1/orders?q=it%27s".to_string(),
+      method: "POST".to_string(),
+      headers: HashMap::from([
+        ("X-Note".to_string(), "it's".to_string()),
+        ("Content-Type".to_string(), "application/json".to_string()),
+      ]),
+      body: Some(json!({ "note": "it's \"here\"" })),
+    };
+    let rendered = render(&request);
+    assert!(rendered.starts_with("curl 'https://shop.test/users/1/orders?q=it%27s' \\\n  -H 'Content-Type: application/json'"));
+
+    let parsed = parse(&rendered, &known()).unwrap();
+    assert_eq!(parsed.method, "POST");
+    assert_eq!(parsed.endpoint.endpoint_id, "3");
+    assert_eq!(parsed.endpoint.query_params["q"], json!("it's"));
+    assert_eq!(parsed.endpoint.body, request.body);
+    assert_eq!(parsed.endpoint.headers.len(), 2);
+
+    let delete = EndpointRequest {
+      method: "DELETE".to_string(),
+      headers: HashMap::new(),
+      body: None,
+      ..request
+    };
+    assert_eq!(render(&delete), "curl -X DELETE 'https://shop.test/users/1/orders?q=it%27s'");
+
+    for method in ["GET", "HEAD"] {
+      let with_body = EndpointRequest {
+        method: method.to_string(),
+        body: Some(json!({ "page": 2 })),
+        ..delete.clone()
+      };
+      let rendered = render(&with_body);
+      assert!(rendered.starts_with(&format!("curl -X {method} ")), "{rendered}");
+      let parsed = parse(&rendered, &known()).unwrap();
+      assert_eq!(parsed.method, method);
+      assert_eq!(parsed.endpoint.body, with_body.body);
+    }
+  }
+}
@@ -0,0 +1,142 @@
+//! Environment configs (`environments.config`) and their resolution into the
+//! concrete variables and API hosts a run uses.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+
+/// Mirrors `EnvironmentConfig` in `src/lib/types/environment.ts`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct EnvironmentConfig {
+  #[serde(default, rename = "type")]
+  pub kind: Option<String>,
+  #[serde(default)]
+  pub environments: HashMap<String, SubEnvironment>,
+  #[serde(default)]
+  pub variable_definitions: HashMap<String, VariableDefinition>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SubEnvironment {
+  #[serde(default)]
+  pub name: String,
+  #[serde(default)]
+  pub variables: Map<String, Value>,
+  /// API ID -> host URL.
+  #[serde(default)]
+  pub api_hosts: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VariableDefinition {
+  #[serde(default, rename = "type")]
+  pub kind: Option<String>,
+  #[serde(default)]
+  pub required: bool,
+  #[serde(default)]
+  pub default_value: Option<Value>,
+}
+
+/// The variables and host overrides of one sub-environment.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedEnvironment {
+  pub sub_environment: String,
+  pub variables: Map<String, Value>,
+  pub api_hosts: HashMap<String, String>,
+}
+
+impl EnvironmentConfig {
+  /// Parse either a bare config or a whole `Environment` row with a `config` field.
+  pub fn from_json(text: &str) -> Result<Self> {
+    let mut value: Value = serde_json::from_str(text)?;
+    if let Some(config) = value.get_mut("config").map(Value::take) {
+      value = config;
+    }
+    Ok(serde_json::from_value(value)?)
+  }
+
+  /// Resolve a sub-environment: definition defaults first, then the
+  /// sub-environment's own values on top.
+  pub fn resolve(&self, sub_environment: &str) -> Result<ResolvedEnvironment> {
+    let Some(sub) = self.environments.get(sub_environment) else {
+      let mut available: Vec<&str> = self.environments.keys().map(String::as_str).collect();
+      available.sort_unstable();
+      return Err(Error::Environment(format!(
+        "sub-environment `{sub_environment}` not found (available: {})",
+        available.join(", ")
+      )));
+    };
+
+    let mut variables = Map::new();
+    for (name, definition) in &self.variable_definitions {
+      match (&definition.default_value, sub.variables.contains_key(name)) {
+        (Some(default), false) => {
+          variables.insert(name.clone(), default.clone());
+        }
+        (None, false) if definition.required => {
+          return Err(Error::Environment(format!(
+            "required variable `{name}` has no value in sub-environment `{sub_environment}` and no default"
+          )));
+        }
+        _ => {}
+      }
+    }
+    variables.extend(sub.variables.clone());
+
+    Ok(ResolvedEnvironment {
+      sub_environment: sub_environment.to_string(),
+      variables,
+      api_hosts: sub.api_hosts.clone(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn config() -> EnvironmentConfig {
+    EnvironmentConfig::from_json(
+      &json!({
+        "config": {
+          "type": "environment_set",
+          "environments": {
+            "dev": { "name": "dev", "variables": { "user": "alice" }, "api_hosts": { "1": "http://dev.local" } },
+            "sit": { "name": "sit", "variables": {}, "api_hosts": {} }
+          },
+          "variable_definitions": {
+            "user": { "type": "string", "required": true, "default_value": null },
+            "retries": { "type": "number", "required": false, "default_value": 3 }
+          }
+        }
+      })
+      .to_string(),
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn sub_environment_values_override_defaults() {
+    let env = config().resolve("dev").unwrap();
+    assert_eq!(env.variables["user"], json!("alice"));
+    assert_eq!(env.variables["retries"], json!(3));
+    assert_eq!(env.api_hosts["1"], "http://dev.local");
+  }
+
+  #[test]
+  fn missing_required_variable_is_an_error() {
+    let err = config().resolve("sit").unwrap_err();
+    assert!(err.to_string().contains("`user`"));
+  }
+
+  #[test]
+  fn unknown_sub_environment_lists_the_available_ones() {
+    let err = config().resolve("prod").unwrap_err();
+    assert!(err.to_string().contains("available: dev, sit"));
+  }
+}
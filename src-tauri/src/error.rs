@@ -0,0 +1,85 @@
+use serde::{Serialize, Serializer};
+
+/// Errors returned by the native backend.
+///
+/// Commands hand these back to the webview as plain strings so the frontend
+/// can surface them in flow logs without knowing the variant.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  #[error("invalid request: {0}")]
+  InvalidRequest(String),
+
+  #[error("request timed out after {0} ms")]
+  Timeout(u64),
+
+  #[error("run cancelled")]
+  Cancelled,
+
+  #[error("too many redirects (limit {0})")]
+  TooManyRedirects(usize),
+
+  #[error("not found: {0}")]
+  NotFound(String),
+
+  #[error("connection failed: {0}")]
+  Connect(String),
+
+  #[error("TLS error: {0}")]
+  Tls(String),
+
+  #[error("invalid flow: {0}")]
+  InvalidFlow(String),
+
+  #[error("environment error: {0}")]
+  Environment(String),
+
+  #[error("template error: {0}")]
+  Template(String),
+
+  #[error("transformation error: {0}")]
+  Transform(String),
+
+  #[error("sequence error: {0}")]
+  Sequence(String),
+
+  #[error("invalid OpenAPI specification: {0}")]
+  OpenApi(String),
+
+  #[error("invalid GraphQL schema: {0}")]
+  GraphQl(String),
+
+  #[error("gRPC error: {0}")]
+  Grpc(String),
+
+  #[error("cannot import: {0}")]
+  Import(String),
+
+  #[error("mock server error: {0}")]
+  Mock(String),
+
+  #[error("proxy error: {0}")]
+  Proxy(String),
+
+  #[error("WebSocket error: {0}")]
+  WebSocket(String),
+
+  #[error("I/O error: {0}")]
+  Io(#[from] std::io::Error),
+
+  #[error("HTTP error: {0}")]
+  Http(#[from] hyper::Error),
+
+  #[error("JSON error: {0}")]
+  Json(#[from] serde_json::Error),
+
+  #[error("storage error: {0}")]
+  Storage(#[from] rusqlite::Error),
+}
+
+impl Serialize for Error {
+  fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.to_string())
+  }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
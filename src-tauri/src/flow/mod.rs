@@ -0,0 +1,337 @@
+//! Test flow documents as stored in `test_flows.flow_json`.
+//!
+//! The shapes mirror `TestFlowData` in `src/lib/components/test-flows/types.ts`:
+//! a `TestFlowJson` plus the `endpoints` definitions it references, which is
+//! what `get_test_flow` hands to the runner. Field names keep the mixed
+//! snake/camel casing of the stored JSON.
+
+pub mod runner;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::Result;
+
+/// A flow together with the endpoint definitions its steps point at.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TestFlow {
+  #[serde(default, deserialize_with = "null_default")]
+  pub settings: FlowSettings,
+  #[serde(default, deserialize_with = "null_default")]
+  pub parameters: Vec<FlowParameter>,
+  #[serde(default, deserialize_with = "null_default")]
+  pub outputs: Vec<FlowOutput>,
+  #[serde(default, deserialize_with = "null_default")]
+  pub steps: Vec<FlowStep>,
+  #[serde(default, deserialize_with = "null_default")]
+  pub endpoints: Vec<EndpointDefinition>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FlowSettings {
+  /// API ID -> host used when the environment does not override it.
+  #[serde(default, deserialize_with = "null_default")]
+  pub api_hosts: HashMap<String, ApiHostInfo>,
+  #[serde(default)]
+  pub environment: Option<EnvironmentSelection>,
+  #[serde(default, rename = "linkedEnvironment")]
+  pub linked_environment: Option<EnvironmentMapping>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApiHostInfo {
+  pub url: String,
+  #[serde(default)]
+  pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentSelection {
+  #[serde(default)]
+  pub environment_id: Option<i64>,
+  #[serde(default)]
+  pub sub_environment: Option<String>,
+}
+
+/// Flow parameter name -> environment variable name.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentMapping {
+  #[serde(default)]
+  pub environment_id: Option<i64>,
+  #[serde(default)]
+  pub environment_name: Option<String>,
+  #[serde(default)]
+  pub parameter_mappings: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowParameter {
+  pub name: String,
+  #[serde(default, rename = "type")]
+  pub kind: Option<String>,
+  #[serde(default)]
+  pub value: Option<Value>,
+  #[serde(default)]
+  pub default_value: Option<Value>,
+  #[serde(default)]
+  pub required: bool,
+  #[serde(default)]
+  pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowOutput {
+  pub name: String,
+  /// Template expression or hardcoded value.
+  #[serde(default)]
+  pub value: String,
+  #[serde(default)]
+  pub is_template: bool,
+  #[serde(default, rename = "type")]
+  pub kind: Option<String>,
+  #[serde(default)]
+  pub cast_to_type: bool,
+  #[serde(default)]
+  pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlowStep {
+  pub step_id: String,
+  #[serde(default)]
+  pub label: String,
+  #[serde(default, deserialize_with = "null_default")]
+  pub endpoints: Vec<StepEndpoint>,
+  #[serde(default, rename = "clearCookiesBeforeExecution")]
+  pub clear_cookies_before_execution: bool,
+}
+
+/// One request within a step.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StepEndpoint {
+  #[serde(deserialize_with = "id_string")]
+  pub endpoint_id: String,
+  #[serde(default, deserialize_with = "id_string")]
+  pub api_id: String,
+  #[serde(default, rename = "pathParams", deserialize_with = "null_default")]
+  pub path_params: Map<String, Value>,
+  /// Values are strings, or arrays of strings for multi-valued parameters.
+  #[serde(default, rename = "queryParams", deserialize_with = "null_default")]
+  pub query_params: Map<String, Value>,
+  #[serde(default)]
+  pub body: Option<Value>,
+  #[serde(default, deserialize_with = "null_default")]
+  pub headers: Vec<HeaderEntry>,
+  #[serde(default, deserialize_with = "null_default")]
+  pub transformations: Vec<Transformation>,
+  #[serde(default, deserialize_with = "null_default")]
+  pub assertions: Vec<Assertion>,
+  #[serde(default, rename = "skipDefaultStatusCheck")]
+  pub skip_default_status_check: bool,
+  /// Send a GraphQL operation as the body instead of `body`.
+  #[serde(default)]
+  pub graphql: Option<GraphQlRequest>,
+  /// Open a WebSocket to the endpoint's URL and play this script instead of
+  /// sending a request.
+  #[serde(default)]
+  pub websocket: Option<WebSocketScript>,
+  /// Read a `text/event-stream` response event by event; the events become
+  /// the response body.
+  #[serde(default, rename = "eventStream")]
+  pub event_stream: Option<EventStreamOptions>,
+  /// Call the endpoint as a gRPC method, `body` being the request message
+  /// in protobuf's JSON mapping.
+  #[serde(default)]
+  pub grpc: Option<GrpcCall>,
+}
+
+/// A GraphQL operation. `query` and `variables` go through the template
+/// engine like a body does.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQlRequest {
+  pub query: String,
+  #[serde(default)]
+  pub variables: Option<Value>,
+  #[serde(default)]
+  pub operation_name: Option<String>,
+}
+
+/// A gRPC call of the method the endpoint's path, `/package.Service/Method`,
+/// names.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrpcCall {
+  /// The descriptors of the API, its `specContent`.
+  pub descriptor_set: String,
+  /// Of a server stream, which is also read until the server ends it.
+  #[serde(default)]
+  pub max_messages: Option<usize>,
+  /// How long to read a server stream. Defaults to the run's request
+  /// timeout.
+  #[serde(default)]
+  pub timeout_ms: Option<u64>,
+}
+
+/// When to stop reading an event stream. Reading also stops when the server
+/// ends the stream.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventStreamOptions {
+  #[serde(default)]
+  pub max_events: Option<usize>,
+  /// An expression such as `$.event == "done"`; the first event it is
+  /// truthy for is the last one read.
+  #[serde(default)]
+  pub until: Option<String>,
+  /// Defaults to the run's request timeout.
+  #[serde(default)]
+  pub timeout_ms: Option<u64>,
+}
+
+/// What a WebSocket step does once connected, in order. The messages its
+/// `expect` actions matched are the step's response.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketScript {
+  #[serde(default, deserialize_with = "null_default")]
+  pub actions: Vec<WebSocketAction>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WebSocketAction {
+  /// Send a message, resolved through the template engine; a string is
+  /// sent as it is, anything else as JSON text.
+  Send { message: Value },
+  /// Wait for a message the `predicate` expression is truthy for, such as
+  /// `$.type == "ack"`. `timeout_ms` defaults to the run's request timeout.
+  #[serde(rename_all = "camelCase")]
+  Expect {
+    predicate: String,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+  },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HeaderEntry {
+  pub name: String,
+  #[serde(default)]
+  pub value: String,
+  #[serde(default = "enabled_by_default")]
+  pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Transformation {
+  pub alias: String,
+  #[serde(default)]
+  pub expression: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Assertion {
+  #[serde(default)]
+  pub id: String,
+  #[serde(default = "response_source")]
+  pub data_source: String,
+  pub assertion_type: String,
+  #[serde(default)]
+  pub data_id: String,
+  pub operator: String,
+  #[serde(default)]
+  pub expected_value: Value,
+  #[serde(default = "enabled_by_default")]
+  pub enabled: bool,
+  /// Resolve `expected_value` through the template engine before comparing.
+  #[serde(default)]
+  pub is_template_expression: bool,
+}
+
+/// An API operation referenced by `StepEndpoint::endpoint_id`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointDefinition {
+  #[serde(deserialize_with = "id_string")]
+  pub id: String,
+  pub path: String,
+  pub method: String,
+  #[serde(default, deserialize_with = "null_default")]
+  pub parameters: Vec<EndpointParameter>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointParameter {
+  pub name: String,
+  #[serde(rename = "in")]
+  pub location: String,
+  #[serde(default, rename = "type")]
+  pub kind: Option<String>,
+  #[serde(default)]
+  pub schema: Option<Value>,
+  #[serde(default)]
+  pub style: Option<String>,
+  #[serde(default)]
+  pub explode: Option<bool>,
+  #[serde(default)]
+  pub collection_format: Option<String>,
+}
+
+impl EndpointParameter {
+  pub fn is_array(&self) -> bool {
+    self.kind.as_deref() == Some("array")
+      || self.schema.as_ref().and_then(|schema| schema.get("type")).and_then(Value::as_str) == Some("array")
+  }
+}
+
+impl TestFlow {
+  /// Parse a flow file: either a bare `TestFlowJson` (optionally carrying
+  /// `endpoints`) or a test flow record with `flowJson` and `endpoints`.
+  pub fn from_json(text: &str) -> Result<Self> {
+    let mut value: Value = serde_json::from_str(text)?;
+    if let Some(mut flow_json) = value.get_mut("flowJson").map(Value::take) {
+      if let (Some(flow), Some(endpoints)) = (flow_json.as_object_mut(), value.get_mut("endpoints")) {
+        if !endpoints.is_null() {
+          flow.insert("endpoints".to_string(), endpoints.take());
+        }
+      }
+      value = flow_json;
+    }
+    Ok(serde_json::from_value(value)?)
+  }
+}
+
+fn enabled_by_default() -> bool {
+  true
+}
+
+fn response_source() -> String {
+  "response".to_string()
+}
+
+/// Treat an explicit `null` like a missing field; the editor writes both.
+fn null_default<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+  D: Deserializer<'de>,
+  T: Default + Deserialize<'de>,
+{
+  Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// IDs are numbers in the database and strings once they pass through the
+/// editor, so accept either.
+fn id_string<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<String, D::Error> {
+  Ok(match Value::deserialize(deserializer)? {
+    Value::String(id) => id,
+    Value::Null => String::new(),
+    other => other.to_string(),
+  })
+}
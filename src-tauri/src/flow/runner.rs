@@ -0,0 +1,1027 @@
+//! Native execution of a [`TestFlow`], outside the webview.
+//!
+//! Follows `FlowExecutionEngine` in `src/lib/flow-runner/execution-engine.ts`:
+//! steps run in order and their endpoints one after another, or concurrently
+//! with `parallelExecution`. Responses are stored under
+//! `{step_id}-{index}`, and an endpoint fails on a failed assertion or, unless
+//! `skipDefaultStatusCheck` is set, on a non-2xx status. Path, query, header
+//! and body values go through the template engine against everything
+//! gathered so far, and each response's transformations are stored under the
+//! same id for later `{{proc:...}}` templates.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures_util::future::join_all;
+use percent_encoding::utf8_percent_encode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{
+  EndpointDefinition, EndpointParameter, EventStreamOptions, FlowStep, GraphQlRequest, GrpcCall, StepEndpoint, TestFlow,
+  WebSocketAction, WebSocketScript,
+};
+use crate::assertions::{self, AssertionResult};
+use crate::cookies::{lock_jar, CookieJar, SharedCookieJar};
+use crate::environment::ResolvedEnvironment;
+use crate::error::{Error, Result};
+use crate::grpc;
+use crate::http::sse::SseEvent;
+use crate::http::{HttpClient, HttpRequest, HttpResponse, RequestTiming, DEFAULT_TIMEOUT_MS};
+use crate::runs::CancellationToken;
+use crate::template::functions::URI_COMPONENT;
+use crate::template::{self, TemplateContext};
+use crate::transform::{self, functions::cast_to_type};
+use crate::websocket::{WebSocket, WebSocketFrame};
+
+/// The subset of `ExecutionPreferences` the native runner honours;
+/// `serverCookieHandling` does not apply since cookies never leave the backend.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RunPreferences {
+  /// Run the endpoints of a step concurrently.
+  pub parallel_execution: bool,
+  pub stop_on_error: bool,
+  /// Extra attempts for a request that got no response at all.
+  pub retry_count: u32,
+  #[serde(rename = "timeout")]
+  pub timeout_ms: u64,
+}
+
+impl Default for RunPreferences {
+  fn default() -> Self {
+    Self {
+      parallel_execution: false,
+      stop_on_error: true,
+      retry_count: 0,
+      timeout_ms: DEFAULT_TIMEOUT_MS,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndpointStatus {
+  Running,
+  Completed,
+  Failed,
+  Cancelled,
+}
+
+/// How a whole run ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+  Completed,
+  #[default]
+  Failed,
+  /// Stopped through its [`CancellationToken`]; not a failure of the flow.
+  Cancelled,
+}
+
+impl RunStatus {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      RunStatus::Completed => "completed",
+      RunStatus::Failed => "failed",
+      RunStatus::Cancelled => "cancelled",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointRequest {
+  pub url: String,
+  pub method: String,
+  pub headers: HashMap<String, String>,
+  pub body: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointResponse {
+  pub status: u16,
+  pub status_text: String,
+  pub headers: Map<String, Value>,
+  pub body: Value,
+}
+
+/// What happened to one endpoint of one step.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointResult {
+  /// `{step_id}-{index}`, the key responses are stored under.
+  pub endpoint_id: String,
+  pub step_id: String,
+  pub status: EndpointStatus,
+  pub request: Option<EndpointRequest>,
+  pub response: Option<EndpointResponse>,
+  pub timing: Option<RequestTiming>,
+  pub assertions: Vec<AssertionResult>,
+  pub error: Option<String>,
+  /// The `errors` a GraphQL response came back with.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub graphql_errors: Vec<Value>,
+  /// Everything a WebSocket step sent and received.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub websocket_frames: Vec<WebSocketFrame>,
+}
+
+impl EndpointResult {
+  fn new(step_id: &str, index: usize, status: EndpointStatus) -> Self {
+    Self {
+      endpoint_id: format!("{step_id}-{index}"),
+      step_id: step_id.to_string(),
+      status,
+      request: None,
+      response: None,
+      timing: None,
+      assertions: Vec::new(),
+      error: None,
+      graphql_errors: Vec::new(),
+      websocket_frames: Vec::new(),
+    }
+  }
+}
+
+/// Progress reported to an [`FlowRunner::on_event`] listener while a flow runs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FlowEvent {
+  /// An endpoint started (with status `running`) or finished.
+  Endpoint(Box<EndpointResult>),
+  #[serde(rename_all = "camelCase")]
+  StepCompleted { step_id: String, step_index: usize, success: bool },
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowRunResult {
+  pub success: bool,
+  pub status: RunStatus,
+  pub endpoints: Vec<EndpointResult>,
+  pub stored_responses: Map<String, Value>,
+  /// Transformation results by endpoint id, then alias.
+  pub stored_transformations: Map<String, Value>,
+  pub parameter_values: Map<String, Value>,
+  pub flow_outputs: Map<String, Value>,
+  /// Required parameters that ended up without a value; nothing was sent.
+  pub missing_parameters: Vec<String>,
+  pub error: Option<String>,
+}
+
+/// Runs one flow against one environment with its own cookie jar.
+pub struct FlowRunner<'a> {
+  client: &'a HttpClient,
+  flow: &'a TestFlow,
+  environment: Option<&'a ResolvedEnvironment>,
+  preferences: RunPreferences,
+  cookie_jar: SharedCookieJar,
+  cancellation: CancellationToken,
+  listener: Option<&'a (dyn Fn(FlowEvent) + Sync)>,
+}
+
+impl<'a> FlowRunner<'a> {
+  pub fn new(client: &'a HttpClient, flow: &'a TestFlow, environment: Option<&'a ResolvedEnvironment>) -> Self {
+    Self {
+      client,
+      flow,
+      environment,
+      preferences: RunPreferences::default(),
+      cookie_jar: Arc::new(Mutex::new(CookieJar::default())),
+      cancellation: CancellationToken::default(),
+      listener: None,
+    }
+  }
+
+  pub fn preferences(mut self, preferences: RunPreferences) -> Self {
+    self.preferences = preferences;
+    self
+  }
+
+  /// Share a jar with other runs, e.g. the flows of one sequence.
+  pub fn cookie_jar(mut self, cookie_jar: SharedCookieJar) -> Self {
+    self.cookie_jar = cookie_jar;
+    self
+  }
+
+  /// Stop the run, in-flight requests included, when `token` is cancelled.
+  pub fn cancellation(mut self, token: CancellationToken) -> Self {
+    self.cancellation = token;
+    self
+  }
+
+  /// Report endpoint and step progress to `listener` as the run goes.
+  pub fn on_event(mut self, listener: &'a (dyn Fn(FlowEvent) + Sync)) -> Self {
+    self.listener = Some(listener);
+    self
+  }
+
+  /// Run every step. `overrides` take precedence over environment mappings
+  /// and parameter defaults.
+  pub async fn run(&self, overrides: &Map<String, Value>) -> FlowRunResult {
+    let mut result = FlowRunResult {
+      parameter_values: self.parameter_values(overrides),
+      ..FlowRunResult::default()
+    };
+
+    result.missing_parameters = self
+      .flow
+      .parameters
+      .iter()
+      .filter(|parameter| parameter.required && result.parameter_values.get(&parameter.name).map_or(true, Value::is_null))
+      .map(|parameter| parameter.name.clone())
+      .collect();
+    if !result.missing_parameters.is_empty() {
+      result.error = Some(format!(
+        "flow requires parameter values for: {}",
+        result.missing_parameters.join(", ")
+      ));
+      return result;
+    }
+
+    let mut context = TemplateContext {
+      parameters: result.parameter_values.clone(),
+      environment: Some(
+        self
+          .environment
+          .map(|environment| environment.variables.clone())
+          .unwrap_or_default(),
+      ),
+      ..TemplateContext::default()
+    };
+
+    for (step_index, step) in self.flow.steps.iter().enumerate() {
+      if self.cancellation.is_cancelled() {
+        break;
+      }
+      if step.clear_cookies_before_execution {
+        log::debug!("[flow] clearing cookies before step {}", step.step_id);
+        lock_jar(&self.cookie_jar).clear(None);
+      }
+
+      let endpoints = if self.preferences.parallel_execution && step.endpoints.len() > 1 {
+        self.execute_parallel(step, &mut context).await
+      } else {
+        self.execute_sequential(step, &mut context).await
+      };
+      let failed = endpoints.iter().find(|endpoint| endpoint.status == EndpointStatus::Failed);
+      let success = endpoints.iter().all(|endpoint| endpoint.status == EndpointStatus::Completed);
+      if result.error.is_none() {
+        result.error = failed.and_then(|endpoint| endpoint.error.clone());
+      }
+      result.endpoints.extend(endpoints);
+      self.emit(|| FlowEvent::StepCompleted {
+        step_id: step.step_id.clone(),
+        step_index,
+        success,
+      });
+
+      if !success && self.preferences.stop_on_error {
+        break;
+      }
+    }
+
+    result.flow_outputs = self.evaluate_outputs(&context);
+    result.stored_responses = context.responses;
+    result.stored_transformations = context.transformed_data;
+    result.success = result.endpoints.iter().all(|endpoint| endpoint.status == EndpointStatus::Completed);
+    result.status = if self.cancellation.is_cancelled() {
+      log::debug!("[flow] run cancelled after {} endpoint(s)", result.endpoints.len());
+      result.success = false;
+      result.error.get_or_insert_with(|| Error::Cancelled.to_string());
+      RunStatus::Cancelled
+    } else if result.success {
+      RunStatus::Completed
+    } else {
+      RunStatus::Failed
+    };
+    result
+  }
+
+  fn parameter_values(&self, overrides: &Map<String, Value>) -> Map<String, Value> {
+    let mappings = self
+      .flow
+      .settings
+      .linked_environment
+      .as_ref()
+      .map(|linked| &linked.parameter_mappings);
+
+    let mut values = Map::new();
+    for parameter in &self.flow.parameters {
+      let from_environment = mappings
+        .and_then(|mappings| mappings.get(&parameter.name))
+        .and_then(|variable| self.environment?.variables.get(variable))
+        .filter(|value| !value.is_null());
+      let value = overrides
+        .get(&parameter.name)
+        .or(from_environment)
+        .or(parameter.default_value.as_ref().filter(|value| !value.is_null()));
+      if let Some(value) = value {
+        values.insert(parameter.name.clone(), value.clone());
+      }
+    }
+    values
+  }
+
+  /// Resolve template outputs against the finished run, casting those that
+  /// ask for it; a failed one is logged and left as its raw template, as
+  /// `FlowOutputEvaluator` does.
+  fn evaluate_outputs(&self, context: &TemplateContext) -> Map<String, Value> {
+    self
+      .flow
+      .outputs
+      .iter()
+      .map(|output| {
+        let value = if output.is_template && !output.value.is_empty() {
+          match template::resolve_template(&output.value, context) {
+            Ok(value) => match output.kind.as_deref().filter(|_| output.cast_to_type) {
+              Some(kind) => cast_to_type(value, kind),
+              None => value,
+            },
+            Err(err) => {
+              log::error!("[flow] template resolution failed for output `{}`: {err}", output.name);
+              Value::String(output.value.clone())
+            }
+          }
+        } else {
+          Value::String(output.value.clone())
+        };
+        (output.name.clone(), value)
+      })
+      .collect()
+  }
+
+  fn emit(&self, event: impl FnOnce() -> FlowEvent) {
+    if let Some(listener) = self.listener {
+      listener(event());
+    }
+  }
+
+  /// Run a step's endpoints one after another, stopping at the first failure
+  /// when `stop_on_error` is set.
+  async fn execute_sequential(&self, step: &FlowStep, context: &mut TemplateContext) -> Vec<EndpointResult> {
+    let mut results = Vec::new();
+    for (index, endpoint) in step.endpoints.iter().enumerate() {
+      let result = self.execute_endpoint(&step.step_id, index, endpoint, context).await;
+      let status = result.status;
+      results.push(result);
+      if status == EndpointStatus::Cancelled || (status == EndpointStatus::Failed && self.preferences.stop_on_error) {
+        break;
+      }
+    }
+    results
+  }
+
+  /// Run a step's endpoints concurrently. Each one sees the context as it was
+  /// when the step began; what they store is merged back in endpoint order.
+  async fn execute_parallel(&self, step: &FlowStep, context: &mut TemplateContext) -> Vec<EndpointResult> {
+    let runs = step.endpoints.iter().enumerate().map(|(index, endpoint)| {
+      let mut snapshot = context.clone();
+      async move {
+        let result = self.execute_endpoint(&step.step_id, index, endpoint, &mut snapshot).await;
+        (result, snapshot)
+      }
+    });
+    let finished = join_all(runs).await;
+
+    let mut results = Vec::with_capacity(finished.len());
+    for (result, mut snapshot) in finished {
+      if let Some(body) = snapshot.responses.remove(&result.endpoint_id) {
+        context.responses.insert(result.endpoint_id.clone(), body);
+      }
+      if let Some(transformed) = snapshot.transformed_data.remove(&result.endpoint_id) {
+        context.transformed_data.insert(result.endpoint_id.clone(), transformed);
+      }
+      results.push(result);
+    }
+    results
+  }
+
+  async fn execute_endpoint(
+    &self,
+    step_id: &str,
+    index: usize,
+    endpoint: &StepEndpoint,
+    context: &mut TemplateContext,
+  ) -> EndpointResult {
+    self.emit(|| FlowEvent::Endpoint(Box::new(EndpointResult::new(step_id, index, EndpointStatus::Running))));
+    let result = self.run_endpoint(step_id, index, endpoint, context).await;
+    self.emit(|| FlowEvent::Endpoint(Box::new(result.clone())));
+    result
+  }
+
+  async fn run_endpoint(
+    &self,
+    step_id: &str,
+    index: usize,
+    endpoint: &StepEndpoint,
+    context: &mut TemplateContext,
+  ) -> EndpointResult {
+    let mut result = EndpointResult::new(step_id, index, EndpointStatus::Failed);
+
+    let request = match self.prepare_request(endpoint, context) {
+      Ok(request) => request,
+      Err(err) => {
+        result.error = Some(err.to_string());
+        return result;
+      }
+    };
+    log::debug!("[flow] {} {} {}", result.endpoint_id, request.method, request.url);
+
+    let http_request = HttpRequest {
+      method: request.method.clone(),
+      url: request.url.clone(),
+      headers: request.headers.clone(),
+      body: request.body.as_ref().map(Value::to_string),
+      timeout_ms: Some(self.preferences.timeout_ms),
+      run_id: None,
+    };
+    result.request = Some(request);
+
+    let exchange = match (&endpoint.websocket, &endpoint.grpc, &endpoint.event_stream) {
+      (Some(script), _, _) => self.run_websocket(script, http_request, context, &mut result.websocket_frames).await,
+      (None, Some(call), _) => self.call_grpc(call, &endpoint.endpoint_id, http_request).await,
+      (None, None, Some(options)) => self.read_events(options, http_request, context).await,
+      (None, None, None) => self.send(&result.endpoint_id, http_request).await.map(|response| {
+        let body = response_data(&response);
+        (response, body)
+      }),
+    };
+    let (response, full_body) = match exchange {
+      Ok(exchange) => exchange,
+      Err(err) => {
+        if matches!(err, Error::Cancelled) {
+          result.status = EndpointStatus::Cancelled;
+        }
+        result.error = Some(err.to_string());
+        return result;
+      }
+    };
+
+    // A GraphQL step's data is what later steps, transformations and
+    // assertions see; its errors are reported alongside.
+    let body = match &endpoint.graphql {
+      Some(_) => {
+        let (data, errors) = graphql_outcome(&full_body);
+        result.graphql_errors = errors;
+        data
+      }
+      None => full_body.clone(),
+    };
+    context.responses.insert(result.endpoint_id.clone(), body.clone());
+
+    if !endpoint.transformations.is_empty() {
+      let transformed = apply_transformations(endpoint, &body, context);
+      context.transformed_data.insert(result.endpoint_id.clone(), Value::Object(transformed));
+    }
+
+    result.assertions = assertions::run(
+      &endpoint.assertions,
+      &response,
+      &body,
+      context.transformed_data.get(&result.endpoint_id),
+      Some(context),
+    );
+    if let Some(failed) = result.assertions.iter().find(|assertion| !assertion.passed) {
+      result.error = Some(failed.message.clone());
+    }
+
+    if result.error.is_none() && !result.graphql_errors.is_empty() && !endpoint.skip_default_status_check {
+      let messages: Vec<String> = result
+        .graphql_errors
+        .iter()
+        .map(|error| error.get("message").map_or_else(|| error.to_string(), scalar_string))
+        .collect();
+      result.error = Some(format!("GraphQL errors: {}", messages.join("; ")));
+    }
+
+    if result.error.is_none() && endpoint.grpc.is_some() && !endpoint.skip_default_status_check {
+      result.error = grpc::client::status_error(&response);
+    }
+
+    // A WebSocket's 101 Switching Protocols is its success.
+    let ok = endpoint.websocket.is_some() || (200..300).contains(&response.status);
+    if result.error.is_none() && !ok && !endpoint.skip_default_status_check {
+      result.error = Some(format!(
+        "Request failed with status {}: {}",
+        response.status, response.status_text
+      ));
+    }
+    if result.error.is_none() {
+      result.status = EndpointStatus::Completed;
+    }
+
+    let mut headers = Map::new();
+    for (name, value) in &response.headers {
+      let combined = match headers.get(name).and_then(Value::as_str) {
+        Some(existing) => format!("{existing}, {value}"),
+        None => value.clone(),
+      };
+      headers.insert(name.clone(), Value::String(combined));
+    }
+    result.response = Some(EndpointResponse {
+      status: response.status,
+      status_text: response.status_text.clone(),
+      headers,
+      body: full_body,
+    });
+    result.timing = Some(response.timing);
+    result
+  }
+
+  /// Send a request, retrying up to `retry_count` times when no response
+  /// came back at all. Error statuses are responses and are not retried.
+  /// Cancelling the run abandons the request mid-flight.
+  async fn send(&self, endpoint_id: &str, request: HttpRequest) -> Result<HttpResponse> {
+    let attempts = async {
+      let mut attempt = 0;
+      loop {
+        match self.client.execute(request.clone(), Some(self.cookie_jar.clone())).await {
+          Err(err) if attempt < self.preferences.retry_count => {
+            attempt += 1;
+            log::debug!("[flow] {endpoint_id} attempt {attempt} failed, retrying: {err}");
+          }
+          outcome => return outcome,
+        }
+      }
+    };
+    self.cancellation.run(attempts).await?
+  }
+
+  /// Send a request and read its response as an event stream. Returns the
+  /// response and, as the body, its events, or the body as usual when the
+  /// response is no event stream. Streams are not retried.
+  async fn read_events(
+    &self,
+    options: &EventStreamOptions,
+    request: HttpRequest,
+    context: &TemplateContext,
+  ) -> Result<(HttpResponse, Value)> {
+    let until = |event: &SseEvent| match &options.until {
+      Some(predicate) => {
+        let event = serde_json::to_value(event)?;
+        transform::evaluate(predicate, &event, context).map(|value| transform::truthy(&value))
+      }
+      None => Ok(false),
+    };
+    let wait_ms = options.timeout_ms.unwrap_or(self.preferences.timeout_ms);
+    let reading = self
+      .client
+      .execute_events(request, Some(self.cookie_jar.clone()), options.max_events, wait_ms, until);
+    let (response, events) = self.cancellation.run(reading).await??;
+    let body = match events {
+      Some(events) => serde_json::to_value(events)?,
+      None => response_data(&response),
+    };
+    Ok((response, body))
+  }
+
+  /// Call the gRPC method the endpoint names, found in the call's
+  /// descriptors. Calls are not retried.
+  async fn call_grpc(&self, call: &GrpcCall, endpoint_id: &str, request: HttpRequest) -> Result<(HttpResponse, Value)> {
+    let pool = grpc::decode(&call.descriptor_set)?;
+    let method = grpc::find_method(&pool, &self.endpoint_definition(endpoint_id)?.path)?;
+    let wait_ms = call.timeout_ms.unwrap_or(self.preferences.timeout_ms);
+    let calling = grpc::client::call(self.client, &method, request, call.max_messages, wait_ms);
+    self.cancellation.run(calling).await?
+  }
+
+  /// Connect to the request's URL as a WebSocket and play `script` with the
+  /// run's cookies. Returns the handshake response and, as the body, the
+  /// messages the `expect` actions matched, in order.
+  async fn run_websocket(
+    &self,
+    script: &WebSocketScript,
+    request: HttpRequest,
+    context: &TemplateContext,
+    frames: &mut Vec<WebSocketFrame>,
+  ) -> Result<(HttpResponse, Value)> {
+    let timeout_ms = self.preferences.timeout_ms;
+    let exchange = async {
+      let (mut socket, response) =
+        WebSocket::connect(self.client, &request.url, &request.headers, Some(&self.cookie_jar), timeout_ms).await?;
+      let mut matched = Vec::new();
+      let mut outcome = Ok(());
+      for action in &script.actions {
+        outcome = match action {
+          WebSocketAction::Send { message } => socket.send(&resolve_value(message, context)).await,
+          WebSocketAction::Expect {
+            predicate,
+            timeout_ms: wait_ms,
+          } => socket
+            .expect(wait_ms.unwrap_or(timeout_ms), |message| {
+              transform::evaluate(predicate, message, context).map(|value| transform::truthy(&value))
+            })
+            .await
+            .map(|message| matched.push(message)),
+        };
+        if outcome.is_err() {
+          break;
+        }
+      }
+      *frames = socket.close().await;
+      outcome.map(|()| (response, Value::Array(matched)))
+    };
+    self.cancellation.run(exchange).await?
+  }
+
+  fn prepare_request(&self, endpoint: &StepEndpoint, context: &TemplateContext) -> Result<EndpointRequest> {
+    let definition = self.endpoint_definition(&endpoint.endpoint_id)?;
+    let host = self.endpoint_host(&endpoint.api_id)?;
+
+    let mut url = format!("{}{}", host.trim_end_matches('/'), definition.path);
+    for (name, value) in &endpoint.path_params {
+      let value = resolve_string(&scalar_string(value), context);
+      let value = utf8_percent_encode(&value, URI_COMPONENT).to_string();
+      url = url.replace(&format!("{{{name}}}"), &value);
+    }
+
+    if !endpoint.query_params.is_empty() {
+      let mut query = url::form_urlencoded::Serializer::new(String::new());
+      for (name, value) in &endpoint.query_params {
+        let parameter = definition
+          .parameters
+          .iter()
+          .find(|parameter| parameter.name == *name && parameter.location == "query")
+          .filter(|parameter| parameter.is_array());
+        match parameter {
+          Some(parameter) => {
+            let values = match value {
+              Value::Array(items) => items
+                .iter()
+                .map(|item| resolve_string(&scalar_string(item), context))
+                .collect(),
+              other => array_values(&resolve_string(&scalar_string(other), context)),
+            };
+            serialize_array_parameter(&mut query, name, &values, parameter);
+          }
+          None => {
+            query.append_pair(name, &resolve_string(&scalar_string(value), context));
+          }
+        }
+      }
+      url.push('?');
+      url.push_str(&query.finish());
+    }
+
+    let mut headers: HashMap<String, String> = endpoint
+      .headers
+      .iter()
+      .filter(|header| header.enabled)
+      .map(|header| (header.name.clone(), resolve_string(&header.value, context)))
+      .collect();
+
+    if endpoint.event_stream.is_some() && !headers.keys().any(|name| name.eq_ignore_ascii_case("accept")) {
+      headers.insert("Accept".to_string(), "text/event-stream".to_string());
+    }
+    let body = match &endpoint.graphql {
+      Some(graphql) => {
+        if !headers.keys().any(|name| name.eq_ignore_ascii_case("content-type")) {
+          headers.insert("Content-Type".to_string(), "application/json".to_string());
+        }
+        Some(graphql_body(graphql, context))
+      }
+      None => endpoint.body.as_ref().filter(|body| !body.is_null()).map(|body| resolve_value(body, context)),
+    };
+
+    Ok(EndpointRequest {
+      url,
+      method: definition.method.to_uppercase(),
+      headers,
+      body,
+    })
+  }
+
+  fn endpoint_definition(&self, endpoint_id: &str) -> Result<&EndpointDefinition> {
+    self
+      .flow
+      .endpoints
+      .iter()
+      .find(|definition| definition.id == endpoint_id)
+      .ok_or_else(|| Error::InvalidFlow(format!("endpoint definition not found for ID: {endpoint_id}")))
+  }
+
+  /// The sub-environment's host for the API wins over the flow's own.
+  fn endpoint_host(&self, api_id: &str) -> Result<String> {
+    let from_environment = self
+      .environment
+      .and_then(|environment| environment.api_hosts.get(api_id))
+      .map(|url| url.trim())
+      .filter(|url| !url.is_empty());
+    let from_flow = || {
+      self
+        .flow
+        .settings
+        .api_hosts
+        .get(api_id)
+        .map(|host| host.url.trim())
+        .filter(|url| !url.is_empty())
+    };
+    from_environment
+      .or_else(from_flow)
+      .map(str::to_string)
+      .ok_or_else(|| Error::InvalidFlow(format!("no API host available for API ID {api_id}")))
+  }
+}
+
+/// Parse the body the way `getResponseData` does: JSON when declared or when
+/// it happens to parse, text otherwise.
+fn response_data(response: &HttpResponse) -> Value {
+  let content_type = response
+    .headers
+    .iter()
+    .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+    .map(|(_, value)| value.to_ascii_lowercase())
+    .unwrap_or_default();
+  let text = String::from_utf8_lossy(&response.body);
+
+  if content_type.contains("application/json") {
+    serde_json::from_str(&text).unwrap_or_else(|_| Value::String("Unable to parse response data".to_string()))
+  } else if content_type.contains("text/") || content_type.contains("application/xml") {
+    Value::String(text.into_owned())
+  } else {
+    serde_json::from_str(&text).unwrap_or_else(|_| Value::String(text.into_owned()))
+  }
+}
+
+/// The `{query, variables, operationName}` body of a GraphQL request.
+fn graphql_body(graphql: &GraphQlRequest, context: &TemplateContext) -> Value {
+  let mut body = Map::new();
+  body.insert("query".to_string(), Value::String(resolve_string(&graphql.query, context)));
+  if let Some(variables) = graphql.variables.as_ref().filter(|variables| !variables.is_null()) {
+    body.insert("variables".to_string(), resolve_value(variables, context));
+  }
+  if let Some(name) = graphql.operation_name.as_deref().filter(|name| !name.is_empty()) {
+    body.insert("operationName".to_string(), Value::String(name.to_string()));
+  }
+  Value::Object(body)
+}
+
+/// Split a GraphQL response into its `data` and `errors`. A body that is
+/// not a GraphQL response, such as a proxy's error page, is kept whole.
+fn graphql_outcome(body: &Value) -> (Value, Vec<Value>) {
+  let Some(object) = body.as_object().filter(|object| object.contains_key("data") || object.contains_key("errors")) else {
+    return (body.clone(), Vec::new());
+  };
+  let errors = match object.get("errors") {
+    Some(Value::Array(errors)) => errors.clone(),
+    Some(Value::Null) | None => Vec::new(),
+    Some(other) => vec![other.clone()],
+  };
+  (object.get("data").cloned().unwrap_or(Value::Null), errors)
+}
+
+/// Evaluate each transformation against `body`. An empty expression stores
+/// the raw response; a failing one is logged and its alias left out.
+fn apply_transformations(endpoint: &StepEndpoint, body: &Value, context: &TemplateContext) -> Map<String, Value> {
+  let mut transformed = Map::new();
+  for transformation in &endpoint.transformations {
+    match transform::evaluate(&transformation.expression, body, context) {
+      Ok(value) => {
+        transformed.insert(transformation.alias.clone(), value);
+      }
+      Err(err) => log::error!("[flow] transformation `{}` failed: {err}", transformation.alias),
+    }
+  }
+  transformed
+}
+
+/// Resolve a body; on failure the raw value is sent.
+fn resolve_value(value: &Value, context: &TemplateContext) -> Value {
+  template::resolve_template_value(value, context).unwrap_or_else(|err| {
+    log::error!("[flow] template object resolution failed: {err}");
+    value.clone()
+  })
+}
+
+/// Resolve a path, query or header value; on failure the raw value is sent.
+fn resolve_string(value: &str, context: &TemplateContext) -> String {
+  template::resolve_template_string(value, context).unwrap_or_else(|err| {
+    log::error!("[flow] template resolution failed for \"{value}\": {err}");
+    value.to_string()
+  })
+}
+
+fn scalar_string(value: &Value) -> String {
+  match value {
+    Value::String(text) => text.clone(),
+    Value::Null => String::new(),
+    other => other.to_string(),
+  }
+}
+
+/// Split a comma-separated array query value.
+fn array_values(value: &str) -> Vec<String> {
+  value
+    .split(',')
+    .map(str::trim)
+    .filter(|item| !item.is_empty())
+    .map(str::to_string)
+    .collect()
+}
+
+fn serialize_array_parameter(
+  query: &mut url::form_urlencoded::Serializer<'_, String>,
+  name: &str,
+  values: &[String],
+  parameter: &EndpointParameter,
+) {
+  if values.is_empty() {
+    return;
+  }
+  let format = parameter
+    .collection_format
+    .as_deref()
+    .or(parameter.style.as_deref())
+    .unwrap_or("csv");
+  let explode = parameter.explode != Some(false);
+
+  let separator = match format {
+    "csv" | "form" if !explode => ",",
+    "ssv" | "spaceDelimited" => " ",
+    "tsv" => "\t",
+    "pipes" | "pipeDelimited" => "|",
+    _ => {
+      for value in values {
+        query.append_pair(name, value);
+      }
+      return;
+    }
+  };
+  query.append_pair(name, &values.join(separator));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn flow() -> TestFlow {
+    serde_json::from_value(json!({
+      "settings": { "api_hosts": { "1": { "url": "http://flow.local/", "name": "API" } } },
+      "parameters": [],
+      "steps": [],
+      "endpoints": [{
+        "id": 5,
+        "path": "/users/{id}/items",
+        "method": "get",
+        "parameters": [{ "name": "tags", "in": "query", "schema": { "type": "array" }, "explode": false }]
+      }]
+    }))
+    .unwrap()
+  }
+
+  fn endpoint(value: Value) -> StepEndpoint {
+    serde_json::from_value(value).unwrap()
+  }
+
+  #[test]
+  fn builds_url_from_path_and_query_params() {
+    let client = HttpClient::new().unwrap();
+    let flow = flow();
+    let runner = FlowRunner::new(&client, &flow, None);
+    let request = runner
+      .prepare_request(&endpoint(json!({
+        "endpoint_id": 5,
+        "api_id": 1,
+        "pathParams": { "id": "a b/c" },
+        "queryParams": { "tags": "red, blue", "q": "x y" },
+        "headers": [{ "name": "X-On", "value": "1", "enabled": true }, { "name": "X-Off", "value": "0", "enabled": false }],
+        "body": null
+      })), &TemplateContext::default())
+      .unwrap();
+
+    assert_eq!(request.method, "GET");
+    // Query parameters keep the order the step lists them in.
+    assert_eq!(request.url, "http://flow.local/users/a%20b%2Fc/items?tags=red%2Cblue&q=x+y");
+    assert_eq!(request.headers.len(), 1);
+    assert!(request.body.is_none());
+  }
+
+  #[test]
+  fn environment_host_overrides_flow_host() {
+    let client = HttpClient::new().unwrap();
+    let flow = flow();
+    let environment = ResolvedEnvironment {
+      api_hosts: HashMap::from([("1".to_string(), "http://env.local".to_string())]),
+      ..ResolvedEnvironment::default()
+    };
+    let runner = FlowRunner::new(&client, &flow, Some(&environment));
+    let request = runner
+      .prepare_request(&endpoint(json!({ "endpoint_id": "5", "api_id": "1" })), &TemplateContext::default())
+      .unwrap();
+    assert!(request.url.starts_with("http://env.local/users/"));
+  }
+
+  #[test]
+  fn missing_host_is_reported() {
+    let client = HttpClient::new().unwrap();
+    let flow = flow();
+    let runner = FlowRunner::new(&client, &flow, None);
+    let err = runner
+      .prepare_request(&endpoint(json!({ "endpoint_id": 5, "api_id": 9 })), &TemplateContext::default())
+      .unwrap_err();
+    assert!(err.to_string().contains("API ID 9"));
+  }
+
+  #[test]
+  fn resolves_templates_in_request() {
+    let client = HttpClient::new().unwrap();
+    let flow = flow();
+    let runner = FlowRunner::new(&client, &flow, None);
+    let context: TemplateContext = serde_json::from_value(json!({
+      "responses": { "login-0": { "token": "abc", "id": 7 } },
+      "parameters": { "tags": "red,blue" }
+    }))
+    .unwrap();
+    let request = runner
+      .prepare_request(
+        &endpoint(json!({
+          "endpoint_id": 5,
+          "api_id": 1,
+          "pathParams": { "id": "{{res:login-0.$.id}}" },
+          "queryParams": { "tags": "{{param:tags}}", "missing": "{{param:nope}}" },
+          "headers": [{ "name": "Authorization", "value": "Bearer {{res:login-0.$.token}}" }],
+          "body": { "userId": "{{{res:login-0.$.id}}}" }
+        })),
+        &context,
+      )
+      .unwrap();
+
+    assert_eq!(request.url, "http://flow.local/users/7/items?tags=red%2Cblue&missing=%7B%7Bparam%3Anope%7D%7D");
+    assert_eq!(request.headers["Authorization"], "Bearer abc");
+    assert_eq!(request.body, Some(json!({ "userId": 7 })));
+  }
+
+  #[test]
+  fn sends_graphql_operations_and_splits_their_responses() {
+    let client = HttpClient::new().unwrap();
+    let flow = flow();
+    let runner = FlowRunner::new(&client, &flow, None);
+    let context: TemplateContext = serde_json::from_value(json!({ "responses": { "login-0": { "id": 7 } } })).unwrap();
+    let request = runner
+      .prepare_request(
+        &endpoint(json!({
+          "endpoint_id": 5,
+          "api_id": 1,
+          "body": { "ignored": true },
+          "graphql": {
+            "query": "query User($id: ID!) { user(id: $id) { name } }",
+            "variables": { "id": "{{{res:login-0.$.id}}}" },
+            "operationName": "User"
+          }
+        })),
+        &context,
+      )
+      .unwrap();
+    assert_eq!(request.headers["Content-Type"], "application/json");
+    assert_eq!(
+      request.body,
+      Some(json!({ "query": "query User($id: ID!) { user(id: $id) { name } }", "variables": { "id": 7 }, "operationName": "User" }))
+    );
+
+    let (data, errors) = graphql_outcome(&json!({ "data": { "user": null }, "errors": [{ "message": "not found" }] }));
+    assert_eq!((data, errors), (json!({ "user": null }), vec![json!({ "message": "not found" })]));
+    let (data, errors) = graphql_outcome(&json!("Bad Gateway"));
+    assert_eq!((data, errors.len()), (json!("Bad Gateway"), 0));
+  }
+
+  #[test]
+  fn reads_execution_preferences() {
+    let preferences: RunPreferences = serde_json::from_value(json!({
+      "parallelExecution": true,
+      "stopOnError": false,
+      "serverCookieHandling": true,
+      "retryCount": 2,
+      "timeout": 5000
+    }))
+    .unwrap();
+    assert!(preferences.parallel_execution && !preferences.stop_on_error);
+    assert_eq!((preferences.retry_count, preferences.timeout_ms), (2, 5000));
+
+    let defaults: RunPreferences = serde_json::from_value(json!({})).unwrap();
+    assert!(defaults.stop_on_error && !defaults.parallel_execution);
+    assert_eq!(defaults.timeout_ms, DEFAULT_TIMEOUT_MS);
+  }
+
+  #[test]
+  fn applies_transformations_and_skips_failures() {
+    let body = json!({ "items": [{ "id": 1, "on": true }, { "id": 2, "on": false }] });
+    let transformed = apply_transformations(
+      &endpoint(json!({
+        "endpoint_id": 5,
+        "api_id": 1,
+        "transformations": [
+          { "alias": "ids", "expression": "$.items | where($.on == true) | map($.id)" },
+          { "alias": "raw", "expression": "" },
+          { "alias": "broken", "expression": "$.items | nope()" }
+        ]
+      })),
+      &body,
+      &TemplateContext::default(),
+    );
+    assert_eq!(Value::Object(transformed), json!({ "ids": [1], "raw": body }));
+  }
+}
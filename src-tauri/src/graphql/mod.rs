@@ -0,0 +1,471 @@
+//! GraphQL schema import.
+//!
+//! A schema comes from SDL ([`sdl`]) or from the JSON result of the
+//! [`INTROSPECTION_QUERY`], run against a server or saved to a file.
+//! [`extract_endpoints`] turns its query and mutation fields into the same
+//! [`ApiEndpoint`] records an OpenAPI import produces, one per field, all
+//! `POST`ed to the GraphQL endpoint. Each carries a ready-made operation
+//! document as the example of its `query`, which flows send through a step's
+//! `graphql` request.
+
+pub mod sdl;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::{json, Map, Value};
+
+use crate::error::{Error, Result};
+use crate::openapi::ApiEndpoint;
+
+/// Nesting of object fields selected in generated documents; deeper object
+/// fields are left out.
+const MAX_SELECTION_DEPTH: usize = 3;
+
+/// Nesting of input objects described in variable schemas.
+const MAX_INPUT_DEPTH: usize = 6;
+
+/// The standard introspection query, as GraphiQL sends it, minus
+/// directives, which the import does not use.
+pub const INTROSPECTION_QUERY: &str = r#"query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types { ...FullType }
+  }
+}
+fragment FullType on __Type {
+  kind
+  name
+  description
+  fields(includeDeprecated: true) {
+    name
+    description
+    args { ...InputValue }
+    type { ...TypeRef }
+  }
+  inputFields { ...InputValue }
+  enumValues(includeDeprecated: true) { name }
+  possibleTypes { name }
+}
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name } } } } } } }
+}"#;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+  pub query_type: Option<String>,
+  pub mutation_type: Option<String>,
+  pub types: BTreeMap<String, TypeDef>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDef {
+  pub kind: TypeKind,
+  pub description: Option<String>,
+  /// Of objects and interfaces.
+  pub fields: Vec<Field>,
+  /// Of input objects.
+  pub input_fields: Vec<InputValue>,
+  pub enum_values: Vec<String>,
+}
+
+impl TypeDef {
+  fn new(kind: TypeKind) -> Self {
+    Self {
+      kind,
+      description: None,
+      fields: Vec::new(),
+      input_fields: Vec::new(),
+      enum_values: Vec::new(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeKind {
+  Scalar,
+  Object,
+  Interface,
+  Union,
+  Enum,
+  InputObject,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+  pub name: String,
+  pub description: Option<String>,
+  pub args: Vec<InputValue>,
+  pub ty: TypeRef,
+}
+
+/// An argument or input object field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputValue {
+  pub name: String,
+  pub description: Option<String>,
+  pub ty: TypeRef,
+  /// As GraphQL source text.
+  pub default_value: Option<String>,
+}
+
+impl InputValue {
+  fn is_required(&self) -> bool {
+    matches!(self.ty, TypeRef::NonNull(_)) && self.default_value.is_none()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeRef {
+  Named(String),
+  List(Box<TypeRef>),
+  NonNull(Box<TypeRef>),
+}
+
+impl TypeRef {
+  /// The named type inside any list and non-null wrappers.
+  pub fn name(&self) -> &str {
+    match self {
+      TypeRef::Named(name) => name,
+      TypeRef::List(inner) | TypeRef::NonNull(inner) => inner.name(),
+    }
+  }
+}
+
+/// GraphQL notation, such as `[ID!]!`.
+impl fmt::Display for TypeRef {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TypeRef::Named(name) => f.write_str(name),
+      TypeRef::List(inner) => write!(f, "[{inner}]"),
+      TypeRef::NonNull(inner) => write!(f, "{inner}!"),
+    }
+  }
+}
+
+/// Read a schema file: introspection JSON if it parses as JSON, else SDL.
+pub fn parse(content: &str) -> Result<Schema> {
+  match serde_json::from_str::<Value>(content) {
+    Ok(result) => from_introspection(&result),
+    Err(_) => sdl::parse(content),
+  }
+}
+
+/// Read an introspection result, with or without the `data` envelope.
+pub fn from_introspection(result: &Value) -> Result<Schema> {
+  let Some(introspection) = result.pointer("/data/__schema").or_else(|| result.get("__schema")) else {
+    let message = result
+      .pointer("/errors/0/message")
+      .and_then(Value::as_str)
+      .unwrap_or("expected an introspection result with `__schema`");
+    return Err(Error::GraphQl(message.to_string()));
+  };
+  let root = |key: &str| introspection[key]["name"].as_str().map(str::to_string);
+  let mut schema = Schema {
+    query_type: root("queryType"),
+    mutation_type: root("mutationType"),
+    types: BTreeMap::new(),
+  };
+
+  for ty in introspection["types"].as_array().into_iter().flatten() {
+    let Some(name) = ty["name"].as_str().filter(|name| !name.starts_with("__")) else {
+      continue;
+    };
+    let kind = match ty["kind"].as_str().unwrap_or_default() {
+      "SCALAR" => TypeKind::Scalar,
+      "OBJECT" => TypeKind::Object,
+      "INTERFACE" => TypeKind::Interface,
+      "UNION" => TypeKind::Union,
+      "ENUM" => TypeKind::Enum,
+      "INPUT_OBJECT" => TypeKind::InputObject,
+      other => return Err(Error::GraphQl(format!("type `{name}` has an unknown kind `{other}`"))),
+    };
+    let mut def = TypeDef::new(kind);
+    def.description = description(ty);
+    for field in ty["fields"].as_array().into_iter().flatten() {
+      def.fields.push(Field {
+        name: field["name"].as_str().unwrap_or_default().to_string(),
+        description: description(field),
+        args: input_values(&field["args"])?,
+        ty: type_ref(&field["type"])?,
+      });
+    }
+    def.input_fields = input_values(&ty["inputFields"])?;
+    def.enum_values = ty["enumValues"]
+      .as_array()
+      .into_iter()
+      .flatten()
+      .filter_map(|value| value["name"].as_str().map(str::to_string))
+      .collect();
+    schema.types.insert(name.to_string(), def);
+  }
+  Ok(schema)
+}
+
+fn description(value: &Value) -> Option<String> {
+  value["description"].as_str().filter(|text| !text.is_empty()).map(str::to_string)
+}
+
+fn input_values(values: &Value) -> Result<Vec<InputValue>> {
+  values
+    .as_array()
+    .into_iter()
+    .flatten()
+    .map(|value| {
+      Ok(InputValue {
+        name: value["name"].as_str().unwrap_or_default().to_string(),
+        description: description(value),
+        ty: type_ref(&value["type"])?,
+        default_value: value["defaultValue"].as_str().map(str::to_string),
+      })
+    })
+    .collect()
+}
+
+fn type_ref(value: &Value) -> Result<TypeRef> {
+  match value["kind"].as_str() {
+    Some("NON_NULL") => Ok(TypeRef::NonNull(Box::new(type_ref(&value["ofType"])?))),
+    Some("LIST") => Ok(TypeRef::List(Box::new(type_ref(&value["ofType"])?))),
+    _ => match value["name"].as_str() {
+      Some(name) => Ok(TypeRef::Named(name.to_string())),
+      None => Err(Error::GraphQl("a type reference has no name; the introspection query nests too shallowly".to_string())),
+    },
+  }
+}
+
+/// One endpoint per query and mutation field, all `POST` to `path`, tagged
+/// `query` or `mutation`.
+pub fn extract_endpoints(schema: &Schema, path: &str) -> Vec<ApiEndpoint> {
+  let mut endpoints = Vec::new();
+  for (kind, root) in [("query", &schema.query_type), ("mutation", &schema.mutation_type)] {
+    let Some(root) = root.as_deref().and_then(|name| schema.types.get(name)) else {
+      continue;
+    };
+    for field in &root.fields {
+      let (document, data) = operation(schema, kind, field);
+      endpoints.push(ApiEndpoint {
+        path: path.to_string(),
+        method: "POST".to_string(),
+        operation_id: Some(field.name.clone()),
+        summary: field.description.as_deref().and_then(|text| text.lines().next()).map(str::to_string),
+        description: field.description.clone(),
+        request_schema: Some(json!({
+          "type": "object",
+          "required": ["query"],
+          "properties": {
+            "query": { "type": "string", "example": document },
+            "operationName": { "type": "string", "example": operation_name(&field.name) },
+            "variables": variables_schema(schema, &field.args),
+          },
+        })),
+        response_schema: Some(json!({
+          "type": "object",
+          "properties": {
+            "data": { "type": "object", "properties": { field.name.clone(): data } },
+            "errors": {
+              "type": "array",
+              "items": { "type": "object", "properties": { "message": { "type": "string" } } },
+            },
+          },
+        })),
+        parameters: Vec::new(),
+        tags: vec![kind.to_string()],
+      });
+    }
+  }
+  endpoints
+}
+
+/// `createUser` as the operation `CreateUser`.
+pub fn operation_name(field: &str) -> String {
+  let mut chars = field.chars();
+  chars
+    .next()
+    .map(|first| first.to_uppercase().chain(chars).collect())
+    .unwrap_or_default()
+}
+
+/// An operation document calling `field` with every argument as a variable,
+/// and the schema of the data it selects.
+fn operation(schema: &Schema, kind: &str, field: &Field) -> (String, Value) {
+  let mut document = format!("{kind} {}", operation_name(&field.name));
+  if !field.args.is_empty() {
+    let variables: Vec<String> = field.args.iter().map(|arg| format!("${}: {}", arg.name, arg.ty)).collect();
+    document.push_str(&format!("({})", variables.join(", ")));
+  }
+  document.push_str(" {\n  ");
+  document.push_str(&field.name);
+  if !field.args.is_empty() {
+    let arguments: Vec<String> = field.args.iter().map(|arg| format!("{0}: ${0}", arg.name)).collect();
+    document.push_str(&format!("({})", arguments.join(", ")));
+  }
+  let (selection, data) = select(schema, &field.ty, 1, 1);
+  document.push_str(&selection);
+  document.push_str("\n}");
+  (document, data)
+}
+
+/// The selection set for a field of type `ty`, indented for `level`, with
+/// the schema of the value it yields. Leaf types select nothing; object
+/// fields that need arguments are left out.
+fn select(schema: &Schema, ty: &TypeRef, depth: usize, level: usize) -> (String, Value) {
+  let name = match ty {
+    TypeRef::NonNull(inner) => return select(schema, inner, depth, level),
+    TypeRef::List(inner) => {
+      let (selection, items) = select(schema, inner, depth, level);
+      return (selection, json!({ "type": "array", "items": items }));
+    }
+    TypeRef::Named(name) => name,
+  };
+  let Some(def) = schema.types.get(name) else {
+    return (String::new(), json!({}));
+  };
+  let fields: &[Field] = match def.kind {
+    TypeKind::Scalar => return (String::new(), scalar_schema(name)),
+    TypeKind::Enum => return (String::new(), json!({ "type": "string", "enum": def.enum_values })),
+    TypeKind::Object | TypeKind::Interface => &def.fields,
+    TypeKind::Union | TypeKind::InputObject => &[],
+  };
+
+  let indent = "  ".repeat(level + 1);
+  let mut lines = Vec::new();
+  let mut properties = Map::new();
+  for field in fields {
+    if field.args.iter().any(InputValue::is_required) {
+      continue;
+    }
+    let leaf = schema
+      .types
+      .get(field.ty.name())
+      .map_or(true, |def| matches!(def.kind, TypeKind::Scalar | TypeKind::Enum));
+    if !leaf && depth >= MAX_SELECTION_DEPTH {
+      continue;
+    }
+    let (selection, value) = select(schema, &field.ty, depth + 1, level + 1);
+    lines.push(format!("{indent}{}{selection}", field.name));
+    properties.insert(field.name.clone(), value);
+  }
+  // A selection set cannot be empty; unions have no fields of their own.
+  if lines.is_empty() {
+    lines.push(format!("{indent}__typename"));
+    properties.insert("__typename".to_string(), json!({ "type": "string" }));
+  }
+  let selection = format!(" {{\n{}\n{}}}", lines.join("\n"), "  ".repeat(level));
+  (selection, json!({ "type": "object", "properties": properties }))
+}
+
+fn variables_schema(schema: &Schema, args: &[InputValue]) -> Value {
+  input_object_schema(schema, args, 0)
+}
+
+fn input_object_schema(schema: &Schema, values: &[InputValue], depth: usize) -> Value {
+  let mut properties = Map::new();
+  for value in values {
+    let mut property = input_schema(schema, &value.ty, depth);
+    if let (Some(description), Value::Object(property)) = (&value.description, &mut property) {
+      property.insert("description".to_string(), Value::from(description.as_str()));
+    }
+    properties.insert(value.name.clone(), property);
+  }
+  let required: Vec<&str> = values.iter().filter(|value| value.is_required()).map(|value| value.name.as_str()).collect();
+  let mut object = json!({ "type": "object", "properties": properties });
+  if !required.is_empty() {
+    object["required"] = json!(required);
+  }
+  object
+}
+
+fn input_schema(schema: &Schema, ty: &TypeRef, depth: usize) -> Value {
+  match ty {
+    TypeRef::NonNull(inner) => input_schema(schema, inner, depth),
+    TypeRef::List(inner) => json!({ "type": "array", "items": input_schema(schema, inner, depth) }),
+    TypeRef::Named(name) => match schema.types.get(name) {
+      Some(def) if def.kind == TypeKind::Enum => json!({ "type": "string", "enum": def.enum_values }),
+      Some(def) if def.kind == TypeKind::InputObject && depth < MAX_INPUT_DEPTH => {
+        input_object_schema(schema, &def.input_fields, depth + 1)
+      }
+      _ => scalar_schema(name),
+    },
+  }
+}
+
+/// Built-in scalars by their JSON type; custom ones can be anything.
+fn scalar_schema(name: &str) -> Value {
+  match name {
+    "Int" => json!({ "type": "integer" }),
+    "Float" => json!({ "type": "number" }),
+    "String" | "ID" => json!({ "type": "string" }),
+    "Boolean" => json!({ "type": "boolean" }),
+    _ => json!({}),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn extracts_an_endpoint_per_root_field() {
+    let introspection = json!({ "data": { "__schema": {
+      "queryType": { "name": "Query" },
+      "mutationType": null,
+      "types": [
+        { "kind": "OBJECT", "name": "Query", "fields": [{
+          "name": "user",
+          "description": "Look up a user.\nBy ID.",
+          "args": [{ "name": "id", "type": { "kind": "NON_NULL", "ofType": { "kind": "SCALAR", "name": "ID" } }, "defaultValue": null }],
+          "type": { "kind": "OBJECT", "name": "User" }
+        }] },
+        { "kind": "OBJECT", "name": "User", "fields": [
+          { "name": "id", "args": [], "type": { "kind": "NON_NULL", "ofType": { "kind": "SCALAR", "name": "ID" } } },
+          { "name": "role", "args": [], "type": { "kind": "ENUM", "name": "Role" } },
+          { "name": "friends", "args": [{ "name": "first", "type": { "kind": "NON_NULL", "ofType": { "kind": "SCALAR", "name": "Int" } } }],
+            "type": { "kind": "LIST", "ofType": { "kind": "OBJECT", "name": "User" } } },
+          { "name": "pets", "args": [], "type": { "kind": "LIST", "ofType": { "kind": "UNION", "name": "Pet" } } }
+        ] },
+        { "kind": "ENUM", "name": "Role", "enumValues": [{ "name": "ADMIN" }, { "name": "USER" }] },
+        { "kind": "UNION", "name": "Pet", "possibleTypes": [{ "name": "Dog" }] },
+        { "kind": "SCALAR", "name": "ID" },
+        { "kind": "OBJECT", "name": "__Type", "fields": [] }
+      ]
+    } } });
+    let schema = from_introspection(&introspection).unwrap();
+    assert!(!schema.types.contains_key("__Type"));
+
+    let endpoints = extract_endpoints(&schema, "/graphql");
+    assert_eq!(endpoints.len(), 1);
+    let user = &endpoints[0];
+    assert_eq!((user.method.as_str(), user.path.as_str()), ("POST", "/graphql"));
+    assert_eq!(user.operation_id.as_deref(), Some("user"));
+    assert_eq!(user.summary.as_deref(), Some("Look up a user."));
+    assert_eq!(user.tags, ["query"]);
+
+    let request = user.request_schema.as_ref().unwrap();
+    assert_eq!(
+      request["properties"]["query"]["example"],
+      "query User($id: ID!) {\n  user(id: $id) {\n    id\n    role\n    pets {\n      __typename\n    }\n  }\n}"
+    );
+    assert_eq!(request["properties"]["operationName"]["example"], "User");
+    assert_eq!(
+      request["properties"]["variables"],
+      json!({ "type": "object", "properties": { "id": { "type": "string" } }, "required": ["id"] })
+    );
+    assert_eq!(
+      user.response_schema.as_ref().unwrap()["properties"]["data"]["properties"]["user"]["properties"]["role"],
+      json!({ "type": "string", "enum": ["ADMIN", "USER"] })
+    );
+
+    let error = from_introspection(&json!({ "errors": [{ "message": "introspection is disabled" }] })).unwrap_err();
+    assert!(error.to_string().contains("introspection is disabled"));
+  }
+}
@@ -0,0 +1,486 @@
+//! GraphQL schema definition language (SDL), as in `schema.graphql`.
+//!
+//! Reads the type system: `schema`, `type`, `interface`, `input`, `enum`,
+//! `union` and `scalar` definitions and their `extend` forms, with
+//! descriptions. Directives are skipped, as are `directive` definitions.
+
+use std::collections::BTreeMap;
+
+use super::{Field, InputValue, Schema, TypeDef, TypeKind, TypeRef};
+use crate::error::{Error, Result};
+
+const BUILT_IN_SCALARS: [&str; 5] = ["Int", "Float", "String", "Boolean", "ID"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Name(String),
+  Punct(char),
+  /// A string or block string, unescaped.
+  Str(String),
+  /// A number or other literal, as written.
+  Literal(String),
+}
+
+pub fn parse(sdl: &str) -> Result<Schema> {
+  let mut parser = Parser {
+    tokens: tokenize(sdl)?,
+    position: 0,
+  };
+  let mut types: BTreeMap<String, TypeDef> = BUILT_IN_SCALARS
+    .iter()
+    .map(|name| (name.to_string(), TypeDef::new(TypeKind::Scalar)))
+    .collect();
+  let mut roots: Option<(Option<String>, Option<String>)> = None;
+
+  while parser.peek().is_some() {
+    let description = parser.description();
+    let mut keyword = parser.name()?;
+    if keyword == "extend" {
+      keyword = parser.name()?;
+    }
+    if keyword == "schema" {
+      parser.directives()?;
+      parser.expect('{')?;
+      let (query, mutation) = roots.get_or_insert((None, None));
+      while !parser.eat('}') {
+        let operation = parser.name()?;
+        parser.expect(':')?;
+        let name = parser.name()?;
+        match operation.as_str() {
+          "query" => *query = Some(name),
+          "mutation" => *mutation = Some(name),
+          _ => {}
+        }
+      }
+      continue;
+    }
+    if keyword == "directive" {
+      parser.skip_directive_definition()?;
+      continue;
+    }
+
+    let kind = match keyword.as_str() {
+      "scalar" => TypeKind::Scalar,
+      "type" => TypeKind::Object,
+      "interface" => TypeKind::Interface,
+      "union" => TypeKind::Union,
+      "enum" => TypeKind::Enum,
+      "input" => TypeKind::InputObject,
+      other => return Err(parser.error(&format!("unexpected `{other}`"))),
+    };
+    let name = parser.name()?;
+    let def = types.entry(name).or_insert_with(|| TypeDef::new(kind));
+    if description.is_some() {
+      def.description = description;
+    }
+    if matches!(kind, TypeKind::Object | TypeKind::Interface) && parser.peek() == Some(&Token::Name("implements".to_string())) {
+      parser.position += 1;
+      parser.eat('&');
+      parser.name()?;
+      while parser.eat('&') {
+        parser.name()?;
+      }
+    }
+    parser.directives()?;
+    match kind {
+      TypeKind::Object | TypeKind::Interface if parser.eat('{') => {
+        while !parser.eat('}') {
+          def.fields.push(parser.field()?);
+        }
+      }
+      TypeKind::InputObject if parser.eat('{') => {
+        while !parser.eat('}') {
+          def.input_fields.push(parser.input_value()?);
+        }
+      }
+      TypeKind::Enum if parser.eat('{') => {
+        while !parser.eat('}') {
+          parser.description();
+          def.enum_values.push(parser.name()?);
+          parser.directives()?;
+        }
+      }
+      TypeKind::Union if parser.eat('=') => {
+        parser.eat('|');
+        parser.name()?;
+        while parser.eat('|') {
+          parser.name()?;
+        }
+      }
+      _ => {}
+    }
+  }
+
+  // Without a `schema` block the roots go by their conventional names.
+  let (query_type, mutation_type) = roots.unwrap_or_else(|| {
+    let conventional = |name: &str| types.contains_key(name).then(|| name.to_string());
+    (conventional("Query"), conventional("Mutation"))
+  });
+  Ok(Schema {
+    query_type,
+    mutation_type,
+    types,
+  })
+}
+
+struct Parser {
+  /// Each token with its line.
+  tokens: Vec<(Token, usize)>,
+  position: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position).map(|(token, _)| token)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token = self.tokens.get(self.position).map(|(token, _)| token.clone());
+    self.position += 1;
+    token
+  }
+
+  fn eat(&mut self, punct: char) -> bool {
+    let found = self.peek() == Some(&Token::Punct(punct));
+    if found {
+      self.position += 1;
+    }
+    found
+  }
+
+  fn expect(&mut self, punct: char) -> Result<()> {
+    if self.eat(punct) {
+      Ok(())
+    } else {
+      Err(self.error(&format!("expected `{punct}`")))
+    }
+  }
+
+  fn name(&mut self) -> Result<String> {
+    match self.peek() {
+      Some(Token::Name(name)) => {
+        let name = name.clone();
+        self.position += 1;
+        Ok(name)
+      }
+      _ => Err(self.error("expected a name")),
+    }
+  }
+
+  fn description(&mut self) -> Option<String> {
+    match self.peek() {
+      Some(Token::Str(text)) => {
+        let text = text.clone();
+        self.position += 1;
+        Some(text).filter(|text| !text.is_empty())
+      }
+      _ => None,
+    }
+  }
+
+  /// `description? name arguments? : Type directives`
+  fn field(&mut self) -> Result<Field> {
+    let description = self.description();
+    let name = self.name()?;
+    let mut args = Vec::new();
+    if self.eat('(') {
+      while !self.eat(')') {
+        args.push(self.input_value()?);
+      }
+    }
+    self.expect(':')?;
+    let ty = self.type_ref()?;
+    self.directives()?;
+    Ok(Field {
+      name,
+      description,
+      args,
+      ty,
+    })
+  }
+
+  /// `description? name : Type (= value)? directives`
+  fn input_value(&mut self) -> Result<InputValue> {
+    let description = self.description();
+    let name = self.name()?;
+    self.expect(':')?;
+    let ty = self.type_ref()?;
+    let default_value = if self.eat('=') { Some(self.value()?) } else { None };
+    self.directives()?;
+    Ok(InputValue {
+      name,
+      description,
+      ty,
+      default_value,
+    })
+  }
+
+  fn type_ref(&mut self) -> Result<TypeRef> {
+    let ty = if self.eat('[') {
+      let inner = self.type_ref()?;
+      self.expect(']')?;
+      TypeRef::List(Box::new(inner))
+    } else {
+      TypeRef::Named(self.name()?)
+    };
+    Ok(if self.eat('!') { TypeRef::NonNull(Box::new(ty)) } else { ty })
+  }
+
+  /// A constant value, rendered back as GraphQL source.
+  fn value(&mut self) -> Result<String> {
+    match self.next() {
+      Some(Token::Name(name)) => Ok(name),
+      Some(Token::Literal(literal)) => Ok(literal),
+      Some(Token::Str(text)) => Ok(serde_json::Value::from(text).to_string()),
+      Some(Token::Punct('[')) => {
+        let mut items = Vec::new();
+        while !self.eat(']') {
+          items.push(self.value()?);
+        }
+        Ok(format!("[{}]", items.join(", ")))
+      }
+      Some(Token::Punct('{')) => {
+        let mut fields = Vec::new();
+        while !self.eat('}') {
+          let name = self.name()?;
+          self.expect(':')?;
+          fields.push(format!("{name}: {}", self.value()?));
+        }
+        Ok(format!("{{{}}}", fields.join(", ")))
+      }
+      _ => {
+        self.position -= 1;
+        Err(self.error("expected a value"))
+      }
+    }
+  }
+
+  fn directives(&mut self) -> Result<()> {
+    while self.eat('@') {
+      self.name()?;
+      if self.eat('(') {
+        while !self.eat(')') {
+          self.name()?;
+          self.expect(':')?;
+          self.value()?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// `@name arguments? repeatable? on Location | Location`
+  fn skip_directive_definition(&mut self) -> Result<()> {
+    self.expect('@')?;
+    self.name()?;
+    if self.eat('(') {
+      while !self.eat(')') {
+        self.input_value()?;
+      }
+    }
+    if self.name()? == "repeatable" {
+      self.name()?;
+    }
+    self.eat('|');
+    self.name()?;
+    while self.eat('|') {
+      self.name()?;
+    }
+    Ok(())
+  }
+
+  fn error(&self, message: &str) -> Error {
+    match self.tokens.get(self.position) {
+      Some((_, line)) => Error::GraphQl(format!("{message} on line {line}")),
+      None => Error::GraphQl(format!("{message} at the end of the schema")),
+    }
+  }
+}
+
+fn tokenize(sdl: &str) -> Result<Vec<(Token, usize)>> {
+  let chars: Vec<char> = sdl.chars().collect();
+  let mut tokens = Vec::new();
+  let mut line = 1;
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    match c {
+      '\n' => {
+        line += 1;
+        i += 1;
+      }
+      // Commas are insignificant, like whitespace.
+      c if c.is_whitespace() || c == ',' || c == '\u{feff}' => i += 1,
+      '#' => {
+        while i < chars.len() && chars[i] != '\n' {
+          i += 1;
+        }
+      }
+      '"' if chars[i..].starts_with(&['"', '"', '"']) => {
+        let start = line;
+        let mut text = String::new();
+        i += 3;
+        loop {
+          if i >= chars.len() {
+            return Err(Error::GraphQl(format!("unterminated block string on line {start}")));
+          }
+          if chars[i..].starts_with(&['"', '"', '"']) {
+            i += 3;
+            break;
+          }
+          if chars[i..].starts_with(&['\\', '"', '"', '"']) {
+            text.push_str("\"\"\"");
+            i += 4;
+            continue;
+          }
+          if chars[i] == '\n' {
+            line += 1;
+          }
+          text.push(chars[i]);
+          i += 1;
+        }
+        tokens.push((Token::Str(block_string(&text)), start));
+      }
+      '"' => {
+        let mut text = String::new();
+        i += 1;
+        loop {
+          match chars.get(i) {
+            None | Some('\n') => return Err(Error::GraphQl(format!("unterminated string on line {line}"))),
+            Some('"') => break,
+            Some('\\') => {
+              let escaped = match chars.get(i + 1) {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('b') => '\u{8}',
+                Some('f') => '\u{c}',
+                Some('u') => {
+                  let hex: String = chars.iter().skip(i + 2).take(4).collect();
+                  i += 4;
+                  u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).unwrap_or('\u{fffd}')
+                }
+                Some(other) => *other,
+                None => '\\',
+              };
+              text.push(escaped);
+              i += 2;
+            }
+            Some(other) => {
+              text.push(*other);
+              i += 1;
+            }
+          }
+        }
+        i += 1;
+        tokens.push((Token::Str(text), line));
+      }
+      c if c.is_ascii_alphabetic() || c == '_' => {
+        let start = i;
+        while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+          i += 1;
+        }
+        tokens.push((Token::Name(chars[start..i].iter().collect()), line));
+      }
+      c if c.is_ascii_digit() || c == '-' => {
+        let start = i;
+        i += 1;
+        while i < chars.len() && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '.' | '+' | '-')) {
+          i += 1;
+        }
+        tokens.push((Token::Literal(chars[start..i].iter().collect()), line));
+      }
+      '{' | '}' | '(' | ')' | '[' | ']' | ':' | '!' | '=' | '@' | '|' | '&' | '$' => {
+        tokens.push((Token::Punct(c), line));
+        i += 1;
+      }
+      other => return Err(Error::GraphQl(format!("unexpected `{other}` on line {line}"))),
+    }
+  }
+  Ok(tokens)
+}
+
+/// A block string's text without its common indentation and leading and
+/// trailing blank lines, as the spec's `BlockStringValue` has it.
+fn block_string(raw: &str) -> String {
+  let lines: Vec<&str> = raw.lines().collect();
+  let indent = lines
+    .iter()
+    .skip(1)
+    .filter(|line| !line.trim().is_empty())
+    .map(|line| line.len() - line.trim_start().len())
+    .min()
+    .unwrap_or(0);
+  let lines: Vec<&str> = lines
+    .iter()
+    .enumerate()
+    .map(|(index, line)| if index == 0 { line } else { line.get(indent..).unwrap_or_default() })
+    .collect();
+  let first = lines.iter().position(|line| !line.trim().is_empty()).unwrap_or(lines.len());
+  let last = lines.iter().rposition(|line| !line.trim().is_empty()).map_or(first, |last| last + 1);
+  lines[first..last].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::graphql::extract_endpoints;
+
+  #[test]
+  fn reads_type_definitions() {
+    let sdl = r#"
+      # Shop API
+      schema { query: RootQuery, mutation: RootMutation }
+      directive @auth(role: String = "user") repeatable on FIELD_DEFINITION | OBJECT
+
+      """
+      Everything readable.
+        Indented.
+      """
+      type RootQuery {
+        "A product by SKU."
+        product(sku: String!, currency: Currency = EUR): Product @auth
+        products(filter: ProductFilter = {inStock: true, tags: ["a"]}): [Product!]!
+      }
+      type RootMutation { addToCart(input: CartInput!): Cart }
+      extend type RootQuery { cart: Cart }
+      type Product implements Node & Priced @key(fields: "sku") { sku: ID! price(currency: Currency): Float }
+      type Cart { items: [Product] }
+      interface Node { sku: ID! }
+      interface Priced { price: Float }
+      enum Currency { "Euro" EUR USD @deprecated }
+      union SearchResult = | Product | Cart
+      input ProductFilter { inStock: Boolean tags: [String!] }
+      input CartInput { sku: ID!, quantity: Int = 1 }
+      scalar DateTime
+    "#;
+    let schema = parse(sdl).unwrap();
+    assert_eq!(schema.query_type.as_deref(), Some("RootQuery"));
+    assert_eq!(schema.mutation_type.as_deref(), Some("RootMutation"));
+    let query = &schema.types["RootQuery"];
+    assert_eq!(query.description.as_deref(), Some("Everything readable.\n  Indented."));
+    let fields: Vec<&str> = query.fields.iter().map(|field| field.name.as_str()).collect();
+    assert_eq!(fields, ["product", "products", "cart"]);
+    assert_eq!(query.fields[1].ty.to_string(), "[Product!]!");
+    assert_eq!(query.fields[1].args[0].default_value.as_deref(), Some("{inStock: true, tags: [\"a\"]}"));
+    assert_eq!(schema.types["Currency"].enum_values, ["EUR", "USD"]);
+    assert_eq!(schema.types["SearchResult"].kind, TypeKind::Union);
+    assert_eq!(schema.types["Int"].kind, TypeKind::Scalar);
+
+    let endpoints = extract_endpoints(&schema, "/graphql");
+    let operations: Vec<(&str, &str)> = endpoints
+      .iter()
+      .map(|endpoint| (endpoint.tags[0].as_str(), endpoint.operation_id.as_deref().unwrap()))
+      .collect();
+    assert_eq!(operations, [("query", "product"), ("query", "products"), ("query", "cart"), ("mutation", "addToCart")]);
+    assert_eq!(
+      endpoints[0].request_schema.as_ref().unwrap()["properties"]["query"]["example"],
+      "query Product($sku: String!, $currency: Currency) {\n  product(sku: $sku, currency: $currency) {\n    sku\n    price\n  }\n}"
+    );
+    let input = &endpoints[3].request_schema.as_ref().unwrap()["properties"]["variables"]["properties"]["input"];
+    assert_eq!(input["required"], serde_json::json!(["sku"]));
+
+    let error = parse("type Query { name String }").unwrap_err();
+    assert_eq!(error.to_string(), "invalid GraphQL schema: expected `:` on line 1");
+  }
+}
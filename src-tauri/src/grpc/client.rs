@@ -0,0 +1,377 @@
+//! gRPC calls over HTTP/2, with messages taken from and given back in
+//! protobuf's JSON mapping.
+//!
+//! A call opens a fresh connection the way an HTTP request does, with
+//! prior knowledge for `http:` URLs and ALPN for `https:` ones. Messages go
+//! out uncompressed and compressed replies are refused. Unary and
+//! server-streaming methods can be called.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use ::http::header::{CONTENT_TYPE, TE};
+use ::http::{HeaderValue, Method, Request};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use percent_encoding::percent_decode_str;
+use prost::Message;
+use prost_reflect::{DynamicMessage, MethodDescriptor, SerializeOptions};
+use serde_json::Value;
+use url::Url;
+
+use crate::error::{Error, Result};
+use crate::http::{self, HttpClient, HttpRequest, HttpResponse, RequestTiming, DEFAULT_TIMEOUT_MS};
+
+/// Status code names, indexed by code.
+const STATUS_NAMES: [&str; 17] = [
+  "OK",
+  "CANCELLED",
+  "UNKNOWN",
+  "INVALID_ARGUMENT",
+  "DEADLINE_EXCEEDED",
+  "NOT_FOUND",
+  "ALREADY_EXISTS",
+  "PERMISSION_DENIED",
+  "RESOURCE_EXHAUSTED",
+  "FAILED_PRECONDITION",
+  "ABORTED",
+  "OUT_OF_RANGE",
+  "UNIMPLEMENTED",
+  "INTERNAL",
+  "UNAVAILABLE",
+  "DATA_LOSS",
+  "UNAUTHENTICATED",
+];
+
+/// The longest `grpc-timeout` value, in its eight digits.
+const MAX_TIMEOUT_MS: u64 = 99_999_999;
+
+/// Call `method` at the request's URL, its JSON body (or an empty message)
+/// being the request message. A server stream is read until `max_messages`
+/// have arrived, the server ends it or `wait_ms` passes, whichever comes
+/// first. Returns the response, trailers included among its headers, and
+/// as the body the reply, or the array of streamed replies, as JSON.
+pub async fn call(
+  client: &HttpClient,
+  method: &MethodDescriptor,
+  request: HttpRequest,
+  max_messages: Option<usize>,
+  wait_ms: u64,
+) -> Result<(HttpResponse, Value)> {
+  if method.is_client_streaming() {
+    return Err(Error::Grpc(format!(
+      "`{}` streams its requests; only unary and server-streaming methods can be called",
+      method.full_name()
+    )));
+  }
+  let url = Url::parse(&request.url).map_err(|err| Error::InvalidRequest(format!("invalid URL `{}`: {err}", request.url)))?;
+  let message = match request.body.as_deref().filter(|body| !body.trim().is_empty()) {
+    Some(body) => {
+      let mut deserializer = serde_json::Deserializer::from_str(body);
+      DynamicMessage::deserialize(method.input(), &mut deserializer)
+        .and_then(|message| deserializer.end().map(|()| message))
+        .map_err(|err| Error::Grpc(format!("the body is no `{}` message: {err}", method.input().full_name())))?
+    }
+    None => DynamicMessage::new(method.input()),
+  };
+  let encoded = message.encode_to_vec();
+  let mut frame = Vec::with_capacity(5 + encoded.len());
+  frame.push(0);
+  frame.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+  frame.extend_from_slice(&encoded);
+
+  let timeout_ms = request.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
+  let mut headers = http::build_header_map(&request.headers)?;
+  headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+  headers.insert(TE, HeaderValue::from_static("trailers"));
+  headers.insert("grpc-accept-encoding", HeaderValue::from_static("identity"));
+  let grpc_timeout = format!("{}m", timeout_ms.min(MAX_TIMEOUT_MS));
+  headers.insert("grpc-timeout", HeaderValue::from_str(&grpc_timeout).map_err(|err| Error::InvalidRequest(err.to_string()))?);
+  let mut outgoing = Request::builder()
+    .method(Method::POST)
+    .uri(url.as_str())
+    .body(Full::new(Bytes::from(frame)))
+    .map_err(|err| Error::InvalidRequest(err.to_string()))?;
+  *outgoing.headers_mut() = headers;
+
+  let started_at = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or_default();
+  let start = Instant::now();
+  let exchange = async {
+    let (mut sender, timing) = client.open_http2(&url).await?;
+    let request_start = Instant::now();
+    let response = sender.send_request(outgoing).await?;
+    Ok::<_, Error>((response, RequestTiming {
+      ttfb_ms: elapsed_ms(request_start),
+      ..timing
+    }))
+  };
+  let timeout = Duration::from_millis(timeout_ms);
+  let (response, timing) = tokio::time::timeout(timeout, exchange)
+    .await
+    .map_err(|_| Error::Timeout(timeout_ms))??;
+  log::debug!("[grpc] {} answered {}", method.full_name(), response.status());
+  let (parts, mut body) = response.into_parts();
+
+  // A reply must arrive within the request's timeout; a stream is read
+  // for as long as the call allows.
+  let streaming = method.is_server_streaming();
+  let deadline = if streaming {
+    tokio::time::Instant::now() + Duration::from_millis(wait_ms)
+  } else {
+    tokio::time::Instant::from_std(start) + timeout
+  };
+  let limit = max_messages.filter(|_| streaming);
+  let download_start = Instant::now();
+  let mut buffer = Vec::new();
+  let mut messages = Vec::new();
+  let mut trailers = ::http::HeaderMap::new();
+  let mut ended = false;
+  while limit.map_or(true, |max| messages.len() < max) {
+    let frame = match tokio::time::timeout_at(deadline, body.frame()).await {
+      Ok(frame) => frame,
+      Err(_) if streaming => break,
+      Err(_) => return Err(Error::Timeout(timeout_ms)),
+    };
+    let Some(frame) = frame else {
+      ended = true;
+      break;
+    };
+    match frame?.into_data() {
+      Ok(data) => {
+        buffer.extend_from_slice(&data);
+        while let Some(payload) = next_message(&mut buffer)? {
+          messages.push(decode(method, &payload)?);
+        }
+      }
+      Err(frame) => {
+        if let Ok(map) = frame.into_trailers() {
+          trailers = map;
+        }
+      }
+    }
+  }
+  if let Some(max) = limit {
+    messages.truncate(max);
+  }
+
+  let headers: Vec<(String, String)> = parts
+    .headers
+    .iter()
+    .chain(trailers.iter())
+    .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+    .collect();
+  let has_status = headers.iter().any(|(name, _)| name == "grpc-status");
+  if ended && parts.status.is_success() && !has_status {
+    return Err(Error::Grpc("the response ended without a grpc-status".to_string()));
+  }
+  let body = if streaming {
+    Value::Array(messages)
+  } else {
+    messages.into_iter().next().unwrap_or(Value::Null)
+  };
+  let response = HttpResponse {
+    status: parts.status.as_u16(),
+    status_text: parts.status.canonical_reason().unwrap_or_default().to_string(),
+    url: url.to_string(),
+    headers,
+    set_cookies: Vec::new(),
+    body: serde_json::to_vec(&body)?,
+    timing: RequestTiming {
+      started_at,
+      download_ms: elapsed_ms(download_start),
+      total_ms: elapsed_ms(start),
+      ..timing
+    },
+  };
+  Ok((response, body))
+}
+
+/// What a response's `grpc-status` reports unless it is `OK`. There is none
+/// to report for a stream read only in part, which has no status yet.
+pub fn status_error(response: &HttpResponse) -> Option<String> {
+  let header = |name: &str| {
+    response
+      .headers
+      .iter()
+      .rev()
+      .find(|(header, _)| header.eq_ignore_ascii_case(name))
+      .map(|(_, value)| value.trim())
+  };
+  let code = header("grpc-status")?;
+  if code == "0" {
+    return None;
+  }
+  let name = code.parse::<usize>().ok().and_then(|code| STATUS_NAMES.get(code)).unwrap_or(&"UNKNOWN");
+  let message = header("grpc-message")
+    .map(|message| percent_decode_str(message).decode_utf8_lossy().into_owned())
+    .unwrap_or_default();
+  Some(if message.is_empty() {
+    format!("gRPC call failed with status {name} ({code})")
+  } else {
+    format!("gRPC call failed with status {name} ({code}): {message}")
+  })
+}
+
+/// Take the next complete length-prefixed message off `buffer`.
+fn next_message(buffer: &mut Vec<u8>) -> Result<Option<Vec<u8>>> {
+  let Some(prefix) = buffer.get(..5) else {
+    return Ok(None);
+  };
+  if prefix[0] != 0 {
+    return Err(Error::Grpc("the server sent a compressed message, which is not supported".to_string()));
+  }
+  let length = u32::from_be_bytes([prefix[1], prefix[2], prefix[3], prefix[4]]) as usize;
+  if buffer.len() < 5 + length {
+    return Ok(None);
+  }
+  let payload = buffer[5..5 + length].to_vec();
+  buffer.drain(..5 + length);
+  Ok(Some(payload))
+}
+
+/// A reply as JSON, fields at their default values included so that
+/// assertions can rely on them.
+fn decode(method: &MethodDescriptor, payload: &[u8]) -> Result<Value> {
+  let output = method.output();
+  let message = DynamicMessage::decode(output.clone(), payload)
+    .map_err(|err| Error::Grpc(format!("cannot decode a `{}` message: {err}", output.full_name())))?;
+  let options = SerializeOptions::new().skip_default_fields(false);
+  Ok(message.serialize_with_options(serde_json::value::Serializer, &options)?)
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+  start.elapsed().as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+  use std::convert::Infallible;
+
+  use ::http::HeaderMap;
+  use futures_util::stream;
+  use http_body_util::StreamBody;
+  use hyper::body::{Frame, Incoming};
+  use hyper::service::service_fn;
+  use hyper_util::rt::{TokioExecutor, TokioIo};
+  use prost_reflect::DescriptorPool;
+  use serde_json::json;
+
+  use super::*;
+  use crate::flow::runner::{EndpointStatus, FlowRunner};
+  use crate::flow::TestFlow;
+  use crate::grpc::{encode, find_method, proto};
+  use crate::test_support::serve;
+
+  type Body = StreamBody<stream::Iter<std::vec::IntoIter<Result<Frame<Bytes>, Infallible>>>>;
+
+  const GREETER: &str = r#"
+    syntax = "proto3";
+    package greet;
+    message HelloRequest { string name = 1; int32 times = 2; }
+    message HelloReply { string message = 1; int64 count = 2; bool last = 3; }
+    service Greeter {
+      rpc SayHello(HelloRequest) returns (HelloReply);
+      rpc SayHellos(HelloRequest) returns (stream HelloReply);
+    }
+  "#;
+
+  /// Greets `name` once, or `times` times as a stream. `nobody` gets a
+  /// trailers-only `NOT_FOUND`.
+  async fn respond(pool: DescriptorPool, request: ::http::Request<Incoming>) -> Result<::http::Response<Body>, Infallible> {
+    assert_eq!(request.headers()["te"], "trailers");
+    let method = find_method(&pool, request.uri().path()).unwrap();
+    let body = request.into_body().collect().await.unwrap().to_bytes();
+    let input = DynamicMessage::decode(method.input(), &body[5..]).unwrap();
+    let name = input.get_field_by_name("name").unwrap().as_str().unwrap().to_string();
+    let response = ::http::Response::builder().header("content-type", "application/grpc");
+    if name == "nobody" {
+      let response = response.header("grpc-status", "5").header("grpc-message", "no%20such%20person");
+      return Ok(response.body(StreamBody::new(stream::iter(Vec::new()))).unwrap());
+    }
+
+    let count = if method.is_server_streaming() {
+      input.get_field_by_name("times").unwrap().as_i32().unwrap()
+    } else {
+      1
+    };
+    let mut frames: Vec<Result<Frame<Bytes>, Infallible>> = (1..=count)
+      .map(|n| {
+        let mut reply = DynamicMessage::new(method.output());
+        reply.set_field_by_name("message", prost_reflect::Value::String(format!("Hello, {name}")));
+        reply.set_field_by_name("count", prost_reflect::Value::I64(n.into()));
+        reply.set_field_by_name("last", prost_reflect::Value::Bool(n == count && count > 1));
+        let encoded = reply.encode_to_vec();
+        let mut frame = vec![0];
+        frame.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&encoded);
+        Ok(Frame::data(Bytes::from(frame)))
+      })
+      .collect();
+    let mut trailers = HeaderMap::new();
+    trailers.insert("grpc-status", HeaderValue::from_static("0"));
+    frames.push(Ok(Frame::trailers(trailers)));
+    Ok(response.body(StreamBody::new(stream::iter(frames))).unwrap())
+  }
+
+  async fn server(pool: DescriptorPool) -> u16 {
+    serve(move |stream| {
+      let pool = pool.clone();
+      async move {
+        let service = service_fn(move |request| respond(pool.clone(), request));
+        let connection = hyper::server::conn::http2::Builder::new(TokioExecutor::new()).serve_connection(TokioIo::new(stream), service);
+        let _ = connection.await;
+      }
+    })
+    .await
+  }
+
+  #[tokio::test]
+  async fn calls_unary_and_server_streaming_methods() {
+    let mut pool = DescriptorPool::global();
+    pool.add_file_descriptor_proto(proto::parse("greet.proto", GREETER).unwrap()).unwrap();
+    let port = server(pool.clone()).await;
+    let grpc = |extra: Value| {
+      let mut call = json!({ "descriptorSet": encode(&pool) });
+      call.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+      call
+    };
+    let flow: TestFlow = serde_json::from_value(json!({
+      "settings": { "api_hosts": { "1": { "url": format!("http://127.0.0.1:{port}"), "name": "Greeter" } } },
+      "parameters": [{ "name": "who", "defaultValue": "Ada" }],
+      "endpoints": [
+        { "id": 1, "path": "/greet.Greeter/SayHello", "method": "POST" },
+        { "id": 2, "path": "/greet.Greeter/SayHellos", "method": "POST" }
+      ],
+      "steps": [{ "step_id": "step1", "endpoints": [
+        {
+          "endpoint_id": 1, "api_id": 1, "grpc": grpc(json!({})),
+          "body": { "name": "{{param:who}}" },
+          "assertions": [{ "assertion_type": "json_body", "data_id": "$.message", "operator": "equals", "expected_value": "Hello, Ada" }]
+        },
+        {
+          "endpoint_id": 2, "api_id": 1, "grpc": grpc(json!({})),
+          "body": { "name": "Ada", "times": 3 },
+          "transformations": [{ "alias": "counts", "expression": "$ | map($.count)" }]
+        },
+        { "endpoint_id": 2, "api_id": 1, "grpc": grpc(json!({ "maxMessages": 2 })), "body": { "name": "Ada", "times": 5 } },
+        { "endpoint_id": 1, "api_id": 1, "grpc": grpc(json!({})), "body": { "name": "nobody" } }
+      ] }]
+    }))
+    .unwrap();
+
+    let client = HttpClient::new().unwrap();
+    let result = FlowRunner::new(&client, &flow, None).run(&Default::default()).await;
+    let statuses: Vec<EndpointStatus> = result.endpoints.iter().map(|endpoint| endpoint.status).collect();
+    use EndpointStatus::{Completed, Failed};
+    assert_eq!(statuses, [Completed, Completed, Completed, Failed], "{:?}", result.endpoints[0].error);
+    assert_eq!(result.stored_responses["step1-0"], json!({ "message": "Hello, Ada", "count": "1", "last": false }));
+    assert_eq!(result.stored_transformations["step1-1"]["counts"], json!(["1", "2", "3"]));
+    assert_eq!(result.stored_responses["step1-2"].as_array().unwrap().len(), 2);
+    assert_eq!(
+      result.endpoints[3].error.as_deref(),
+      Some("gRPC call failed with status NOT_FOUND (5): no such person")
+    );
+  }
+}
@@ -0,0 +1,221 @@
+//! gRPC APIs, described by `.proto` files or compiled descriptor sets.
+//!
+//! [`load`] reads either kind into a [`DescriptorPool`], `.proto` files
+//! through the [`proto`] parser along with their imports; the well-known
+//! `google/protobuf/*.proto` files need not be at hand. [`extract_endpoints`]
+//! turns every method into the same [`ApiEndpoint`] records an OpenAPI
+//! import produces, `POST`ed to `/package.Service/Method` as gRPC has it,
+//! with schemas of its messages in protobuf's JSON mapping. The API keeps
+//! its descriptors as a base64 `FileDescriptorSet` ([`encode`]), which flow
+//! steps carry in their `grpc` call for [`client::call`].
+
+pub mod client;
+pub mod proto;
+
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use prost::Message;
+use prost_reflect::{DescriptorError, DescriptorPool, FileDescriptor, Kind, MessageDescriptor, MethodDescriptor};
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
+use serde_json::{json, Map, Value};
+
+use crate::error::{Error, Result};
+use crate::openapi::ApiEndpoint;
+
+/// Nesting of messages described in schemas; deeper ones are left open.
+const MAX_SCHEMA_DEPTH: usize = 6;
+
+/// Load `.proto` files and descriptor sets, told apart by extension. A
+/// `.proto` file is known by its path under the first of `import_paths`
+/// holding it, and its imports are looked up there, then next to it.
+pub fn load(paths: &[PathBuf], import_paths: &[PathBuf]) -> Result<DescriptorPool> {
+  let mut pool = DescriptorPool::global();
+  let mut files = Vec::new();
+  for path in paths {
+    if path.extension().is_some_and(|extension| extension == "proto") {
+      let mut roots = import_paths.to_vec();
+      roots.extend(path.parent().map(Path::to_path_buf));
+      let name = roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .map(|relative| {
+          let parts: Vec<_> = relative.iter().map(|part| part.to_string_lossy()).collect();
+          parts.join("/")
+        })
+        .unwrap_or_else(|| path.to_string_lossy().into_owned());
+      load_proto(&name, path, &roots, &pool, &mut files)?;
+    } else {
+      let bytes = std::fs::read(path)?;
+      pool.decode_file_descriptor_set(bytes.as_slice()).map_err(descriptor_error)?;
+    }
+  }
+  pool.add_file_descriptor_protos(files).map_err(descriptor_error)?;
+  if pool.services().len() == 0 {
+    return Err(Error::Grpc("the files define no services".to_string()));
+  }
+  Ok(pool)
+}
+
+/// Parse the file imported as `name` and, recursively, what it imports.
+fn load_proto(name: &str, path: &Path, roots: &[PathBuf], pool: &DescriptorPool, files: &mut Vec<FileDescriptorProto>) -> Result<()> {
+  if pool.get_file_by_name(name).is_some() || files.iter().any(|file| file.name() == name) {
+    return Ok(());
+  }
+  let file = proto::parse(name, &std::fs::read_to_string(path)?)?;
+  let dependencies = file.dependency.clone();
+  files.push(file);
+  for dependency in &dependencies {
+    match roots.iter().map(|root| root.join(dependency)).find(|path| path.is_file()) {
+      Some(path) => load_proto(dependency, &path, roots, pool, files)?,
+      None if pool.get_file_by_name(dependency).is_some() => {}
+      None => {
+        return Err(Error::Grpc(format!("`{name}` imports `{dependency}`, which is in none of the import paths")));
+      }
+    }
+  }
+  Ok(())
+}
+
+/// The files defining services, and everything they import, as a base64
+/// `FileDescriptorSet`.
+pub fn encode(pool: &DescriptorPool) -> String {
+  fn add(file: FileDescriptor, files: &mut Vec<FileDescriptor>) {
+    if files.iter().any(|known| known.name() == file.name()) {
+      return;
+    }
+    for dependency in file.dependencies() {
+      add(dependency, files);
+    }
+    files.push(file);
+  }
+
+  let mut files = Vec::new();
+  for service in pool.services() {
+    add(service.parent_file(), &mut files);
+  }
+  let set = FileDescriptorSet {
+    file: files.iter().map(|file| file.file_descriptor_proto().clone()).collect(),
+  };
+  base64::engine::general_purpose::STANDARD.encode(set.encode_to_vec())
+}
+
+/// The pool of a descriptor set made by [`encode`].
+pub fn decode(descriptor_set: &str) -> Result<DescriptorPool> {
+  let bytes = base64::engine::general_purpose::STANDARD
+    .decode(descriptor_set.trim())
+    .map_err(|err| Error::Grpc(format!("invalid descriptor set: {err}")))?;
+  DescriptorPool::decode(bytes.as_slice()).map_err(descriptor_error)
+}
+
+/// The method an endpoint path such as `/shop.Catalog/GetProduct` names.
+pub fn find_method(pool: &DescriptorPool, path: &str) -> Result<MethodDescriptor> {
+  let (service, method) = path
+    .trim_start_matches('/')
+    .split_once('/')
+    .ok_or_else(|| Error::Grpc(format!("`{path}` is not a method path such as `/package.Service/Method`")))?;
+  let service = pool
+    .get_service_by_name(service)
+    .ok_or_else(|| Error::Grpc(format!("no service `{service}` in the descriptor set")))?;
+  let found = service.methods().find(|candidate| candidate.name() == method);
+  found.ok_or_else(|| Error::Grpc(format!("service `{}` has no method `{method}`", service.full_name())))
+}
+
+/// One endpoint per method, tagged with its service's full name.
+pub fn extract_endpoints(pool: &DescriptorPool) -> Vec<ApiEndpoint> {
+  let mut endpoints = Vec::new();
+  for service in pool.services() {
+    for method in service.methods() {
+      let kind = match (method.is_client_streaming(), method.is_server_streaming()) {
+        (false, false) => "Unary call",
+        (false, true) => "Server-streaming call",
+        (true, false) => "Client-streaming call",
+        (true, true) => "Bidirectional streaming call",
+      };
+      let output = message_schema(&method.output(), 0);
+      endpoints.push(ApiEndpoint {
+        path: format!("/{}/{}", service.full_name(), method.name()),
+        method: "POST".to_string(),
+        operation_id: Some(method.name().to_string()),
+        summary: Some(kind.to_string()),
+        description: None,
+        request_schema: Some(message_schema(&method.input(), 0)),
+        response_schema: Some(if method.is_server_streaming() {
+          json!({ "type": "array", "items": output })
+        } else {
+          output
+        }),
+        parameters: Vec::new(),
+        tags: vec![service.full_name().to_string()],
+      });
+    }
+  }
+  endpoints
+}
+
+fn message_schema(message: &MessageDescriptor, depth: usize) -> Value {
+  if let Some(schema) = well_known_schema(message.full_name()) {
+    return schema;
+  }
+  if depth >= MAX_SCHEMA_DEPTH {
+    return json!({ "type": "object" });
+  }
+  let mut properties = Map::new();
+  for field in message.fields() {
+    let schema = if field.is_map() {
+      let Kind::Message(entry) = field.kind() else {
+        continue;
+      };
+      json!({ "type": "object", "additionalProperties": kind_schema(&entry.map_entry_value_field().kind(), depth) })
+    } else if field.is_list() {
+      json!({ "type": "array", "items": kind_schema(&field.kind(), depth) })
+    } else {
+      kind_schema(&field.kind(), depth)
+    };
+    properties.insert(field.json_name().to_string(), schema);
+  }
+  json!({ "type": "object", "properties": properties })
+}
+
+fn kind_schema(kind: &Kind, depth: usize) -> Value {
+  match kind {
+    Kind::Double | Kind::Float => json!({ "type": "number" }),
+    Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 | Kind::Uint32 | Kind::Fixed32 => json!({ "type": "integer" }),
+    // 64-bit integers are strings in the JSON mapping, though numbers are
+    // read too.
+    Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 | Kind::Uint64 | Kind::Fixed64 => {
+      json!({ "type": "string", "format": "int64" })
+    }
+    Kind::Bool => json!({ "type": "boolean" }),
+    Kind::String => json!({ "type": "string" }),
+    Kind::Bytes => json!({ "type": "string", "format": "byte" }),
+    Kind::Enum(enumeration) => {
+      // In declaration order, which the pool does not keep for aliases.
+      let values: Vec<&str> = enumeration.enum_descriptor_proto().value.iter().map(|value| value.name()).collect();
+      json!({ "type": "string", "enum": values })
+    }
+    Kind::Message(message) => message_schema(message, depth + 1),
+  }
+}
+
+/// Well-known types have JSON forms of their own.
+fn well_known_schema(full_name: &str) -> Option<Value> {
+  Some(match full_name {
+    "google.protobuf.Timestamp" => json!({ "type": "string", "format": "date-time" }),
+    "google.protobuf.Duration" | "google.protobuf.FieldMask" => json!({ "type": "string" }),
+    "google.protobuf.Struct" | "google.protobuf.Empty" | "google.protobuf.Any" => json!({ "type": "object" }),
+    "google.protobuf.ListValue" => json!({ "type": "array" }),
+    "google.protobuf.Value" => json!({}),
+    "google.protobuf.DoubleValue" | "google.protobuf.FloatValue" => json!({ "type": "number" }),
+    "google.protobuf.Int32Value" | "google.protobuf.UInt32Value" => json!({ "type": "integer" }),
+    "google.protobuf.Int64Value" | "google.protobuf.UInt64Value" => json!({ "type": "string", "format": "int64" }),
+    "google.protobuf.BoolValue" => json!({ "type": "boolean" }),
+    "google.protobuf.StringValue" => json!({ "type": "string" }),
+    "google.protobuf.BytesValue" => json!({ "type": "string", "format": "byte" }),
+    _ => return None,
+  })
+}
+
+fn descriptor_error(err: DescriptorError) -> Error {
+  Error::Grpc(format!("invalid descriptors: {err}"))
+}
@@ -0,0 +1,778 @@
+//! Protocol Buffers source files, as in `greeter.proto`.
+//!
+//! Reads `proto2` and `proto3` files into the `FileDescriptorProto` protoc
+//! would produce, except that type references are left as written; the
+//! descriptor pool resolves them against the file's scope and imports.
+//! Options are skipped, bar the few that change how messages are encoded
+//! or mapped to JSON. Extensions and groups are not supported.
+
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{
+  DescriptorProto, EnumDescriptorProto, EnumOptions, EnumValueDescriptorProto, FieldDescriptorProto, FieldOptions,
+  FileDescriptorProto, MessageOptions, MethodDescriptorProto, OneofDescriptorProto, ServiceDescriptorProto,
+};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Ident(String),
+  Punct(char),
+  /// A string literal, unescaped.
+  Str(String),
+  /// A number, as written.
+  Number(String),
+}
+
+/// Parse the source of the file imported as `name`.
+pub fn parse(name: &str, source: &str) -> Result<FileDescriptorProto> {
+  let mut parser = Parser {
+    name,
+    tokens: tokenize(name, source)?,
+    position: 0,
+    proto3: false,
+  };
+  let mut file = FileDescriptorProto {
+    name: Some(name.to_string()),
+    ..Default::default()
+  };
+
+  while parser.peek().is_some() {
+    if parser.eat(';') {
+      continue;
+    }
+    match parser.ident()?.as_str() {
+      "syntax" => {
+        parser.expect('=')?;
+        let syntax = parser.string()?;
+        match syntax.as_str() {
+          "proto2" => {}
+          "proto3" => {
+            parser.proto3 = true;
+            file.syntax = Some(syntax);
+          }
+          other => return Err(parser.error(&format!("unsupported syntax `{other}`"))),
+        }
+        parser.expect(';')?;
+      }
+      "edition" => return Err(parser.error("editions are not supported")),
+      "package" => {
+        file.package = Some(parser.full_name()?);
+        parser.expect(';')?;
+      }
+      "import" => {
+        let index = file.dependency.len() as i32;
+        if parser.eat_ident("public") {
+          file.public_dependency.push(index);
+        } else if parser.eat_ident("weak") {
+          file.weak_dependency.push(index);
+        }
+        file.dependency.push(parser.string()?);
+        parser.expect(';')?;
+      }
+      "option" => parser.skip_statement()?,
+      "message" => file.message_type.push(parser.message()?),
+      "enum" => file.enum_type.push(parser.enumeration()?),
+      "service" => file.service.push(parser.service()?),
+      "extend" => parser.skip_block()?,
+      other => return Err(parser.error_before(&format!("unexpected `{other}`"))),
+    }
+  }
+  Ok(file)
+}
+
+struct Parser<'a> {
+  /// Of the file, for errors.
+  name: &'a str,
+  /// Each token with its line.
+  tokens: Vec<(Token, usize)>,
+  position: usize,
+  proto3: bool,
+}
+
+impl Parser<'_> {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position).map(|(token, _)| token)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token = self.tokens.get(self.position).map(|(token, _)| token.clone());
+    self.position += 1;
+    token
+  }
+
+  fn eat(&mut self, punct: char) -> bool {
+    let found = self.peek() == Some(&Token::Punct(punct));
+    if found {
+      self.position += 1;
+    }
+    found
+  }
+
+  fn expect(&mut self, punct: char) -> Result<()> {
+    if self.eat(punct) {
+      Ok(())
+    } else {
+      Err(self.error(&format!("expected `{punct}`")))
+    }
+  }
+
+  fn eat_ident(&mut self, ident: &str) -> bool {
+    let found = matches!(self.peek(), Some(Token::Ident(name)) if name == ident);
+    if found {
+      self.position += 1;
+    }
+    found
+  }
+
+  fn ident(&mut self) -> Result<String> {
+    match self.peek() {
+      Some(Token::Ident(name)) => {
+        let name = name.clone();
+        self.position += 1;
+        Ok(name)
+      }
+      _ => Err(self.error("expected a name")),
+    }
+  }
+
+  /// A dotted name, with the leading dot of a fully-qualified one kept.
+  fn full_name(&mut self) -> Result<String> {
+    let mut name = String::new();
+    if self.eat('.') {
+      name.push('.');
+    }
+    name.push_str(&self.ident()?);
+    while self.eat('.') {
+      name.push('.');
+      name.push_str(&self.ident()?);
+    }
+    Ok(name)
+  }
+
+  /// One or more adjacent string literals, joined.
+  fn string(&mut self) -> Result<String> {
+    let mut text = match self.next() {
+      Some(Token::Str(text)) => text,
+      _ => {
+        self.position -= 1;
+        return Err(self.error("expected a string"));
+      }
+    };
+    while let Some(Token::Str(more)) = self.peek() {
+      text.push_str(more);
+      self.position += 1;
+    }
+    Ok(text)
+  }
+
+  fn integer(&mut self) -> Result<i64> {
+    let literal = match self.next() {
+      Some(Token::Number(literal)) => literal,
+      _ => {
+        self.position -= 1;
+        return Err(self.error("expected a number"));
+      }
+    };
+    let (negative, digits) = match literal.strip_prefix('-') {
+      Some(digits) => (true, digits),
+      None => (false, literal.as_str()),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+      i64::from_str_radix(hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+      i64::from_str_radix(&digits[1..], 8)
+    } else {
+      digits.parse()
+    };
+    match value {
+      Ok(value) if negative => Ok(-value),
+      Ok(value) => Ok(value),
+      Err(_) => {
+        self.position -= 1;
+        Err(self.error(&format!("invalid number `{literal}`")))
+      }
+    }
+  }
+
+  fn number(&mut self) -> Result<i32> {
+    let value = self.integer()?;
+    i32::try_from(value).map_err(|_| self.error_before(&format!("{value} is out of range")))
+  }
+
+  /// `message Name { ... }`, after `message`.
+  fn message(&mut self) -> Result<DescriptorProto> {
+    let mut message = DescriptorProto {
+      name: Some(self.ident()?),
+      ..Default::default()
+    };
+    self.expect('{')?;
+    while !self.eat('}') {
+      if self.eat(';') {
+        continue;
+      }
+      let map = matches!(self.peek(), Some(Token::Ident(name)) if name == "map")
+        && matches!(self.tokens.get(self.position + 1), Some((Token::Punct('<'), _)));
+      if map {
+        self.position += 1;
+        self.map_field(&mut message)?;
+        continue;
+      }
+      if self.peek() == Some(&Token::Punct('.')) {
+        message.field.push(self.field(Label::Optional)?);
+        continue;
+      }
+      match self.ident()?.as_str() {
+        "message" => message.nested_type.push(self.message()?),
+        "enum" => message.enum_type.push(self.enumeration()?),
+        "option" | "reserved" | "extensions" => self.skip_statement()?,
+        "extend" => self.skip_block()?,
+        "oneof" => {
+          let index = message.oneof_decl.len() as i32;
+          message.oneof_decl.push(OneofDescriptorProto {
+            name: Some(self.ident()?),
+            ..Default::default()
+          });
+          self.expect('{')?;
+          while !self.eat('}') {
+            if self.eat(';') {
+              continue;
+            }
+            if self.eat_ident("option") {
+              self.skip_statement()?;
+              continue;
+            }
+            let mut field = self.field(Label::Optional)?;
+            field.oneof_index = Some(index);
+            message.field.push(field);
+          }
+        }
+        "repeated" => message.field.push(self.field(Label::Repeated)?),
+        "required" => message.field.push(self.field(Label::Required)?),
+        "optional" => {
+          let mut field = self.field(Label::Optional)?;
+          if self.proto3 {
+            field.proto3_optional = Some(true);
+          }
+          message.field.push(field);
+        }
+        _ => {
+          self.position -= 1;
+          message.field.push(self.field(Label::Optional)?);
+        }
+      }
+    }
+
+    // Each proto3 `optional` field sits in a oneof of its own, declared
+    // after the real ones.
+    for index in 0..message.field.len() {
+      if message.field[index].proto3_optional == Some(true) {
+        message.field[index].oneof_index = Some(message.oneof_decl.len() as i32);
+        message.oneof_decl.push(OneofDescriptorProto {
+          name: Some(format!("_{}", message.field[index].name())),
+          ..Default::default()
+        });
+      }
+    }
+    Ok(message)
+  }
+
+  /// `Type name = number [options];`, after any label.
+  fn field(&mut self, label: Label) -> Result<FieldDescriptorProto> {
+    if self.eat_ident("group") {
+      self.position -= 1;
+      return Err(self.error("groups are not supported"));
+    }
+    let type_name = self.full_name()?;
+    let mut field = FieldDescriptorProto {
+      name: Some(self.ident()?),
+      label: Some(label as i32),
+      ..Default::default()
+    };
+    match scalar_type(&type_name) {
+      Some(ty) => field.r#type = Some(ty as i32),
+      None => field.type_name = Some(type_name),
+    }
+    self.expect('=')?;
+    field.number = Some(self.number()?);
+    self.field_options(&mut field)?;
+    self.expect(';')?;
+    Ok(field)
+  }
+
+  /// `map<Key, Value> name = number [options];`, after `map`. The entries
+  /// are a nested message, as protoc declares them.
+  fn map_field(&mut self, message: &mut DescriptorProto) -> Result<()> {
+    self.expect('<')?;
+    let key_type = self.full_name()?;
+    self.expect(',')?;
+    let value_type = self.full_name()?;
+    self.expect('>')?;
+    let name = self.ident()?;
+    let entry_name = format!("{}Entry", upper_camel(&name));
+
+    let entry_field = |name: &str, number: i32, type_name: String| {
+      let mut field = FieldDescriptorProto {
+        name: Some(name.to_string()),
+        number: Some(number),
+        label: Some(Label::Optional as i32),
+        ..Default::default()
+      };
+      match scalar_type(&type_name) {
+        Some(ty) => field.r#type = Some(ty as i32),
+        None => field.type_name = Some(type_name),
+      }
+      field
+    };
+    message.nested_type.push(DescriptorProto {
+      name: Some(entry_name.clone()),
+      field: vec![entry_field("key", 1, key_type), entry_field("value", 2, value_type)],
+      options: Some(MessageOptions {
+        map_entry: Some(true),
+        ..Default::default()
+      }),
+      ..Default::default()
+    });
+
+    let mut field = FieldDescriptorProto {
+      name: Some(name),
+      label: Some(Label::Repeated as i32),
+      r#type: Some(Type::Message as i32),
+      type_name: Some(entry_name),
+      ..Default::default()
+    };
+    self.expect('=')?;
+    field.number = Some(self.number()?);
+    self.field_options(&mut field)?;
+    self.expect(';')?;
+    message.field.push(field);
+    Ok(())
+  }
+
+  /// `[name = value, ...]`, keeping `default`, `json_name` and `packed`.
+  fn field_options(&mut self, field: &mut FieldDescriptorProto) -> Result<()> {
+    if !self.eat('[') {
+      return Ok(());
+    }
+    loop {
+      let option = if self.eat('(') {
+        self.full_name()?;
+        self.expect(')')?;
+        None
+      } else {
+        Some(self.ident()?)
+      };
+      while self.eat('.') {
+        self.ident()?;
+      }
+      self.expect('=')?;
+      let value = self.constant()?;
+      match option.as_deref() {
+        Some("default") => field.default_value = Some(value),
+        Some("json_name") => field.json_name = Some(value),
+        Some("packed") => {
+          field.options.get_or_insert_with(FieldOptions::default).packed = Some(value == "true");
+        }
+        _ => {}
+      }
+      if self.eat(']') {
+        return Ok(());
+      }
+      self.expect(',')?;
+    }
+  }
+
+  /// An option value, as its text; aggregate values come back empty.
+  fn constant(&mut self) -> Result<String> {
+    match self.peek() {
+      Some(Token::Str(_)) => self.string(),
+      Some(Token::Punct('{')) => {
+        self.skip_braces()?;
+        Ok(String::new())
+      }
+      _ => match self.next() {
+        Some(Token::Ident(text) | Token::Number(text)) => Ok(text),
+        _ => {
+          self.position -= 1;
+          Err(self.error("expected a value"))
+        }
+      },
+    }
+  }
+
+  /// `enum Name { ... }`, after `enum`.
+  fn enumeration(&mut self) -> Result<EnumDescriptorProto> {
+    let mut enumeration = EnumDescriptorProto {
+      name: Some(self.ident()?),
+      ..Default::default()
+    };
+    self.expect('{')?;
+    while !self.eat('}') {
+      if self.eat(';') {
+        continue;
+      }
+      match self.ident()?.as_str() {
+        "option" => {
+          if self.eat_ident("allow_alias") {
+            self.expect('=')?;
+            let allow = self.constant()? == "true";
+            enumeration.options.get_or_insert_with(EnumOptions::default).allow_alias = Some(allow);
+            self.expect(';')?;
+          } else {
+            self.skip_statement()?;
+          }
+        }
+        "reserved" => self.skip_statement()?,
+        name => {
+          let name = name.to_string();
+          self.expect('=')?;
+          let number = self.number()?;
+          if self.peek() == Some(&Token::Punct('[')) {
+            self.skip_brackets()?;
+          }
+          self.expect(';')?;
+          enumeration.value.push(EnumValueDescriptorProto {
+            name: Some(name),
+            number: Some(number),
+            ..Default::default()
+          });
+        }
+      }
+    }
+    Ok(enumeration)
+  }
+
+  /// `service Name { rpc ... }`, after `service`.
+  fn service(&mut self) -> Result<ServiceDescriptorProto> {
+    let mut service = ServiceDescriptorProto {
+      name: Some(self.ident()?),
+      ..Default::default()
+    };
+    self.expect('{')?;
+    while !self.eat('}') {
+      if self.eat(';') {
+        continue;
+      }
+      match self.ident()?.as_str() {
+        "option" => self.skip_statement()?,
+        "rpc" => {
+          let name = self.ident()?;
+          let (client_streaming, input_type) = self.rpc_type()?;
+          if !self.eat_ident("returns") {
+            return Err(self.error("expected `returns`"));
+          }
+          let (server_streaming, output_type) = self.rpc_type()?;
+          if self.peek() == Some(&Token::Punct('{')) {
+            self.skip_braces()?;
+          } else {
+            self.expect(';')?;
+          }
+          service.method.push(MethodDescriptorProto {
+            name: Some(name),
+            input_type: Some(input_type),
+            output_type: Some(output_type),
+            client_streaming: Some(client_streaming),
+            server_streaming: Some(server_streaming),
+            ..Default::default()
+          });
+        }
+        other => return Err(self.error_before(&format!("unexpected `{other}`"))),
+      }
+    }
+    Ok(service)
+  }
+
+  /// `(stream? Type)`
+  fn rpc_type(&mut self) -> Result<(bool, String)> {
+    self.expect('(')?;
+    // `stream` is also a valid message name.
+    let keyword = matches!(self.tokens.get(self.position + 1), Some((Token::Ident(_) | Token::Punct('.'), _)));
+    let streaming = keyword && self.eat_ident("stream");
+    let name = self.full_name()?;
+    self.expect(')')?;
+    Ok((streaming, name))
+  }
+
+  /// Everything up to and including the next `;` outside braces.
+  fn skip_statement(&mut self) -> Result<()> {
+    loop {
+      match self.peek() {
+        Some(Token::Punct(';')) => {
+          self.position += 1;
+          return Ok(());
+        }
+        Some(Token::Punct('{')) => self.skip_braces()?,
+        Some(_) => self.position += 1,
+        None => return Err(self.error("expected `;`")),
+      }
+    }
+  }
+
+  /// Everything up to and including the next block in braces.
+  fn skip_block(&mut self) -> Result<()> {
+    while self.peek() != Some(&Token::Punct('{')) {
+      if self.next().is_none() {
+        return Err(self.error("expected `{`"));
+      }
+    }
+    self.skip_braces()
+  }
+
+  fn skip_braces(&mut self) -> Result<()> {
+    self.skip_nested('{', '}')
+  }
+
+  fn skip_brackets(&mut self) -> Result<()> {
+    self.skip_nested('[', ']')
+  }
+
+  fn skip_nested(&mut self, open: char, close: char) -> Result<()> {
+    self.expect(open)?;
+    let mut depth = 1;
+    while depth > 0 {
+      match self.next() {
+        Some(Token::Punct(c)) if c == open => depth += 1,
+        Some(Token::Punct(c)) if c == close => depth -= 1,
+        Some(_) => {}
+        None => {
+          self.position -= 1;
+          return Err(self.error(&format!("expected `{close}`")));
+        }
+      }
+    }
+    Ok(())
+  }
+
+  fn error(&self, message: &str) -> Error {
+    match self.tokens.get(self.position) {
+      Some((_, line)) => Error::Grpc(format!("{message} on line {line} of `{}`", self.name)),
+      None => Error::Grpc(format!("{message} at the end of `{}`", self.name)),
+    }
+  }
+
+  /// An error about the token just read.
+  fn error_before(&mut self, message: &str) -> Error {
+    self.position -= 1;
+    self.error(message)
+  }
+}
+
+fn scalar_type(name: &str) -> Option<Type> {
+  Some(match name {
+    "double" => Type::Double,
+    "float" => Type::Float,
+    "int64" => Type::Int64,
+    "uint64" => Type::Uint64,
+    "int32" => Type::Int32,
+    "fixed64" => Type::Fixed64,
+    "fixed32" => Type::Fixed32,
+    "bool" => Type::Bool,
+    "string" => Type::String,
+    "bytes" => Type::Bytes,
+    "uint32" => Type::Uint32,
+    "sfixed32" => Type::Sfixed32,
+    "sfixed64" => Type::Sfixed64,
+    "sint32" => Type::Sint32,
+    "sint64" => Type::Sint64,
+    _ => return None,
+  })
+}
+
+/// `tag_counts` to `TagCounts`, as protoc names map entry messages.
+fn upper_camel(name: &str) -> String {
+  let mut result = String::with_capacity(name.len());
+  let mut upper = true;
+  for c in name.chars() {
+    if c == '_' {
+      upper = true;
+    } else if upper {
+      result.push(c.to_ascii_uppercase());
+      upper = false;
+    } else {
+      result.push(c);
+    }
+  }
+  result
+}
+
+fn tokenize(name: &str, source: &str) -> Result<Vec<(Token, usize)>> {
+  let chars: Vec<char> = source.chars().collect();
+  let mut tokens = Vec::new();
+  let mut line = 1;
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    match c {
+      '\n' => {
+        line += 1;
+        i += 1;
+      }
+      c if c.is_whitespace() || c == '\u{feff}' => i += 1,
+      '/' if chars.get(i + 1) == Some(&'/') => {
+        while i < chars.len() && chars[i] != '\n' {
+          i += 1;
+        }
+      }
+      '/' if chars.get(i + 1) == Some(&'*') => {
+        let start = line;
+        i += 2;
+        loop {
+          match chars.get(i) {
+            None => return Err(Error::Grpc(format!("unterminated comment on line {start} of `{name}`"))),
+            Some('*') if chars.get(i + 1) == Some(&'/') => break,
+            Some('\n') => line += 1,
+            Some(_) => {}
+          }
+          i += 1;
+        }
+        i += 2;
+      }
+      '"' | '\'' => {
+        let quote = c;
+        let mut text = String::new();
+        i += 1;
+        loop {
+          match chars.get(i) {
+            None | Some('\n') => return Err(Error::Grpc(format!("unterminated string on line {line} of `{name}`"))),
+            Some(&c) if c == quote => break,
+            Some('\\') => {
+              let (escaped, length) = unescape(&chars[i + 1..]);
+              text.push(escaped);
+              i += 1 + length;
+            }
+            Some(other) => {
+              text.push(*other);
+              i += 1;
+            }
+          }
+        }
+        i += 1;
+        tokens.push((Token::Str(text), line));
+      }
+      c if c.is_ascii_alphabetic() || c == '_' => {
+        let start = i;
+        while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+          i += 1;
+        }
+        tokens.push((Token::Ident(chars[start..i].iter().collect()), line));
+      }
+      c if c.is_ascii_digit() || c == '-' || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit)) => {
+        let start = i;
+        i += 1;
+        while i < chars.len()
+          && (chars[i].is_ascii_alphanumeric()
+            || chars[i] == '.'
+            || (matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E')))
+        {
+          i += 1;
+        }
+        tokens.push((Token::Number(chars[start..i].iter().collect()), line));
+      }
+      '{' | '}' | '(' | ')' | '[' | ']' | '<' | '>' | ';' | ',' | '=' | '.' | ':' => {
+        tokens.push((Token::Punct(c), line));
+        i += 1;
+      }
+      other => return Err(Error::Grpc(format!("unexpected `{other}` on line {line} of `{name}`"))),
+    }
+  }
+  Ok(tokens)
+}
+
+/// The character an escape sequence stands for, given what follows the
+/// backslash, and how many characters the sequence takes up.
+fn unescape(rest: &[char]) -> (char, usize) {
+  let digits = |skip: usize, radix: u32, max: usize| {
+    rest.iter().skip(skip).take(max).take_while(|c| c.is_digit(radix)).count()
+  };
+  match rest.first() {
+    Some('n') => ('\n', 1),
+    Some('t') => ('\t', 1),
+    Some('r') => ('\r', 1),
+    Some('a') => ('\u{7}', 1),
+    Some('b') => ('\u{8}', 1),
+    Some('f') => ('\u{c}', 1),
+    Some('v') => ('\u{b}', 1),
+    Some('x' | 'X') => {
+      let count = digits(1, 16, 2);
+      let hex: String = rest[1..1 + count].iter().collect();
+      (u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32).unwrap_or('\u{fffd}'), 1 + count)
+    }
+    Some(c) if c.is_digit(8) => {
+      let count = digits(0, 8, 3);
+      let octal: String = rest[..count].iter().collect();
+      (u32::from_str_radix(&octal, 8).ok().and_then(char::from_u32).unwrap_or('\u{fffd}'), count)
+    }
+    Some(other) => (*other, 1),
+    None => ('\\', 0),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use prost_reflect::{DescriptorPool, Kind};
+  use serde_json::json;
+
+  use super::*;
+  use crate::grpc::extract_endpoints;
+
+  #[test]
+  fn reads_messages_enums_and_services() {
+    let source = r#"
+      // Shop catalog.
+      syntax = "proto3";
+      package shop.v1;
+      option go_package = "example.com/shop;shop";
+      import "google/protobuf/timestamp.proto";
+
+      message Product {
+        string sku = 1 [json_name = "id"];
+        optional double price = 2;
+        repeated string tags = 3;
+        map<string, int64> stock_by_store = 4;
+        Kind kind = 5;
+        google.protobuf.Timestamp added_at = 6;
+        oneof discount { uint32 percent = 7; Money amount = 8; }
+        reserved 9 to 11, 15;
+        enum Kind { option allow_alias = true; KIND_UNSPECIFIED = 0; PHYSICAL = 1; GOODS = 1 [deprecated = true]; }
+        message Money { string currency = 1; sint64 cents = 2; }
+      }
+      message GetProductRequest { string sku = 1; }
+      /* Streams the catalog. */
+      message ListProductsRequest { .shop.v1.Product.Kind kind = 1; }
+
+      service Catalog {
+        option (shop.auth) = { scopes: ["read"] };
+        rpc GetProduct(GetProductRequest) returns (Product);
+        rpc ListProducts(ListProductsRequest) returns (stream Product) { option idempotency_level = NO_SIDE_EFFECTS; }
+      }
+    "#;
+    let file = parse("shop/v1/catalog.proto", source).unwrap();
+    assert_eq!(file.dependency, ["google/protobuf/timestamp.proto"]);
+    let product = &file.message_type[0];
+    let oneofs: Vec<&str> = product.oneof_decl.iter().map(|oneof| oneof.name()).collect();
+    assert_eq!(oneofs, ["discount", "_price"]);
+    assert_eq!(product.nested_type[0].name(), "StockByStoreEntry");
+
+    let mut pool = DescriptorPool::global();
+    pool.add_file_descriptor_proto(file).unwrap();
+    let message = pool.get_message_by_name("shop.v1.Product").unwrap();
+    let kind = message.get_field_by_name("kind").unwrap().kind();
+    assert!(matches!(kind, Kind::Enum(kind) if kind.full_name() == "shop.v1.Product.Kind"));
+    assert!(message.get_field_by_name("price").unwrap().supports_presence());
+    assert!(message.get_field_by_name("stock_by_store").unwrap().is_map());
+
+    let endpoints = extract_endpoints(&pool);
+    let paths: Vec<&str> = endpoints.iter().map(|endpoint| endpoint.path.as_str()).collect();
+    assert_eq!(paths, ["/shop.v1.Catalog/GetProduct", "/shop.v1.Catalog/ListProducts"]);
+    assert_eq!(endpoints[1].summary.as_deref(), Some("Server-streaming call"));
+    let schema = endpoints[0].response_schema.as_ref().unwrap();
+    assert_eq!(schema["properties"]["id"], json!({ "type": "string" }));
+    assert_eq!(schema["properties"]["stockByStore"]["additionalProperties"]["format"], "int64");
+    assert_eq!(schema["properties"]["addedAt"]["format"], "date-time");
+    assert_eq!(schema["properties"]["kind"]["enum"], json!(["KIND_UNSPECIFIED", "PHYSICAL", "GOODS"]));
+    assert_eq!(endpoints[1].response_schema.as_ref().unwrap()["type"], "array");
+
+    let error = parse("a.proto", "syntax = \"proto3\";\nmessage A { string name }").unwrap_err();
+    assert_eq!(error.to_string(), "gRPC error: expected `=` on line 2 of `a.proto`");
+  }
+}
@@ -0,0 +1,433 @@
+//! Run history: every native flow and sequence run, appended to the local
+//! database by [`Store::record_run`](crate::storage::Store::record_run).
+//!
+//! Unlike `test-flow-run-cache.ts`, which keeps the latest successful
+//! snapshot per flow, nothing is overwritten and failed or cancelled runs are
+//! kept too. Results are stored whole, requests, responses, assertions and
+//! outputs included, with sensitive headers redacted as `sanitize.ts` does.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::Result;
+use crate::flow::runner::{EndpointStatus, FlowRunResult, RunStatus};
+use crate::sequence::runner::SequenceRunResult;
+
+pub const REDACTED: &str = "[redacted]";
+
+const SENSITIVE_HEADERS: &[&str] = &[
+  "authorization",
+  "cookie",
+  "set-cookie",
+  "x-api-key",
+  "x-auth-token",
+  "proxy-authorization",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunKind {
+  Flow,
+  Sequence,
+}
+
+impl RunKind {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      RunKind::Flow => "flow",
+      RunKind::Sequence => "sequence",
+    }
+  }
+}
+
+/// One recorded run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunEntry {
+  /// Row id in the history; `0` until recorded.
+  pub id: i64,
+  pub run_id: String,
+  pub kind: RunKind,
+  pub test_flow_id: Option<i64>,
+  pub sequence_id: Option<i64>,
+  pub status: RunStatus,
+  /// ISO 8601, in UTC.
+  pub started_at: String,
+  pub duration_ms: u64,
+  /// Endpoints of a flow run, or flows of a sequence run.
+  pub total: usize,
+  pub failed: usize,
+  pub error: Option<String>,
+  /// The sanitized run result; left out of listings.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub result: Option<Value>,
+}
+
+impl RunEntry {
+  pub fn flow(run_id: &str, test_flow_id: Option<i64>, started: DateTime<Utc>, result: &FlowRunResult) -> Result<Self> {
+    let failed = result
+      .endpoints
+      .iter()
+      .filter(|endpoint| endpoint.status != EndpointStatus::Completed)
+      .count();
+    Ok(Self {
+      test_flow_id,
+      total: result.endpoints.len(),
+      failed,
+      error: result.error.clone(),
+      ..Self::new(run_id, RunKind::Flow, result.status, started, serde_json::to_value(result)?)
+    })
+  }
+
+  pub fn sequence(
+    run_id: &str,
+    sequence_id: Option<i64>,
+    started: DateTime<Utc>,
+    result: &SequenceRunResult,
+  ) -> Result<Self> {
+    let failed = result.flow_results.iter().filter(|flow| !flow.matched_expectation).count();
+    let error = result.error.clone().or_else(|| {
+      result
+        .flow_results
+        .iter()
+        .find(|flow| !flow.matched_expectation)
+        .and_then(|flow| flow.error.clone())
+    });
+    Ok(Self {
+      sequence_id,
+      total: result.flow_results.len(),
+      failed,
+      error,
+      ..Self::new(run_id, RunKind::Sequence, result.status, started, serde_json::to_value(result)?)
+    })
+  }
+
+  fn new(run_id: &str, kind: RunKind, status: RunStatus, started: DateTime<Utc>, mut result: Value) -> Self {
+    sanitize(&mut result);
+    Self {
+      id: 0,
+      run_id: run_id.to_string(),
+      kind,
+      test_flow_id: None,
+      sequence_id: None,
+      status,
+      started_at: timestamp(started),
+      duration_ms: (Utc::now() - started).num_milliseconds().max(0) as u64,
+      total: 0,
+      failed: 0,
+      error: None,
+      result: Some(result),
+    }
+  }
+}
+
+/// Formatted like `Date.toISOString`, so timestamps sort as text.
+pub fn timestamp(time: DateTime<Utc>) -> String {
+  time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Which runs to list, newest first.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RunQuery {
+  pub kind: Option<RunKind>,
+  pub test_flow_id: Option<i64>,
+  pub sequence_id: Option<i64>,
+  pub status: Option<RunStatus>,
+  /// Only runs recorded before this history id, for paging.
+  pub before_id: Option<i64>,
+  pub limit: usize,
+}
+
+impl Default for RunQuery {
+  fn default() -> Self {
+    Self {
+      kind: None,
+      test_flow_id: None,
+      sequence_id: None,
+      status: None,
+      before_id: None,
+      limit: 50,
+    }
+  }
+}
+
+/// Which runs to delete. Both rules apply when both are given.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PruneOptions {
+  /// Keep only this many of the newest runs of each flow and sequence.
+  pub keep_last: Option<usize>,
+  /// Delete runs started before this ISO 8601 timestamp.
+  pub before: Option<String>,
+}
+
+/// Redact sensitive headers anywhere in a result, the way `sanitizeValue`
+/// does: inside every `headers` object, and wherever a sensitive name sits
+/// directly under a `headers` key.
+pub fn sanitize(value: &mut Value) {
+  sanitize_under(value, None);
+}
+
+fn sanitize_under(value: &mut Value, parent_key: Option<&str>) {
+  match value {
+    Value::Array(items) => items.iter_mut().for_each(|item| sanitize_under(item, parent_key)),
+    Value::Object(map) => {
+      for (key, child) in map.iter_mut() {
+        if key == "headers" && child.is_object() {
+          sanitize_headers(child);
+        } else if parent_key == Some("headers") && is_sensitive_header(key) {
+          *child = Value::from(REDACTED);
+        } else {
+          sanitize_under(child, Some(key));
+        }
+      }
+    }
+    _ => {}
+  }
+}
+
+fn sanitize_headers(headers: &mut Value) {
+  if let Value::Object(headers) = headers {
+    for (name, value) in headers.iter_mut() {
+      if is_sensitive_header(name) {
+        *value = Value::from(REDACTED);
+      }
+    }
+  }
+}
+
+pub fn is_sensitive_header(name: &str) -> bool {
+  SENSITIVE_HEADERS.iter().any(|sensitive| name.eq_ignore_ascii_case(sensitive))
+}
+
+/// How two runs differ.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunComparison {
+  pub base: RunEntry,
+  pub other: RunEntry,
+  pub duration_delta_ms: i64,
+  /// Endpoints whose outcome differs, or that only one of the runs reached.
+  pub endpoints: Vec<EndpointChange>,
+  /// Outputs that differ; sequence outputs are compared per flow.
+  pub outputs: Vec<OutputChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointChange {
+  /// The endpoint id, prefixed with `flow_{step_order}/` and
+  /// `iteration_{i}/` for sequence runs.
+  pub key: String,
+  pub base: Option<EndpointOutcome>,
+  pub other: Option<EndpointOutcome>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointOutcome {
+  pub status: Value,
+  pub response_status: Value,
+  pub error: Value,
+  pub failed_assertions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputChange {
+  pub name: String,
+  pub base: Value,
+  pub other: Value,
+}
+
+/// Compare two recorded runs. The entries come back without their results.
+pub fn compare(mut base: RunEntry, mut other: RunEntry) -> RunComparison {
+  let base_result = base.result.take().unwrap_or_default();
+  let other_result = other.result.take().unwrap_or_default();
+
+  let base_endpoints = endpoint_outcomes(&base_result);
+  let other_endpoints = endpoint_outcomes(&other_result);
+  let mut endpoints = Vec::new();
+  for (key, outcome) in &base_endpoints {
+    let counterpart = other_endpoints.iter().find(|(other_key, _)| other_key == key).map(|(_, outcome)| outcome);
+    if counterpart != Some(outcome) {
+      endpoints.push(EndpointChange {
+        key: key.clone(),
+        base: Some(outcome.clone()),
+        other: counterpart.cloned(),
+      });
+    }
+  }
+  for (key, outcome) in &other_endpoints {
+    if !base_endpoints.iter().any(|(base_key, _)| base_key == key) {
+      endpoints.push(EndpointChange {
+        key: key.clone(),
+        base: None,
+        other: Some(outcome.clone()),
+      });
+    }
+  }
+
+  let base_outputs = outputs(&base_result);
+  let other_outputs = outputs(&other_result);
+  let mut names: Vec<&String> = base_outputs.keys().chain(other_outputs.keys()).collect();
+  names.sort();
+  names.dedup();
+  let outputs = names
+    .into_iter()
+    .filter_map(|name| {
+      let base = base_outputs.get(name).cloned().unwrap_or_default();
+      let other = other_outputs.get(name).cloned().unwrap_or_default();
+      (base != other).then(|| OutputChange {
+        name: name.clone(),
+        base,
+        other,
+      })
+    })
+    .collect();
+
+  RunComparison {
+    duration_delta_ms: other.duration_ms as i64 - base.duration_ms as i64,
+    base,
+    other,
+    endpoints,
+    outputs,
+  }
+}
+
+/// Every endpoint outcome in a flow or sequence result, in run order.
+fn endpoint_outcomes(result: &Value) -> Vec<(String, EndpointOutcome)> {
+  let mut outcomes = Vec::new();
+  let Some(flows) = result["flowResults"].as_array() else {
+    collect_endpoints(result, "", &mut outcomes);
+    return outcomes;
+  };
+  for flow in flows {
+    let prefix = format!("flow_{}/", flow["stepOrder"]);
+    collect_endpoints(&flow["flow"], &prefix, &mut outcomes);
+    for iteration in flow["loop"]["iterations"].as_array().into_iter().flatten() {
+      let prefix = format!("{prefix}iteration_{}/", iteration["index"]);
+      collect_endpoints(&iteration["flow"], &prefix, &mut outcomes);
+    }
+  }
+  outcomes
+}
+
+fn collect_endpoints(flow: &Value, prefix: &str, outcomes: &mut Vec<(String, EndpointOutcome)>) {
+  for endpoint in flow["endpoints"].as_array().into_iter().flatten() {
+    let id = endpoint["endpointId"].as_str().unwrap_or_default();
+    let failed_assertions = endpoint["assertions"]
+      .as_array()
+      .into_iter()
+      .flatten()
+      .filter(|assertion| assertion["passed"] == Value::Bool(false))
+      .map(|assertion| assertion["message"].as_str().unwrap_or_default().to_string())
+      .collect();
+    outcomes.push((
+      format!("{prefix}{id}"),
+      EndpointOutcome {
+        status: endpoint["status"].clone(),
+        response_status: endpoint["response"]["status"].clone(),
+        error: endpoint["error"].clone(),
+        failed_assertions,
+      },
+    ));
+  }
+}
+
+fn outputs(result: &Value) -> Map<String, Value> {
+  let outputs = if result["flowResults"].is_array() {
+    &result["sequenceOutputs"]
+  } else {
+    &result["flowOutputs"]
+  };
+  outputs.as_object().cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn entry(duration_ms: u64, result: Value) -> RunEntry {
+    RunEntry {
+      duration_ms,
+      ..RunEntry::new("run", RunKind::Flow, RunStatus::Completed, Utc::now(), result)
+    }
+  }
+
+  #[test]
+  fn redacts_sensitive_headers() {
+    let mut result = json!({
+      "endpoints": [{
+        "request": { "headers": { "Authorization": "Bearer secret", "Accept": "application/json" } },
+        "response": { "headers": { "set-cookie": "sid=1", "content-type": "text/plain" } }
+      }],
+      "parameterValues": { "headers": "not an object", "cookie": "kept" }
+    });
+    sanitize(&mut result);
+    assert_eq!(result["endpoints"][0]["request"]["headers"]["Authorization"], json!(REDACTED));
+    assert_eq!(result["endpoints"][0]["request"]["headers"]["Accept"], json!("application/json"));
+    assert_eq!(result["endpoints"][0]["response"]["headers"]["set-cookie"], json!(REDACTED));
+    assert_eq!(result["parameterValues"]["cookie"], json!("kept"));
+  }
+
+  #[test]
+  fn compares_endpoint_outcomes_and_outputs() {
+    let base = entry(
+      120,
+      json!({
+        "endpoints": [
+          { "endpointId": "s1-0", "status": "completed", "response": { "status": 200 }, "error": null, "assertions": [] },
+          { "endpointId": "s2-0", "status": "completed", "response": { "status": 200 }, "error": null, "assertions": [] }
+        ],
+        "flowOutputs": { "token": "abc", "count": 2 }
+      }),
+    );
+    let other = entry(
+      150,
+      json!({
+        "endpoints": [
+          { "endpointId": "s1-0", "status": "completed", "response": { "status": 200 }, "error": null, "assertions": [] },
+          {
+            "endpointId": "s2-0",
+            "status": "failed",
+            "response": { "status": 500 },
+            "error": "Request failed with status 500: Internal Server Error",
+            "assertions": [{ "passed": false, "message": "Assertion failed: status_code  equals 200, actual value: 500" }]
+          }
+        ],
+        "flowOutputs": { "token": "abc", "count": 3 }
+      }),
+    );
+
+    let comparison = compare(base, other);
+    assert_eq!(comparison.duration_delta_ms, 30);
+    assert!(comparison.base.result.is_none());
+    assert_eq!(comparison.endpoints.len(), 1);
+    assert_eq!(comparison.endpoints[0].key, "s2-0");
+    assert_eq!(comparison.endpoints[0].other.as_ref().unwrap().response_status, json!(500));
+    assert_eq!(comparison.endpoints[0].other.as_ref().unwrap().failed_assertions.len(), 1);
+    assert_eq!(comparison.outputs.len(), 1);
+    assert_eq!(comparison.outputs[0].name, "count");
+  }
+
+  #[test]
+  fn keys_sequence_endpoints_by_flow_and_iteration() {
+    let result = json!({
+      "flowResults": [
+        { "stepOrder": 1, "flow": { "endpoints": [{ "endpointId": "s1-0", "status": "completed" }] } },
+        {
+          "stepOrder": 2,
+          "flow": null,
+          "loop": { "iterations": [{ "index": 0, "flow": { "endpoints": [{ "endpointId": "s1-0", "status": "failed" }] } }] }
+        }
+      ],
+      "sequenceOutputs": {}
+    });
+    let keys: Vec<String> = endpoint_outcomes(&result).into_iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, ["flow_1/s1-0", "flow_2/iteration_0/s1-0"]);
+  }
+}
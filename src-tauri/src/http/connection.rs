@@ -0,0 +1,145 @@
+//! Connection setup for native requests, timed phase by phase.
+//!
+//! Every request opens a fresh connection so DNS, TCP and TLS timings always
+//! describe the request they are reported with.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::client::conn::http1::{self, SendRequest};
+use hyper::client::conn::http2;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use rustls::pki_types::ServerName;
+use rustls::ClientConfig;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use url::Url;
+
+use crate::error::{Error, Result};
+
+/// Durations of the phases needed before a request can be written.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ConnectTiming {
+  pub dns_ms: f64,
+  pub connect_ms: f64,
+  pub tls_ms: f64,
+}
+
+/// Root store and protocol settings shared by every HTTPS connection that
+/// negotiates `protocol` through ALPN.
+pub(crate) fn tls_config(protocol: &[u8]) -> Result<Arc<ClientConfig>> {
+  let roots = rustls::RootCertStore {
+    roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+  };
+  let mut config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+    .with_safe_default_protocol_versions()
+    .map_err(|err| Error::Tls(err.to_string()))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+  config.alpn_protocols = vec![protocol.to_vec()];
+  Ok(Arc::new(config))
+}
+
+/// A connected byte stream, plain or TLS.
+pub(crate) trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+/// Resolve, connect and (for HTTPS) handshake with the host of `url`,
+/// returning an HTTP/1.1 sender bound to the new connection.
+pub(crate) async fn open(url: &Url, tls: &Arc<ClientConfig>) -> Result<(SendRequest<Full<Bytes>>, ConnectTiming)> {
+  let (stream, timing) = stream(url, tls).await?;
+  Ok((handshake(stream).await?, timing))
+}
+
+/// Like [`open`], but speaking HTTP/2: negotiated through ALPN over TLS,
+/// with prior knowledge over plain TCP.
+pub(crate) async fn open_http2(url: &Url, tls: &Arc<ClientConfig>) -> Result<(http2::SendRequest<Full<Bytes>>, ConnectTiming)> {
+  let (stream, timing) = stream(url, tls).await?;
+  let (sender, connection) = http2::handshake(TokioExecutor::new(), TokioIo::new(stream)).await?;
+  tokio::spawn(async move {
+    if let Err(err) = connection.await {
+      log::debug!("[http] HTTP/2 connection closed with error: {err}");
+    }
+  });
+  Ok((sender, timing))
+}
+
+/// Resolve, connect and (for HTTPS and WSS) handshake with the host of
+/// `url`, returning the connection before any HTTP is spoken on it.
+pub(crate) async fn stream(url: &Url, tls: &Arc<ClientConfig>) -> Result<(Box<dyn Io>, ConnectTiming)> {
+  let host = url
+    .host_str()
+    .ok_or_else(|| Error::InvalidRequest(format!("URL `{url}` has no host")))?;
+  let port = url
+    .port_or_known_default()
+    .ok_or_else(|| Error::InvalidRequest(format!("URL `{url}` has no port")))?;
+  let mut timing = ConnectTiming::default();
+
+  let dns_start = Instant::now();
+  let addrs = resolve(host, port).await?;
+  timing.dns_ms = elapsed_ms(dns_start);
+
+  let connect_start = Instant::now();
+  let tcp = connect(&addrs).await?;
+  timing.connect_ms = elapsed_ms(connect_start);
+  let _ = tcp.set_nodelay(true);
+
+  match url.scheme() {
+    "http" | "ws" => Ok((Box::new(tcp), timing)),
+    "https" | "wss" => {
+      let server_name = ServerName::try_from(host.trim_start_matches('[').trim_end_matches(']').to_string())
+        .map_err(|err| Error::Tls(format!("invalid server name `{host}`: {err}")))?;
+      let tls_start = Instant::now();
+      let stream = TlsConnector::from(tls.clone())
+        .connect(server_name, tcp)
+        .await
+        .map_err(|err| Error::Tls(err.to_string()))?;
+      timing.tls_ms = elapsed_ms(tls_start);
+      Ok((Box::new(stream), timing))
+    }
+    scheme => Err(Error::InvalidRequest(format!("unsupported URL scheme `{scheme}`"))),
+  }
+}
+
+async fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+  let host = host.trim_start_matches('[').trim_end_matches(']');
+  let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+    .await
+    .map_err(|err| Error::Connect(format!("failed to resolve `{host}`: {err}")))?
+    .collect();
+  if addrs.is_empty() {
+    return Err(Error::Connect(format!("`{host}` did not resolve to any address")));
+  }
+  Ok(addrs)
+}
+
+/// Try each resolved address in turn, returning the first that accepts.
+async fn connect(addrs: &[SocketAddr]) -> Result<TcpStream> {
+  let mut last_error = None;
+  for addr in addrs {
+    match TcpStream::connect(addr).await {
+      Ok(stream) => return Ok(stream),
+      Err(err) => last_error = Some(format!("{addr}: {err}")),
+    }
+  }
+  Err(Error::Connect(last_error.unwrap_or_else(|| "no address to connect to".into())))
+}
+
+async fn handshake(stream: Box<dyn Io>) -> Result<SendRequest<Full<Bytes>>> {
+  let (sender, connection) = http1::handshake(TokioIo::new(stream)).await?;
+  tokio::spawn(async move {
+    if let Err(err) = connection.await {
+      log::debug!("[http] connection closed with error: {err}");
+    }
+  });
+  Ok(sender)
+}
+
+pub(crate) fn elapsed_ms(start: Instant) -> f64 {
+  start.elapsed().as_secs_f64() * 1000.0
+}
@@ -0,0 +1,502 @@
+//! The native HTTP client flow steps and `execute_request` go through.
+//!
+//! It is deliberately plain: every request, and every redirect hop, opens a
+//! new TCP (and TLS) connection, with no pooling or keep-alive. Requests speak
+//! HTTP/1.1 only; HTTP/2 connections are opened separately for gRPC. Bodies
+//! come back as sent, `Content-Encoding` not decoded, so the client offers no
+//! `Accept-Encoding` of its own.
+
+mod connection;
+pub mod sse;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use bytes::Bytes;
+use http::header::{
+  AUTHORIZATION, CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_LOCATION, CONTENT_TYPE, COOKIE, HOST,
+  LOCATION, SET_COOKIE,
+};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::client::conn::http2::SendRequest;
+use rustls::ClientConfig;
+use serde::{Deserialize, Serialize, Serializer};
+use url::Url;
+
+use crate::cookies::{lock_jar, SharedCookieJar};
+use crate::error::{Error, Result};
+use connection::elapsed_ms;
+pub(crate) use connection::Io;
+use sse::{EventParser, SseEvent};
+
+/// Timeout applied when the caller does not provide one. Matches the default
+/// `ExecutionPreferences.timeout` used by the flow editor.
+pub const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// Redirect hops followed before giving up, same as the browser fetch limit.
+pub const MAX_REDIRECTS: usize = 20;
+
+/// A single outgoing request, as built by the flow execution engine.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpRequest {
+  pub method: String,
+  pub url: String,
+  #[serde(default)]
+  pub headers: HashMap<String, String>,
+  /// Already-serialized request body.
+  #[serde(default)]
+  pub body: Option<String>,
+  #[serde(default)]
+  pub timeout_ms: Option<u64>,
+  /// Run whose cookie jar supplies and receives cookies for this request.
+  #[serde(default)]
+  pub run_id: Option<String>,
+}
+
+/// Where the time of a request went.
+///
+/// Phase durations describe the final hop; time spent on earlier redirect
+/// hops is reported as a whole in `redirect_ms`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestTiming {
+  /// Unix epoch milliseconds at which the request was started.
+  pub started_at: u64,
+  /// DNS lookup of the target host.
+  pub dns_ms: f64,
+  /// TCP connection establishment.
+  pub connect_ms: f64,
+  /// TLS handshake; zero for plain HTTP.
+  pub tls_ms: f64,
+  /// From writing the request until the response head arrived.
+  pub ttfb_ms: f64,
+  /// Reading the response body.
+  pub download_ms: f64,
+  /// Time spent on redirect hops before the final one.
+  pub redirect_ms: f64,
+  /// Time from sending the request until the whole body was read,
+  /// including any redirects that were followed.
+  pub total_ms: f64,
+}
+
+/// The response handed back to the webview.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpResponse {
+  pub status: u16,
+  pub status_text: String,
+  /// Final URL after redirects.
+  pub url: String,
+  /// Every response header as a `[name, value]` pair, duplicates preserved.
+  pub headers: Vec<(String, String)>,
+  /// Raw `Set-Cookie` header values, one entry per header line, collected
+  /// across every redirect hop.
+  pub set_cookies: Vec<String>,
+  /// Sent to the webview base64-encoded, far smaller over IPC than an array
+  /// of numbers.
+  #[serde(serialize_with = "serialize_base64")]
+  pub body: Vec<u8>,
+  pub timing: RequestTiming,
+}
+
+/// HTTP client used by every native request.
+///
+/// The client keeps no cookie store of its own and follows redirects itself,
+/// so cookies set on every hop land in the run's [`crate::cookies::CookieJar`]
+/// and runs never leak sessions into each other.
+pub struct HttpClient {
+  tls: Arc<ClientConfig>,
+  /// For HTTP/2 connections, which offer `h2` through ALPN instead.
+  http2_tls: Arc<ClientConfig>,
+}
+
+/// A response whose head has arrived, its body still to be read.
+struct Head {
+  status: StatusCode,
+  headers: HeaderMap,
+  body: Incoming,
+  timing: RequestTiming,
+}
+
+/// The final response of a request, redirects followed, before its body
+/// is read.
+struct Exchange {
+  status: StatusCode,
+  headers: HeaderMap,
+  url: Url,
+  set_cookies: Vec<String>,
+  start: Instant,
+  timeout_ms: u64,
+  timing: RequestTiming,
+}
+
+impl Exchange {
+  /// Read the whole body within what is left of the request's timeout.
+  async fn read(self, body: Incoming) -> Result<HttpResponse> {
+    let remaining = Duration::from_millis(self.timeout_ms)
+      .checked_sub(self.start.elapsed())
+      .ok_or(Error::Timeout(self.timeout_ms))?;
+    let download_start = Instant::now();
+    let body = tokio::time::timeout(remaining, body.collect())
+      .await
+      .map_err(|_| Error::Timeout(self.timeout_ms))??
+      .to_bytes();
+    Ok(self.response(body.to_vec(), elapsed_ms(download_start)))
+  }
+
+  fn response(self, body: Vec<u8>, download_ms: f64) -> HttpResponse {
+    HttpResponse {
+      status: self.status.as_u16(),
+      status_text: self.status.canonical_reason().unwrap_or_default().to_string(),
+      url: self.url.to_string(),
+      headers: self
+        .headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+        .collect(),
+      set_cookies: self.set_cookies,
+      body,
+      timing: RequestTiming {
+        redirect_ms: self.timing.redirect_ms.max(0.0),
+        download_ms,
+        total_ms: elapsed_ms(self.start),
+        ..self.timing
+      },
+    }
+  }
+}
+
+impl HttpClient {
+  pub fn new() -> Result<Self> {
+    Ok(Self {
+      tls: connection::tls_config(b"http/1.1")?,
+      http2_tls: connection::tls_config(b"h2")?,
+    })
+  }
+
+  pub async fn execute(&self, request: HttpRequest, cookie_jar: Option<SharedCookieJar>) -> Result<HttpResponse> {
+    let (exchange, body) = self.follow(request, cookie_jar).await?;
+    exchange.read(body).await
+  }
+
+  /// Execute a request whose response may be a `text/event-stream`, reading
+  /// its events as they arrive until `max_events` have, `until` holds for
+  /// one, the stream ends or `wait_ms` passes, whichever comes first. The
+  /// events are `None` when the response is no event stream; it is then read
+  /// whole, as [`HttpClient::execute`] does.
+  pub async fn execute_events(
+    &self,
+    request: HttpRequest,
+    cookie_jar: Option<SharedCookieJar>,
+    max_events: Option<usize>,
+    wait_ms: u64,
+    mut until: impl FnMut(&SseEvent) -> Result<bool>,
+  ) -> Result<(HttpResponse, Option<Vec<SseEvent>>)> {
+    let (exchange, mut body) = self.follow(request, cookie_jar).await?;
+    let event_stream = exchange
+      .headers
+      .get(CONTENT_TYPE)
+      .and_then(|value| value.to_str().ok())
+      .is_some_and(|value| value.trim_start().to_ascii_lowercase().starts_with("text/event-stream"));
+    if !event_stream {
+      return Ok((exchange.read(body).await?, None));
+    }
+
+    let download_start = Instant::now();
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(wait_ms);
+    let mut parser = EventParser::default();
+    let mut raw = Vec::new();
+    let mut events = Vec::new();
+    'read: while max_events.map_or(true, |max| events.len() < max) {
+      let Ok(frame) = tokio::time::timeout_at(deadline, body.frame()).await else {
+        break;
+      };
+      let Some(frame) = frame else {
+        break;
+      };
+      let Ok(data) = frame?.into_data() else {
+        continue;
+      };
+      raw.extend_from_slice(&data);
+      for event in parser.feed(&data) {
+        let done = until(&event)?;
+        events.push(event);
+        if done || max_events == Some(events.len()) {
+          break 'read;
+        }
+      }
+    }
+    Ok((exchange.response(raw, elapsed_ms(download_start)), Some(events)))
+  }
+
+  /// Send a request, following redirects and keeping the jar's cookies up
+  /// to date, until the final response's head arrives.
+  async fn follow(&self, request: HttpRequest, cookie_jar: Option<SharedCookieJar>) -> Result<(Exchange, Incoming)> {
+    let mut method = Method::from_bytes(request.method.to_uppercase().as_bytes())
+      .map_err(|_| Error::InvalidRequest(format!("unsupported method `{}`", request.method)))?;
+    let mut url = Url::parse(&request.url)
+      .map_err(|err| Error::InvalidRequest(format!("invalid URL `{}`: {err}", request.url)))?;
+    let mut headers = build_header_map(&request.headers)?;
+    let mut body = request.body.map(Bytes::from);
+    let timeout_ms = request.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
+    let timeout = Duration::from_millis(timeout_ms);
+
+    let started_at = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_millis() as u64)
+      .unwrap_or_default();
+    let start = Instant::now();
+    let mut set_cookies = Vec::new();
+    let mut redirects = 0;
+
+    loop {
+      let remaining = timeout.checked_sub(start.elapsed()).ok_or(Error::Timeout(timeout_ms))?;
+
+      let mut hop_headers = headers.clone();
+      if let Some(jar) = &cookie_jar {
+        add_jar_cookies(&mut hop_headers, jar, &url);
+      }
+
+      let hop_start = Instant::now();
+      let head = tokio::time::timeout(remaining, self.send_head(&method, &url, hop_headers, body.clone()))
+        .await
+        .map_err(|_| Error::Timeout(timeout_ms))??;
+
+      let hop_cookies: Vec<String> = head
+        .headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        .collect();
+      if let Some(jar) = &cookie_jar {
+        lock_jar(jar).store_response_cookies(&url, &hop_cookies);
+      }
+      set_cookies.extend(hop_cookies);
+
+      if let Some(next_url) = redirect_target(head.status, &head.headers, &url) {
+        if redirects == MAX_REDIRECTS {
+          return Err(Error::TooManyRedirects(MAX_REDIRECTS));
+        }
+        redirects += 1;
+
+        // 301/302/303 turn into a body-less GET, as browsers do; 307/308 replay as-is.
+        if head.status == StatusCode::SEE_OTHER
+          || (matches!(head.status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND) && method == Method::POST)
+        {
+          if method != Method::HEAD {
+            method = Method::GET;
+          }
+          body = None;
+          // Nor do the headers describing it survive (Fetch, HTTP-redirect fetch).
+          for name in [CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LOCATION, CONTENT_TYPE, CONTENT_LENGTH] {
+            headers.remove(name);
+          }
+        }
+        // Never forward credentials to a different origin.
+        if next_url.origin() != url.origin() {
+          headers.remove(AUTHORIZATION);
+          headers.remove(COOKIE);
+        }
+        url = next_url;
+        continue;
+      }
+
+      let exchange = Exchange {
+        status: head.status,
+        headers: head.headers,
+        url,
+        set_cookies,
+        start,
+        timeout_ms,
+        timing: RequestTiming {
+          started_at,
+          redirect_ms: elapsed_ms(start) - elapsed_ms(hop_start),
+          ..head.timing
+        },
+      };
+      return Ok((exchange, head.body));
+    }
+  }
+
+  /// Send one request as given, without following redirects or touching
+  /// cookies, the way a proxy passes it on.
+  pub async fn forward(&self, method: &Method, url: &Url, headers: HeaderMap, body: Bytes) -> Result<http::Response<Bytes>> {
+    let exchange = async {
+      let head = self.send_head(method, url, headers, Some(body)).await?;
+      let body = head.body.collect().await?.to_bytes();
+      Ok::<_, Error>((head.status, head.headers, body))
+    };
+    let (status, headers, body) = tokio::time::timeout(Duration::from_millis(DEFAULT_TIMEOUT_MS), exchange)
+      .await
+      .map_err(|_| Error::Timeout(DEFAULT_TIMEOUT_MS))??;
+    let mut response = http::Response::new(body);
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    Ok(response)
+  }
+
+  /// Open a fresh connection to the host of `url`, through TLS for `https`
+  /// and `wss`, for a protocol other than plain HTTP to speak on.
+  pub(crate) async fn open_stream(&self, url: &Url) -> Result<(Box<dyn Io>, RequestTiming)> {
+    let (stream, timing) = connection::stream(url, &self.tls).await?;
+    Ok((
+      stream,
+      RequestTiming {
+        dns_ms: timing.dns_ms,
+        connect_ms: timing.connect_ms,
+        tls_ms: timing.tls_ms,
+        ..RequestTiming::default()
+      },
+    ))
+  }
+
+  /// Open a fresh HTTP/2 connection to the host of `url`, for protocols
+  /// such as gRPC that need one.
+  pub(crate) async fn open_http2(&self, url: &Url) -> Result<(SendRequest<Full<Bytes>>, RequestTiming)> {
+    let (sender, timing) = connection::open_http2(url, &self.http2_tls).await?;
+    Ok((
+      sender,
+      RequestTiming {
+        dns_ms: timing.dns_ms,
+        connect_ms: timing.connect_ms,
+        tls_ms: timing.tls_ms,
+        ..RequestTiming::default()
+      },
+    ))
+  }
+
+  /// Send one request over a fresh connection, returning once the
+  /// response's head arrived.
+  async fn send_head(&self, method: &Method, url: &Url, mut headers: HeaderMap, body: Option<Bytes>) -> Result<Head> {
+    let (mut sender, connect_timing) = connection::open(url, &self.tls).await?;
+
+    if !headers.contains_key(HOST) {
+      let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_string(),
+      };
+      headers.insert(HOST, HeaderValue::from_str(&host).map_err(|_| Error::InvalidRequest(format!("invalid host `{host}`")))?);
+    }
+
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+      target.push('?');
+      target.push_str(query);
+    }
+    let mut request = http::Request::builder()
+      .method(method.clone())
+      .uri(target)
+      .body(Full::new(body.unwrap_or_default()))
+      .map_err(|err| Error::InvalidRequest(err.to_string()))?;
+    *request.headers_mut() = headers;
+
+    let request_start = Instant::now();
+    let response = sender.send_request(request).await?;
+    let ttfb_ms = elapsed_ms(request_start);
+
+    let (parts, body) = response.into_parts();
+    Ok(Head {
+      status: parts.status,
+      headers: parts.headers,
+      body,
+      timing: RequestTiming {
+        dns_ms: connect_timing.dns_ms,
+        connect_ms: connect_timing.connect_ms,
+        tls_ms: connect_timing.tls_ms,
+        ttfb_ms,
+        ..RequestTiming::default()
+      },
+    })
+  }
+}
+
+/// Where a redirect response points, if it is one we should follow.
+fn redirect_target(status: StatusCode, headers: &HeaderMap, current: &Url) -> Option<Url> {
+  if !matches!(
+    status,
+    StatusCode::MOVED_PERMANENTLY
+      | StatusCode::FOUND
+      | StatusCode::SEE_OTHER
+      | StatusCode::TEMPORARY_REDIRECT
+      | StatusCode::PERMANENT_REDIRECT
+  ) {
+    return None;
+  }
+  let location = headers.get(LOCATION)?.to_str().ok()?;
+  current.join(location).ok()
+}
+
+/// Add the jar's cookies for `url` after any explicit `Cookie` header.
+pub(crate) fn add_jar_cookies(headers: &mut HeaderMap, jar: &SharedCookieJar, url: &Url) {
+  if let Some(jar_cookies) = lock_jar(jar).cookie_header(url) {
+    let merged = match headers.get(COOKIE).and_then(|value| value.to_str().ok()) {
+      Some(explicit) if !explicit.is_empty() => format!("{explicit}; {jar_cookies}"),
+      _ => jar_cookies,
+    };
+    if let Ok(value) = HeaderValue::from_str(&merged) {
+      headers.insert(COOKIE, value);
+    }
+  }
+}
+
+pub(crate) fn build_header_map(headers: &HashMap<String, String>) -> Result<HeaderMap> {
+  let mut map = HeaderMap::with_capacity(headers.len());
+  for (name, value) in headers {
+    let header_name = HeaderName::from_bytes(name.trim().as_bytes())
+      .map_err(|_| Error::InvalidRequest(format!("invalid header name `{name}`")))?;
+    let header_value = HeaderValue::from_str(value)
+      .map_err(|_| Error::InvalidRequest(format!("invalid value for header `{name}`")))?;
+    map.append(header_name, header_value);
+  }
+  Ok(map)
+}
+
+fn serialize_base64<S: Serializer>(bytes: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+  serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::serve_http;
+
+  /// `/form` answers with a 303 to `/done`, which echoes the request head.
+  async fn server() -> u16 {
+    serve_http(|request| {
+      if request.starts_with("POST /form ") {
+        return "HTTP/1.1 303 See Other\r\nLocation: /done\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string();
+      }
+      let head = request.split("\r\n\r\n").next().unwrap_or_default().to_ascii_lowercase();
+      format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{head}", head.len())
+    })
+    .await
+  }
+
+  #[tokio::test]
+  async fn see_other_drops_the_body_and_its_headers() {
+    let port = server().await;
+    let request = HttpRequest {
+      method: "POST".to_string(),
+      url: format!("http://127.0.0.1:{port}/form"),
+      headers: HashMap::from([
+        ("Content-Type".to_string(), "application/json".to_string()),
+        ("X-Trace".to_string(), "1".to_string()),
+      ]),
+      body: Some("{\"a\":1}".to_string()),
+      timeout_ms: None,
+      run_id: None,
+    };
+    let response = HttpClient::new().unwrap().execute(request, None).await.unwrap();
+
+    let head = String::from_utf8(response.body).unwrap();
+    assert!(head.starts_with("get /done "), "{head}");
+    assert!(head.contains("x-trace: 1"), "{head}");
+    assert!(!head.contains("content-type"), "{head}");
+    assert!(!head.contains("content-length"), "{head}");
+  }
+}
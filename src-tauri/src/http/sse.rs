@@ -0,0 +1,175 @@
+//! Server-Sent Events, parsed from a `text/event-stream` body as it arrives,
+//! following the HTML spec's event stream interpretation.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One dispatched event. `data` is JSON when it parses as such.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SseEvent {
+  /// `message` unless the stream named another type.
+  pub event: String,
+  pub data: Value,
+  /// The last event ID seen so far in the stream.
+  pub id: Option<String>,
+}
+
+/// Incremental parser; bytes go in as they arrive, complete events come out.
+#[derive(Debug, Default)]
+pub struct EventParser {
+  /// Bytes of a line not yet terminated.
+  pending: Vec<u8>,
+  /// The previous chunk ended in `\r`, so a leading `\n` is part of it.
+  after_cr: bool,
+  event: Option<String>,
+  data: Option<String>,
+  last_id: Option<String>,
+}
+
+impl EventParser {
+  pub fn feed(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+    let mut events = Vec::new();
+    for &byte in bytes {
+      let after_cr = std::mem::replace(&mut self.after_cr, byte == b'\r');
+      match byte {
+        b'\n' if after_cr => {}
+        b'\n' | b'\r' => {
+          let line = String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned();
+          events.extend(self.line(&line));
+        }
+        _ => self.pending.push(byte),
+      }
+    }
+    events
+  }
+
+  fn line(&mut self, line: &str) -> Option<SseEvent> {
+    if line.is_empty() {
+      return self.dispatch();
+    }
+    if line.starts_with(':') {
+      return None;
+    }
+    let (field, value) = line.split_once(':').unwrap_or((line, ""));
+    let value = value.strip_prefix(' ').unwrap_or(value);
+    match field {
+      "event" => self.event = Some(value.to_string()),
+      "data" => match &mut self.data {
+        Some(data) => {
+          data.push('\n');
+          data.push_str(value);
+        }
+        None => self.data = Some(value.to_string()),
+      },
+      "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+      // `retry` only matters to reconnecting clients.
+      _ => {}
+    }
+    None
+  }
+
+  /// End the event being built; one without data is dropped.
+  fn dispatch(&mut self) -> Option<SseEvent> {
+    let event = self.event.take();
+    let data = self.data.take()?;
+    Some(SseEvent {
+      event: event.filter(|event| !event.is_empty()).unwrap_or_else(|| "message".to_string()),
+      data: serde_json::from_str(&data).unwrap_or(Value::String(data)),
+      id: self.last_id.clone(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use serde_json::json;
+  use tokio::io::AsyncWriteExt;
+
+  use super::*;
+  use crate::flow::runner::{EndpointStatus, FlowRunner};
+  use crate::flow::TestFlow;
+  use crate::http::HttpClient;
+  use crate::test_support::{read_request, serve};
+
+  /// Streams four events, then keeps the connection open as a live feed
+  /// would. Requests not accepting `text/event-stream` get a 406.
+  async fn server() -> u16 {
+    serve(|mut stream| async move {
+      let request = read_request(&mut stream).await.to_ascii_lowercase();
+      if !request.contains("accept: text/event-stream") {
+        stream.write_all(b"HTTP/1.1 406 Not Acceptable\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+        return;
+      }
+      stream
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+      for chunk in ["data: {\"n\": 1}\n\n", "event: progress\ndata: {\"n\": 2}\n\n", "event: done\ndata: {\"n\": 3}\n\n", "data: {\"n\": 4}\n\n"] {
+        stream.write_all(chunk.as_bytes()).await.unwrap();
+        stream.flush().await.unwrap();
+      }
+      tokio::time::sleep(Duration::from_secs(60)).await;
+    })
+    .await
+  }
+
+  #[tokio::test]
+  async fn runs_event_stream_steps() {
+    let port = server().await;
+    let endpoint = |event_stream: Value| {
+      json!({ "endpoint_id": 1, "api_id": 1, "eventStream": event_stream, "transformations": [{ "alias": "ns", "expression": "$ | map($.data.n)" }] })
+    };
+    let flow: TestFlow = serde_json::from_value(json!({
+      "settings": { "api_hosts": { "1": { "url": format!("http://127.0.0.1:{port}"), "name": "Feed" } } },
+      "endpoints": [{ "id": 1, "path": "/feed", "method": "GET" }],
+      "steps": [{ "step_id": "step1", "endpoints": [
+        endpoint(json!({ "until": "$.event == \"done\"" })),
+        endpoint(json!({ "maxEvents": 2 })),
+        endpoint(json!({ "timeoutMs": 200 })),
+      ] }]
+    }))
+    .unwrap();
+
+    let client = HttpClient::new().unwrap();
+    let result = FlowRunner::new(&client, &flow, None).run(&Default::default()).await;
+    for endpoint in &result.endpoints {
+      assert_eq!(endpoint.status, EndpointStatus::Completed, "{:?}", endpoint.error);
+    }
+    let ns: Vec<&Value> = (0..3).map(|index| &result.stored_transformations[&format!("step1-{index}")]["ns"]).collect();
+    assert_eq!(ns, [&json!([1, 2, 3]), &json!([1, 2]), &json!([1, 2, 3, 4])]);
+    assert_eq!(result.stored_responses["step1-0"][2], json!({ "event": "done", "data": { "n": 3 }, "id": null }));
+  }
+
+  #[test]
+  fn parses_events_split_across_chunks() {
+    let stream = ": keep-alive\r\nid: 1\r\nevent: progress\r\ndata: {\"done\": 1,\r\ndata:  \"of\": 2}\r\n\r\ndata: plain\n\nevent: ignored\n\nid: 2\rdata:\r\r";
+    let mut parser = EventParser::default();
+    let mut events = Vec::new();
+    for chunk in stream.as_bytes().chunks(5) {
+      events.extend(parser.feed(chunk));
+    }
+    assert_eq!(
+      events,
+      [
+        SseEvent {
+          event: "progress".to_string(),
+          data: json!({ "done": 1, "of": 2 }),
+          id: Some("1".to_string()),
+        },
+        SseEvent {
+          event: "message".to_string(),
+          data: json!("plain"),
+          id: Some("1".to_string()),
+        },
+        SseEvent {
+          event: "message".to_string(),
+          data: json!(""),
+          id: Some("2".to_string()),
+        },
+      ]
+    );
+  }
+}
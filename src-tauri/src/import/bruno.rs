@@ -0,0 +1,528 @@
+//! Bruno collection import.
+//!
+//! A Bruno collection is a folder: `bruno.json` names it, each `.bru` file is
+//! a request, subfolders (with an optional `folder.bru`) are folders, and
+//! `environments/*.bru` are the environments. These map onto flows as
+//! described in [`collection`](super::collection), ordered by each file's
+//! `seq`. Post-response variables that read the response body become `res`
+//! templates in the requests after them, and `assert` entries become
+//! assertions; scripts and tests are reported, not converted.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use super::collection::{self, Auth, Body, Collection, Folder, Item, Request};
+use super::{str_field, Import};
+use crate::error::{Error, Result};
+use crate::flow::{Assertion, HeaderEntry};
+
+const METHODS: &[&str] = &["get", "post", "put", "patch", "delete", "head", "options", "trace", "connect"];
+
+/// Bruno assertion operators and the flow operators they map to.
+const OPERATORS: &[(&str, &str)] = &[
+  ("eq", "equals"),
+  ("neq", "not_equals"),
+  ("gt", "greater_than"),
+  ("gte", "greater_than_or_equal"),
+  ("lt", "less_than"),
+  ("lte", "less_than_or_equal"),
+  ("in", "one_of"),
+  ("notIn", "not_one_of"),
+  ("contains", "contains"),
+  ("notContains", "not_contains"),
+  ("length", "has_length"),
+  ("matches", "matches_regex"),
+  ("startsWith", "starts_with"),
+  ("endsWith", "ends_with"),
+  ("between", "between"),
+  ("isEmpty", "is_empty"),
+  ("isNotEmpty", "is_not_empty"),
+  ("isNull", "is_null"),
+  ("isUndefined", "is_null"),
+  ("isDefined", "exists"),
+];
+
+/// Type checks, which map to `is_type`.
+const TYPES: &[(&str, &str)] = &[
+  ("isNumber", "number"),
+  ("isString", "string"),
+  ("isBoolean", "boolean"),
+  ("isArray", "array"),
+  ("isJson", "object"),
+];
+
+/// Read a collection folder and convert it. `node_modules` and hidden
+/// folders are left out.
+pub fn import_dir(dir: &Path) -> Result<Import> {
+  fn walk(dir: &Path, prefix: &str, files: &mut BTreeMap<String, String>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+      let entry = entry?;
+      let name = entry.file_name().to_string_lossy().into_owned();
+      let path = format!("{prefix}{name}");
+      if entry.file_type()?.is_dir() {
+        if !name.starts_with('.') && name != "node_modules" {
+          walk(&entry.path(), &format!("{path}/"), files)?;
+        }
+      } else if name.ends_with(".bru") || path == "bruno.json" {
+        files.insert(path, std::fs::read_to_string(entry.path())?);
+      }
+    }
+    Ok(())
+  }
+  let mut files = BTreeMap::new();
+  walk(dir, "", &mut files)?;
+  import(&files)
+}
+
+/// Convert a collection from its `bruno.json` and `.bru` files, keyed by
+/// their `/`-separated paths within the collection folder.
+pub fn import(files: &BTreeMap<String, String>) -> Result<Import> {
+  let manifest: Value = files
+    .get("bruno.json")
+    .ok_or_else(|| Error::Import("not a Bruno collection (expected `bruno.json`)".to_string()))
+    .and_then(|text| Ok(serde_json::from_str(text)?))?;
+  let root = files.get("collection.bru").map(|text| Bru::parse(text)).unwrap_or_default();
+
+  let mut environments = Vec::new();
+  let mut notes = root.scripts();
+  for (path, text) in files {
+    let Some(name) = path.strip_prefix("environments/").and_then(|name| name.strip_suffix(".bru")) else {
+      continue;
+    };
+    let environment = Bru::parse(text);
+    let mut variables = environment.variables("vars");
+    for secret in environment.list("vars:secret") {
+      variables.insert(secret.to_string(), Value::from(""));
+      notes.push(format!("secret `{secret}` of environment `{name}` has no value in the collection; set it after importing"));
+    }
+    environments.push((name.to_string(), variables));
+  }
+
+  let mut parser = Parser { files, skipped: 0 };
+  let items = parser.items("", &mut notes);
+  Ok(collection::convert(Collection {
+    name: str_field(&manifest, "name").unwrap_or("Bruno collection").to_string(),
+    description: root.text("docs").filter(|docs| !docs.trim().is_empty()).map(str::to_string),
+    variables: root.variables("vars:pre-request"),
+    environments,
+    headers: root.headers(),
+    auth: root.auth(root.value("auth", "mode")),
+    items,
+    notes,
+    skipped: parser.skipped,
+  }))
+}
+
+struct Parser<'f> {
+  files: &'f BTreeMap<String, String>,
+  skipped: usize,
+}
+
+impl Parser<'_> {
+  /// The folders and requests directly in `dir` (empty for the root), in
+  /// `seq` order.
+  fn items(&mut self, dir: &str, notes: &mut Vec<String>) -> Vec<Item> {
+    let prefix = if dir.is_empty() { String::new() } else { format!("{dir}/") };
+    let mut folders = Vec::new();
+    let mut requests = Vec::new();
+    for path in self.files.keys() {
+      let Some(relative) = path.strip_prefix(&prefix) else {
+        continue;
+      };
+      match relative.split_once('/') {
+        Some(("environments", _)) if dir.is_empty() => {}
+        Some((folder, _)) if folders.last() != Some(&folder) => folders.push(folder),
+        Some(_) => {}
+        None if relative.ends_with(".bru") && relative != "folder.bru" && relative != "collection.bru" => {
+          requests.push(path.as_str())
+        }
+        None => {}
+      }
+    }
+
+    let mut items: Vec<(f64, Item)> = Vec::new();
+    for folder in folders {
+      let path = format!("{prefix}{folder}");
+      let bru = self.files.get(&format!("{path}/folder.bru")).map(|text| Bru::parse(text)).unwrap_or_default();
+      let mut folder_notes = bru.scripts();
+      let children = self.items(&path, &mut folder_notes);
+      items.push((
+        bru.seq(),
+        Item::Folder(Folder {
+          name: bru.value("meta", "name").unwrap_or(folder).to_string(),
+          description: bru.text("docs").filter(|docs| !docs.trim().is_empty()).map(str::to_string),
+          variables: bru.variables("vars:pre-request"),
+          headers: bru.headers(),
+          auth: bru.auth(bru.value("auth", "mode")),
+          items: children,
+          notes: folder_notes,
+        }),
+      ));
+    }
+    for path in requests {
+      let bru = Bru::parse(&self.files[path]);
+      let file_name = path.rsplit('/').next().unwrap_or(path).trim_end_matches(".bru");
+      let name = bru.value("meta", "name").unwrap_or(file_name).to_string();
+      match bru.value("meta", "type").unwrap_or("http") {
+        "http" | "graphql" => items.push((bru.seq(), Item::Request(request(&bru, path, name)))),
+        kind => {
+          self.skipped += 1;
+          notes.push(format!("`{kind}` request `{name}` was skipped; flows only send HTTP requests"));
+        }
+      }
+    }
+    items.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    items.into_iter().map(|(_, item)| item).collect()
+  }
+}
+
+fn request(bru: &Bru, path: &str, name: String) -> Request {
+  let mut notes = bru.scripts();
+  let method = METHODS.iter().copied().find(|method| bru.block(method).is_some()).unwrap_or("get");
+  let mut url = bru.value(method, "url").unwrap_or_default().to_string();
+  // Bruno keeps `params:query` in step with the URL; the block also has the
+  // disabled ones.
+  let query = bru.block("params:query").map(|_| {
+    url = url.split_once('?').map_or(url.as_str(), |(base, _)| base).to_string();
+    bru.pairs("params:query").map(|(key, value)| (key.to_string(), value.to_string())).collect()
+  });
+
+  let body = match bru.value(method, "body").unwrap_or("none") {
+    "none" => Body::None,
+    mode @ ("json" | "text" | "xml") => Body::Raw(bru.text(&format!("body:{mode}")).unwrap_or_default().to_string()),
+    "graphql" => Body::GraphQl {
+      query: bru.text("body:graphql").unwrap_or_default().to_string(),
+      variables: bru.text("body:graphql:vars").map(str::to_string),
+    },
+    mode => Body::Unsupported(mode.to_string()),
+  };
+
+  let mut extracts = Vec::new();
+  for (variable, expression) in bru.pairs("vars:post-response") {
+    match json_path(expression) {
+      Some(path) => extracts.push((variable.to_string(), path)),
+      None => notes.push(format!("post-response variable `{variable}` (`{expression}`) does not read the response body; not converted")),
+    }
+  }
+  let mut assertions = Vec::new();
+  for (target, expression) in bru.pairs("assert") {
+    match assertion(assertions.len(), target, expression) {
+      Some(assertion) => assertions.push(assertion),
+      None => notes.push(format!("assertion `{target}: {expression}` was not converted")),
+    }
+  }
+
+  Request {
+    id: path.to_string(),
+    name,
+    method: method.to_string(),
+    url,
+    query,
+    path_variables: bru.pairs("params:path").map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+    headers: bru.headers(),
+    body,
+    auth: bru.auth(bru.value(method, "auth")),
+    variables: bru.variables("vars:pre-request"),
+    extracts,
+    assertions,
+    notes,
+  }
+}
+
+/// `res.body.a.b` as `$.a.b`; `None` for anything but the body.
+fn json_path(expression: &str) -> Option<String> {
+  let rest = expression.trim().strip_prefix("res.body")?;
+  (rest.is_empty() || rest.starts_with(['.', '['])).then(|| format!("${rest}"))
+}
+
+/// A flow assertion for an `assert` entry such as `res.status: eq 200`.
+fn assertion(index: usize, target: &str, expression: &str) -> Option<Assertion> {
+  let (assertion_type, data_id) = match target.trim() {
+    "res.status" => ("status_code", String::new()),
+    "res.responseTime" => ("response_time", String::new()),
+    target => match target.strip_prefix("res.headers") {
+      Some(header) => {
+        let header = header.trim_start_matches(['.', '[']).trim_end_matches(']');
+        ("header", header.trim_matches(['\'', '"']).to_string())
+      }
+      None => ("json_body", json_path(target)?),
+    },
+  };
+  let (operator, value) = expression.trim().split_once(' ').unwrap_or((expression.trim(), ""));
+  let (operator, expected_value) = match TYPES.iter().find(|(name, _)| *name == operator) {
+    Some((_, kind)) => ("is_type", Value::from(*kind)),
+    None => {
+      let (_, operator) = OPERATORS.iter().find(|(name, _)| *name == operator)?;
+      let list = matches!(*operator, "one_of" | "not_one_of" | "between");
+      (*operator, expected(value.trim(), list))
+    }
+  };
+  Some(Assertion {
+    id: format!("assert{}", index + 1),
+    data_source: "response".to_string(),
+    assertion_type: assertion_type.to_string(),
+    data_id,
+    operator: operator.to_string(),
+    expected_value,
+    enabled: true,
+    is_template_expression: false,
+  })
+}
+
+/// Assertion values are JSON where they parse, text otherwise; lists may be
+/// written without brackets.
+fn expected(value: &str, list: bool) -> Value {
+  let parse = |value: &str| match serde_json::from_str(value) {
+    Ok(value) => value,
+    Err(_) => Value::from(value.trim_matches('\'')),
+  };
+  match parse(value) {
+    Value::Array(values) => Value::Array(values),
+    _ if list => Value::Array(value.split(',').map(|item| parse(item.trim())).collect()),
+    value => value,
+  }
+}
+
+/// A parsed `.bru` file.
+#[derive(Debug, Default)]
+struct Bru {
+  blocks: Vec<(String, Block)>,
+}
+
+#[derive(Debug)]
+enum Block {
+  /// `key: value` lines; a `~` prefix disables an entry.
+  Pairs(Vec<(String, String, bool)>),
+  /// Content indented by two spaces, such as a JSON body or a script.
+  Text(String),
+  /// `name [ ... ]` entries.
+  List(Vec<String>),
+}
+
+impl Bru {
+  fn parse(text: &str) -> Self {
+    let mut blocks = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+      let line = line.trim_end();
+      if let Some(name) = line.strip_suffix('[').map(str::trim).filter(|name| !name.is_empty()) {
+        let entries = lines
+          .by_ref()
+          .take_while(|line| line.trim_end() != "]")
+          .map(|line| line.trim().trim_end_matches(',').to_string())
+          .filter(|entry| !entry.is_empty() && !entry.starts_with('~'))
+          .collect();
+        blocks.push((name.to_string(), Block::List(entries)));
+        continue;
+      }
+      let Some(name) = line.strip_suffix('{').map(str::trim).filter(|name| !name.is_empty()) else {
+        continue;
+      };
+      let content: Vec<&str> = lines.by_ref().take_while(|line| line.trim_end() != "}").collect();
+      let block = if is_text_block(name) {
+        let text: Vec<&str> = content.iter().map(|line| line.strip_prefix("  ").unwrap_or(line)).collect();
+        Block::Text(text.join("\n"))
+      } else {
+        Block::Pairs(
+          content
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+              let (enabled, line) = match line.strip_prefix('~') {
+                Some(line) => (false, line),
+                None => (true, line),
+              };
+              let (key, value) = line.split_once(':').unwrap_or((line, ""));
+              (key.trim().to_string(), value.trim().to_string(), enabled)
+            })
+            .collect(),
+        )
+      };
+      blocks.push((name.to_string(), block));
+    }
+    Bru { blocks }
+  }
+
+  fn block(&self, name: &str) -> Option<&Block> {
+    self.blocks.iter().find(|(block, _)| block == name).map(|(_, block)| block)
+  }
+
+  /// Enabled entries of a `key: value` block.
+  fn pairs<'b>(&'b self, name: &str) -> impl Iterator<Item = (&'b str, &'b str)> {
+    let entries = match self.block(name) {
+      Some(Block::Pairs(entries)) => entries.as_slice(),
+      _ => &[],
+    };
+    entries
+      .iter()
+      .filter(|(_, _, enabled)| *enabled)
+      .map(|(key, value, _)| (key.as_str(), value.as_str()))
+  }
+
+  fn value(&self, block: &str, key: &str) -> Option<&str> {
+    self.pairs(block).find(|(name, _)| *name == key).map(|(_, value)| value)
+  }
+
+  fn text(&self, name: &str) -> Option<&str> {
+    match self.block(name) {
+      Some(Block::Text(text)) => Some(text),
+      _ => None,
+    }
+  }
+
+  fn list(&self, name: &str) -> &[String] {
+    match self.block(name) {
+      Some(Block::List(entries)) => entries,
+      _ => &[],
+    }
+  }
+
+  fn variables(&self, name: &str) -> Map<String, Value> {
+    self.pairs(name).map(|(key, value)| (key.to_string(), Value::from(value))).collect()
+  }
+
+  /// Every header, disabled ones included.
+  fn headers(&self) -> Vec<HeaderEntry> {
+    let Some(Block::Pairs(entries)) = self.block("headers") else {
+      return Vec::new();
+    };
+    entries
+      .iter()
+      .map(|(name, value, enabled)| HeaderEntry {
+        name: name.clone(),
+        value: value.clone(),
+        enabled: *enabled,
+      })
+      .collect()
+  }
+
+  /// `None` for `inherit`, or when the file sets no mode.
+  fn auth(&self, mode: Option<&str>) -> Option<Auth> {
+    let field = |block: &str, key: &str| self.value(block, key).unwrap_or_default().to_string();
+    Some(match mode? {
+      "inherit" => return None,
+      "none" => Auth::None,
+      "bearer" => Auth::Bearer(field("auth:bearer", "token")),
+      "basic" => Auth::Basic(field("auth:basic", "username"), field("auth:basic", "password")),
+      "apikey" => Auth::ApiKey {
+        key: field("auth:apikey", "key"),
+        value: field("auth:apikey", "value"),
+        in_query: field("auth:apikey", "placement") == "queryparams",
+      },
+      other => Auth::Unsupported(other.to_string()),
+    })
+  }
+
+  fn scripts(&self) -> Vec<String> {
+    [
+      ("script:pre-request", "pre-request script was not converted"),
+      ("script:post-response", "post-response script was not converted"),
+      ("tests", "tests were not converted; add assertions to the endpoints instead"),
+    ]
+    .iter()
+    .filter(|(name, _)| self.text(name).is_some_and(|script| !script.trim().is_empty()))
+    .map(|(_, message)| message.to_string())
+    .collect()
+  }
+
+  fn seq(&self) -> f64 {
+    self.value("meta", "seq").and_then(|seq| seq.parse().ok()).unwrap_or(f64::MAX)
+  }
+}
+
+/// Bodies, scripts and docs hold free text; form bodies are `key: value`.
+fn is_text_block(name: &str) -> bool {
+  matches!(name, "tests" | "docs")
+    || name.starts_with("script:")
+    || (name.starts_with("body:") && !matches!(name, "body:form-urlencoded" | "body:multipart-form"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn files(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+    entries.iter().map(|(path, text)| (path.to_string(), text.to_string())).collect()
+  }
+
+  #[test]
+  fn maps_folders_variables_and_assertions() {
+    let files = files(&[
+      ("bruno.json", r#"{ "version": "1", "name": "Shop", "type": "collection" }"#),
+      ("collection.bru", "headers {\n  X-Client: test-pilot\n}\n\nauth {\n  mode: bearer\n}\n\nauth:bearer {\n  token: {{token}}\n}\n"),
+      ("environments/Local.bru", "vars {\n  baseUrl: http://localhost:3000\n}\nvars:secret [\n  apiKey\n]\n"),
+      (
+        "Users/Get user.bru",
+        "meta {\n  name: Get user\n  type: http\n  seq: 2\n}\n\nget {\n  url: {{baseUrl}}/users/:id?full=1\n  body: none\n  auth: inherit\n}\n\n\
+         params:query {\n  full: 1\n  ~debug: true\n}\n\nparams:path {\n  id: {{userId}}\n}\n\n\
+         assert {\n  res.status: eq 200\n  res.body.name: isString\n  res.headers['content-type']: contains json\n  res.body.id: in 1, 2\n}\n",
+      ),
+      (
+        "Users/Login.bru",
+        "meta {\n  name: Login\n  type: http\n  seq: 1\n}\n\npost {\n  url: {{baseUrl}}/login\n  body: json\n  auth: none\n}\n\n\
+         body:json {\n  {\n    \"user\": \"ann\",\n    \"remember\": {{remember}}\n  }\n}\n\nvars:pre-request {\n  remember: true\n}\n\n\
+         vars:post-response {\n  userId: res.body.user.id\n}\n\ntests {\n  test(\"ok\", () => {});\n}\n",
+      ),
+    ]);
+
+    let import = import(&files).unwrap();
+    assert_eq!(import.name, "Shop");
+    let flow = &import.flows[0].flow;
+    let labels: Vec<&str> = flow.steps.iter().map(|step| step.label.as_str()).collect();
+    assert_eq!(labels, ["Login", "Get user"]);
+
+    let login = &flow.steps[0].endpoints[0];
+    assert_eq!(login.body, Some(json!({ "user": "ann", "remember": "{{{param:remember}}}" })));
+    assert!(login.headers.iter().all(|header| header.name != "Authorization"));
+    assert_eq!(flow.parameters[0].default_value, Some(json!("true")));
+
+    let get_user = &flow.steps[1].endpoints[0];
+    assert_eq!(get_user.path_params["id"], json!("{{res:step1-0.$.user.id}}"));
+    assert_eq!(get_user.query_params, json!({ "full": "1" }).as_object().unwrap().clone());
+    let headers: Vec<(&str, &str)> = get_user.headers.iter().map(|header| (header.name.as_str(), header.value.as_str())).collect();
+    assert_eq!(headers, [("X-Client", "test-pilot"), ("Authorization", "Bearer {{param:token}}")]);
+    let assertions: Vec<(&str, &str, &str, Value)> = get_user
+      .assertions
+      .iter()
+      .map(|assertion| {
+        let (kind, data_id, operator) = (&assertion.assertion_type, &assertion.data_id, &assertion.operator);
+        (kind.as_str(), data_id.as_str(), operator.as_str(), assertion.expected_value.clone())
+      })
+      .collect();
+    assert_eq!(
+      assertions,
+      [
+        ("status_code", "", "equals", json!(200)),
+        ("json_body", "$.name", "is_type", json!("string")),
+        ("header", "content-type", "contains", json!("json")),
+        ("json_body", "$.id", "one_of", json!([1, 2])),
+      ]
+    );
+
+    let environment = import.environment.unwrap();
+    assert_eq!(environment.environments["Local"].api_hosts[&get_user.api_id], "http://localhost:3000");
+    let warnings: Vec<&str> = import.warnings.iter().map(|warning| warning.message.split(';').next().unwrap()).collect();
+    assert_eq!(
+      warnings,
+      [
+        "secret `apiKey` of environment `Local` has no value in the collection",
+        "tests were not converted",
+        "variable `token` is not defined in the collection or an environment",
+      ]
+    );
+    assert_eq!(import.summary.sub_environments, 1);
+  }
+
+  #[test]
+  fn parses_bru_blocks() {
+    let bru = Bru::parse("docs {\n  # Title\n    indented\n}\n\nheaders {\n  ~X-Off: 1\n  Accept: a:b\n}\n");
+    assert_eq!(bru.text("docs"), Some("# Title\n  indented"));
+    assert_eq!(bru.pairs("headers").collect::<Vec<_>>(), [("Accept", "a:b")]);
+    assert_eq!(bru.headers().len(), 2);
+    assert!(import(&BTreeMap::new()).is_err());
+  }
+}
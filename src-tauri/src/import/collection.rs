@@ -0,0 +1,790 @@
+//! The folders-of-requests model Postman, Insomnia and Bruno share, and its
+//! conversion into flows.
+//!
+//! Each format's parser builds a [`Collection`] whose strings use `{{var}}`
+//! placeholders, and [`convert`] maps it the same way for all of them.
+//! Top-level folders become flows and their subfolders steps holding every
+//! request beneath them; a request directly inside a flow folder gets a step
+//! of its own, and requests at the collection root form a flow named after
+//! the collection. Each distinct URL origin (`{{baseUrl}}`,
+//! `https://api.example.com`) becomes an API whose host is resolved per
+//! sub-environment, because the runner does not template hosts.
+//!
+//! A `{{var}}` placeholder becomes, in order:
+//! - `{{res:...}}` when an earlier request in the flow sets `var` from its
+//!   response;
+//! - a flow parameter defaulting to the value of an enclosing folder or
+//!   request variable;
+//! - `{{env:var}}` when the collection or one of the environments defines it;
+//! - a flow parameter without a value, which usually means a script sets it.
+//!
+//! Dynamic variables such as `{{$guid}}` map to template functions where one
+//! exists, and a placeholder that already is a template expression is kept.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::OnceLock;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+
+use super::{array_parameter, scalar, Import, ImportWarning, ImportedFlow};
+use crate::environment::{EnvironmentConfig, SubEnvironment, VariableDefinition};
+use crate::flow::{ApiHostInfo, Assertion, EndpointDefinition, FlowParameter, FlowStep, HeaderEntry, StepEndpoint, TestFlow};
+use crate::template::parse_template_expression;
+
+/// Dynamic variables and the template functions standing in for them.
+const DYNAMIC_VARIABLES: &[(&str, &str)] = &[
+  ("$guid", "uuid()"),
+  ("$randomUUID", "uuid()"),
+  ("$timestamp", "dateFormat(0, X)"),
+  ("$isoTimestamp", "isoDate()"),
+  ("$randomInt", "randomInt(0, 1000)"),
+  ("$randomAlphaNumeric", "randomString(1)"),
+];
+
+pub(super) fn placeholder() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(r"\{\{([^{}]+)\}\}").expect("valid placeholder regex"))
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Collection {
+  pub name: String,
+  pub description: Option<String>,
+  /// Collection variables, the defaults behind every environment.
+  pub variables: Map<String, Value>,
+  pub environments: Vec<(String, Map<String, Value>)>,
+  pub headers: Vec<HeaderEntry>,
+  pub auth: Option<Auth>,
+  pub items: Vec<Item>,
+  /// What the parser could not convert, reported against the collection.
+  pub notes: Vec<String>,
+  /// Requests the parser left out, such as gRPC requests.
+  pub skipped: usize,
+}
+
+#[derive(Debug)]
+pub(super) enum Item {
+  Folder(Folder),
+  Request(Request),
+}
+
+impl Item {
+  fn name(&self) -> &str {
+    match self {
+      Item::Folder(folder) => &folder.name,
+      Item::Request(request) => &request.name,
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Folder {
+  pub name: String,
+  pub description: Option<String>,
+  /// Variables scoped to the requests beneath the folder.
+  pub variables: Map<String, Value>,
+  /// Sent by every request beneath the folder unless it sets them itself.
+  pub headers: Vec<HeaderEntry>,
+  /// `None` inherits the parent's.
+  pub auth: Option<Auth>,
+  pub items: Vec<Item>,
+  pub notes: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Request {
+  /// Lets another request in the same flow read this one's response, as
+  /// `{{@id|$.path}}`.
+  pub id: String,
+  pub name: String,
+  pub method: String,
+  /// With its query string, unless `query` is given.
+  pub url: String,
+  /// Enabled query parameters, when the source lists them apart from the URL.
+  pub query: Option<Vec<(String, String)>>,
+  /// Values of `:name` path segments.
+  pub path_variables: Vec<(String, String)>,
+  pub headers: Vec<HeaderEntry>,
+  pub body: Body,
+  /// `None` inherits the parent's.
+  pub auth: Option<Auth>,
+  pub variables: Map<String, Value>,
+  /// Variables set from the response body: name and JSONPath.
+  pub extracts: Vec<(String, String)>,
+  /// Already converted, apart from placeholders in expected values.
+  pub assertions: Vec<Assertion>,
+  pub notes: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub(super) enum Body {
+  #[default]
+  None,
+  /// Text that should be JSON, possibly with unquoted placeholders.
+  Raw(String),
+  GraphQl {
+    query: String,
+    variables: Option<String>,
+  },
+  /// A body mode flows cannot send.
+  Unsupported(String),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Auth {
+  None,
+  Bearer(String),
+  Basic(String, String),
+  ApiKey { key: String, value: String, in_query: bool },
+  Unsupported(String),
+}
+
+/// Convert a parsed collection into flows and an environment.
+pub(super) fn convert(collection: Collection) -> Import {
+  let mut converter = Converter {
+    defaults: collection.variables,
+    environments: collection.environments,
+    origins: Vec::new(),
+    scopes: Vec::new(),
+    parameters: BTreeMap::new(),
+    extracted: HashMap::new(),
+    responses: HashMap::new(),
+    warnings: Vec::new(),
+  };
+  let name = collection.name;
+  converter.notes(&collection.notes, &name);
+  let auth = collection.auth.as_ref();
+  let (folders, requests): (Vec<&Item>, Vec<&Item>) =
+    collection.items.iter().partition(|item| matches!(item, Item::Folder(_)));
+
+  let mut flows = Vec::new();
+  if !requests.is_empty() {
+    flows.push(converter.flow(&name, None, &requests, auth, &collection.headers, ""));
+  }
+  for folder in folders {
+    let Item::Folder(folder) = folder else {
+      continue;
+    };
+    converter.notes(&folder.notes, &folder.name);
+    converter.scopes.push(folder.variables.clone());
+    let children: Vec<&Item> = folder.items.iter().collect();
+    let headers = inherit(&collection.headers, &folder.headers);
+    let auth = folder.auth.as_ref().or(auth);
+    flows.push(converter.flow(&folder.name, folder.description.clone(), &children, auth, &headers, &folder.name));
+    converter.scopes.pop();
+  }
+  log::debug!(
+    "[import] converted collection `{name}` into {} flow(s) with {} warning(s)",
+    flows.len(),
+    converter.warnings.len()
+  );
+
+  let environment = converter.environment();
+  Import::new(name, collection.description, flows, environment, converter.warnings, collection.skipped)
+}
+
+struct Converter {
+  defaults: Map<String, Value>,
+  environments: Vec<(String, Map<String, Value>)>,
+  /// Distinct URL origins; an origin's API ID is its index plus one.
+  origins: Vec<String>,
+  /// Variables of the enclosing folders and request, innermost last.
+  scopes: Vec<Map<String, Value>>,
+  /// Flow parameters of the flow being converted, with their defaults.
+  parameters: BTreeMap<String, Option<Value>>,
+  /// Variables set from earlier responses in the flow, as `res` paths.
+  extracted: HashMap<String, String>,
+  /// Response keys of the flow's requests by request ID.
+  responses: HashMap<String, String>,
+  warnings: Vec<ImportWarning>,
+}
+
+impl Converter {
+  /// One flow from `items`: a subfolder becomes a step with every request
+  /// beneath it, and a request a step of its own.
+  fn flow(
+    &mut self,
+    name: &str,
+    description: Option<String>,
+    items: &[&Item],
+    auth: Option<&Auth>,
+    headers: &[HeaderEntry],
+    location: &str,
+  ) -> ImportedFlow {
+    self.parameters.clear();
+    self.extracted.clear();
+    self.responses.clear();
+    let mut flow = TestFlow::default();
+    for item in items {
+      let label = item.name().to_string();
+      let step_id = format!("step{}", flow.steps.len() + 1);
+      let mut endpoints = Vec::new();
+      self.collect(item, auth, headers, &join(location, &label), &step_id, &mut flow, &mut endpoints);
+      if !endpoints.is_empty() {
+        flow.steps.push(FlowStep {
+          step_id,
+          label,
+          endpoints,
+          clear_cookies_before_execution: false,
+        });
+      }
+    }
+
+    let api_ids: BTreeSet<String> = flow
+      .steps
+      .iter()
+      .flat_map(|step| &step.endpoints)
+      .map(|endpoint| endpoint.api_id.clone())
+      .collect();
+    for api_id in api_ids {
+      let origin = &self.origins[api_id.parse::<usize>().unwrap_or_default() - 1];
+      let url = resolve_host(origin, &self.defaults).unwrap_or_else(|| origin.clone());
+      flow.settings.api_hosts.insert(api_id, ApiHostInfo { url, name: Some(origin.clone()) });
+    }
+    flow.parameters = self
+      .parameters
+      .iter()
+      .map(|(name, default_value)| FlowParameter {
+        name: name.clone(),
+        kind: Some("string".to_string()),
+        value: None,
+        default_value: default_value.clone(),
+        required: default_value.is_none(),
+        description: Some(
+          match default_value {
+            Some(_) => "A folder or request variable in the collection",
+            None => "Not defined in the collection or its environments",
+          }
+          .to_string(),
+        ),
+      })
+      .collect();
+
+    ImportedFlow {
+      name: name.to_string(),
+      description,
+      flow,
+    }
+  }
+
+  /// Add the endpoints of a request, or of every request under a folder.
+  #[allow(clippy::too_many_arguments)]
+  fn collect(
+    &mut self,
+    item: &Item,
+    auth: Option<&Auth>,
+    headers: &[HeaderEntry],
+    location: &str,
+    step_id: &str,
+    flow: &mut TestFlow,
+    endpoints: &mut Vec<StepEndpoint>,
+  ) {
+    match item {
+      Item::Folder(folder) => {
+        self.notes(&folder.notes, location);
+        self.scopes.push(folder.variables.clone());
+        let headers = inherit(headers, &folder.headers);
+        let auth = folder.auth.as_ref().or(auth);
+        for child in &folder.items {
+          self.collect(child, auth, &headers, &join(location, child.name()), step_id, flow, endpoints);
+        }
+        self.scopes.pop();
+      }
+      Item::Request(request) => {
+        self.notes(&request.notes, location);
+        self.scopes.push(request.variables.clone());
+        let key = format!("{step_id}-{}", endpoints.len());
+        endpoints.push(self.endpoint(request, auth, headers, location, flow));
+        self.scopes.pop();
+        if !request.id.is_empty() {
+          self.responses.insert(request.id.clone(), key.clone());
+        }
+        for (variable, path) in &request.extracts {
+          self.extracted.insert(variable.clone(), format!("{key}.{path}"));
+        }
+      }
+    }
+  }
+
+  fn endpoint(
+    &mut self,
+    request: &Request,
+    auth: Option<&Auth>,
+    headers: &[HeaderEntry],
+    location: &str,
+    flow: &mut TestFlow,
+  ) -> StepEndpoint {
+    let (origin, path, query) = split_url(&request.url);
+    let mut endpoint = StepEndpoint {
+      endpoint_id: (flow.endpoints.len() + 1).to_string(),
+      api_id: self.api_id(origin, location),
+      path_params: Map::new(),
+      query_params: Map::new(),
+      body: None,
+      headers: Vec::new(),
+      transformations: Vec::new(),
+      assertions: Vec::new(),
+      skip_default_status_check: false,
+      graphql: None,
+      websocket: None,
+      event_stream: None,
+      grpc: None,
+    };
+    let path = self.path(path, &request.path_variables, location, &mut endpoint.path_params);
+
+    let query = request.query.clone().unwrap_or_else(|| {
+      query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+          let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+          (key.to_string(), value.to_string())
+        })
+        .collect()
+    });
+    let mut parameters = Vec::new();
+    for (key, value) in query {
+      let key = self.template(&key, location);
+      let value = Value::String(self.template(&value, location));
+      match endpoint.query_params.get_mut(&key) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => {
+          *existing = Value::Array(vec![existing.take(), value]);
+          parameters.push(array_parameter(&key));
+        }
+        None => {
+          endpoint.query_params.insert(key, value);
+        }
+      }
+    }
+
+    for header in request.headers.iter().filter(|header| !header.name.is_empty()) {
+      endpoint.headers.push(HeaderEntry {
+        name: self.template(&header.name, location),
+        value: self.template(&header.value, location),
+        enabled: header.enabled,
+      });
+    }
+    for header in headers.iter().filter(|header| header.enabled) {
+      let value = self.template(&header.value, location);
+      add_header(&mut endpoint, &header.name, value);
+    }
+
+    endpoint.body = self.body(&request.body, location);
+    if endpoint.body.is_some() {
+      add_header(&mut endpoint, "Content-Type", "application/json".to_string());
+    }
+    self.auth(request.auth.as_ref().or(auth), &mut endpoint, location);
+
+    for assertion in &request.assertions {
+      let mut assertion = assertion.clone();
+      if let Value::String(expected) = &assertion.expected_value {
+        if placeholder().is_match(expected) {
+          assertion.expected_value = Value::String(self.template(expected, location));
+          assertion.is_template_expression = true;
+        }
+      }
+      endpoint.assertions.push(assertion);
+    }
+
+    let method = if request.method.is_empty() { "GET" } else { &request.method };
+    flow.endpoints.push(EndpointDefinition {
+      id: endpoint.endpoint_id.clone(),
+      path,
+      method: method.to_uppercase(),
+      parameters,
+    });
+    endpoint
+  }
+
+  fn api_id(&mut self, origin: &str, location: &str) -> String {
+    if let Some(index) = self.origins.iter().position(|known| known == origin) {
+      return (index + 1).to_string();
+    }
+    self.origins.push(origin.to_string());
+
+    // A sub-environment without a value falls back to the flow's host.
+    let missing_default = resolve_host(origin, &self.defaults).is_none();
+    let missing_somewhere = self.environments.is_empty()
+      || self
+        .environments
+        .iter()
+        .any(|(_, variables)| resolve_host(origin, &self.merged(variables)).is_none());
+    if missing_default && missing_somewhere {
+      self.warn(location, format!("no value for the host `{origin}`; set the API host in the environment"));
+    }
+    self.origins.len().to_string()
+  }
+
+  /// Path variables (`:id`) and placeholders become `{name}` segments filled
+  /// from path params: the runner substitutes those, but does not resolve
+  /// templates in the path itself.
+  fn path(
+    &mut self,
+    path: &str,
+    variables: &[(String, String)],
+    location: &str,
+    path_params: &mut Map<String, Value>,
+  ) -> String {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+      if let Some(name) = segment.strip_prefix(':').filter(|name| !name.is_empty()) {
+        let value = variables
+          .iter()
+          .find(|(key, _)| key == name)
+          .map(|(_, value)| value.as_str())
+          .unwrap_or_default();
+        path_params.insert(name.to_string(), Value::String(self.template(value, location)));
+        segments.push(format!("{{{name}}}"));
+      } else {
+        let segment = placeholder().replace_all(segment, |captures: &Captures| {
+          let expression = self.expression(&captures[1], location);
+          // `{{@req_1|$.user.id}}` or `{{$guid}}` make poor parameter names.
+          let name = captures[1]
+            .rsplit(|c: char| !c.is_alphanumeric() && c != '_' && c != '-')
+            .find(|name| !name.is_empty())
+            .unwrap_or("value");
+          let mut key = name.to_string();
+          while path_params.contains_key(&key) {
+            key.push('_');
+          }
+          path_params.insert(key.clone(), Value::String(expression));
+          format!("{{{key}}}")
+        });
+        segments.push(segment.into_owned());
+      }
+    }
+    segments.join("/")
+  }
+
+  fn body(&mut self, body: &Body, location: &str) -> Option<Value> {
+    match body {
+      Body::None => None,
+      Body::Raw(raw) => {
+        let raw = raw.trim();
+        if raw.is_empty() {
+          return None;
+        }
+        let body = self.json(raw, location);
+        if body.is_none() {
+          self.warn(location, "the body is not JSON and was dropped; flows only send JSON bodies");
+        }
+        body
+      }
+      Body::GraphQl { query, variables } => {
+        let mut converted = Map::new();
+        let query = self.template(query, location);
+        converted.insert("query".to_string(), Value::String(query));
+        if let Some(variables) = variables.as_deref().map(str::trim).filter(|text| !text.is_empty()) {
+          match self.json(variables, location) {
+            Some(variables) => {
+              converted.insert("variables".to_string(), variables);
+            }
+            None => self.warn(location, "the GraphQL variables are not JSON and were dropped"),
+          }
+        }
+        Some(Value::Object(converted))
+      }
+      Body::Unsupported(mode) => {
+        self.warn(location, format!("`{mode}` bodies are not supported and were dropped; flows only send JSON bodies"));
+        None
+      }
+    }
+  }
+
+  /// Parse JSON text after converting its placeholders.
+  fn json(&mut self, raw: &str, location: &str) -> Option<Value> {
+    serde_json::from_str(&self.template(&quote_bare_placeholders(raw), location)).ok()
+  }
+
+  fn auth(&mut self, auth: Option<&Auth>, endpoint: &mut StepEndpoint, location: &str) {
+    match auth {
+      None | Some(Auth::None) => {}
+      Some(Auth::Bearer(token)) => {
+        let token = self.template(token, location);
+        add_header(endpoint, "Authorization", format!("Bearer {token}"));
+      }
+      Some(Auth::Basic(username, password)) => {
+        let credentials = format!("{username}:{password}");
+        if placeholder().is_match(&credentials) {
+          self.warn(
+            location,
+            "basic auth credentials that use variables cannot be encoded ahead of time; add the Authorization header by hand",
+          );
+        } else {
+          add_header(endpoint, "Authorization", format!("Basic {}", STANDARD.encode(credentials)));
+        }
+      }
+      Some(Auth::ApiKey { key, value, in_query }) => {
+        let key = self.template(key, location);
+        let value = self.template(value, location);
+        if *in_query {
+          endpoint.query_params.entry(key).or_insert(Value::String(value));
+        } else {
+          add_header(endpoint, &key, value);
+        }
+      }
+      Some(Auth::Unsupported(kind)) => {
+        self.warn(location, format!("`{kind}` authentication is not supported; add its headers by hand"))
+      }
+    }
+  }
+
+  fn template(&mut self, text: &str, location: &str) -> String {
+    placeholder()
+      .replace_all(text, |captures: &Captures| self.expression(&captures[1], location))
+      .into_owned()
+  }
+
+  /// The template expression standing in for a placeholder.
+  fn expression(&mut self, name: &str, location: &str) -> String {
+    let name = name.trim();
+    let expression = format!("{{{{{name}}}}}");
+    if parse_template_expression(&expression).is_some() {
+      return expression;
+    }
+    if let Some(reference) = name.strip_prefix('@') {
+      return self.response(reference, location);
+    }
+    if let Some((_, function)) = DYNAMIC_VARIABLES.iter().find(|(variable, _)| *variable == name) {
+      return format!("{{{{func:{function}}}}}");
+    }
+    if let Some(path) = self.extracted.get(name) {
+      return format!("{{{{res:{path}}}}}");
+    }
+    if let Some(value) = self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned() {
+      return self.parameter(name, Some(value), location);
+    }
+    if self.defaults.contains_key(name) || self.environments.iter().any(|(_, variables)| variables.contains_key(name)) {
+      return format!("{{{{env:{name}}}}}");
+    }
+    self.parameter(name, None, location)
+  }
+
+  /// A value read from the response of another request, given as
+  /// `id|$.path`.
+  fn response(&mut self, reference: &str, location: &str) -> String {
+    let (id, path) = reference.split_once('|').unwrap_or((reference, "$"));
+    if let Some(key) = self.responses.get(id) {
+      return format!("{{{{res:{key}.{path}}}}}");
+    }
+    self.warn(
+      location,
+      format!("refers to the response of request `{id}`, which does not run earlier in this flow; added a flow parameter"),
+    );
+    self.parameter(id, Some(Value::from("")), location)
+  }
+
+  /// Use a flow parameter, adding it on first use.
+  fn parameter(&mut self, name: &str, default_value: Option<Value>, location: &str) -> String {
+    let parameter = name.trim_start_matches('$').to_string();
+    match self.parameters.get(&parameter) {
+      Some(Some(existing)) if default_value.as_ref().is_some_and(|value| value != existing) => {
+        let message = format!("variable `{name}` has different values across the flow; its parameter defaults to the first");
+        self.warn(location, message);
+      }
+      Some(_) => {}
+      None => {
+        if default_value.is_none() {
+          let message = if name.starts_with('$') {
+            format!("dynamic variable `{name}` has no template function; added flow parameter `{parameter}`")
+          } else {
+            format!("variable `{name}` is not defined in the collection or an environment; added flow parameter `{parameter}`")
+          };
+          self.warn(location, message);
+        }
+        self.parameters.insert(parameter.clone(), default_value);
+      }
+    }
+    format!("{{{{param:{parameter}}}}}")
+  }
+
+  /// Collection variables become definitions with defaults and each
+  /// environment a sub-environment; without environments, a `default`
+  /// sub-environment selects the defaults.
+  fn environment(&self) -> Option<EnvironmentConfig> {
+    if self.defaults.is_empty() && self.environments.is_empty() {
+      return None;
+    }
+    let mut config = EnvironmentConfig {
+      kind: Some("environment_set".to_string()),
+      ..Default::default()
+    };
+    for (name, value) in &self.defaults {
+      config.variable_definitions.insert(name.clone(), definition(value, Some(value.clone())));
+    }
+    for (name, value) in self.environments.iter().flat_map(|(_, variables)| variables) {
+      config
+        .variable_definitions
+        .entry(name.clone())
+        .or_insert_with(|| definition(value, None));
+    }
+
+    let default = [("default".to_string(), Map::new())];
+    let environments = if self.environments.is_empty() { &default[..] } else { &self.environments[..] };
+    for (name, variables) in environments {
+      let merged = self.merged(variables);
+      let api_hosts = self
+        .origins
+        .iter()
+        .enumerate()
+        .filter(|(_, origin)| placeholder().is_match(origin))
+        .filter_map(|(index, origin)| Some(((index + 1).to_string(), resolve_host(origin, &merged)?)))
+        .collect();
+      let mut key = name.clone();
+      let mut copy = 1;
+      while config.environments.contains_key(&key) {
+        copy += 1;
+        key = format!("{name} ({copy})");
+      }
+      config.environments.insert(
+        key.clone(),
+        SubEnvironment {
+          name: key,
+          variables: variables.clone(),
+          api_hosts,
+        },
+      );
+    }
+    Some(config)
+  }
+
+  /// An environment's values over the collection defaults.
+  fn merged(&self, variables: &Map<String, Value>) -> Map<String, Value> {
+    let mut merged = self.defaults.clone();
+    merged.extend(variables.clone());
+    merged
+  }
+
+  fn notes(&mut self, notes: &[String], location: &str) {
+    for note in notes {
+      self.warn(location, note.clone());
+    }
+  }
+
+  fn warn(&mut self, location: &str, message: impl Into<String>) {
+    self.warnings.push(ImportWarning {
+      location: location.to_string(),
+      message: message.into(),
+    });
+  }
+}
+
+/// Split a raw URL into origin, path and query string. The origin runs up to
+/// the first `/` after the scheme, so `{{baseUrl}}/users` splits right after
+/// the variable.
+pub(super) fn split_url(raw: &str) -> (&str, &str, &str) {
+  let raw = raw.trim();
+  let raw = raw.split_once('#').map_or(raw, |(url, _)| url);
+  let (base, query) = raw.split_once('?').unwrap_or((raw, ""));
+  let start = base.find("://").map_or(0, |scheme| scheme + 3);
+  match base[start..].find('/') {
+    Some(slash) => (&base[..start + slash], &base[start + slash..], query),
+    None => (base, "", query),
+  }
+}
+
+/// Substitute variables into an origin, following variables that refer to
+/// others; `None` if one has no value. `http://` is assumed when the scheme
+/// is left out, as all three tools do.
+fn resolve_host(origin: &str, variables: &Map<String, Value>) -> Option<String> {
+  let mut host = origin.to_string();
+  for _ in 0..8 {
+    if !placeholder().is_match(&host) {
+      return Some(if host.contains("://") { host } else { format!("http://{host}") });
+    }
+    let mut missing = false;
+    host = placeholder()
+      .replace_all(&host, |captures: &Captures| match variables.get(captures[1].trim()) {
+        Some(value) => scalar(Some(value)),
+        None => {
+          missing = true;
+          String::new()
+        }
+      })
+      .into_owned();
+    if missing {
+      return None;
+    }
+  }
+  None
+}
+
+/// Quote placeholders that stand for whole JSON values, as in
+/// `{"id": {{id}}}`, as `"{{{id}}}"`: the body then parses, and the template
+/// engine still substitutes the variable's JSON.
+fn quote_bare_placeholders(raw: &str) -> String {
+  let mut quoted = String::with_capacity(raw.len());
+  let (mut in_string, mut escaped) = (false, false);
+  let mut rest = raw;
+  while let Some(c) = rest.chars().next() {
+    if !in_string && rest.starts_with("{{") {
+      if let Some(end) = rest.find("}}") {
+        quoted.push_str("\"{");
+        quoted.push_str(&rest[..end + 2]);
+        quoted.push_str("}\"");
+        rest = &rest[end + 2..];
+        continue;
+      }
+    }
+    if c == '"' && !escaped {
+      in_string = !in_string;
+    }
+    escaped = in_string && c == '\\' && !escaped;
+    quoted.push(c);
+    rest = &rest[c.len_utf8()..];
+  }
+  quoted
+}
+
+/// A folder's headers over its parent's.
+fn inherit(parent: &[HeaderEntry], own: &[HeaderEntry]) -> Vec<HeaderEntry> {
+  let mut headers = own.to_vec();
+  for header in parent {
+    if !own.iter().any(|known| known.name.eq_ignore_ascii_case(&header.name)) {
+      headers.push(header.clone());
+    }
+  }
+  headers
+}
+
+/// Add a header unless the request already sets it.
+fn add_header(endpoint: &mut StepEndpoint, name: &str, value: String) {
+  let exists = endpoint
+    .headers
+    .iter()
+    .any(|header| header.enabled && header.name.eq_ignore_ascii_case(name));
+  if !exists {
+    endpoint.headers.push(HeaderEntry {
+      name: name.to_string(),
+      value,
+      enabled: true,
+    });
+  }
+}
+
+fn definition(value: &Value, default_value: Option<Value>) -> VariableDefinition {
+  let kind = match value {
+    Value::Bool(_) => "boolean",
+    Value::Number(_) => "number",
+    Value::Array(_) => "array",
+    Value::Object(_) => "object",
+    _ => "string",
+  };
+  VariableDefinition {
+    kind: Some(kind.to_string()),
+    required: false,
+    default_value,
+  }
+}
+
+fn join(location: &str, name: &str) -> String {
+  if location.is_empty() {
+    name.to_string()
+  } else {
+    format!("{location} / {name}")
+  }
+}
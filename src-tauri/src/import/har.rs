@@ -0,0 +1,616 @@
+//! HAR (HTTP Archive) import: replay a recorded browser session as a flow.
+//!
+//! Every recorded request becomes a step with one endpoint, in the order it
+//! was sent, and asserts the status it got. A value that a request sends
+//! after an earlier JSON response returned it, such as an ID or a token, is
+//! replaced by a `{{res:stepN-0.$.path}}` template pointing at the latest
+//! response that held it, so the replay carries the new values instead of the
+//! recorded ones.
+
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use url::Url;
+
+use super::{array, array_parameter, str_field, Import, ImportWarning, ImportedFlow};
+use crate::error::{Error, Result};
+use crate::flow::{ApiHostInfo, Assertion, EndpointDefinition, FlowStep, HeaderEntry, StepEndpoint, TestFlow};
+
+/// Request headers the browser or the connection sets rather than the
+/// application. Cookies are left to the run's cookie jar.
+const DEFAULT_EXCLUDED_HEADERS: &[&str] = &[
+  "accept-encoding",
+  "accept-language",
+  "cache-control",
+  "connection",
+  "content-length",
+  "cookie",
+  "dnt",
+  "host",
+  "if-modified-since",
+  "if-none-match",
+  "origin",
+  "pragma",
+  "priority",
+  "referer",
+  "sec-*",
+  "te",
+  "upgrade-insecure-requests",
+  "user-agent",
+];
+
+/// Response types of page assets rather than API calls.
+const STATIC_TYPES: &[&str] = &["text/html", "text/css", "javascript", "image/", "font/", "audio/", "video/"];
+
+/// Shortest value linked when it makes up only part of a header or query
+/// value, as a token does in `Bearer <token>`.
+const MIN_EMBEDDED_LENGTH: usize = 8;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HarOptions {
+  /// Flow name; defaults to the first page's title.
+  pub name: Option<String>,
+  /// Only keep requests to these hosts; all hosts when empty.
+  pub hosts: Vec<String>,
+  /// Keep requests for pages, scripts, styles, images and fonts.
+  pub include_static: bool,
+  /// When set, keep only these request headers.
+  pub include_headers: Option<Vec<String>>,
+  /// Request headers to drop, ignoring case; a trailing `*` matches a prefix.
+  pub exclude_headers: Vec<String>,
+  /// Replace values that came from earlier responses with templates.
+  pub link_responses: bool,
+}
+
+impl Default for HarOptions {
+  fn default() -> Self {
+    Self {
+      name: None,
+      hosts: Vec::new(),
+      include_static: false,
+      include_headers: None,
+      exclude_headers: DEFAULT_EXCLUDED_HEADERS.iter().map(|name| name.to_string()).collect(),
+      link_responses: true,
+    }
+  }
+}
+
+impl HarOptions {
+  fn keeps_header(&self, name: &str) -> bool {
+    // HTTP/2 pseudo-headers such as `:authority` are never sent as headers.
+    if name.starts_with(':') {
+      return false;
+    }
+    match &self.include_headers {
+      Some(include) => include.iter().any(|pattern| header_matches(pattern, name)),
+      None => !self.exclude_headers.iter().any(|pattern| header_matches(pattern, name)),
+    }
+  }
+}
+
+/// Convert the entries of a HAR file into a single flow.
+pub fn import(har: &str, options: &HarOptions) -> Result<Import> {
+  import_log(&serde_json::from_str(har)?, options)
+}
+
+/// [`import`] for a HAR document already parsed, or recorded in memory.
+pub fn import_log(har: &Value, options: &HarOptions) -> Result<Import> {
+  let mut entries: Vec<&Value> = har
+    .pointer("/log/entries")
+    .and_then(Value::as_array)
+    .ok_or_else(|| Error::Import("not a HAR file (expected `log.entries`)".to_string()))?
+    .iter()
+    .collect();
+  // ISO 8601 timestamps sort as text.
+  entries.sort_by(|a, b| str_field(a, "startedDateTime").cmp(&str_field(b, "startedDateTime")));
+  let name = options
+    .name
+    .clone()
+    .or_else(|| har.pointer("/log/pages/0/title").and_then(Value::as_str).map(str::to_string))
+    .filter(|name| !name.is_empty())
+    .unwrap_or_else(|| "HAR import".to_string());
+
+  let mut converter = Converter {
+    options,
+    flow: TestFlow::default(),
+    origins: Vec::new(),
+    values: HashMap::new(),
+    redirect: None,
+    skipped: 0,
+    dropped: 0,
+    warnings: Vec::new(),
+  };
+  for entry in entries {
+    converter.entry(entry);
+  }
+  if converter.skipped > 0 {
+    let message = format!("skipped {} CORS preflight or page asset request(s)", converter.skipped);
+    converter.warn(&name, message);
+  }
+  log::debug!(
+    "[import] converted HAR `{name}` into {} step(s) with {} warning(s)",
+    converter.flow.steps.len(),
+    converter.warnings.len()
+  );
+
+  let flow = ImportedFlow {
+    name: name.clone(),
+    description: None,
+    flow: converter.flow,
+  };
+  let skipped = converter.skipped + converter.dropped;
+  Ok(Import::new(name, None, vec![flow], None, converter.warnings, skipped))
+}
+
+/// Where a recorded response value can be read at run time.
+struct Source {
+  /// `stepN-0.$.path`
+  path: String,
+  /// The key that held the value, used to name path params.
+  name: String,
+}
+
+impl Source {
+  fn template(&self) -> String {
+    format!("{{{{res:{}}}}}", self.path)
+  }
+
+  /// Substituted as JSON, so a number stays a number in a body.
+  fn typed_template(&self) -> String {
+    format!("{{{{{{res:{}}}}}}}", self.path)
+  }
+}
+
+struct Converter<'o> {
+  options: &'o HarOptions,
+  flow: TestFlow,
+  /// Distinct origins; an origin's API ID is its index plus one.
+  origins: Vec<String>,
+  /// Distinctive response values and the latest response holding each.
+  values: HashMap<String, Source>,
+  /// Where the last request was redirected to, with its step: the client
+  /// follows redirects itself, so the recorded follow-up is not replayed.
+  redirect: Option<(Url, String)>,
+  /// Preflight and asset requests left out on purpose.
+  skipped: usize,
+  /// Requests that could not be replayed.
+  dropped: usize,
+  warnings: Vec<ImportWarning>,
+}
+
+impl Converter<'_> {
+  fn entry(&mut self, entry: &Value) {
+    let (request, response) = (&entry["request"], &entry["response"]);
+    let method = str_field(request, "method").unwrap_or("GET").to_uppercase();
+    let raw_url = str_field(request, "url").unwrap_or_default();
+    let Ok(url) = Url::parse(raw_url) else {
+      self.dropped += 1;
+      self.warn(raw_url, "not a valid URL; skipped");
+      return;
+    };
+    // `data:`, `blob:` and extension URLs never hit the network.
+    if !matches!(url.scheme(), "http" | "https") {
+      return;
+    }
+    if let Some((target, step_id)) = self.redirect.take() {
+      if method == "GET" && url == target {
+        self.response(&step_id, &url, response);
+        return;
+      }
+    }
+
+    let host = url.host_str().unwrap_or_default();
+    if !self.options.hosts.is_empty() && !self.options.hosts.iter().any(|known| known.eq_ignore_ascii_case(host)) {
+      return;
+    }
+    let mime_type = response.pointer("/content/mimeType").and_then(Value::as_str).unwrap_or_default();
+    if method == "OPTIONS" || (!self.options.include_static && is_static(mime_type)) {
+      self.skipped += 1;
+      return;
+    }
+    let label = format!("{method} {}", url.path());
+    if response.get("status").and_then(Value::as_u64).unwrap_or(0) == 0 {
+      self.dropped += 1;
+      self.warn(&label, "no response was recorded (blocked or cancelled); skipped");
+      return;
+    }
+
+    let step_id = format!("step{}", self.flow.steps.len() + 1);
+    let mut endpoint = StepEndpoint {
+      endpoint_id: (self.flow.endpoints.len() + 1).to_string(),
+      api_id: self.api_id(&url),
+      path_params: Map::new(),
+      query_params: Map::new(),
+      body: None,
+      headers: Vec::new(),
+      transformations: Vec::new(),
+      assertions: Vec::new(),
+      skip_default_status_check: false,
+      graphql: None,
+      websocket: None,
+      event_stream: None,
+      grpc: None,
+    };
+    let path = self.path(&url, &mut endpoint.path_params);
+
+    let mut parameters = Vec::new();
+    for (key, value) in url.query_pairs() {
+      let value = Value::String(self.link_text(&value));
+      match endpoint.query_params.get_mut(key.as_ref()) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => {
+          *existing = Value::Array(vec![existing.take(), value]);
+          parameters.push(array_parameter(&key));
+        }
+        None => {
+          endpoint.query_params.insert(key.into_owned(), value);
+        }
+      }
+    }
+
+    for header in array(request, "headers") {
+      let Some(name) = str_field(header, "name").filter(|name| self.options.keeps_header(name)) else {
+        continue;
+      };
+      endpoint.headers.push(HeaderEntry {
+        name: name.to_string(),
+        value: self.link_text(str_field(header, "value").unwrap_or_default()),
+        enabled: true,
+      });
+    }
+    endpoint.body = self.body(request, &label);
+
+    self.flow.endpoints.push(EndpointDefinition {
+      id: endpoint.endpoint_id.clone(),
+      path,
+      method,
+      parameters,
+    });
+    self.flow.steps.push(FlowStep {
+      step_id: step_id.clone(),
+      label,
+      endpoints: vec![endpoint],
+      clear_cookies_before_execution: false,
+    });
+    self.response(&step_id, &url, response);
+  }
+
+  /// Assert the recorded status and remember the response's values, or wait
+  /// for the entry a redirect leads to.
+  fn response(&mut self, step_id: &str, url: &Url, response: &Value) {
+    let status = response.get("status").and_then(Value::as_u64).unwrap_or(0);
+    if (300..400).contains(&status) {
+      let target = str_field(response, "redirectURL").filter(|target| !target.is_empty());
+      if let Some(target) = target.and_then(|target| url.join(target).ok()) {
+        self.redirect = Some((target, step_id.to_string()));
+        return;
+      }
+    }
+
+    if let Some(endpoint) = self
+      .flow
+      .steps
+      .iter_mut()
+      .find(|step| step.step_id == step_id)
+      .and_then(|step| step.endpoints.first_mut())
+    {
+      endpoint.assertions.push(status_assertion(step_id, status));
+      endpoint.skip_default_status_check = !(200..300).contains(&status);
+    }
+    if !self.options.link_responses {
+      return;
+    }
+    if let Some(body) = response_json(response) {
+      let mut found = Vec::new();
+      distinctive_values(&body, "$", "value", &mut found);
+      for (value, path, name) in found {
+        let path = format!("{step_id}-0.{path}");
+        self.values.insert(value, Source { path, name });
+      }
+    }
+  }
+
+  fn api_id(&mut self, url: &Url) -> String {
+    let origin = url.origin().ascii_serialization();
+    let index = match self.origins.iter().position(|known| *known == origin) {
+      Some(index) => index,
+      None => {
+        self.origins.push(origin.clone());
+        self.origins.len() - 1
+      }
+    };
+    let api_id = (index + 1).to_string();
+    self.flow.settings.api_hosts.entry(api_id.clone()).or_insert_with(|| ApiHostInfo {
+      url: origin,
+      name: url.host_str().map(str::to_string),
+    });
+    api_id
+  }
+
+  /// Segments holding a linked value become `{name}` path params.
+  fn path(&self, url: &Url, path_params: &mut Map<String, Value>) -> String {
+    let mut segments = Vec::new();
+    for segment in url.path().split('/') {
+      let decoded = percent_decode_str(segment).decode_utf8_lossy();
+      match self.values.get(decoded.as_ref()) {
+        Some(source) => {
+          let mut name = source.name.clone();
+          let mut copy = 1;
+          while path_params.contains_key(&name) {
+            copy += 1;
+            name = format!("{}{copy}", source.name);
+          }
+          path_params.insert(name.clone(), Value::String(source.template()));
+          segments.push(format!("{{{name}}}"));
+        }
+        None => segments.push(segment.to_string()),
+      }
+    }
+    segments.join("/")
+  }
+
+  fn body(&mut self, request: &Value, label: &str) -> Option<Value> {
+    let post = request.get("postData")?;
+    let mime_type = str_field(post, "mimeType").unwrap_or_default();
+    let text = str_field(post, "text").unwrap_or_default();
+    if text.trim().is_empty() && array(post, "params").is_empty() {
+      return None;
+    }
+    // `fetch` labels a string body `text/plain` even when it holds JSON.
+    match serde_json::from_str::<Value>(text) {
+      Ok(body) if mime_type.contains("json") || body.is_object() || body.is_array() => Some(self.link_json(body)),
+      _ => {
+        self.warn(label, format!("the `{mime_type}` body is not JSON and was dropped; flows only send JSON bodies"));
+        None
+      }
+    }
+  }
+
+  /// A header or query value with linked values templated: the whole value,
+  /// or longer values embedded in it.
+  fn link_text(&self, text: &str) -> String {
+    if let Some(source) = self.values.get(text) {
+      return source.template();
+    }
+    let mut embedded: Vec<(&String, &Source)> = self
+      .values
+      .iter()
+      .filter(|(value, _)| value.len() >= MIN_EMBEDDED_LENGTH && text.contains(value.as_str()))
+      .collect();
+    embedded.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    let mut linked = text.to_string();
+    for (value, source) in embedded {
+      linked = linked.replace(value.as_str(), &source.template());
+    }
+    linked
+  }
+
+  fn link_json(&self, value: Value) -> Value {
+    match value {
+      Value::String(text) => match self.values.get(&text) {
+        Some(source) => Value::String(source.template()),
+        None => Value::String(text),
+      },
+      Value::Number(number) => match self.values.get(&number.to_string()) {
+        Some(source) => Value::String(source.typed_template()),
+        None => Value::Number(number),
+      },
+      Value::Array(items) => items.into_iter().map(|item| self.link_json(item)).collect(),
+      Value::Object(map) => map.into_iter().map(|(key, value)| (key, self.link_json(value))).collect(),
+      other => other,
+    }
+  }
+
+  fn warn(&mut self, location: &str, message: impl Into<String>) {
+    self.warnings.push(ImportWarning {
+      location: location.to_string(),
+      message: message.into(),
+    });
+  }
+}
+
+/// Collect the distinctive scalars of a response body with the paths the
+/// template engine reads them by. Keys it cannot address are skipped.
+fn distinctive_values(value: &Value, path: &str, name: &str, found: &mut Vec<(String, String, String)>) {
+  match value {
+    Value::Object(map) => {
+      for (key, child) in map {
+        if !key.is_empty() && !key.contains(['.', '[', ']', '{', '}']) {
+          distinctive_values(child, &format!("{path}.{key}"), key, found);
+        }
+      }
+    }
+    Value::Array(items) => {
+      for (index, item) in items.iter().enumerate() {
+        // `a[0][1]` is not understood, but `a[0].1` is.
+        let path = if path.ends_with(']') { format!("{path}.{index}") } else { format!("{path}[{index}]") };
+        distinctive_values(item, &path, name, found);
+      }
+    }
+    Value::String(text) if is_distinctive(text) => found.push((text.clone(), path.to_string(), name.to_string())),
+    Value::Number(number) if !number.is_f64() && is_distinctive(&number.to_string()) => {
+      found.push((number.to_string(), path.to_string(), name.to_string()));
+    }
+    _ => {}
+  }
+}
+
+/// Whether finding a value in a later request means it came from the
+/// response: IDs and tokens, not words, flags or small counts, which turn up
+/// by coincidence.
+fn is_distinctive(value: &str) -> bool {
+  let word = !value.is_empty() && !value.chars().any(char::is_whitespace);
+  word && (value.len() >= 16 || (value.len() >= 4 && value.chars().any(|c| c.is_ascii_digit())))
+}
+
+fn response_json(response: &Value) -> Option<Value> {
+  let content = response.get("content")?;
+  let text = str_field(content, "text")?;
+  if str_field(content, "encoding") == Some("base64") {
+    serde_json::from_slice(&STANDARD.decode(text).ok()?).ok()
+  } else {
+    serde_json::from_str(text).ok()
+  }
+}
+
+fn status_assertion(step_id: &str, status: u64) -> Assertion {
+  Assertion {
+    id: format!("{step_id}-status"),
+    data_source: "response".to_string(),
+    assertion_type: "status_code".to_string(),
+    data_id: String::new(),
+    operator: "equals".to_string(),
+    expected_value: Value::from(status),
+    enabled: true,
+    is_template_expression: false,
+  }
+}
+
+fn is_static(mime_type: &str) -> bool {
+  let mime_type = mime_type.to_ascii_lowercase();
+  STATIC_TYPES.iter().any(|kind| mime_type.contains(kind))
+}
+
+fn header_matches(pattern: &str, name: &str) -> bool {
+  match pattern.strip_suffix('*') {
+    Some(prefix) => name.to_ascii_lowercase().starts_with(&prefix.to_ascii_lowercase()),
+    None => pattern.eq_ignore_ascii_case(name),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn entry(method: &str, url: &str, headers: Value, body: Option<Value>, status: u64, response: Value) -> Value {
+    let mut request = json!({ "method": method, "url": url, "headers": headers });
+    if let Some(body) = body {
+      request["postData"] = json!({ "mimeType": "application/json", "text": body.to_string() });
+    }
+    json!({
+      "startedDateTime": "",
+      "request": request,
+      "response": { "status": status, "content": { "mimeType": "application/json", "text": response.to_string() } }
+    })
+  }
+
+  fn har(entries: Vec<Value>) -> String {
+    json!({ "log": { "pages": [{ "title": "Checkout" }], "entries": entries } }).to_string()
+  }
+
+  #[test]
+  fn links_values_from_earlier_responses() {
+    let token = "tok_abcdefghijklmnop";
+    let har = har(vec![
+      entry(
+        "POST",
+        "https://shop.test/api/login",
+        json!([{ "name": "Content-Type", "value": "application/json" }, { "name": ":authority", "value": "shop.test" }]),
+        Some(json!({ "user": "ann" })),
+        200,
+        json!({ "token": token, "user": { "id": 4711, "roles": ["admin"] }, "count": 3 }),
+      ),
+      json!({
+        "request": { "method": "GET", "url": "https://shop.test/app.js", "headers": [] },
+        "response": { "status": 200, "content": { "mimeType": "application/javascript" } }
+      }),
+      entry(
+        "GET",
+        "https://shop.test/api/users/4711/orders?since=3&tag=a&tag=b&session=tok_abcdefghijklmnop",
+        json!([
+          { "name": "Authorization", "value": format!("Bearer {token}") },
+          { "name": "User-Agent", "value": "Mozilla/5.0" },
+          { "name": "Sec-Fetch-Mode", "value": "cors" },
+          { "name": "Cookie", "value": "sid=1" }
+        ]),
+        None,
+        200,
+        json!([{ "id": 90001, "items": [[{ "sku": "SKU-1234" }]] }]),
+      ),
+      entry(
+        "POST",
+        "https://cdn.shop.test/api/orders/90001/pay",
+        json!([]),
+        Some(json!({ "userId": 4711, "sku": "SKU-1234", "count": 3 })),
+        402,
+        json!({}),
+      ),
+    ]);
+
+    let import = import(&har, &HarOptions::default()).unwrap();
+    assert_eq!(import.name, "Checkout");
+    let flow = &import.flows[0].flow;
+    assert_eq!(flow.steps.len(), 3);
+    assert_eq!(flow.endpoints[0].path, "/api/login");
+    assert_eq!(flow.steps[0].endpoints[0].headers.len(), 1);
+
+    let orders = &flow.steps[1].endpoints[0];
+    assert_eq!(flow.endpoints[1].path, "/api/users/{id}/orders");
+    assert_eq!(orders.path_params["id"], json!("{{res:step1-0.$.user.id}}"));
+    assert_eq!(orders.query_params["since"], json!("3"));
+    assert_eq!(orders.query_params["tag"], json!(["a", "b"]));
+    assert_eq!(orders.query_params["session"], json!("{{res:step1-0.$.token}}"));
+    assert_eq!(flow.endpoints[1].parameters[0].name, "tag");
+    let headers: Vec<(&str, &str)> = orders.headers.iter().map(|h| (h.name.as_str(), h.value.as_str())).collect();
+    assert_eq!(headers, [("Authorization", "Bearer {{res:step1-0.$.token}}")]);
+
+    let pay = &flow.steps[2].endpoints[0];
+    assert_eq!(flow.endpoints[2].path, "/api/orders/{id}/pay");
+    assert_eq!(pay.path_params["id"], json!("{{res:step2-0.$[0].id}}"));
+    assert_eq!(
+      pay.body,
+      Some(json!({ "userId": "{{{res:step1-0.$.user.id}}}", "sku": "{{res:step2-0.$[0].items[0].0.sku}}", "count": 3 }))
+    );
+    assert_eq!(pay.assertions[0].expected_value, json!(402));
+    assert!(pay.skip_default_status_check);
+    assert_ne!(pay.api_id, orders.api_id);
+    assert_eq!(flow.settings.api_hosts[&pay.api_id].url, "https://cdn.shop.test");
+
+    assert_eq!(import.warnings.len(), 1);
+    assert!(import.warnings[0].message.contains("skipped 1"));
+  }
+
+  #[test]
+  fn follows_redirects_and_applies_options() {
+    let mut login = entry("POST", "http://app.test/login", json!([{ "name": "X-Csrf", "value": "c" }]), None, 302, json!({}));
+    login["request"]["postData"] = json!({ "mimeType": "application/x-www-form-urlencoded", "text": "user=ann", "params": [] });
+    login["response"]["redirectURL"] = json!("/home");
+    let har = har(vec![
+      login,
+      entry("GET", "http://app.test/home", json!([]), None, 200, json!({ "session": "s-12345" })),
+      entry("GET", "http://other.test/s-12345", json!([{ "name": "X-Csrf", "value": "c" }]), None, 200, json!({})),
+      entry("GET", "http://app.test/s-12345", json!([{ "name": "X-Other", "value": "c" }]), None, 200, json!({})),
+    ]);
+    let options = HarOptions {
+      name: Some("Login".to_string()),
+      hosts: vec!["APP.test".to_string()],
+      include_headers: Some(vec!["x-csrf".to_string()]),
+      link_responses: false,
+      ..HarOptions::default()
+    };
+
+    let import = import(&har, &options).unwrap();
+    assert_eq!(import.name, "Login");
+    let flow = &import.flows[0].flow;
+    let labels: Vec<&str> = flow.steps.iter().map(|step| step.label.as_str()).collect();
+    assert_eq!(labels, ["POST /login", "GET /s-12345"]);
+
+    let login = &flow.steps[0].endpoints[0];
+    assert_eq!(login.headers[0].name, "X-Csrf");
+    assert!(login.body.is_none());
+    assert_eq!(login.assertions.len(), 1);
+    assert_eq!(login.assertions[0].expected_value, json!(200));
+    assert!(!login.skip_default_status_check);
+    assert!(flow.steps[1].endpoints[0].headers.is_empty());
+    assert!(flow.steps[1].endpoints[0].path_params.is_empty());
+
+    let warnings: Vec<&str> = import.warnings.iter().map(|warning| warning.location.as_str()).collect();
+    assert_eq!(warnings, ["POST /login"]);
+  }
+}
@@ -0,0 +1,413 @@
+//! Insomnia export (format 4) import.
+//!
+//! Request groups, requests and environments map onto flows as described in
+//! [`collection`](super::collection): the base environment holds the
+//! defaults, its sub-environments become sub-environments, and a folder's
+//! environment becomes flow parameters with defaults. Nunjucks variables
+//! (`{{ _.base_url }}`) are read like Postman's; of the template tags,
+//! `response` (reading the body of a request earlier in the flow), `uuid` and
+//! `now` are converted. gRPC and WebSocket requests are skipped.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+
+use super::collection::{self, Auth, Body, Collection, Folder, Item, Request};
+use super::{array, is_true, scalar, str_field, Import};
+use crate::error::{Error, Result};
+use crate::flow::HeaderEntry;
+
+fn variable() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(r"\{\{\s*(?:_\.)?([A-Za-z_$][\w$.-]*)\s*\}\}").expect("valid variable regex"))
+}
+
+fn tag() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(r"\{%\s*(\w+)(.*?)%\}").expect("valid tag regex"))
+}
+
+fn quoted() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  RE.get_or_init(|| Regex::new(r#"'([^']*)'|"([^"]*)""#).expect("valid argument regex"))
+}
+
+/// Convert the first workspace of an export.
+pub fn import(export: &str) -> Result<Import> {
+  let export: Value = serde_json::from_str(export)?;
+  if export.get("__export_format").and_then(Value::as_u64) != Some(4) {
+    return Err(Error::Import(
+      "not an Insomnia export (expected `__export_format` 4); export the data as Insomnia v4 JSON".to_string(),
+    ));
+  }
+  let resources = array(&export, "resources");
+  let workspaces: Vec<&Value> = resources.iter().filter(|resource| kind(resource) == "workspace").collect();
+  let workspace = *workspaces
+    .first()
+    .ok_or_else(|| Error::Import("the export holds no workspace".to_string()))?;
+
+  let mut children: HashMap<&str, Vec<&Value>> = HashMap::new();
+  for resource in resources {
+    children.entry(str_field(resource, "parentId").unwrap_or_default()).or_default().push(resource);
+  }
+  for siblings in children.values_mut() {
+    siblings.sort_by(|a, b| sort_key(a).total_cmp(&sort_key(b)));
+  }
+
+  let mut parser = Parser { children, skipped: 0 };
+  let mut notes = Vec::new();
+  if workspaces.len() > 1 {
+    notes.push(format!("the export holds {} workspaces; only this one was imported", workspaces.len()));
+  }
+  let (variables, environments) = parser.environments(id(workspace));
+  let items = parser.items(id(workspace), &mut notes);
+  Ok(collection::convert(Collection {
+    name: str_field(workspace, "name").unwrap_or("Insomnia collection").to_string(),
+    description: description(workspace),
+    variables,
+    environments,
+    items,
+    notes,
+    skipped: parser.skipped,
+    ..Default::default()
+  }))
+}
+
+struct Parser<'e> {
+  /// Resources by parent ID, in the order Insomnia shows them.
+  children: HashMap<&'e str, Vec<&'e Value>>,
+  skipped: usize,
+}
+
+impl<'e> Parser<'e> {
+  /// The base environment's data and each of its sub-environments'.
+  #[allow(clippy::type_complexity)]
+  fn environments(&self, workspace: &str) -> (Map<String, Value>, Vec<(String, Map<String, Value>)>) {
+    let Some(base) = self.children(workspace).into_iter().find(|resource| kind(resource) == "environment") else {
+      return (Map::new(), Vec::new());
+    };
+    let environments = self
+      .children(id(base))
+      .into_iter()
+      .filter(|resource| kind(resource) == "environment")
+      .map(|environment| {
+        let name = str_field(environment, "name").unwrap_or("environment").to_string();
+        (name, flatten(environment.get("data")))
+      })
+      .collect();
+    (flatten(base.get("data")), environments)
+  }
+
+  /// The folders and requests under `parent`; what is skipped is noted
+  /// against the parent.
+  fn items(&mut self, parent: &str, notes: &mut Vec<String>) -> Vec<Item> {
+    let mut items = Vec::new();
+    for resource in self.children(parent) {
+      let name = str_field(resource, "name").unwrap_or_default().to_string();
+      match kind(resource) {
+        "request_group" => {
+          let mut folder_notes = scripts(resource);
+          let auth = auth(resource.get("authentication"), &mut folder_notes);
+          let children = self.items(id(resource), &mut folder_notes);
+          items.push(Item::Folder(Folder {
+            name,
+            description: description(resource),
+            variables: flatten(resource.get("environment")),
+            auth,
+            items: children,
+            notes: folder_notes,
+            ..Default::default()
+          }));
+        }
+        "request" => items.push(Item::Request(request(resource, name))),
+        "grpc_request" | "websocket_request" => {
+          self.skipped += 1;
+          let protocol = if kind(resource) == "grpc_request" { "gRPC" } else { "WebSocket" };
+          notes.push(format!("{protocol} request `{name}` was skipped; flows only send HTTP requests"));
+        }
+        _ => {}
+      }
+    }
+    items
+  }
+
+  fn children(&self, parent: &str) -> Vec<&'e Value> {
+    self.children.get(parent).cloned().unwrap_or_default()
+  }
+}
+
+fn request(resource: &Value, name: String) -> Request {
+  let mut notes = scripts(resource);
+  let mut url = str_field(resource, "url").unwrap_or_default().to_string();
+  let parameters: Vec<&Value> = array(resource, "parameters")
+    .iter()
+    .filter(|parameter| !is_true(parameter.get("disabled")))
+    .collect();
+  // Insomnia lists query parameters apart from the URL, which can carry some
+  // of its own.
+  let query = (!parameters.is_empty()).then(|| {
+    let (base, own) = url.split_once('?').map_or((url.as_str(), ""), |(base, query)| (base, query));
+    let mut query: Vec<(String, String)> = own
+      .split('&')
+      .filter(|pair| !pair.is_empty())
+      .map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (template(key, &mut notes), template(value, &mut notes))
+      })
+      .collect();
+    for parameter in &parameters {
+      let key = template(str_field(parameter, "name").unwrap_or_default(), &mut notes);
+      query.push((key, template(&scalar(parameter.get("value")), &mut notes)));
+    }
+    url = base.to_string();
+    query
+  });
+
+  let path_variables = array(resource, "pathParameters")
+    .iter()
+    .map(|parameter| {
+      let name = str_field(parameter, "name").unwrap_or_default().to_string();
+      (name, template(&scalar(parameter.get("value")), &mut notes))
+    })
+    .collect();
+  let headers = array(resource, "headers")
+    .iter()
+    .map(|header| HeaderEntry {
+      name: template(str_field(header, "name").unwrap_or_default(), &mut notes),
+      value: template(&scalar(header.get("value")), &mut notes),
+      enabled: !is_true(header.get("disabled")),
+    })
+    .collect();
+  let body = body(resource.get("body"), &mut notes);
+  let auth = auth(resource.get("authentication"), &mut notes);
+
+  Request {
+    id: id(resource).to_string(),
+    name,
+    method: str_field(resource, "method").unwrap_or("GET").to_string(),
+    url: template(&url, &mut notes),
+    query,
+    path_variables,
+    headers,
+    body,
+    auth,
+    notes,
+    ..Default::default()
+  }
+}
+
+fn body(body: Option<&Value>, notes: &mut Vec<String>) -> Body {
+  let Some(body) = body else {
+    return Body::None;
+  };
+  let text = template(str_field(body, "text").unwrap_or_default(), notes);
+  match str_field(body, "mimeType").unwrap_or_default() {
+    _ if body.get("fileName").is_some() => Body::Unsupported("file".to_string()),
+    "application/graphql" => {
+      let graphql: Value = serde_json::from_str(&text).unwrap_or_default();
+      Body::GraphQl {
+        query: str_field(&graphql, "query").unwrap_or_default().to_string(),
+        variables: graphql.get("variables").filter(|variables| !variables.is_null()).map(Value::to_string),
+      }
+    }
+    "application/x-www-form-urlencoded" | "multipart/form-data" => {
+      Body::Unsupported(str_field(body, "mimeType").unwrap_or_default().to_string())
+    }
+    _ if text.trim().is_empty() => Body::None,
+    _ => Body::Raw(text),
+  }
+}
+
+/// `None` for an empty setting, which inherits the folder's.
+fn auth(auth: Option<&Value>, notes: &mut Vec<String>) -> Option<Auth> {
+  let auth = auth?;
+  let kind = str_field(auth, "type")?;
+  if is_true(auth.get("disabled")) {
+    return Some(Auth::None);
+  }
+  let mut field = |key: &str| template(&scalar(auth.get(key)), notes);
+  Some(match kind {
+    "none" => Auth::None,
+    "bearer" => {
+      let token = field("token");
+      match str_field(auth, "prefix").filter(|prefix| !prefix.is_empty() && *prefix != "Bearer") {
+        Some(prefix) => Auth::ApiKey {
+          key: "Authorization".to_string(),
+          value: format!("{prefix} {token}"),
+          in_query: false,
+        },
+        None => Auth::Bearer(token),
+      }
+    }
+    "basic" => Auth::Basic(field("username"), field("password")),
+    "apikey" => Auth::ApiKey {
+      key: field("key"),
+      value: field("value"),
+      in_query: str_field(auth, "addTo") == Some("queryParams"),
+    },
+    other => Auth::Unsupported(other.to_string()),
+  })
+}
+
+/// Rewrite Nunjucks variables and tags as `{{var}}` placeholders.
+fn template(text: &str, notes: &mut Vec<String>) -> String {
+  let text = variable().replace_all(text, "{{$1}}");
+  tag()
+    .replace_all(&text, |captures: &Captures| {
+      let arguments: Vec<&str> = quoted()
+        .captures_iter(&captures[2])
+        .filter_map(|argument| argument.get(1).or_else(|| argument.get(2)))
+        .map(|argument| argument.as_str())
+        .collect();
+      match (&captures[1], arguments.as_slice()) {
+        ("response", ["body", request, path, ..]) => format!("{{{{@{request}|{}}}}}", json_path(path)),
+        ("response", [field, ..]) => {
+          notes.push(format!("response `{field}` tags are not supported; added a flow parameter instead"));
+          "{{response}}".to_string()
+        }
+        ("uuid", _) => "{{$guid}}".to_string(),
+        ("now", ["millis", ..]) => "{{func:timestamp()}}".to_string(),
+        ("now", ["unix", ..]) => "{{$timestamp}}".to_string(),
+        ("now", _) => "{{$isoTimestamp}}".to_string(),
+        (name, _) => {
+          notes.push(format!("template tag `{name}` was not converted; added a flow parameter instead"));
+          format!("{{{{{name}}}}}")
+        }
+      }
+    })
+    .into_owned()
+}
+
+/// Newer exports encode response filters as `b64::<base64>::46b`.
+fn json_path(filter: &str) -> String {
+  let decoded = filter
+    .strip_prefix("b64::")
+    .and_then(|encoded| encoded.split("::").next())
+    .and_then(|encoded| STANDARD.decode(encoded).ok())
+    .and_then(|bytes| String::from_utf8(bytes).ok());
+  match decoded.as_deref().unwrap_or(filter) {
+    "" => "$".to_string(),
+    path => path.to_string(),
+  }
+}
+
+/// Environment data as dotted keys, which is how `{{ _.a.b }}` reads it.
+fn flatten(data: Option<&Value>) -> Map<String, Value> {
+  fn walk(value: &Value, prefix: &str, variables: &mut Map<String, Value>) {
+    match value {
+      Value::Object(entries) if !prefix.is_empty() || !entries.is_empty() => {
+        for (key, value) in entries {
+          let key = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+          walk(value, &key, variables);
+        }
+      }
+      Value::Object(_) => {}
+      value => {
+        variables.insert(prefix.to_string(), value.clone());
+      }
+    }
+  }
+  let mut variables = Map::new();
+  if let Some(data) = data {
+    walk(data, "", &mut variables);
+  }
+  variables
+}
+
+fn scripts(resource: &Value) -> Vec<String> {
+  [
+    ("preRequestScript", "pre-request script was not converted"),
+    ("afterResponseScript", "after-response script was not converted; add assertions to the endpoints instead"),
+  ]
+  .iter()
+  .filter(|(key, _)| str_field(resource, key).is_some_and(|script| !script.trim().is_empty()))
+  .map(|(_, message)| message.to_string())
+  .collect()
+}
+
+fn description(resource: &Value) -> Option<String> {
+  str_field(resource, "description").filter(|text| !text.is_empty()).map(str::to_string)
+}
+
+fn id(resource: &Value) -> &str {
+  str_field(resource, "_id").unwrap_or_default()
+}
+
+fn kind(resource: &Value) -> &str {
+  str_field(resource, "_type").unwrap_or_default()
+}
+
+fn sort_key(resource: &Value) -> f64 {
+  resource.get("metaSortKey").and_then(Value::as_f64).unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn export(resources: Value) -> String {
+    json!({ "_type": "export", "__export_format": 4, "resources": resources }).to_string()
+  }
+
+  #[test]
+  fn maps_groups_requests_and_environments() {
+    let text = export(json!([
+      { "_id": "wrk_1", "_type": "workspace", "name": "Shop" },
+      { "_id": "env_base", "_type": "environment", "parentId": "wrk_1", "data": { "base_url": "https://shop.test", "auth": { "user": "ann" } } },
+      { "_id": "env_stg", "_type": "environment", "parentId": "env_base", "name": "Staging", "data": { "base_url": "https://staging.shop.test" } },
+      { "_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Orders", "environment": { "page": 2 } },
+      {
+        "_id": "req_list", "_type": "request", "parentId": "fld_1", "name": "List", "metaSortKey": 2,
+        "method": "GET", "url": "{{ _.base_url }}/orders",
+        "parameters": [{ "name": "page", "value": "{{ page }}" }, { "name": "off", "value": "1", "disabled": true }],
+        "headers": [{ "name": "X-Request-Id", "value": "{% uuid 'v4' %}" }],
+        "authentication": { "type": "bearer", "token": "{% response 'body', 'req_login', 'b64::JC50b2tlbg==::46b', 'never', 60 %}" }
+      },
+      {
+        "_id": "req_login", "_type": "request", "parentId": "fld_1", "name": "Login", "metaSortKey": 1,
+        "method": "POST", "url": "{{ _.base_url }}/login",
+        "body": { "mimeType": "application/json", "text": "{\"user\": \"{{ _.auth.user }}\", \"at\": {% now 'millis' %}}" },
+        "authentication": {}
+      },
+      { "_id": "ws_1", "_type": "websocket_request", "parentId": "fld_1", "name": "Live" }
+    ]));
+
+    let import = import(&text).unwrap();
+    assert_eq!(import.name, "Shop");
+    let flow = &import.flows[0].flow;
+    let labels: Vec<&str> = flow.steps.iter().map(|step| step.label.as_str()).collect();
+    assert_eq!(labels, ["Login", "List"]);
+
+    let login = &flow.steps[0].endpoints[0];
+    assert_eq!(login.body, Some(json!({ "user": "{{env:auth.user}}", "at": "{{{func:timestamp()}}}" })));
+    assert_eq!(flow.settings.api_hosts[&login.api_id].url, "https://shop.test");
+
+    let list = &flow.steps[1].endpoints[0];
+    assert_eq!(list.query_params, json!({ "page": "{{param:page}}" }).as_object().unwrap().clone());
+    let headers: Vec<(&str, &str)> = list.headers.iter().map(|header| (header.name.as_str(), header.value.as_str())).collect();
+    assert_eq!(
+      headers,
+      [("X-Request-Id", "{{func:uuid()}}"), ("Authorization", "Bearer {{res:step1-0.$.token}}")]
+    );
+    assert_eq!(flow.parameters[0].default_value, Some(json!(2)));
+
+    let environment = import.environment.unwrap();
+    assert_eq!(environment.environments["Staging"].api_hosts[&list.api_id], "https://staging.shop.test");
+    assert_eq!(import.summary.skipped_requests, 1);
+    assert_eq!(import.summary.requests, 2);
+    assert_eq!(import.warnings.len(), 1, "{:?}", import.warnings);
+    assert!(import.warnings[0].message.starts_with("WebSocket request `Live` was skipped"));
+  }
+
+  #[test]
+  fn rejects_other_formats() {
+    let error = import(&json!({ "_type": "export", "__export_format": 3, "resources": [] }).to_string()).unwrap_err();
+    assert!(error.to_string().contains("Insomnia v4"));
+    assert!(import(&export(json!([]))).is_err());
+  }
+}
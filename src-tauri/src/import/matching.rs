@@ -0,0 +1,174 @@
+//! Matching imported requests to the API endpoints already imported from
+//! specifications.
+//!
+//! A request matches an endpoint with the same method whose path template
+//! fits its path, possibly after a base path such as `/api/v1`, which then
+//! becomes part of the API's host. The template with the most literal
+//! segments wins. Matched requests point at the stored endpoint and API, so
+//! the flow runs against the real definitions; the rest keep their own,
+//! renumbered past the stored IDs so the two never collide.
+
+use std::collections::HashMap;
+
+use percent_encoding::percent_decode_str;
+use serde_json::{Map, Value};
+
+use super::{Import, ImportWarning};
+use crate::curl::{self, KnownEndpoint};
+use crate::flow::{ApiHostInfo, EndpointDefinition, TestFlow};
+
+impl Import {
+  /// Point the flows' requests at the matching `known` endpoints, warning
+  /// about each request left unmatched. Returns how many matched.
+  pub fn match_endpoints(&mut self, known: &[KnownEndpoint]) -> usize {
+    let mut matched = 0;
+    for imported in &mut self.flows {
+      matched += match_flow(&mut imported.flow, known, &mut self.warnings);
+    }
+    self.summary.warnings = self.warnings.len();
+    matched
+  }
+}
+
+fn match_flow(flow: &mut TestFlow, known: &[KnownEndpoint], warnings: &mut Vec<ImportWarning>) -> usize {
+  let own_endpoints = std::mem::take(&mut flow.endpoints);
+  let own_hosts = std::mem::take(&mut flow.settings.api_hosts);
+  let next_id = |ids: &mut dyn Iterator<Item = &String>| ids.filter_map(|id| id.parse::<u64>().ok()).max().unwrap_or(0) + 1;
+  let mut next_api_id = next_id(&mut known.iter().map(|endpoint| &endpoint.api_id));
+  let mut next_endpoint_id = next_id(&mut known.iter().map(|endpoint| &endpoint.definition.id));
+  let mut api_ids: HashMap<String, String> = HashMap::new();
+  let mut endpoint_ids: HashMap<String, String> = HashMap::new();
+  let mut matched = 0;
+
+  for step in &mut flow.steps {
+    for endpoint in &mut step.endpoints {
+      let Some(definition) = own_endpoints.iter().find(|definition| definition.id == endpoint.endpoint_id) else {
+        continue;
+      };
+      let host = own_hosts.get(&endpoint.api_id);
+      if let Some((found, base_path, path_params)) = best_match(definition, &endpoint.path_params, known) {
+        matched += 1;
+        endpoint.endpoint_id = found.definition.id.clone();
+        endpoint.api_id = found.api_id.clone();
+        endpoint.path_params = path_params;
+        if let Some(host) = host {
+          flow.settings.api_hosts.entry(found.api_id.clone()).or_insert_with(|| ApiHostInfo {
+            url: format!("{}{base_path}", host.url.trim_end_matches('/')),
+            name: host.name.clone(),
+          });
+        }
+        if !flow.endpoints.iter().any(|definition| definition.id == found.definition.id) {
+          flow.endpoints.push(found.definition.clone());
+        }
+        continue;
+      }
+
+      warnings.push(ImportWarning {
+        location: step.label.clone(),
+        message: "no imported API endpoint matches; kept as recorded".to_string(),
+      });
+      let api_id = api_ids.entry(endpoint.api_id.clone()).or_insert_with(|| {
+        next_api_id += 1;
+        (next_api_id - 1).to_string()
+      });
+      if let Some(host) = host {
+        flow.settings.api_hosts.entry(api_id.clone()).or_insert_with(|| host.clone());
+      }
+      endpoint.api_id = api_id.clone();
+      let endpoint_id = endpoint_ids.entry(endpoint.endpoint_id.clone()).or_insert_with(|| {
+        next_endpoint_id += 1;
+        flow.endpoints.push(EndpointDefinition {
+          id: (next_endpoint_id - 1).to_string(),
+          ..definition.clone()
+        });
+        (next_endpoint_id - 1).to_string()
+      });
+      endpoint.endpoint_id = endpoint_id.clone();
+    }
+  }
+  matched
+}
+
+/// The known endpoint fitting `definition` best, as `curl` commands are
+/// matched, with the base path before its template and the path params it
+/// needs. Segments the import already turned into `{name}` params take the
+/// param's value.
+fn best_match<'k>(
+  definition: &EndpointDefinition,
+  path_params: &Map<String, Value>,
+  known: &'k [KnownEndpoint],
+) -> Option<(&'k KnownEndpoint, String, Map<String, Value>)> {
+  let raw: Vec<&str> = definition.path.split('/').filter(|segment| !segment.is_empty()).collect();
+  let segments: Vec<String> = raw
+    .iter()
+    .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+    .collect();
+  let (endpoint, matched, prefix) = curl::match_endpoint(known, &definition.method, &segments)?;
+  // A param in the base path, or a root template below one, is no match.
+  let root = endpoint.definition.path.trim_matches('/').is_empty();
+  if raw[..prefix].iter().any(|segment| parameter(segment).is_some()) || (root && prefix > 0) {
+    return None;
+  }
+  let params = matched
+    .into_iter()
+    .map(|(name, value)| {
+      let own = value.as_str().and_then(parameter).and_then(|own| path_params.get(own));
+      (name, own.cloned().unwrap_or(value))
+    })
+    .collect();
+  let base_path: String = raw[..prefix].iter().map(|segment| format!("/{segment}")).collect();
+  Some((endpoint, base_path, params))
+}
+
+fn parameter(segment: &str) -> Option<&str> {
+  segment.strip_prefix('{')?.strip_suffix('}')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::import::har::{self, HarOptions};
+  use serde_json::json;
+
+  #[test]
+  fn points_requests_at_known_endpoints() {
+    let entry = |method: &str, url: &str, response: Value| {
+      json!({
+        "request": { "method": method, "url": url, "headers": [] },
+        "response": { "status": 200, "content": { "mimeType": "application/json", "text": response.to_string() } }
+      })
+    };
+    let har = json!({ "log": { "entries": [
+      entry("POST", "https://shop.test/api/v1/users", json!({ "id": 4711 })),
+      entry("GET", "https://shop.test/api/v1/users/4711", json!({})),
+      entry("GET", "https://shop.test/api/v1/users/me", json!({})),
+      entry("GET", "https://shop.test/health", json!({})),
+    ] } });
+    let known: Vec<KnownEndpoint> = [
+      json!({ "id": 11, "apiId": 3, "path": "/users", "method": "POST", "parameters": null }),
+      json!({ "id": 12, "apiId": 3, "path": "/users/{userId}", "method": "GET", "parameters": [{ "name": "userId", "in": "path", "type": "integer" }] }),
+      json!({ "id": 13, "apiId": 3, "path": "/users/me", "method": "GET" }),
+      json!({ "id": 14, "apiId": 3, "path": "/", "method": "GET" }),
+    ]
+    .iter()
+    .map(|record| KnownEndpoint::from_record(record.clone()).unwrap())
+    .collect();
+
+    let mut import = har::import_log(&har, &HarOptions::default()).unwrap();
+    assert_eq!(import.match_endpoints(&known), 3);
+    let flow = &import.flows[0].flow;
+    let ids: Vec<(&str, &str)> = flow
+      .steps
+      .iter()
+      .map(|step| (step.endpoints[0].api_id.as_str(), step.endpoints[0].endpoint_id.as_str()))
+      .collect();
+    assert_eq!(ids, [("3", "11"), ("3", "12"), ("3", "13"), ("4", "15")]);
+    assert_eq!(flow.steps[1].endpoints[0].path_params, json!({ "userId": "{{res:step1-0.$.id}}" }).as_object().cloned().unwrap());
+    assert_eq!(flow.settings.api_hosts["3"].url, "https://shop.test/api/v1");
+    assert_eq!(flow.settings.api_hosts["4"].url, "https://shop.test");
+    let paths: Vec<&str> = flow.endpoints.iter().map(|definition| definition.path.as_str()).collect();
+    assert_eq!(paths, ["/users", "/users/{userId}", "/users/me", "/health"]);
+    assert_eq!(import.warnings[0].location, "GET /health");
+    assert_eq!(import.summary.warnings, 1);
+  }
+}
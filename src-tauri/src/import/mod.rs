@@ -0,0 +1,131 @@
+//! Importers that turn other tools' collections into Test-Pilot flows and
+//! environments.
+//!
+//! An importer converts whatever has an equivalent and reports the rest as
+//! [`ImportWarning`]s instead of failing, so a collection that is only partly
+//! convertible still comes through.
+
+pub mod bruno;
+mod collection;
+pub mod har;
+pub mod insomnia;
+pub mod matching;
+pub mod postman;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::environment::EnvironmentConfig;
+use crate::flow::{EndpointParameter, TestFlow};
+
+/// The flows and environment converted from one collection.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Import {
+  pub name: String,
+  pub description: Option<String>,
+  pub flows: Vec<ImportedFlow>,
+  /// `None` when the source defines no variables.
+  pub environment: Option<EnvironmentConfig>,
+  pub warnings: Vec<ImportWarning>,
+  pub summary: ImportSummary,
+}
+
+impl Import {
+  fn new(
+    name: String,
+    description: Option<String>,
+    flows: Vec<ImportedFlow>,
+    environment: Option<EnvironmentConfig>,
+    warnings: Vec<ImportWarning>,
+    skipped_requests: usize,
+  ) -> Self {
+    let steps = flows.iter().flat_map(|flow| &flow.flow.steps);
+    let summary = ImportSummary {
+      flows: flows.len(),
+      steps: steps.clone().count(),
+      requests: steps.map(|step| step.endpoints.len()).sum(),
+      sub_environments: environment.as_ref().map_or(0, |environment| environment.environments.len()),
+      variables: environment.as_ref().map_or(0, |environment| environment.variable_definitions.len()),
+      skipped_requests,
+      warnings: warnings.len(),
+    };
+    Import {
+      name,
+      description,
+      flows,
+      environment,
+      warnings,
+      summary,
+    }
+  }
+}
+
+/// Counts of what an import produced, for the confirmation dialog.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+  pub flows: usize,
+  pub steps: usize,
+  /// Converted requests, i.e. step endpoints.
+  pub requests: usize,
+  pub sub_environments: usize,
+  pub variables: usize,
+  /// Requests left out entirely, such as gRPC requests or page assets.
+  pub skipped_requests: usize,
+  /// Requests converted only in part count once per warning.
+  pub warnings: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedFlow {
+  pub name: String,
+  pub description: Option<String>,
+  /// Carries its own `endpoints`; API IDs are local to the import and match
+  /// the environment's `api_hosts`.
+  pub flow: TestFlow,
+}
+
+/// Something in the source that was dropped or only partly converted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportWarning {
+  /// Where in the source: folder and request names joined with ` / `, or a
+  /// recorded request.
+  pub location: String,
+  pub message: String,
+}
+
+/// A query parameter given more than once, sent as repeated keys.
+fn array_parameter(name: &str) -> EndpointParameter {
+  EndpointParameter {
+    name: name.to_string(),
+    location: "query".to_string(),
+    kind: Some("array".to_string()),
+    schema: None,
+    style: Some("form".to_string()),
+    explode: Some(true),
+    collection_format: None,
+  }
+}
+
+fn str_field<'v>(value: &'v Value, key: &str) -> Option<&'v str> {
+  value.get(key)?.as_str()
+}
+
+fn array<'v>(value: &'v Value, key: &str) -> &'v [Value] {
+  value.get(key).and_then(Value::as_array).map_or(&[], Vec::as_slice)
+}
+
+fn is_true(value: Option<&Value>) -> bool {
+  value.and_then(Value::as_bool).unwrap_or(false)
+}
+
+/// Values are mostly strings, but numbers and booleans turn up too.
+fn scalar(value: Option<&Value>) -> String {
+  match value {
+    Some(Value::String(text)) => text.clone(),
+    None | Some(Value::Null) => String::new(),
+    Some(other) => other.to_string(),
+  }
+}
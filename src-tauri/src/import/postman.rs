@@ -0,0 +1,378 @@
+//! Postman collection (v2.1) import.
+//!
+//! Folders, requests and variables map onto flows as described in
+//! [`collection`](super::collection); folder variables become flow parameters
+//! with defaults. Pre-request and test scripts are reported, not converted.
+
+use serde_json::{Map, Value};
+
+use super::collection::{self, Auth, Body, Collection, Folder, Item, Request};
+use super::{array, is_true, scalar, str_field, Import};
+use crate::error::{Error, Result};
+use crate::flow::HeaderEntry;
+
+/// Convert an exported collection, along with any exported environments,
+/// each of which becomes a sub-environment.
+pub fn import(collection: &str, environments: &[&str]) -> Result<Import> {
+  let collection: Value = serde_json::from_str(collection)?;
+  let info = collection
+    .get("info")
+    .filter(|_| is_folder(&collection))
+    .ok_or_else(|| Error::Import("not a Postman collection (expected `info` and `item`)".to_string()))?;
+  let schema = str_field(info, "schema").unwrap_or("none");
+  if !schema.contains("/v2.1") {
+    return Err(Error::Import(format!(
+      "unsupported collection schema `{schema}`; export the collection as Collection v2.1"
+    )));
+  }
+
+  Ok(collection::convert(Collection {
+    name: str_field(info, "name").unwrap_or("Postman collection").to_string(),
+    description: description(info),
+    variables: variables(array(&collection, "variable")),
+    environments: environments.iter().map(|text| environment(text)).collect::<Result<Vec<_>>>()?,
+    auth: collection.get("auth").and_then(auth),
+    items: items(&collection),
+    notes: scripts(&collection),
+    ..Default::default()
+  }))
+}
+
+/// An exported environment's name and enabled values.
+fn environment(text: &str) -> Result<(String, Map<String, Value>)> {
+  let environment: Value = serde_json::from_str(text)?;
+  let values = environment
+    .get("values")
+    .and_then(Value::as_array)
+    .ok_or_else(|| Error::Import("not a Postman environment (expected `values`)".to_string()))?;
+  let name = str_field(&environment, "name").unwrap_or("environment");
+  Ok((name.to_string(), variables(values)))
+}
+
+fn items(folder: &Value) -> Vec<Item> {
+  array(folder, "item").iter().filter_map(item).collect()
+}
+
+fn item(item: &Value) -> Option<Item> {
+  let name = str_field(item, "name").unwrap_or_default().to_string();
+  if is_folder(item) {
+    return Some(Item::Folder(Folder {
+      name,
+      description: description(item),
+      variables: variables(array(item, "variable")),
+      auth: item.get("auth").and_then(auth),
+      items: items(item),
+      notes: scripts(item),
+      ..Default::default()
+    }));
+  }
+
+  let request = item.get("request")?;
+  // A request can be just its URL.
+  let url = match request {
+    Value::String(_) => request,
+    request => request.get("url").unwrap_or(&Value::Null),
+  };
+  let query = url.get("query").and_then(Value::as_array).map(|entries| {
+    entries
+      .iter()
+      .filter(|entry| !is_true(entry.get("disabled")))
+      .filter_map(|entry| Some((str_field(entry, "key")?.to_string(), scalar(entry.get("value")))))
+      .collect()
+  });
+  let headers = array(request, "header")
+    .iter()
+    .filter_map(|header| {
+      Some(HeaderEntry {
+        name: str_field(header, "key")?.to_string(),
+        value: scalar(header.get("value")),
+        enabled: !is_true(header.get("disabled")),
+      })
+    })
+    .collect();
+
+  Some(Item::Request(Request {
+    name,
+    method: str_field(request, "method").unwrap_or("GET").to_string(),
+    url: match url {
+      Value::String(raw) => raw.clone(),
+      url => str_field(url, "raw").unwrap_or_default().to_string(),
+    },
+    query,
+    path_variables: array(url, "variable")
+      .iter()
+      .filter_map(|variable| Some((str_field(variable, "key")?.to_string(), scalar(variable.get("value")))))
+      .collect(),
+    headers,
+    body: body(request.get("body")),
+    auth: request.get("auth").and_then(auth),
+    notes: scripts(item),
+    ..Default::default()
+  }))
+}
+
+fn body(body: Option<&Value>) -> Body {
+  let Some(body) = body.filter(|body| !is_true(body.get("disabled"))) else {
+    return Body::None;
+  };
+  match str_field(body, "mode").unwrap_or("raw") {
+    "raw" => Body::Raw(str_field(body, "raw").unwrap_or_default().to_string()),
+    "graphql" => match body.get("graphql") {
+      Some(graphql) => Body::GraphQl {
+        query: str_field(graphql, "query").unwrap_or_default().to_string(),
+        variables: str_field(graphql, "variables").map(str::to_string),
+      },
+      None => Body::None,
+    },
+    mode => Body::Unsupported(mode.to_string()),
+  }
+}
+
+/// `None` for `inherit`, which newer exports spell out.
+fn auth(auth: &Value) -> Option<Auth> {
+  let kind = str_field(auth, "type").unwrap_or("noauth");
+  let param = |key: &str| auth_param(auth, kind, key);
+  Some(match kind {
+    "inherit" => return None,
+    "noauth" => Auth::None,
+    "bearer" => Auth::Bearer(param("token")),
+    "basic" => Auth::Basic(param("username"), param("password")),
+    "apikey" => Auth::ApiKey {
+      key: param("key"),
+      value: param("value"),
+      in_query: param("in") == "query",
+    },
+    other => Auth::Unsupported(other.to_string()),
+  })
+}
+
+/// Auth settings are `[{key, value}]` lists under the auth type.
+fn auth_param(auth: &Value, kind: &str, key: &str) -> String {
+  array(auth, kind)
+    .iter()
+    .find(|param| str_field(param, "key") == Some(key))
+    .map(|param| scalar(param.get("value")))
+    .unwrap_or_default()
+}
+
+fn scripts(item: &Value) -> Vec<String> {
+  let mut notes = Vec::new();
+  for event in array(item, "event").iter().filter(|event| !is_true(event.get("disabled"))) {
+    let script = match event.get("script").and_then(|script| script.get("exec")) {
+      Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect::<Vec<_>>().join("\n"),
+      Some(Value::String(script)) => script.clone(),
+      _ => String::new(),
+    };
+    if script.trim().is_empty() {
+      continue;
+    }
+    let message = match str_field(event, "listen") {
+      Some("prerequest") => "pre-request script was not converted",
+      Some("test") => "test script was not converted; add assertions to the endpoints instead",
+      _ => "script was not converted",
+    };
+    notes.push(message.to_string());
+  }
+  notes
+}
+
+/// Enabled `{key, value}` entries: collections mark the others `disabled`,
+/// environments `enabled: false`.
+fn variables(entries: &[Value]) -> Map<String, Value> {
+  entries
+    .iter()
+    .filter(|entry| !is_true(entry.get("disabled")) && entry.get("enabled").and_then(Value::as_bool) != Some(false))
+    .filter_map(|entry| {
+      let key = str_field(entry, "key").filter(|key| !key.is_empty())?;
+      let value = entry.get("value").filter(|value| !value.is_null()).cloned();
+      Some((key.to_string(), value.unwrap_or_else(|| Value::from(""))))
+    })
+    .collect()
+}
+
+/// Descriptions are either text or `{content, type}`.
+fn description(item: &Value) -> Option<String> {
+  match item.get("description")? {
+    Value::String(text) => Some(text.clone()),
+    description => str_field(description, "content").map(str::to_string),
+  }
+  .filter(|text| !text.is_empty())
+}
+
+fn is_folder(item: &Value) -> bool {
+  item.get("item").is_some_and(Value::is_array)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  const SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+  fn collection(item: Value) -> String {
+    json!({
+      "info": { "name": "Shop", "schema": SCHEMA },
+      "variable": [
+        { "key": "baseUrl", "value": "https://shop.test/api" },
+        { "key": "token", "value": "secret" }
+      ],
+      "auth": { "type": "bearer", "bearer": [{ "key": "token", "value": "{{token}}", "type": "string" }] },
+      "item": item
+    })
+    .to_string()
+  }
+
+  #[test]
+  fn maps_folders_requests_and_variables() {
+    let text = collection(json!([
+      { "name": "Health", "request": { "method": "get", "url": "{{baseUrl}}/health" } },
+      {
+        "name": "Users",
+        "item": [
+          {
+            "name": "Setup",
+            "item": [{ "name": "Login", "request": { "method": "POST", "url": "{{baseUrl}}/login", "auth": { "type": "noauth" } } }]
+          },
+          {
+            "name": "Get user",
+            "request": {
+              "method": "GET",
+              "header": [{ "key": "X-Trace", "value": "{{$guid}}" }, { "key": "X-Off", "value": "1", "disabled": true }],
+              "url": {
+                "raw": "{{baseUrl}}/users/:id/{{section}}?tag=a&tag={{tag}}",
+                "query": [{ "key": "tag", "value": "a" }, { "key": "tag", "value": "{{tag}}" }, { "key": "skip", "value": "1", "disabled": true }],
+                "variable": [{ "key": "id", "value": "{{userId}}" }]
+              }
+            }
+          }
+        ]
+      }
+    ]));
+    let staging = json!({ "name": "Staging", "values": [
+      { "key": "baseUrl", "value": "https://staging.shop.test/api", "enabled": true },
+      { "key": "userId", "value": "7", "enabled": true },
+      { "key": "unused", "value": "x", "enabled": false }
+    ] })
+    .to_string();
+
+    let import = import(&text, &[&staging]).unwrap();
+    assert_eq!(import.name, "Shop");
+    let names: Vec<&str> = import.flows.iter().map(|flow| flow.name.as_str()).collect();
+    assert_eq!(names, ["Shop", "Users"]);
+
+    let users = &import.flows[1].flow;
+    let labels: Vec<&str> = users.steps.iter().map(|step| step.label.as_str()).collect();
+    assert_eq!(labels, ["Setup", "Get user"]);
+    assert!(users.steps[0].endpoints[0].headers.is_empty());
+
+    let get_user = &users.steps[1].endpoints[0];
+    let definition = users.endpoints.iter().find(|definition| definition.id == get_user.endpoint_id).unwrap();
+    assert_eq!(definition.path, "/users/{id}/{section}");
+    assert_eq!(definition.method, "GET");
+    assert_eq!(definition.parameters[0].name, "tag");
+    assert_eq!(get_user.path_params["id"], json!("{{env:userId}}"));
+    assert_eq!(get_user.path_params["section"], json!("{{param:section}}"));
+    assert_eq!(get_user.query_params, json!({ "tag": ["a", "{{param:tag}}"] }).as_object().unwrap().clone());
+    let headers: Vec<(&str, &str, bool)> = get_user
+      .headers
+      .iter()
+      .map(|header| (header.name.as_str(), header.value.as_str(), header.enabled))
+      .collect();
+    assert_eq!(
+      headers,
+      [("X-Trace", "{{func:uuid()}}", true), ("X-Off", "1", false), ("Authorization", "Bearer {{env:token}}", true)]
+    );
+    let parameters: Vec<&str> = users.parameters.iter().map(|parameter| parameter.name.as_str()).collect();
+    assert_eq!(parameters, ["section", "tag"]);
+    assert_eq!(users.settings.api_hosts[&get_user.api_id].url, "https://shop.test/api");
+
+    let environment = import.environment.unwrap();
+    let staging = &environment.environments["Staging"];
+    assert_eq!(staging.api_hosts[&get_user.api_id], "https://staging.shop.test/api");
+    assert!(!staging.variables.contains_key("unused"));
+    assert_eq!(environment.variable_definitions["token"].default_value, Some(json!("secret")));
+    assert_eq!(environment.variable_definitions["userId"].default_value, None);
+  }
+
+  #[test]
+  fn converts_json_bodies_and_auth() {
+    let text = collection(json!([
+      {
+        "name": "Create",
+        "request": {
+          "method": "POST",
+          "url": "https://other.test/items",
+          "body": { "mode": "raw", "raw": "{\"owner\": {{token}}, \"note\": \"{{token}} {\\\"q\\\"}\"}" },
+          "auth": { "type": "basic", "basic": [{ "key": "username", "value": "ann" }, { "key": "password", "value": "pw" }] }
+        }
+      },
+      {
+        "name": "Query",
+        "request": {
+          "method": "POST",
+          "url": "{{baseUrl}}/graphql",
+          "body": { "mode": "graphql", "graphql": { "query": "{ item(id: {{token}}) { id } }", "variables": "{\"n\": 1}" } },
+          "auth": { "type": "apikey", "apikey": [{ "key": "key", "value": "api_key" }, { "key": "value", "value": "k" }, { "key": "in", "value": "query" }] }
+        }
+      }
+    ]));
+    let import = import(&text, &[]).unwrap();
+    let flow = &import.flows[0].flow;
+
+    let create = &flow.steps[0].endpoints[0];
+    assert_eq!(create.body, Some(json!({ "owner": "{{{env:token}}}", "note": "{{env:token}} {\"q\"}" })));
+    assert_eq!(create.headers[0].name, "Content-Type");
+    assert_eq!(create.headers[1].value, "Basic YW5uOnB3");
+    assert_eq!(flow.settings.api_hosts[&create.api_id].url, "https://other.test");
+
+    let query = &flow.steps[1].endpoints[0];
+    assert_eq!(query.body, Some(json!({ "query": "{ item(id: {{env:token}}) { id } }", "variables": { "n": 1 } })));
+    assert_eq!(query.query_params["api_key"], json!("k"));
+
+    let environment = import.environment.unwrap();
+    assert_eq!(environment.environments["default"].api_hosts[&query.api_id], "https://shop.test/api");
+    assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+  }
+
+  #[test]
+  fn reports_what_it_cannot_convert() {
+    let text = json!({
+      "info": { "name": "Legacy", "schema": SCHEMA },
+      "item": [{
+        "name": "Orders",
+        "event": [{ "listen": "prerequest", "script": { "exec": ["pm.environment.set('id', 1)"] } }],
+        "item": [{
+          "name": "Upload",
+          "event": [{ "listen": "test", "script": { "exec": [""] } }],
+          "request": {
+            "method": "POST",
+            "url": "{{host}}/upload",
+            "body": { "mode": "formdata", "formdata": [] },
+            "auth": { "type": "oauth2" }
+          }
+        }]
+      }]
+    })
+    .to_string();
+    let import = import(&text, &[]).unwrap();
+    assert!(import.environment.is_none());
+    let warnings: Vec<(&str, &str)> = import
+      .warnings
+      .iter()
+      .map(|warning| (warning.location.as_str(), warning.message.split(';').next().unwrap()))
+      .collect();
+    assert_eq!(
+      warnings,
+      [
+        ("Orders", "pre-request script was not converted"),
+        ("Orders / Upload", "no value for the host `{{host}}`"),
+        ("Orders / Upload", "`formdata` bodies are not supported and were dropped"),
+        ("Orders / Upload", "`oauth2` authentication is not supported"),
+      ]
+    );
+
+    let v1 = json!({ "info": { "name": "Old", "schema": "https://schema.getpostman.com/json/collection/v1.0.0/collection.json" }, "item": [] });
+    assert!(super::import(&v1.to_string(), &[]).unwrap_err().to_string().contains("v2.1"));
+  }
+}
@@ -1,16 +1,105 @@
+pub mod assertions;
+mod commands;
+pub mod cookies;
+pub mod curl;
+pub mod environment;
+pub mod error;
+pub mod flow;
+pub mod graphql;
+pub mod grpc;
+pub mod history;
+pub mod http;
+pub mod import;
+pub mod mock;
+pub mod openapi;
+pub mod proxy;
+pub mod report;
+pub mod runs;
+pub mod sequence;
+pub mod storage;
+pub mod template;
+#[cfg(test)]
+mod test_support;
+pub mod transform;
+pub mod websocket;
+
+use tauri::Manager;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   // Configure logging first
   let log_plugin = tauri_plugin_log::Builder::default()
     .level(log::LevelFilter::Debug) // Use Debug level to see detailed HTTP logs
     .build();
+
+  let http_client = http::HttpClient::new().expect("failed to initialize native HTTP client");
   
   tauri::Builder::default()
     // Initialize HTTP plugin
     .plugin(tauri_plugin_http::init())
+    // Save dialogs for exported reports
+    .plugin(tauri_plugin_dialog::init())
     // Add logging plugin
     .plugin(log_plugin)
-    .setup(|_app| {
+    // Native HTTP client shared by all flow requests
+    .manage(http_client)
+    // Cookie jars keyed by run ID
+    .manage(cookies::CookieJars::default())
+    // Cancellation handles of active flow and sequence runs
+    .manage(runs::RunRegistry::default())
+    // Mock servers started from imported specs, keyed by port
+    .manage(mock::MockServers::default())
+    // Recording proxies, keyed by port
+    .manage(proxy::RecordingProxies::default())
+    .invoke_handler(tauri::generate_handler![
+      commands::http::execute_request,
+      commands::cookies::create_cookie_jar,
+      commands::cookies::get_cookie_jar,
+      commands::cookies::clear_cookie_jar,
+      commands::cookies::export_cookie_jar,
+      commands::cookies::delete_cookie_jar,
+      commands::template::resolve_template,
+      commands::transform::evaluate_transformation,
+      commands::flow::execute_flow,
+      commands::sequence::execute_sequence,
+      commands::runs::cancel_run,
+      commands::storage::storage_list,
+      commands::storage::storage_get,
+      commands::storage::storage_create,
+      commands::storage::storage_update,
+      commands::storage::storage_delete,
+      commands::history::list_runs,
+      commands::history::get_run,
+      commands::history::compare_runs,
+      commands::history::prune_runs,
+      commands::report::save_junit_report,
+      commands::report::export_run_html,
+      commands::openapi::parse_openapi_file,
+      commands::openapi::import_openapi_file,
+      commands::graphql::import_graphql_file,
+      commands::graphql::import_graphql_introspection,
+      commands::grpc::import_grpc_files,
+      commands::import::import_postman_collection,
+      commands::import::import_har_file,
+      commands::import::import_insomnia_export,
+      commands::import::import_bruno_collection,
+      commands::curl::parse_curl_command,
+      commands::curl::render_curl_command,
+      commands::mock::start_mock_server,
+      commands::mock::stop_mock_server,
+      commands::mock::list_mock_servers,
+      commands::mock::get_mock_server_calls,
+      commands::proxy::start_recording_proxy,
+      commands::proxy::stop_recording_proxy,
+      commands::proxy::list_recording_proxies,
+      commands::proxy::get_recorded_exchanges,
+      commands::proxy::build_recorded_flow,
+    ])
+    .setup(|app| {
+      // Local database for offline use, in the app data directory
+      let database = app.path().app_data_dir()?.join(storage::DATABASE_FILE);
+      app.manage(storage::Store::open(&database)?);
+
       // Set up a listener for HTTP events through environment vars
       std::env::set_var("RUST_LOG", "tauri=debug,tauri_plugin_http=debug");
       
@@ -0,0 +1,305 @@
+//! Local mock servers answering for imported OpenAPI specifications.
+//!
+//! A [`MockServer`] listens on localhost and serves every operation of the
+//! APIs it was started with, answering from their examples and schemas and,
+//! unless told otherwise, rejecting requests the specification does not
+//! allow. Each call is logged, so a flow pointed at the server through its
+//! `api_hosts` can be checked against what the API actually received.
+
+mod routes;
+pub mod schema;
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bytes::Bytes;
+use http::header::CONTENT_TYPE;
+use http::{HeaderValue, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use tokio::net::TcpListener;
+
+use crate::error::{Error, Result};
+use crate::runs::CancellationToken;
+
+pub use routes::{MockRequest, MockResponse, Routes};
+
+/// Calls kept per server; older ones are dropped first.
+const MAX_CALLS: usize = 500;
+
+/// A request the mock server answered.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockCall {
+  pub timestamp: String,
+  pub method: String,
+  pub path: String,
+  pub query: Option<String>,
+  pub headers: Vec<(String, String)>,
+  pub body: Option<String>,
+  /// The API and operation that answered, if any matched.
+  pub api_id: Option<i64>,
+  pub operation: Option<String>,
+  pub status: u16,
+  /// Why the request was rejected as not matching the specification.
+  pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockServerInfo {
+  pub port: u16,
+  pub url: String,
+  pub api_ids: Vec<i64>,
+  /// The server's URL for every API, ready to use as a sub-environment's
+  /// `api_hosts`.
+  pub api_hosts: HashMap<String, String>,
+  /// Number of operations served.
+  pub routes: usize,
+  pub validate: bool,
+}
+
+/// A running mock server; it stops when stopped or dropped.
+pub struct MockServer {
+  info: MockServerInfo,
+  calls: Arc<Mutex<VecDeque<MockCall>>>,
+  shutdown: CancellationToken,
+}
+
+struct Shared {
+  routes: Routes,
+  validate: bool,
+  calls: Arc<Mutex<VecDeque<MockCall>>>,
+}
+
+impl MockServer {
+  /// Start serving `routes` on `127.0.0.1:port`, port `0` picking a free one.
+  pub async fn start(routes: Routes, api_ids: Vec<i64>, port: u16, validate: bool) -> Result<Self> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+      .await
+      .map_err(|err| Error::Mock(format!("cannot listen on port {port}: {err}")))?;
+    let port = listener.local_addr()?.port();
+    let url = format!("http://127.0.0.1:{port}");
+    let info = MockServerInfo {
+      port,
+      api_hosts: api_ids.iter().map(|api_id| (api_id.to_string(), url.clone())).collect(),
+      url,
+      api_ids,
+      routes: routes.len(),
+      validate,
+    };
+    let calls = Arc::default();
+    let shutdown = CancellationToken::default();
+    let shared = Arc::new(Shared {
+      routes,
+      validate,
+      calls: Arc::clone(&calls),
+    });
+    log::debug!("[mock] serving {} operation(s) on {}", info.routes, info.url);
+    tokio::spawn(serve(listener, shared, shutdown.clone()));
+    Ok(Self { info, calls, shutdown })
+  }
+
+  pub fn info(&self) -> &MockServerInfo {
+    &self.info
+  }
+
+  /// The logged calls, oldest first.
+  pub fn calls(&self) -> Vec<MockCall> {
+    lock_calls(&self.calls).iter().cloned().collect()
+  }
+
+  pub fn clear_calls(&self) {
+    lock_calls(&self.calls).clear();
+  }
+
+  /// Stop accepting requests and close open connections.
+  pub fn stop(&self) {
+    self.shutdown.cancel();
+  }
+}
+
+impl Drop for MockServer {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+async fn serve(listener: TcpListener, shared: Arc<Shared>, shutdown: CancellationToken) {
+  loop {
+    let stream = match shutdown.run(listener.accept()).await {
+      Ok(Ok((stream, _))) => stream,
+      Ok(Err(err)) => {
+        log::debug!("[mock] failed to accept a connection: {err}");
+        continue;
+      }
+      Err(_) => break,
+    };
+    let shared = Arc::clone(&shared);
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move {
+      let service = service_fn(move |request| handle(Arc::clone(&shared), request));
+      let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+      if let Ok(Err(err)) = shutdown.run(connection).await {
+        log::debug!("[mock] connection failed: {err}");
+      }
+    });
+  }
+  log::debug!("[mock] stopped");
+}
+
+async fn handle(shared: Arc<Shared>, request: Request<Incoming>) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+  let (parts, body) = request.into_parts();
+  let body = body.collect().await.map(|body| body.to_bytes()).unwrap_or_default();
+  let headers: Vec<(String, String)> = parts
+    .headers
+    .iter()
+    .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+    .collect();
+  let request = MockRequest {
+    method: parts.method.as_str(),
+    path: parts.uri.path(),
+    query: parts.uri.query().unwrap_or_default(),
+    headers: &headers,
+    body: &body,
+  };
+  let response = shared.routes.respond(&request, shared.validate);
+  log::debug!("[mock] {} {} -> {}", request.method, request.path, response.status);
+
+  let call = MockCall {
+    timestamp: chrono::Utc::now().to_rfc3339(),
+    method: request.method.to_string(),
+    path: request.path.to_string(),
+    query: parts.uri.query().map(str::to_string),
+    headers: headers.clone(),
+    body: (!body.is_empty()).then(|| String::from_utf8_lossy(&body).into_owned()),
+    api_id: response.api_id,
+    operation: response.operation,
+    status: response.status,
+    errors: response.errors,
+  };
+  let mut calls = lock_calls(&shared.calls);
+  if calls.len() == MAX_CALLS {
+    calls.pop_front();
+  }
+  calls.push_back(call);
+  drop(calls);
+
+  let mut reply = Response::new(match &response.body {
+    Some(body) => Full::new(Bytes::from(body.to_string())),
+    None => Full::default(),
+  });
+  *reply.status_mut() = StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK);
+  if response.body.is_some() {
+    reply.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+  }
+  Ok(reply)
+}
+
+fn lock_calls(calls: &Mutex<VecDeque<MockCall>>) -> MutexGuard<'_, VecDeque<MockCall>> {
+  calls.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Running mock servers keyed by port, held as Tauri managed state.
+#[derive(Default)]
+pub struct MockServers {
+  servers: Mutex<HashMap<u16, MockServer>>,
+}
+
+impl MockServers {
+  pub fn insert(&self, server: MockServer) -> MockServerInfo {
+    let info = server.info().clone();
+    self.lock().insert(info.port, server);
+    info
+  }
+
+  /// Stop the server on `port`; `false` if none runs there.
+  pub fn stop(&self, port: u16) -> bool {
+    self.lock().remove(&port).is_some()
+  }
+
+  pub fn list(&self) -> Vec<MockServerInfo> {
+    let mut servers: Vec<MockServerInfo> = self.lock().values().map(|server| server.info().clone()).collect();
+    servers.sort_by_key(|info| info.port);
+    servers
+  }
+
+  /// The calls the server on `port` logged, emptying its log if `clear`.
+  pub fn calls(&self, port: u16, clear: bool) -> Result<Vec<MockCall>> {
+    let servers = self.lock();
+    let server = servers
+      .get(&port)
+      .ok_or_else(|| Error::NotFound(format!("no mock server on port {port}")))?;
+    let calls = server.calls();
+    if clear {
+      server.clear_calls();
+    }
+    Ok(calls)
+  }
+
+  fn lock(&self) -> MutexGuard<'_, HashMap<u16, MockServer>> {
+    self.servers.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::openapi::{parse, SpecFormat};
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+  use tokio::net::TcpStream;
+
+  async fn send(port: u16, request: &str) -> String {
+    let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+  }
+
+  #[tokio::test]
+  async fn serves_and_logs_calls() {
+    let spec = r#"{
+      "swagger": "2.0",
+      "basePath": "/api",
+      "paths": {
+        "/pets": {
+          "post": {
+            "parameters": [{ "name": "pet", "in": "body", "required": true, "schema": { "type": "object", "required": ["name"] } }],
+            "responses": { "201": { "examples": { "application/json": { "id": 1 } } } }
+          }
+        }
+      }
+    }"#;
+    let mut routes = Routes::default();
+    routes.add(3, &parse(spec, SpecFormat::Json, None).unwrap());
+    let servers = MockServers::default();
+    let info = servers.insert(MockServer::start(routes, vec![3], 0, true).await.unwrap());
+    assert_eq!(info.api_hosts["3"], format!("http://127.0.0.1:{}", info.port));
+
+    let created = send(
+      info.port,
+      "POST /api/pets HTTP/1.1\r\nHost: localhost\r\nContent-Length: 14\r\nConnection: close\r\n\r\n{\"name\":\"Rex\"}",
+    )
+    .await;
+    assert!(created.starts_with("HTTP/1.1 201"), "{created}");
+    assert!(created.ends_with(r#"{"id":1}"#), "{created}");
+    let rejected = send(info.port, "POST /api/pets HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}").await;
+    assert!(rejected.starts_with("HTTP/1.1 400"), "{rejected}");
+
+    let calls = servers.calls(info.port, true).unwrap();
+    assert_eq!(calls.iter().map(|call| call.status).collect::<Vec<_>>(), [201, 400]);
+    assert_eq!(calls[0].body.as_deref(), Some(r#"{"name":"Rex"}"#));
+    assert_eq!(calls[1].errors, ["body.name is required"]);
+    assert!(servers.calls(info.port, false).unwrap().is_empty());
+
+    assert!(servers.stop(info.port));
+    assert!(servers.list().is_empty());
+  }
+}
@@ -0,0 +1,439 @@
+//! The operations a mock server answers, matched by method and path
+//! template, and the responses made up for them.
+//!
+//! A response is the operation's lowest 2xx response, or the status a
+//! `Prefer: code=404` request header asks for; its body is the media type's
+//! example, the first of its named examples (or the one `Prefer:
+//! example=name` picks), or else one generated from its schema.
+
+use percent_encoding::percent_decode_str;
+use serde_json::{json, Value};
+
+use super::schema::{self, schema_type};
+use crate::openapi::{self, ApiParameter, Spec, SpecVersion};
+
+/// A request as the mock server received it.
+#[derive(Debug, Clone, Copy)]
+pub struct MockRequest<'r> {
+  pub method: &'r str,
+  pub path: &'r str,
+  /// Raw query string, without the `?`.
+  pub query: &'r str,
+  pub headers: &'r [(String, String)],
+  pub body: &'r [u8],
+}
+
+impl MockRequest<'_> {
+  fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|(header, _)| header.eq_ignore_ascii_case(name))
+      .map(|(_, value)| value.as_str())
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+  pub status: u16,
+  /// `None` for responses without content.
+  pub body: Option<Value>,
+  pub api_id: Option<i64>,
+  /// The matched operation: its `operationId`, else `METHOD /path`.
+  pub operation: Option<String>,
+  /// Where the request does not match the specification.
+  pub errors: Vec<String>,
+}
+
+impl MockResponse {
+  fn error(status: u16, message: String) -> Self {
+    Self {
+      status,
+      body: Some(json!({ "error": message })),
+      api_id: None,
+      operation: None,
+      errors: Vec::new(),
+    }
+  }
+}
+
+/// Every operation of the mocked APIs, matched in the order added.
+#[derive(Debug, Default)]
+pub struct Routes {
+  routes: Vec<Route>,
+}
+
+#[derive(Debug)]
+struct Route {
+  api_id: i64,
+  method: String,
+  path: String,
+  segments: Vec<Segment>,
+  /// Path of the spec's server URL or `basePath`, which requests may or may
+  /// not include.
+  base_path: String,
+  version: SpecVersion,
+  operation: Value,
+  parameters: Vec<ApiParameter>,
+  body_required: bool,
+  body_schema: Option<Value>,
+}
+
+#[derive(Debug)]
+enum Segment {
+  Literal(String),
+  Parameter(String),
+}
+
+impl Routes {
+  /// Add every operation of `spec` under `api_id`, returning how many.
+  pub fn add(&mut self, api_id: i64, spec: &Spec) -> usize {
+    let base_path = base_path(spec);
+    let endpoints = openapi::extract_endpoints(spec);
+    for endpoint in &endpoints {
+      let operation = spec.document["paths"][&endpoint.path][endpoint.method.to_lowercase()].clone();
+      let body_parameter = endpoint.parameters.iter().find(|parameter| parameter.location == "body");
+      let body_required = operation["requestBody"]["required"]
+        .as_bool()
+        .or(body_parameter.map(|parameter| parameter.required))
+        .unwrap_or(false);
+      self.routes.push(Route {
+        api_id,
+        method: endpoint.method.clone(),
+        path: endpoint.path.clone(),
+        segments: segments(&endpoint.path),
+        base_path: base_path.clone(),
+        version: spec.version,
+        operation,
+        parameters: endpoint.parameters.clone(),
+        body_required,
+        body_schema: endpoint.request_schema.clone(),
+      });
+    }
+    endpoints.len()
+  }
+
+  pub fn len(&self) -> usize {
+    self.routes.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.routes.is_empty()
+  }
+
+  /// Answer `request`: 404 when no path matches, 405 when no method does,
+  /// and 400 listing the problems when `validate` is set and the request
+  /// breaks the specification.
+  pub fn respond(&self, request: &MockRequest, validate: bool) -> MockResponse {
+    let path = match request.path.trim_end_matches('/') {
+      "" => "/",
+      path => path,
+    };
+    let matches: Vec<(&Route, Vec<(String, String)>)> =
+      self.routes.iter().filter_map(|route| Some((route, route.matches(path)?))).collect();
+    if matches.is_empty() {
+      return MockResponse::error(404, format!("no mocked operation matches {} {path}", request.method));
+    }
+    // The most literal template wins: `/users/me` over `/users/{id}`.
+    let Some((route, path_params)) = matches
+      .iter()
+      .filter(|(route, _)| route.method.eq_ignore_ascii_case(request.method))
+      .min_by_key(|(_, path_params)| path_params.len())
+    else {
+      let mut allowed: Vec<&str> = matches.iter().map(|(route, _)| route.method.as_str()).collect();
+      allowed.dedup();
+      return MockResponse::error(405, format!("{path} allows {}", allowed.join(", ")));
+    };
+
+    let errors = if validate { route.validate(request, path_params) } else { Vec::new() };
+    let mut response = if errors.is_empty() {
+      let prefer = request.header("prefer").unwrap_or_default();
+      let (status, body) = route.response(preference(prefer, "code"), preference(prefer, "example"));
+      MockResponse {
+        status,
+        body,
+        api_id: None,
+        operation: None,
+        errors: Vec::new(),
+      }
+    } else {
+      MockResponse {
+        status: 400,
+        body: Some(json!({ "error": "the request does not match the specification", "details": errors })),
+        api_id: None,
+        operation: None,
+        errors,
+      }
+    };
+    response.api_id = Some(route.api_id);
+    response.operation = Some(
+      route.operation["operationId"]
+        .as_str()
+        .map_or_else(|| format!("{} {}", route.method, route.path), str::to_string),
+    );
+    response
+  }
+}
+
+impl Route {
+  /// Path parameter values if `path` fits the template, with or without the
+  /// base path.
+  fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+    let stripped = path
+      .strip_prefix(self.base_path.as_str())
+      .filter(|rest| !self.base_path.is_empty() && (rest.is_empty() || rest.starts_with('/')));
+    [Some(path), stripped].into_iter().flatten().find_map(|path| {
+      let parts: Vec<&str> = path.split('/').skip(1).collect();
+      if parts.len() != self.segments.len() {
+        return None;
+      }
+      let mut path_params = Vec::new();
+      for (segment, part) in self.segments.iter().zip(parts) {
+        match segment {
+          Segment::Literal(literal) if literal == part => {}
+          Segment::Parameter(name) if !part.is_empty() => {
+            path_params.push((name.clone(), percent_decode_str(part).decode_utf8_lossy().into_owned()));
+          }
+          _ => return None,
+        }
+      }
+      Some(path_params)
+    })
+  }
+
+  fn validate(&self, request: &MockRequest, path_params: &[(String, String)]) -> Vec<String> {
+    let mut errors = Vec::new();
+    let query: Vec<(String, String)> = url::form_urlencoded::parse(request.query.as_bytes()).into_owned().collect();
+    for parameter in &self.parameters {
+      let name = parameter.name.as_str();
+      let values: Vec<String> = match parameter.location.as_str() {
+        "path" => path_params.iter().filter(|(key, _)| key == name).map(|(_, value)| value.clone()).collect(),
+        "query" => query.iter().filter(|(key, _)| key == name).map(|(_, value)| value.clone()).collect(),
+        "header" => request.header(name).map(str::to_string).into_iter().collect(),
+        _ => continue,
+      };
+      if values.is_empty() {
+        if parameter.required {
+          errors.push(format!("{} parameter `{name}` is required", parameter.location));
+        }
+        continue;
+      }
+      if let Some(schema) = &parameter.schema {
+        let value = coerce(&values, schema);
+        schema::validate(&value, schema, &format!("{} parameter `{name}`", parameter.location), &mut errors);
+      }
+    }
+
+    if request.body.is_empty() {
+      if self.body_required {
+        errors.push("the request body is required".to_string());
+      }
+    } else if let Some(schema) = &self.body_schema {
+      // Only JSON bodies are checked; a request without a content type is
+      // taken to send JSON too.
+      if request.header("content-type").map_or(true, |content_type| content_type.contains("json")) {
+        match serde_json::from_slice::<Value>(request.body) {
+          Ok(body) => schema::validate(&body, schema, "body", &mut errors),
+          Err(_) => errors.push("the request body is not valid JSON".to_string()),
+        }
+      }
+    }
+    errors
+  }
+
+  /// The status and body for the response with status `code`, or the
+  /// operation's first success response.
+  fn response(&self, code: Option<&str>, example: Option<&str>) -> (u16, Option<Value>) {
+    let Some(responses) = self.operation["responses"].as_object() else {
+      return (200, None);
+    };
+    let chosen = code
+      .and_then(|code| responses.get_key_value(code))
+      .or_else(|| responses.iter().filter(|(status, _)| status.starts_with('2')).min_by_key(|(status, _)| *status))
+      .or_else(|| responses.get_key_value("default"));
+    let Some((status, response)) = chosen else {
+      return (200, None);
+    };
+    // `default` and ranges such as `2XX` answer with the plain status.
+    let status = status.parse().unwrap_or_else(|_| status.get(..1).and_then(|class| class.parse::<u16>().ok()).map_or(200, |class| class * 100));
+
+    let body = match self.version {
+      SpecVersion::OpenApi3 => response["content"].as_object().and_then(|content| {
+        let media = content
+          .get("application/json")
+          .or_else(|| content.iter().find(|(name, _)| name.contains("json")).map(|(_, media)| media))
+          .or_else(|| content.values().next())?;
+        let examples = media["examples"].as_object();
+        let named = example
+          .and_then(|name| examples?.get(name))
+          .or_else(|| examples?.values().next())
+          .and_then(|example| example.get("value"));
+        Some(match media.get("example").or(named) {
+          Some(example) => example.clone(),
+          None => schema::example(&media["schema"]),
+        })
+      }),
+      SpecVersion::Swagger2 => match response["examples"]["application/json"] {
+        Value::Null => response.get("schema").map(schema::example),
+        ref example => Some(example.clone()),
+      },
+    };
+    (status, body)
+  }
+}
+
+fn segments(path: &str) -> Vec<Segment> {
+  path
+    .trim_end_matches('/')
+    .split('/')
+    .skip(1)
+    .map(|segment| match segment.strip_prefix('{').and_then(|name| name.strip_suffix('}')) {
+      Some(name) => Segment::Parameter(name.to_string()),
+      None => Segment::Literal(segment.to_string()),
+    })
+    .collect()
+}
+
+/// The path of the first server URL (OpenAPI 3) or `basePath` (Swagger 2.0),
+/// without a trailing slash.
+fn base_path(spec: &Spec) -> String {
+  let raw = match spec.version {
+    SpecVersion::Swagger2 => spec.document["basePath"].as_str(),
+    SpecVersion::OpenApi3 => spec.document["servers"][0]["url"].as_str(),
+  }
+  .unwrap_or_default();
+  // Server URLs may be templated, so they are not parsed as URLs.
+  let path = match raw.find("://") {
+    Some(scheme) => raw[scheme + 3..].find('/').map_or("", |slash| &raw[scheme + 3 + slash..]),
+    None => raw,
+  };
+  path.trim_end_matches('/').to_string()
+}
+
+/// Parameter text as the JSON value its schema describes, so it can be
+/// validated like a body; text that does not convert is left as is.
+fn coerce(values: &[String], schema: &Value) -> Value {
+  let single = |value: &str, schema: &Value| match schema_type(schema) {
+    Some("integer") => value.parse::<i64>().map_or_else(|_| Value::from(value), Value::from),
+    Some("number") => value.parse::<f64>().map_or_else(|_| Value::from(value), Value::from),
+    Some("boolean") => value.parse::<bool>().map_or_else(|_| Value::from(value), Value::from),
+    _ => Value::from(value),
+  };
+  match schema_type(schema) {
+    Some("array") => {
+      let items = &schema["items"];
+      // One value holds a comma-separated list unless the key is repeated.
+      let values: Vec<&str> = match values {
+        [value] => value.split(',').collect(),
+        values => values.iter().map(String::as_str).collect(),
+      };
+      Value::Array(values.into_iter().map(|value| single(value, items)).collect())
+    }
+    _ => single(&values[0], schema),
+  }
+}
+
+/// A `Prefer` header setting, as in `Prefer: code=404, example=empty`.
+fn preference<'p>(prefer: &'p str, key: &str) -> Option<&'p str> {
+  prefer
+    .split([',', ';'])
+    .filter_map(|part| part.trim().split_once('='))
+    .find(|(name, _)| name.trim().eq_ignore_ascii_case(key))
+    .map(|(_, value)| value.trim().trim_matches('"'))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::openapi::{parse, SpecFormat};
+
+  fn routes() -> Routes {
+    let spec = json!({
+      "openapi": "3.0.3",
+      "info": { "title": "Shop", "version": "1" },
+      "servers": [{ "url": "https://api.shop.test/v1/" }],
+      "paths": {
+        "/users/{id}": {
+          "get": {
+            "operationId": "getUser",
+            "parameters": [
+              { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+              { "name": "fields", "in": "query", "schema": { "type": "array", "items": { "type": "string", "enum": ["name", "email"] } } }
+            ],
+            "responses": {
+              "200": { "content": { "application/json": { "schema": { "$ref": "#/components/schemas/User" } } } },
+              "404": { "content": { "application/json": { "examples": { "missing": { "value": { "error": "not found" } } } } } }
+            }
+          }
+        },
+        "/users/me": { "get": { "responses": { "200": { "content": { "application/json": { "example": { "id": 0 } } } } } } },
+        "/users": {
+          "post": {
+            "requestBody": { "required": true, "content": { "application/json": { "schema": { "$ref": "#/components/schemas/User" } } } },
+            "responses": { "201": { "description": "Created" } }
+          }
+        }
+      },
+      "components": {
+        "schemas": {
+          "User": {
+            "type": "object",
+            "required": ["name"],
+            "properties": { "id": { "type": "integer", "example": 7 }, "name": { "type": "string" } }
+          }
+        }
+      }
+    });
+    let mut routes = Routes::default();
+    assert_eq!(routes.add(1, &parse(&spec.to_string(), SpecFormat::Json, None).unwrap()), 3);
+    routes
+  }
+
+  fn request<'r>(method: &'r str, path: &'r str, query: &'r str, headers: &'r [(String, String)], body: &'r [u8]) -> MockRequest<'r> {
+    MockRequest {
+      method,
+      path,
+      query,
+      headers,
+      body,
+    }
+  }
+
+  #[test]
+  fn answers_with_examples_and_generated_bodies() {
+    let routes = routes();
+    let response = routes.respond(&request("GET", "/v1/users/3", "fields=name,email", &[], b""), true);
+    assert_eq!((response.status, response.body), (200, Some(json!({ "id": 7, "name": "string" }))));
+    assert_eq!(response.operation.as_deref(), Some("getUser"));
+
+    let me = routes.respond(&request("GET", "/users/me/", "", &[], b""), true);
+    assert_eq!(me.body, Some(json!({ "id": 0 })));
+
+    let prefer = [("Prefer".to_string(), "code=404".to_string())];
+    let missing = routes.respond(&request("GET", "/users/3", "", &prefer, b""), true);
+    assert_eq!((missing.status, missing.body), (404, Some(json!({ "error": "not found" }))));
+
+    let created = routes.respond(&request("POST", "/users", "", &[], br#"{"name": "Ann"}"#), true);
+    assert_eq!((created.status, created.body), (201, None));
+    assert_eq!(routes.respond(&request("DELETE", "/users", "", &[], b""), true).status, 405);
+    assert_eq!(routes.respond(&request("GET", "/orders", "", &[], b""), true).status, 404);
+  }
+
+  #[test]
+  fn rejects_requests_that_break_the_spec() {
+    let routes = routes();
+    let response = routes.respond(&request("GET", "/users/abc", "fields=name,phone", &[], b""), true);
+    assert_eq!(response.status, 400);
+    assert_eq!(
+      response.errors,
+      ["path parameter `id` must be an integer", "query parameter `fields`[1] must be one of [\"name\",\"email\"]"]
+    );
+
+    let response = routes.respond(&request("POST", "/users", "", &[], br#"{"id": "x"}"#), true);
+    let mut errors = response.errors.clone();
+    errors.sort();
+    assert_eq!(errors, ["body.id must be an integer", "body.name is required"]);
+    assert_eq!(routes.respond(&request("POST", "/users", "", &[], b""), true).errors, ["the request body is required"]);
+    assert_eq!(routes.respond(&request("POST", "/users", "", &[], b""), false).status, 201);
+  }
+}
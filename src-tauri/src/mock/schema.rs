@@ -0,0 +1,295 @@
+//! Example values generated from, and values validated against, the JSON
+//! Schema subset OpenAPI uses.
+//!
+//! Both work on dereferenced schemas; a `$ref` left in place because it is
+//! circular generates `null` and accepts anything.
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+/// Nesting beyond which generation stops, for schemas that expand forever.
+const MAX_DEPTH: usize = 8;
+
+/// A value matching `schema`, preferring the examples, defaults and enum
+/// values it gives over made-up ones. Generation is deterministic, so the
+/// mock answers the same request the same way every time.
+pub fn example(schema: &Value) -> Value {
+  generate(schema, 0)
+}
+
+fn generate(schema: &Value, depth: usize) -> Value {
+  if depth > MAX_DEPTH || !schema.is_object() || schema.get("$ref").is_some() {
+    return Value::Null;
+  }
+  for key in ["example", "default", "const"] {
+    if let Some(value) = schema.get(key) {
+      return value.clone();
+    }
+  }
+  // OpenAPI 3.1 uses JSON Schema's `examples` list.
+  if let Some(value) = schema.get("examples").and_then(Value::as_array).and_then(|examples| examples.first()) {
+    return value.clone();
+  }
+  if let Some(value) = schema.get("enum").and_then(Value::as_array).and_then(|values| values.first()) {
+    return value.clone();
+  }
+  if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+    let mut merged = Map::new();
+    for part in all {
+      match generate(part, depth + 1) {
+        Value::Object(properties) => merged.extend(properties),
+        other if all.len() == 1 => return other,
+        _ => {}
+      }
+    }
+    return Value::Object(merged);
+  }
+  if let Some(first) = ["oneOf", "anyOf"]
+    .iter()
+    .find_map(|key| schema.get(*key).and_then(Value::as_array).and_then(|options| options.first()))
+  {
+    return generate(first, depth + 1);
+  }
+
+  let number = |key: &str| schema.get(key).and_then(Value::as_f64);
+  match schema_type(schema) {
+    Some("object") => {
+      let mut object = Map::new();
+      if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (name, property) in properties {
+          object.insert(name.clone(), generate(property, depth + 1));
+        }
+      }
+      Value::Object(object)
+    }
+    Some("array") => {
+      let item = schema.get("items").map_or(Value::Null, |items| generate(items, depth + 1));
+      let count = schema.get("minItems").and_then(Value::as_u64).unwrap_or(1).max(1);
+      Value::Array(vec![item; count as usize])
+    }
+    Some("string") => {
+      let text = match schema.get("format").and_then(Value::as_str) {
+        Some("date-time") => "2024-01-01T00:00:00Z",
+        Some("date") => "2024-01-01",
+        Some("time") => "00:00:00",
+        Some("uuid") => "00000000-0000-4000-8000-000000000000",
+        Some("email") => "user@example.com",
+        Some("uri" | "url") => "https://example.com",
+        Some("hostname") => "example.com",
+        Some("ipv4") => "127.0.0.1",
+        _ => "string",
+      };
+      let min = schema.get("minLength").and_then(Value::as_u64).unwrap_or(0) as usize;
+      Value::from(format!("{text:x<min$}"))
+    }
+    Some("integer") => Value::from(number("minimum").map_or(1, |minimum| minimum.ceil() as i64)),
+    Some("number") => Value::from(number("minimum").unwrap_or(1.0)),
+    Some("boolean") => Value::Bool(true),
+    _ => Value::Null,
+  }
+}
+
+/// Problems with `value` against `schema`, each prefixed with where in the
+/// value it is, `path` being the value itself.
+pub fn validate(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+  if !schema.is_object() || schema.get("$ref").is_some() {
+    return;
+  }
+  if value.is_null() && (schema.get("nullable") == Some(&Value::Bool(true)) || allows_null(schema)) {
+    return;
+  }
+  if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+    for part in all {
+      validate(value, part, path, errors);
+    }
+  }
+  for key in ["oneOf", "anyOf"] {
+    if let Some(options) = schema.get(key).and_then(Value::as_array) {
+      let matches = options.iter().any(|option| {
+        let mut option_errors = Vec::new();
+        validate(value, option, path, &mut option_errors);
+        option_errors.is_empty()
+      });
+      if !matches {
+        errors.push(format!("{path} matches none of the allowed schemas"));
+      }
+    }
+  }
+  if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+    if !values.contains(value) {
+      errors.push(format!("{path} must be one of {}", Value::Array(values.clone())));
+      return;
+    }
+  }
+
+  if let Some(expected) = schema_type(schema) {
+    let matches = match expected {
+      "object" => value.is_object(),
+      "array" => value.is_array(),
+      "string" => value.is_string(),
+      "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+      "number" => value.is_number(),
+      "boolean" => value.is_boolean(),
+      "null" => value.is_null(),
+      _ => true,
+    };
+    if !matches {
+      errors.push(format!("{path} must be {} {expected}", article(expected)));
+      return;
+    }
+  }
+
+  match value {
+    Value::Object(object) => {
+      for required in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+        if let Some(name) = required.as_str().filter(|name| !object.contains_key(*name)) {
+          errors.push(format!("{path}.{name} is required"));
+        }
+      }
+      let properties = schema.get("properties").and_then(Value::as_object);
+      for (name, property) in object {
+        match properties.and_then(|properties| properties.get(name)) {
+          Some(property_schema) => validate(property, property_schema, &format!("{path}.{name}"), errors),
+          None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+            errors.push(format!("{path}.{name} is not allowed"));
+          }
+          None => {
+            if let Some(additional) = schema.get("additionalProperties").filter(|additional| additional.is_object()) {
+              validate(property, additional, &format!("{path}.{name}"), errors);
+            }
+          }
+        }
+      }
+    }
+    Value::Array(items) => {
+      let bound = |key: &str| schema.get(key).and_then(Value::as_u64).map(|bound| bound as usize);
+      if let Some(min) = bound("minItems").filter(|min| items.len() < *min) {
+        errors.push(format!("{path} must have at least {min} item(s)"));
+      }
+      if let Some(max) = bound("maxItems").filter(|max| items.len() > *max) {
+        errors.push(format!("{path} must have at most {max} item(s)"));
+      }
+      if let Some(item_schema) = schema.get("items") {
+        for (index, item) in items.iter().enumerate() {
+          validate(item, item_schema, &format!("{path}[{index}]"), errors);
+        }
+      }
+    }
+    Value::String(text) => {
+      let length = text.chars().count();
+      let bound = |key: &str| schema.get(key).and_then(Value::as_u64).map(|bound| bound as usize);
+      if let Some(min) = bound("minLength").filter(|min| length < *min) {
+        errors.push(format!("{path} must be at least {min} character(s) long"));
+      }
+      if let Some(max) = bound("maxLength").filter(|max| length > *max) {
+        errors.push(format!("{path} must be at most {max} character(s) long"));
+      }
+      // A pattern the regex crate cannot compile is not held against the value.
+      if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        if Regex::new(pattern).is_ok_and(|regex| !regex.is_match(text)) {
+          errors.push(format!("{path} must match `{pattern}`"));
+        }
+      }
+    }
+    Value::Number(number) => {
+      let number = number.as_f64().unwrap_or_default();
+      if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64).filter(|minimum| number < *minimum) {
+        errors.push(format!("{path} must be at least {minimum}"));
+      }
+      if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64).filter(|maximum| number > *maximum) {
+        errors.push(format!("{path} must be at most {maximum}"));
+      }
+    }
+    _ => {}
+  }
+}
+
+/// The schema's type; OpenAPI 3.1 allows a list such as `["string", "null"]`,
+/// of which the first non-null one counts.
+pub fn schema_type(schema: &Value) -> Option<&str> {
+  match schema.get("type") {
+    Some(Value::String(kind)) => Some(kind),
+    Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).find(|kind| *kind != "null"),
+    _ if schema.get("properties").is_some() => Some("object"),
+    _ => None,
+  }
+}
+
+fn allows_null(schema: &Value) -> bool {
+  match schema.get("type") {
+    Some(Value::Array(kinds)) => kinds.iter().any(|kind| kind == "null"),
+    Some(kind) => kind == "null",
+    None => false,
+  }
+}
+
+fn article(kind: &str) -> &'static str {
+  if kind.starts_with(['a', 'e', 'i', 'o', 'u']) {
+    "an"
+  } else {
+    "a"
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn generates_examples_from_schemas() {
+    let schema = json!({
+      "type": "object",
+      "properties": {
+        "id": { "type": "integer", "minimum": 10 },
+        "email": { "type": "string", "format": "email" },
+        "role": { "type": "string", "enum": ["admin", "user"] },
+        "tags": { "type": "array", "items": { "type": "string", "example": "new" } },
+        "owner": { "allOf": [{ "properties": { "name": { "type": "string" } } }, { "properties": { "active": { "type": "boolean" } } }] },
+        "manager": { "$ref": "#/components/schemas/User" }
+      }
+    });
+    assert_eq!(
+      example(&schema),
+      json!({
+        "id": 10,
+        "email": "user@example.com",
+        "role": "admin",
+        "tags": ["new"],
+        "owner": { "name": "string", "active": true },
+        "manager": null
+      })
+    );
+    assert_eq!(example(&json!({ "type": ["string", "null"], "minLength": 8 })), json!("stringxx"));
+  }
+
+  #[test]
+  fn validates_values_against_schemas() {
+    let schema = json!({
+      "type": "object",
+      "required": ["name", "age"],
+      "additionalProperties": false,
+      "properties": {
+        "name": { "type": "string", "minLength": 2 },
+        "age": { "type": "integer", "minimum": 0 },
+        "tags": { "type": "array", "items": { "type": "string" } },
+        "nickname": { "type": "string", "nullable": true }
+      }
+    });
+    let mut errors = Vec::new();
+    validate(&json!({ "name": "Ann", "age": 3, "nickname": null }), &schema, "$", &mut errors);
+    assert!(errors.is_empty(), "{errors:?}");
+
+    validate(&json!({ "name": "A", "tags": ["a", 1], "extra": true }), &schema, "$", &mut errors);
+    errors.sort();
+    assert_eq!(
+      errors,
+      [
+        "$.age is required",
+        "$.extra is not allowed",
+        "$.name must be at least 2 character(s) long",
+        "$.tags[1] must be a string",
+      ]
+    );
+  }
+}
@@ -0,0 +1,549 @@
+//! OpenAPI and Swagger import for the desktop app, which has no
+//! `swagger-parser` to lean on.
+//!
+//! [`load`] and [`parse`] read Swagger 2.0 or OpenAPI 3.0/3.1 from YAML or
+//! JSON and resolve its `$ref`s (see [`refs`]). [`extract_endpoints`] and
+//! [`extract_host`] then produce the records their namesakes in
+//! `src/lib/server/swagger/parser.ts` do, ready for the `api_endpoints`
+//! table.
+
+mod refs;
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+
+const METHODS: [&str; 7] = ["get", "post", "put", "delete", "patch", "options", "head"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecFormat {
+  Json,
+  Yaml,
+}
+
+impl SpecFormat {
+  /// By extension: `.json` is JSON and anything else YAML, which also reads
+  /// JSON.
+  pub fn from_path(path: &Path) -> Self {
+    match path.extension().and_then(|extension| extension.to_str()) {
+      Some(extension) if extension.eq_ignore_ascii_case("json") => SpecFormat::Json,
+      _ => SpecFormat::Yaml,
+    }
+  }
+
+  /// As stored in `apis.specFormat`.
+  pub fn as_str(self) -> &'static str {
+    match self {
+      SpecFormat::Json => "json",
+      SpecFormat::Yaml => "yaml",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecVersion {
+  Swagger2,
+  OpenApi3,
+}
+
+/// A specification with its `$ref`s resolved.
+#[derive(Debug, Clone)]
+pub struct Spec {
+  pub version: SpecVersion,
+  pub document: Value,
+}
+
+impl Spec {
+  pub fn title(&self) -> Option<&str> {
+    self.document["info"]["title"].as_str()
+  }
+}
+
+/// One operation, as `extractEndpoints` returns it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiEndpoint {
+  pub path: String,
+  /// Upper case.
+  pub method: String,
+  pub operation_id: Option<String>,
+  pub summary: Option<String>,
+  pub description: Option<String>,
+  pub request_schema: Option<Value>,
+  /// The schema of the 200, else the 201, response.
+  pub response_schema: Option<Value>,
+  pub parameters: Vec<ApiParameter>,
+  pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiParameter {
+  pub name: String,
+  #[serde(rename = "in")]
+  pub location: String,
+  pub required: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub example: Option<Value>,
+  /// The schema's type, `"string"` if it has none; kept for older callers.
+  #[serde(rename = "type")]
+  pub param_type: Value,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub schema: Option<Value>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub style: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub explode: Option<bool>,
+  /// Swagger 2.0 only.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub collection_format: Option<String>,
+}
+
+/// Read and dereference a specification file. Refs to other files resolve
+/// relative to it.
+pub fn load(path: &Path) -> Result<Spec> {
+  let content = std::fs::read_to_string(path)?;
+  parse(&content, SpecFormat::from_path(path), Some(path))
+}
+
+/// Parse and dereference a specification. `path` is where it was read from,
+/// needed only to resolve refs to other files.
+pub fn parse(content: &str, format: SpecFormat, path: Option<&Path>) -> Result<Spec> {
+  let document = parse_text(content, format).map_err(Error::OpenApi)?;
+  let version = version(&document)?;
+  let path = path.map(Path::canonicalize).transpose()?;
+  let document = refs::Resolver::new(document, path).dereference()?;
+  log::debug!("[openapi] parsed {:?} specification {:?}", version, document["info"]["title"]);
+  Ok(Spec { version, document })
+}
+
+fn parse_text(content: &str, format: SpecFormat) -> std::result::Result<Value, String> {
+  match format {
+    SpecFormat::Json => serde_json::from_str(content).map_err(|err| format!("not valid JSON: {err}")),
+    SpecFormat::Yaml => {
+      let document: serde_yaml::Value = serde_yaml::from_str(content).map_err(|err| format!("not valid YAML: {err}"))?;
+      yaml_to_json(document)
+    }
+  }
+}
+
+/// YAML allows keys JSON does not, like the unquoted `200:` of a response;
+/// scalar keys become strings.
+fn yaml_to_json(value: serde_yaml::Value) -> std::result::Result<Value, String> {
+  use serde_yaml::Value as Yaml;
+  Ok(match value {
+    Yaml::Null => Value::Null,
+    Yaml::Bool(boolean) => Value::Bool(boolean),
+    Yaml::Number(number) => {
+      if let Some(integer) = number.as_i64() {
+        Value::from(integer)
+      } else if let Some(integer) = number.as_u64() {
+        Value::from(integer)
+      } else {
+        number.as_f64().map_or(Value::Null, Value::from)
+      }
+    }
+    Yaml::String(text) => Value::String(text),
+    Yaml::Sequence(items) => Value::Array(items.into_iter().map(yaml_to_json).collect::<std::result::Result<_, _>>()?),
+    Yaml::Mapping(mapping) => {
+      let mut object = Map::new();
+      for (key, value) in mapping {
+        let key = match key {
+          Yaml::String(key) => key,
+          Yaml::Number(number) => number.to_string(),
+          Yaml::Bool(boolean) => boolean.to_string(),
+          Yaml::Null => "null".to_string(),
+          key => return Err(format!("unsupported mapping key {key:?}")),
+        };
+        object.insert(key, yaml_to_json(value)?);
+      }
+      Value::Object(object)
+    }
+    Yaml::Tagged(tagged) => yaml_to_json(tagged.value)?,
+  })
+}
+
+fn version(document: &Value) -> Result<SpecVersion> {
+  if !document.is_object() {
+    return Err(Error::OpenApi("the document is not an object".to_string()));
+  }
+  // Unquoted in YAML, `swagger: 2.0` is a number.
+  let field = |name: &str| match &document[name] {
+    Value::String(version) => Some(version.clone()),
+    Value::Number(version) => Some(version.to_string()),
+    _ => None,
+  };
+  let version = match (field("swagger"), field("openapi")) {
+    (Some(swagger), _) if swagger == "2.0" || swagger == "2" => SpecVersion::Swagger2,
+    (_, Some(openapi)) if openapi.starts_with("3.") => SpecVersion::OpenApi3,
+    _ => {
+      return Err(Error::OpenApi(
+        "expected `swagger: \"2.0\"` or `openapi: 3.x`; other versions are not supported".to_string(),
+      ))
+    }
+  };
+  if !matches!(document["paths"], Value::Null | Value::Object(_)) {
+    return Err(Error::OpenApi("`paths` must be an object".to_string()));
+  }
+  Ok(version)
+}
+
+/// Every operation of every path.
+pub fn extract_endpoints(spec: &Spec) -> Vec<ApiEndpoint> {
+  let Some(paths) = spec.document["paths"].as_object() else {
+    return Vec::new();
+  };
+  let mut endpoints = Vec::new();
+  for (path, item) in paths {
+    for method in METHODS {
+      let operation = &item[method];
+      if !operation.is_object() {
+        continue;
+      }
+      let (request_schema, response_schema, parameters) = match spec.version {
+        SpecVersion::OpenApi3 => (
+          request_schema(operation),
+          media_schema(&success_response(operation)["content"]),
+          parameters(item, operation, openapi3_parameter),
+        ),
+        SpecVersion::Swagger2 => (
+          swagger2_request_schema(operation),
+          success_response(operation).get("schema").cloned(),
+          parameters(item, operation, swagger2_parameter),
+        ),
+      };
+      endpoints.push(ApiEndpoint {
+        path: path.clone(),
+        method: method.to_uppercase(),
+        operation_id: string(&operation["operationId"]),
+        summary: string(&operation["summary"]),
+        description: string(&operation["description"]),
+        request_schema,
+        response_schema,
+        parameters,
+        tags: operation["tags"]
+          .as_array()
+          .into_iter()
+          .flatten()
+          .filter_map(|tag| tag.as_str().map(str::to_string))
+          .collect(),
+      });
+    }
+  }
+  endpoints
+}
+
+/// The host of the first server (OpenAPI 3) or `host` (Swagger 2.0). A
+/// server URL that does not parse, e.g. a relative or templated one, is
+/// returned whole.
+pub fn extract_host(spec: &Spec) -> Option<String> {
+  match spec.version {
+    SpecVersion::Swagger2 => string(&spec.document["host"]),
+    SpecVersion::OpenApi3 => {
+      let server = spec.document["servers"][0]["url"].as_str()?;
+      let host = url::Url::parse(server).ok().and_then(|url| {
+        let host = url.host_str()?.to_string();
+        Some(match url.port() {
+          Some(port) => format!("{host}:{port}"),
+          None => host,
+        })
+      });
+      Some(host.unwrap_or_else(|| server.to_string()))
+    }
+  }
+}
+
+fn string(value: &Value) -> Option<String> {
+  value.as_str().map(str::to_string)
+}
+
+fn success_response(operation: &Value) -> &Value {
+  let responses = &operation["responses"];
+  if responses["200"].is_object() {
+    &responses["200"]
+  } else {
+    &responses["201"]
+  }
+}
+
+fn request_schema(operation: &Value) -> Option<Value> {
+  media_schema(&operation["requestBody"]["content"])
+}
+
+/// The schema of a `content` map's JSON media type, else of its first one.
+/// The TS parser takes the first key, but key order is not kept here.
+fn media_schema(content: &Value) -> Option<Value> {
+  let content = content.as_object()?;
+  let media = content
+    .get("application/json")
+    .or_else(|| content.iter().find(|(name, _)| name.contains("json")).map(|(_, media)| media))
+    .or_else(|| content.values().next())?;
+  media.get("schema").cloned()
+}
+
+fn swagger2_request_schema(operation: &Value) -> Option<Value> {
+  operation["parameters"]
+    .as_array()?
+    .iter()
+    .find(|parameter| parameter["in"] == "body")?
+    .get("schema")
+    .cloned()
+}
+
+/// Path-level parameters followed by the operation's, where an operation
+/// parameter replaces a path-level one with the same name and location.
+/// Refs that could not be resolved are skipped.
+fn parameters(item: &Value, operation: &Value, convert: fn(&Map<String, Value>) -> ApiParameter) -> Vec<ApiParameter> {
+  let all = item["parameters"].as_array().into_iter().flatten().chain(operation["parameters"].as_array().into_iter().flatten());
+  let mut parameters: Vec<ApiParameter> = Vec::new();
+  for parameter in all {
+    let Some(parameter) = parameter.as_object().filter(|parameter| !parameter.contains_key("$ref")) else {
+      log::debug!("[openapi] skipping unresolved parameter {parameter}");
+      continue;
+    };
+    let parameter = convert(parameter);
+    match parameters
+      .iter_mut()
+      .find(|existing| existing.name == parameter.name && existing.location == parameter.location)
+    {
+      Some(existing) => *existing = parameter,
+      None => parameters.push(parameter),
+    }
+  }
+  parameters
+}
+
+fn openapi3_parameter(parameter: &Map<String, Value>) -> ApiParameter {
+  let schema = parameter.get("schema").cloned();
+  ApiParameter {
+    param_type: schema
+      .as_ref()
+      .and_then(|schema| schema.get("type"))
+      .cloned()
+      .unwrap_or_else(|| Value::from("string")),
+    schema,
+    style: parameter.get("style").and_then(string),
+    explode: parameter.get("explode").and_then(Value::as_bool),
+    collection_format: None,
+    ..common_parameter(parameter)
+  }
+}
+
+fn swagger2_parameter(parameter: &Map<String, Value>) -> ApiParameter {
+  let param_type = parameter.get("type").cloned().unwrap_or_else(|| Value::from("string"));
+  let schema = if param_type == "array" {
+    let items_type = parameter
+      .get("items")
+      .and_then(|items| items.get("type"))
+      .cloned()
+      .unwrap_or_else(|| Value::from("string"));
+    serde_json::json!({ "type": "array", "items": { "type": items_type } })
+  } else {
+    serde_json::json!({ "type": param_type })
+  };
+  ApiParameter {
+    param_type,
+    schema: Some(schema),
+    collection_format: parameter.get("collectionFormat").and_then(string),
+    ..common_parameter(parameter)
+  }
+}
+
+fn common_parameter(parameter: &Map<String, Value>) -> ApiParameter {
+  ApiParameter {
+    name: parameter.get("name").and_then(string).unwrap_or_default(),
+    location: parameter.get("in").and_then(string).unwrap_or_default(),
+    required: parameter.get("required").and_then(Value::as_bool).unwrap_or(false),
+    description: parameter.get("description").and_then(string),
+    example: parameter.get("example").cloned(),
+    param_type: Value::Null,
+    schema: None,
+    style: None,
+    explode: None,
+    collection_format: None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn extracts_openapi3_endpoints_with_refs_resolved() {
+    let spec = json!({
+      "openapi": "3.1.0",
+      "info": { "title": "Shop", "version": "1" },
+      "servers": [{ "url": "https://api.shop.test:8443/v1" }],
+      "paths": {
+        "/users/{id}": {
+          "parameters": [
+            { "$ref": "#/components/parameters/Id" },
+            { "name": "trace", "in": "header" }
+          ],
+          "get": {
+            "operationId": "getUser",
+            "tags": ["users"],
+            "parameters": [{ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }],
+            "responses": {
+              "200": {
+                "content": {
+                  "text/plain": { "schema": { "type": "string" } },
+                  "application/json": { "schema": { "$ref": "#/components/schemas/User" } }
+                }
+              }
+            }
+          },
+          "put": {
+            "requestBody": {
+              "content": { "application/json": { "schema": { "$ref": "#/components/schemas/User", "description": "New state" } } }
+            },
+            "responses": { "204": {} }
+          }
+        }
+      },
+      "components": {
+        "parameters": { "Id": { "name": "id", "in": "path", "required": true, "schema": { "type": "string" } } },
+        "schemas": {
+          "User": {
+            "type": "object",
+            "properties": { "id": { "type": "integer" }, "manager": { "$ref": "#/components/schemas/User" } }
+          }
+        }
+      }
+    });
+
+    let spec = parse(&spec.to_string(), SpecFormat::Json, None).unwrap();
+    assert_eq!(spec.version, SpecVersion::OpenApi3);
+    assert_eq!(extract_host(&spec).as_deref(), Some("api.shop.test:8443"));
+
+    let endpoints = extract_endpoints(&spec);
+    assert_eq!(endpoints.len(), 2);
+    let get = &endpoints[0];
+    assert_eq!((get.method.as_str(), get.operation_id.as_deref()), ("GET", Some("getUser")));
+    assert_eq!(get.tags, ["users"]);
+    let response = get.response_schema.as_ref().unwrap();
+    assert_eq!(response["properties"]["id"], json!({ "type": "integer" }));
+    // The cycle back into `User` stays a ref.
+    assert_eq!(response["properties"]["manager"], json!({ "$ref": "#/components/schemas/User" }));
+
+    // The operation's `id` replaces the path-level one.
+    let parameters = serde_json::to_value(&get.parameters).unwrap();
+    assert_eq!(
+      parameters,
+      json!([
+        { "name": "id", "in": "path", "required": true, "type": "integer", "schema": { "type": "integer" } },
+        { "name": "trace", "in": "header", "required": false, "type": "string" }
+      ])
+    );
+
+    let put = &endpoints[1];
+    assert_eq!(put.request_schema.as_ref().unwrap()["description"], json!("New state"));
+    assert_eq!(put.response_schema, None);
+  }
+
+  #[test]
+  fn extracts_swagger2_endpoints() {
+    let spec = json!({
+      "swagger": "2.0",
+      "info": { "title": "Legacy", "version": "1" },
+      "host": "legacy.test",
+      "paths": {
+        "/orders": {
+          "post": {
+            "summary": "Create an order",
+            "parameters": [
+              { "name": "body", "in": "body", "schema": { "$ref": "#/definitions/Order" } },
+              { "name": "ids", "in": "query", "type": "array", "items": { "type": "integer" }, "collectionFormat": "csv" }
+            ],
+            "responses": { "201": { "schema": { "$ref": "#/definitions/Order" } } }
+          }
+        }
+      },
+      "definitions": { "Order": { "type": "object", "properties": { "total": { "type": "number" } } } }
+    });
+
+    let spec = parse(&spec.to_string(), SpecFormat::Json, None).unwrap();
+    assert_eq!(extract_host(&spec).as_deref(), Some("legacy.test"));
+    let endpoints = extract_endpoints(&spec);
+    assert_eq!(endpoints.len(), 1);
+    let order = json!({ "type": "object", "properties": { "total": { "type": "number" } } });
+    assert_eq!(endpoints[0].request_schema, Some(order.clone()));
+    assert_eq!(endpoints[0].response_schema, Some(order));
+    let ids = &endpoints[0].parameters[1];
+    assert_eq!(ids.schema, Some(json!({ "type": "array", "items": { "type": "integer" } })));
+    assert_eq!(ids.collection_format.as_deref(), Some("csv"));
+  }
+
+  #[test]
+  fn resolves_refs_to_other_files() {
+    let dir = std::env::temp_dir().join(format!("openapi-refs-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("schemas")).unwrap();
+    std::fs::write(
+      dir.join("spec.json"),
+      json!({
+        "openapi": "3.0.3",
+        "info": { "title": "Split" },
+        "paths": {
+          "/pets": { "get": { "responses": { "200": { "$ref": "schemas/common.json#/responses/Pets" } } } }
+        }
+      })
+      .to_string(),
+    )
+    .unwrap();
+    std::fs::write(
+      dir.join("schemas/common.json"),
+      json!({
+        "responses": {
+          "Pets": { "content": { "application/json": { "schema": { "type": "array", "items": { "$ref": "pet.json" } } } } }
+        }
+      })
+      .to_string(),
+    )
+    .unwrap();
+    std::fs::write(dir.join("schemas/pet.json"), json!({ "type": "object", "title": "Pet" }).to_string()).unwrap();
+
+    let spec = load(&dir.join("spec.json"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    let endpoints = extract_endpoints(&spec.unwrap());
+    assert_eq!(
+      endpoints[0].response_schema,
+      Some(json!({ "type": "array", "items": { "type": "object", "title": "Pet" } }))
+    );
+  }
+
+  #[test]
+  fn reads_yaml_with_numeric_keys() {
+    let content = "
+swagger: 2.0
+info: { title: Pets }
+paths:
+  /pets:
+    get:
+      tags: [pets]
+      responses:
+        200:
+          schema: { type: array, items: { type: string } }
+";
+    let spec = parse(content, SpecFormat::Yaml, None).unwrap();
+    assert_eq!(spec.version, SpecVersion::Swagger2);
+    assert_eq!(spec.title(), Some("Pets"));
+    let endpoints = extract_endpoints(&spec);
+    assert_eq!(endpoints[0].response_schema, Some(json!({ "type": "array", "items": { "type": "string" } })));
+  }
+
+  #[test]
+  fn rejects_unsupported_documents() {
+    for content in [r#"{ "swagger": "1.2", "paths": {} }"#, r#"{ "openapi": "3.0.0", "paths": [] }"#, "[]"] {
+      assert!(matches!(parse(content, SpecFormat::Json, None), Err(Error::OpenApi(_))), "{content}");
+    }
+    let missing = r##"{ "openapi": "3.0.0", "paths": { "/a": { "get": { "$ref": "#/nowhere" } } } }"##;
+    assert!(matches!(parse(missing, SpecFormat::Json, None), Err(Error::OpenApi(_))));
+  }
+}
@@ -0,0 +1,138 @@
+//! `$ref` resolution, in place of `SwaggerParser.dereference`.
+//!
+//! Local refs (`#/components/schemas/User`) are looked up in the document
+//! they appear in; file refs (`common.yaml#/User`, `./user.json`) are read
+//! relative to that document's file, so a ref inside `common.yaml` resolves
+//! against `common.yaml`'s directory. Remote (`http(s)://`) refs are left in
+//! place since imports run offline, and so is a ref back into a schema that
+//! is still being expanded: a `Value` tree cannot hold a cycle the way the JS
+//! object graph does.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use percent_encoding::percent_decode_str;
+use serde_json::{Map, Value};
+
+use super::{parse_text, SpecFormat};
+use crate::error::{Error, Result};
+
+/// A ref target: the file (`None` for the root document when it was not
+/// loaded from disk) and the JSON pointer within it.
+type Target = (Option<PathBuf>, String);
+
+pub(super) struct Resolver {
+  root: Value,
+  root_file: Option<PathBuf>,
+  files: HashMap<PathBuf, Value>,
+  resolved: HashMap<Target, Value>,
+  active: Vec<Target>,
+}
+
+impl Resolver {
+  pub(super) fn new(root: Value, root_file: Option<PathBuf>) -> Self {
+    Self {
+      root,
+      root_file,
+      files: HashMap::new(),
+      resolved: HashMap::new(),
+      active: Vec::new(),
+    }
+  }
+
+  /// The root document with every resolvable ref replaced by its target.
+  pub(super) fn dereference(mut self) -> Result<Value> {
+    let (root, file) = (self.root.clone(), self.root_file.clone());
+    self.walk(&root, &file)
+  }
+
+  fn walk(&mut self, value: &Value, file: &Option<PathBuf>) -> Result<Value> {
+    match value {
+      Value::Object(map) => {
+        if let Some(Value::String(reference)) = map.get("$ref") {
+          let resolved = self.follow(reference, file)?;
+          // OpenAPI 3.1 lets `summary` and `description` sit next to a ref
+          // and override the target's.
+          return match resolved {
+            Value::Object(mut target) if map.len() > 1 => {
+              for (key, sibling) in map.iter().filter(|(key, _)| *key != "$ref") {
+                target.insert(key.clone(), self.walk(sibling, file)?);
+              }
+              Ok(Value::Object(target))
+            }
+            resolved => Ok(resolved),
+          };
+        }
+        let mut walked = Map::new();
+        for (key, child) in map {
+          walked.insert(key.clone(), self.walk(child, file)?);
+        }
+        Ok(Value::Object(walked))
+      }
+      Value::Array(items) => items.iter().map(|item| self.walk(item, file)).collect(),
+      value => Ok(value.clone()),
+    }
+  }
+
+  fn follow(&mut self, reference: &str, file: &Option<PathBuf>) -> Result<Value> {
+    let unresolved = || Value::Object(Map::from_iter([("$ref".to_string(), Value::from(reference))]));
+    let (location, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+    if location.starts_with("http://") || location.starts_with("https://") {
+      log::warn!("[openapi] leaving remote reference `{reference}` unresolved");
+      return Ok(unresolved());
+    }
+
+    let target_file = if location.is_empty() {
+      file.clone()
+    } else {
+      let base = file.as_deref().and_then(Path::parent).ok_or_else(|| {
+        Error::OpenApi(format!("cannot resolve `{reference}` without the location of the specification file"))
+      })?;
+      let path = base.join(percent_decode_str(location).decode_utf8_lossy().as_ref());
+      let path = path
+        .canonicalize()
+        .map_err(|err| Error::OpenApi(format!("cannot read `{}` for `{reference}`: {err}", path.display())))?;
+      Some(path)
+    };
+    let pointer = percent_decode_str(fragment).decode_utf8_lossy().into_owned();
+    let target = (target_file, pointer);
+
+    if let Some(resolved) = self.resolved.get(&target) {
+      return Ok(resolved.clone());
+    }
+    if self.active.contains(&target) {
+      log::debug!("[openapi] leaving circular reference `{reference}` in place");
+      return Ok(unresolved());
+    }
+
+    let document = self.document(&target.0)?;
+    let value = document
+      .pointer(&target.1)
+      .cloned()
+      .ok_or_else(|| Error::OpenApi(format!("`$ref` `{reference}` points to nothing")))?;
+
+    self.active.push(target.clone());
+    let resolved = self.walk(&value, &target.0);
+    self.active.pop();
+    let resolved = resolved?;
+    self.resolved.insert(target, resolved.clone());
+    Ok(resolved)
+  }
+
+  fn document(&mut self, file: &Option<PathBuf>) -> Result<&Value> {
+    let Some(path) = file else {
+      return Ok(&self.root);
+    };
+    if self.root_file.as_ref() == Some(path) {
+      return Ok(&self.root);
+    }
+    if !self.files.contains_key(path) {
+      log::debug!("[openapi] loading referenced file {}", path.display());
+      let content = std::fs::read_to_string(path)?;
+      let document = parse_text(&content, SpecFormat::from_path(path))
+        .map_err(|err| Error::OpenApi(format!("{}: {err}", path.display())))?;
+      self.files.insert(path.clone(), document);
+    }
+    Ok(&self.files[path])
+  }
+}
@@ -0,0 +1,445 @@
+//! Recording proxy: a local HTTP proxy whose traffic becomes a test flow.
+//!
+//! Point an app or browser at the proxy as its HTTP proxy, or, when the proxy
+//! has a `target`, at the proxy itself as the API's base URL. Every exchange
+//! with the chosen hosts is kept as a HAR entry, and the recording converts
+//! into a flow the way a HAR file does (see [`crate::import::har`]), with its
+//! requests matched to imported API endpoints.
+//!
+//! HTTPS requests sent through the proxy arrive as `CONNECT` tunnels it
+//! cannot see into, so they are passed on unrecorded; to record an HTTPS API,
+//! make it the target instead.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use chrono::{SecondsFormat, Utc};
+use http::header::{CONTENT_TYPE, HOST, LOCATION};
+use http::{HeaderMap, Method, Request, Response, StatusCode, Uri};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+use crate::curl::KnownEndpoint;
+use crate::error::{Error, Result};
+use crate::http::HttpClient;
+use crate::import::har::{self, HarOptions};
+use crate::import::Import;
+use crate::runs::CancellationToken;
+
+/// Headers that only concern one connection and are never passed on.
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+  "connection",
+  "keep-alive",
+  "proxy-authenticate",
+  "proxy-authorization",
+  "proxy-connection",
+  "te",
+  "trailer",
+  "transfer-encoding",
+  "upgrade",
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProxyOptions {
+  /// Only record exchanges with these hosts; all hosts when empty. Other
+  /// traffic is still passed on.
+  pub hosts: Vec<String>,
+  /// Base URL that requests made to the proxy itself, rather than through
+  /// it, are sent to, such as `https://api.example.com/v1`.
+  pub target: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyInfo {
+  pub port: u16,
+  pub url: String,
+  pub hosts: Vec<String>,
+  pub target: Option<String>,
+  /// Exchanges recorded so far.
+  pub recorded: usize,
+  /// Hosts reached through unrecorded `CONNECT` tunnels.
+  pub tunnelled: Vec<String>,
+}
+
+/// A running recording proxy; it stops when stopped or dropped.
+pub struct RecordingProxy {
+  port: u16,
+  shared: Arc<Shared>,
+}
+
+struct Shared {
+  client: HttpClient,
+  hosts: Vec<String>,
+  target: Option<Url>,
+  /// HAR 1.2 entries, in the order the responses arrived.
+  entries: Mutex<Vec<Value>>,
+  tunnelled: Mutex<Vec<String>>,
+  shutdown: CancellationToken,
+}
+
+impl RecordingProxy {
+  /// Start listening on `127.0.0.1:port`, port `0` picking a free one.
+  pub async fn start(port: u16, options: ProxyOptions) -> Result<Self> {
+    let target = options
+      .target
+      .as_deref()
+      .map(|target| Url::parse(target).map_err(|err| Error::Proxy(format!("invalid target `{target}`: {err}"))))
+      .transpose()?;
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+      .await
+      .map_err(|err| Error::Proxy(format!("cannot listen on port {port}: {err}")))?;
+    let port = listener.local_addr()?.port();
+    let shared = Arc::new(Shared {
+      client: HttpClient::new()?,
+      hosts: options.hosts,
+      target,
+      entries: Mutex::default(),
+      tunnelled: Mutex::default(),
+      shutdown: CancellationToken::default(),
+    });
+    log::debug!("[proxy] recording on 127.0.0.1:{port}");
+    tokio::spawn(serve(listener, Arc::clone(&shared)));
+    Ok(Self { port, shared })
+  }
+
+  pub fn info(&self) -> ProxyInfo {
+    ProxyInfo {
+      port: self.port,
+      url: format!("http://127.0.0.1:{}", self.port),
+      hosts: self.shared.hosts.clone(),
+      target: self.shared.target.as_ref().map(Url::to_string),
+      recorded: lock(&self.shared.entries).len(),
+      tunnelled: lock(&self.shared.tunnelled).clone(),
+    }
+  }
+
+  /// The recorded exchanges as HAR entries, oldest first.
+  pub fn entries(&self) -> Vec<Value> {
+    lock(&self.shared.entries).clone()
+  }
+
+  pub fn clear(&self) {
+    lock(&self.shared.entries).clear();
+  }
+
+  /// Convert the recording into a flow, pointing its requests at the
+  /// `known` endpoints they match.
+  pub fn flow(&self, name: Option<String>, known: &[KnownEndpoint]) -> Result<Import> {
+    let har = json!({
+      "log": {
+        "version": "1.2",
+        "creator": { "name": "Test-Pilot recording proxy", "version": env!("CARGO_PKG_VERSION") },
+        "entries": self.entries(),
+      }
+    });
+    let options = HarOptions {
+      name: Some(name.unwrap_or_else(|| "Recorded session".to_string())),
+      ..HarOptions::default()
+    };
+    let mut import = har::import_log(&har, &options)?;
+    let matched = import.match_endpoints(known);
+    log::debug!("[proxy] matched {matched} recorded request(s) to imported endpoints");
+    Ok(import)
+  }
+
+  /// Stop accepting requests and close open connections and tunnels.
+  pub fn stop(&self) {
+    self.shared.shutdown.cancel();
+  }
+}
+
+impl Drop for RecordingProxy {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+async fn serve(listener: TcpListener, shared: Arc<Shared>) {
+  loop {
+    let stream = match shared.shutdown.run(listener.accept()).await {
+      Ok(Ok((stream, _))) => stream,
+      Ok(Err(err)) => {
+        log::debug!("[proxy] failed to accept a connection: {err}");
+        continue;
+      }
+      Err(_) => break,
+    };
+    let shared = Arc::clone(&shared);
+    tokio::spawn(async move {
+      let shutdown = shared.shutdown.clone();
+      let service = service_fn(move |request| handle(Arc::clone(&shared), request));
+      let connection = http1::Builder::new()
+        .serve_connection(TokioIo::new(stream), service)
+        .with_upgrades();
+      if let Ok(Err(err)) = shutdown.run(connection).await {
+        log::debug!("[proxy] connection failed: {err}");
+      }
+    });
+  }
+  log::debug!("[proxy] stopped");
+}
+
+async fn handle(shared: Arc<Shared>, request: Request<Incoming>) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+  if request.method() == Method::CONNECT {
+    return Ok(tunnel(shared, request));
+  }
+  let (parts, body) = request.into_parts();
+  let Some(url) = destination(&parts.uri, shared.target.as_ref()) else {
+    let message = "not a proxy request; give the proxy a target to send requests made to it directly";
+    return Ok(error_response(StatusCode::BAD_REQUEST, message));
+  };
+  let body = body.collect().await.map(|body| body.to_bytes()).unwrap_or_default();
+
+  let mut headers = without_hop_by_hop(&parts.headers);
+  headers.remove(HOST);
+  // Uncompressed responses can be read back when the flow is built.
+  headers.remove(http::header::ACCEPT_ENCODING);
+  let started = Utc::now();
+  let start = Instant::now();
+  let response = match shared.client.forward(&parts.method, &url, headers, body.clone()).await {
+    Ok(response) => response,
+    Err(err) => {
+      log::debug!("[proxy] {} {url} failed: {err}", parts.method);
+      return Ok(error_response(StatusCode::BAD_GATEWAY, &err.to_string()));
+    }
+  };
+  log::debug!("[proxy] {} {url} -> {}", parts.method, response.status());
+
+  let host = url.host_str().unwrap_or_default();
+  if shared.hosts.is_empty() || shared.hosts.iter().any(|known| known.eq_ignore_ascii_case(host)) {
+    let time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let entry = har_entry(started, time_ms, (&parts.method, &url, &parts.headers, &body), &response);
+    lock(&shared.entries).push(entry);
+  }
+
+  let (response_parts, response_body) = response.into_parts();
+  let mut reply = Response::new(Full::new(response_body));
+  *reply.status_mut() = response_parts.status;
+  *reply.headers_mut() = without_hop_by_hop(&response_parts.headers);
+  Ok(reply)
+}
+
+/// Where a request should go: the URL it names when sent through the proxy,
+/// or the target's base URL followed by its path when sent to the proxy.
+fn destination(uri: &Uri, target: Option<&Url>) -> Option<Url> {
+  if uri.scheme().is_some() {
+    return Url::parse(&uri.to_string()).ok();
+  }
+  let path = uri.path_and_query().map_or("/", |path| path.as_str());
+  Url::parse(&format!("{}{path}", target?.as_str().trim_end_matches('/'))).ok()
+}
+
+/// Answer a `CONNECT` and splice the connection to the requested host.
+fn tunnel(shared: Arc<Shared>, request: Request<Incoming>) -> Response<Full<Bytes>> {
+  let Some(authority) = request.uri().authority().map(|authority| authority.to_string()) else {
+    return error_response(StatusCode::BAD_REQUEST, "CONNECT needs a host and port");
+  };
+  let host = request.uri().host().unwrap_or_default().to_string();
+  let mut tunnelled = lock(&shared.tunnelled);
+  if !tunnelled.contains(&host) {
+    log::debug!("[proxy] tunnelling to {authority} without recording");
+    tunnelled.push(host);
+  }
+  drop(tunnelled);
+
+  let shutdown = shared.shutdown.clone();
+  tokio::spawn(async move {
+    let splice = async {
+      let upgraded = hyper::upgrade::on(request).await.map_err(|err| err.to_string())?;
+      let mut server = TcpStream::connect(authority.as_str()).await.map_err(|err| err.to_string())?;
+      tokio::io::copy_bidirectional(&mut TokioIo::new(upgraded), &mut server)
+        .await
+        .map_err(|err| err.to_string())
+    };
+    if let Ok(Err(err)) = shutdown.run(splice).await {
+      log::debug!("[proxy] tunnel to {authority} failed: {err}");
+    }
+  });
+  Response::new(Full::default())
+}
+
+fn har_entry(
+  started: chrono::DateTime<Utc>,
+  time_ms: f64,
+  (method, url, headers, body): (&Method, &Url, &HeaderMap, &Bytes),
+  response: &Response<Bytes>,
+) -> Value {
+  let mut request = json!({
+    "method": method.as_str(),
+    "url": url.as_str(),
+    "httpVersion": "HTTP/1.1",
+    "headers": har_headers(headers),
+    "queryString": url.query_pairs().map(|(name, value)| json!({ "name": name, "value": value })).collect::<Vec<_>>(),
+  });
+  if !body.is_empty() {
+    request["postData"] = json!({
+      "mimeType": header(headers, CONTENT_TYPE),
+      "text": String::from_utf8_lossy(body),
+    });
+  }
+
+  let body = response.body();
+  let mut content = json!({ "size": body.len(), "mimeType": header(response.headers(), CONTENT_TYPE) });
+  match std::str::from_utf8(body) {
+    Ok(text) => content["text"] = Value::from(text),
+    Err(_) => {
+      content["text"] = Value::from(STANDARD.encode(body));
+      content["encoding"] = Value::from("base64");
+    }
+  }
+  json!({
+    "startedDateTime": started.to_rfc3339_opts(SecondsFormat::Millis, true),
+    "time": time_ms,
+    "request": request,
+    "response": {
+      "status": response.status().as_u16(),
+      "statusText": response.status().canonical_reason().unwrap_or_default(),
+      "httpVersion": "HTTP/1.1",
+      "headers": har_headers(response.headers()),
+      "redirectURL": header(response.headers(), LOCATION),
+      "content": content,
+    },
+  })
+}
+
+fn har_headers(headers: &HeaderMap) -> Vec<Value> {
+  headers
+    .iter()
+    .map(|(name, value)| json!({ "name": name.as_str(), "value": String::from_utf8_lossy(value.as_bytes()) }))
+    .collect()
+}
+
+fn header(headers: &HeaderMap, name: http::header::HeaderName) -> String {
+  headers
+    .get(name)
+    .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+    .unwrap_or_default()
+}
+
+fn without_hop_by_hop(headers: &HeaderMap) -> HeaderMap {
+  let mut headers = headers.clone();
+  for name in HOP_BY_HOP_HEADERS {
+    headers.remove(*name);
+  }
+  headers
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+  let mut response = Response::new(Full::new(Bytes::from(message.to_string())));
+  *response.status_mut() = status;
+  response
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+  mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Running recording proxies keyed by port, held as Tauri managed state.
+#[derive(Default)]
+pub struct RecordingProxies {
+  proxies: Mutex<HashMap<u16, RecordingProxy>>,
+}
+
+impl RecordingProxies {
+  pub fn insert(&self, proxy: RecordingProxy) -> ProxyInfo {
+    let info = proxy.info();
+    lock(&self.proxies).insert(proxy.port, proxy);
+    info
+  }
+
+  /// Stop the proxy on `port`, discarding its recording; `false` if none
+  /// runs there.
+  pub fn stop(&self, port: u16) -> bool {
+    lock(&self.proxies).remove(&port).is_some()
+  }
+
+  pub fn list(&self) -> Vec<ProxyInfo> {
+    let mut proxies: Vec<ProxyInfo> = lock(&self.proxies).values().map(RecordingProxy::info).collect();
+    proxies.sort_by_key(|info| info.port);
+    proxies
+  }
+
+  /// Run `f` on the proxy listening on `port`.
+  pub fn with<T>(&self, port: u16, f: impl FnOnce(&RecordingProxy) -> Result<T>) -> Result<T> {
+    let proxies = lock(&self.proxies);
+    let proxy = proxies
+      .get(&port)
+      .ok_or_else(|| Error::NotFound(format!("no recording proxy on port {port}")))?;
+    f(proxy)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support;
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+  /// Answers every request with `{"id":"user-4711"}`.
+  async fn upstream() -> u16 {
+    test_support::serve(|stream| async move {
+      let service = service_fn(|_: Request<Incoming>| async {
+        let mut response = Response::new(Full::new(Bytes::from_static(br#"{"id":"user-4711"}"#)));
+        response.headers_mut().insert(CONTENT_TYPE, "application/json".parse().unwrap());
+        Ok::<_, Infallible>(response)
+      });
+      let _ = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await;
+    })
+    .await
+  }
+
+  async fn send(port: u16, request: &str) -> String {
+    let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+  }
+
+  #[tokio::test]
+  async fn records_forwarded_requests_into_a_flow() {
+    let upstream = upstream().await;
+    let proxies = RecordingProxies::default();
+    let options = ProxyOptions {
+      hosts: vec!["127.0.0.1".to_string()],
+      target: Some(format!("http://127.0.0.1:{upstream}/api")),
+    };
+    let info = proxies.insert(RecordingProxy::start(0, options).await.unwrap());
+
+    let forwarded = format!("POST http://127.0.0.1:{upstream}/api/users HTTP/1.1\r\nHost: 127.0.0.1\r\nContent-Type: application/json\r\nContent-Length: 12\r\nConnection: close\r\n\r\n{{\"name\":\"a\"}}");
+    let response = send(info.port, &forwarded).await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.ends_with(r#"{"id":"user-4711"}"#), "{response}");
+    let direct = send(info.port, "GET /users/user-4711 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await;
+    assert!(direct.starts_with("HTTP/1.1 200"), "{direct}");
+
+    let known = [KnownEndpoint::from_record(json!({ "id": 8, "apiId": 2, "path": "/users/{userId}", "method": "GET" })).unwrap()];
+    let import = proxies.with(info.port, |proxy| proxy.flow(None, &known)).unwrap();
+    assert_eq!(import.name, "Recorded session");
+    let flow = &import.flows[0].flow;
+    assert_eq!(flow.steps.len(), 2);
+    assert_eq!(flow.steps[0].endpoints[0].body, Some(json!({ "name": "a" })));
+    let lookup = &flow.steps[1].endpoints[0];
+    assert_eq!((lookup.api_id.as_str(), lookup.endpoint_id.as_str()), ("2", "8"));
+    assert_eq!(lookup.path_params["userId"], json!("{{res:step1-0.$.id}}"));
+    assert_eq!(flow.settings.api_hosts["2"].url, format!("http://127.0.0.1:{upstream}/api"));
+
+    assert!(proxies.stop(info.port));
+    assert!(proxies.with(info.port, |proxy| Ok(proxy.info())).is_err());
+  }
+}
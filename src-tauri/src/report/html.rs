@@ -0,0 +1,490 @@
+//! A single-file HTML report of a run, for sharing it outside the app.
+//!
+//! The report holds what `FlowLogsViewer` and `ResponseViewer` show: every
+//! endpoint's request and response, assertions, transformation outputs and
+//! timings, with the styles inlined so the file opens anywhere. Sensitive
+//! headers are redacted as in the run history, and so are parameters whose
+//! names look like secrets, by the pattern `parameter-manager.ts` uses for
+//! logs, wherever their values turn up.
+
+use std::fmt::Write;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::{escape, ReportDetails, RunReport};
+use crate::flow::runner::{EndpointResult, EndpointStatus, FlowRunResult, RunStatus};
+use crate::history::{is_sensitive_header, REDACTED};
+use crate::sequence::runner::SequenceFlowResult;
+
+/// Bodies longer than this many characters are cut short.
+const MAX_BODY_CHARS: usize = 100_000;
+
+/// Secret values shorter than this are only masked as parameters, not inside
+/// other text, where they would match by accident.
+const MIN_MASKED_LENGTH: usize = 4;
+
+const STYLE: &str = "
+body { font: 14px/1.5 system-ui, sans-serif; color: #1f2328; margin: 0 auto; max-width: 1100px; padding: 24px; }
+h1 { margin: 0 0 8px; } h2 { margin: 32px 0 8px; } h3 { margin: 20px 0 8px; } h4 { margin: 16px 0 4px; }
+code, pre { font: 12px/1.4 ui-monospace, monospace; }
+pre { background: #f6f8fa; border-radius: 6px; padding: 8px; overflow: auto; max-height: 400px; white-space: pre-wrap; word-break: break-all; }
+table { border-collapse: collapse; width: 100%; margin: 4px 0; }
+th, td { border: 1px solid #d0d7de; padding: 4px 8px; text-align: left; vertical-align: top; word-break: break-all; }
+th { background: #f6f8fa; font-weight: 600; }
+dl.meta { display: grid; grid-template-columns: max-content 1fr; gap: 2px 16px; margin: 12px 0; }
+dl.meta dt { color: #656d76; } dl.meta dd { margin: 0; }
+details.endpoint { border: 1px solid #d0d7de; border-radius: 6px; margin: 8px 0; }
+details.endpoint > summary { cursor: pointer; padding: 8px; }
+details.endpoint > div { padding: 0 12px 12px; }
+.badge { display: inline-block; border-radius: 10px; padding: 0 8px; font-size: 12px; font-weight: 600; color: #fff; }
+.completed, .passed { background: #1a7f37; } .failed { background: #cf222e; } .cancelled, .running { background: #9a6700; }
+.muted { color: #656d76; } .error { color: #cf222e; }
+";
+
+/// The report for whichever kind of run `report` holds.
+pub fn render(details: &ReportDetails, report: &RunReport) -> String {
+  match report {
+    RunReport::Flow(result) => flow_report(details, result),
+    RunReport::Sequence(results) => sequence_report(details, results),
+  }
+}
+
+pub fn flow_report(details: &ReportDetails, result: &FlowRunResult) -> String {
+  let masker = Masker::new([result]);
+  let mut content = String::new();
+  write_flow(&mut content, &masker, result);
+  document(details, result.status.as_str(), &[result], &content)
+}
+
+pub fn sequence_report(details: &ReportDetails, results: &[SequenceFlowResult]) -> String {
+  let runs: Vec<&FlowRunResult> = results.iter().flat_map(flow_runs).collect();
+  let masker = Masker::new(runs.iter().copied());
+
+  let mut content = String::new();
+  for flow in results {
+    let expectation = if flow.expects_error { " · expected to fail" } else { "" };
+    let _ = writeln!(
+      content,
+      "<h2>flow_{} <span class=\"muted\">test flow {}{expectation}</span> {}</h2>",
+      flow.step_order,
+      flow.test_flow_id,
+      badge(if flow.matched_expectation { "passed" } else { "failed" })
+    );
+    if let Some(error) = &flow.error {
+      let _ = writeln!(content, "<p class=\"error\">{}</p>", masker.text(error));
+    }
+    match (&flow.loop_result, &flow.flow) {
+      (Some(loop_result), _) => {
+        for iteration in &loop_result.iterations {
+          let _ = writeln!(
+            content,
+            "<h3>{} {}</h3>",
+            masker.text(&iteration.label),
+            badge(if iteration.matched_expectation { "passed" } else { "failed" })
+          );
+          write_flow(&mut content, &masker, &iteration.flow);
+        }
+      }
+      (None, Some(run)) => write_flow(&mut content, &masker, run),
+      (None, None) => {}
+    }
+  }
+
+  let status = if results.iter().all(|flow| flow.matched_expectation) {
+    RunStatus::Completed
+  } else if runs.iter().any(|run| run.status == RunStatus::Cancelled) {
+    RunStatus::Cancelled
+  } else {
+    RunStatus::Failed
+  };
+  document(details, status.as_str(), &runs, &content)
+}
+
+fn flow_runs(flow: &SequenceFlowResult) -> Vec<&FlowRunResult> {
+  match &flow.loop_result {
+    Some(loop_result) => loop_result.iterations.iter().map(|iteration| &iteration.flow).collect(),
+    None => flow.flow.iter().collect(),
+  }
+}
+
+fn document(details: &ReportDetails, status: &str, runs: &[&FlowRunResult], content: &str) -> String {
+  let endpoints = runs.iter().flat_map(|run| &run.endpoints);
+  let total = endpoints.clone().count();
+  let passed = endpoints.clone().filter(|endpoint| endpoint.status == EndpointStatus::Completed).count();
+  let time_ms: f64 = endpoints.filter_map(|endpoint| endpoint.timing.as_ref()).map(|timing| timing.total_ms).sum();
+  let name = escape(&details.name);
+  let optional = |value: &Option<String>| value.as_deref().map_or_else(|| "none".to_string(), escape);
+
+  let mut html = String::new();
+  let _ = write!(
+    html,
+    "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{name} · run report</title>
+<style>{STYLE}</style>
+</head>
+<body>
+<header>
+<h1>{name} {}</h1>
+<dl class=\"meta\">
+<dt>Environment</dt><dd>{}</dd>
+<dt>Sub-environment</dt><dd>{}</dd>
+<dt>Endpoints</dt><dd>{passed}/{total} passed</dd>
+<dt>Request time</dt><dd>{}</dd>
+<dt>Generated</dt><dd>{}</dd>
+</dl>
+</header>
+<main>
+{content}</main>
+</body>
+</html>
+",
+    badge(status),
+    optional(&details.environment),
+    optional(&details.sub_environment),
+    millis(time_ms),
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+  );
+  html
+}
+
+fn write_flow(html: &mut String, masker: &Masker, result: &FlowRunResult) {
+  if let Some(error) = &result.error {
+    let _ = writeln!(html, "<p class=\"error\">{}</p>", masker.text(error));
+  }
+  if !result.missing_parameters.is_empty() {
+    let _ = writeln!(
+      html,
+      "<p class=\"error\">Missing required parameters: {}</p>",
+      masker.text(&result.missing_parameters.join(", "))
+    );
+  }
+  if !result.parameter_values.is_empty() {
+    html.push_str("<h4>Parameters</h4>\n");
+    let rows = result
+      .parameter_values
+      .iter()
+      .map(|(name, value)| vec![escape(name), masker.parameter(name, value)]);
+    table(html, &["Name", "Value"], rows);
+  }
+  if !result.flow_outputs.is_empty() {
+    html.push_str("<h4>Outputs</h4>\n");
+    let rows = result
+      .flow_outputs
+      .iter()
+      .map(|(name, value)| vec![escape(name), masker.parameter(name, value)]);
+    table(html, &["Name", "Value"], rows);
+  }
+
+  let mut step = None;
+  for endpoint in &result.endpoints {
+    if step != Some(endpoint.step_id.as_str()) {
+      step = Some(endpoint.step_id.as_str());
+      let _ = writeln!(html, "<h3>Step {}</h3>", escape(&endpoint.step_id));
+    }
+    write_endpoint(html, masker, endpoint, result.stored_transformations.get(&endpoint.endpoint_id));
+  }
+}
+
+fn write_endpoint(html: &mut String, masker: &Masker, endpoint: &EndpointResult, transformations: Option<&Value>) {
+  let status = match endpoint.status {
+    EndpointStatus::Running => "running",
+    EndpointStatus::Completed => "completed",
+    EndpointStatus::Failed => "failed",
+    EndpointStatus::Cancelled => "cancelled",
+  };
+  let (method, url) = endpoint
+    .request
+    .as_ref()
+    .map_or(("", ""), |request| (request.method.as_str(), request.url.as_str()));
+  let response_status = endpoint
+    .response
+    .as_ref()
+    .map_or_else(|| "no response".to_string(), |response| response.status.to_string());
+  let time = endpoint.timing.as_ref().map_or_else(String::new, |timing| format!(" · {}", millis(timing.total_ms)));
+  let _ = writeln!(
+    html,
+    "<details class=\"endpoint\"{}>\n<summary>{} <code>{}</code> <b>{}</b> {} <span class=\"muted\">→ {response_status}{time}</span></summary>\n<div>",
+    if endpoint.status == EndpointStatus::Completed { "" } else { " open" },
+    badge(status),
+    escape(&endpoint.endpoint_id),
+    escape(method),
+    masker.text(url),
+  );
+
+  if let Some(error) = &endpoint.error {
+    let _ = writeln!(html, "<p class=\"error\">{}</p>", masker.text(error));
+  }
+  if let Some(timing) = &endpoint.timing {
+    html.push_str("<h4>Timing</h4>\n");
+    let phases = [
+      timing.dns_ms,
+      timing.connect_ms,
+      timing.tls_ms,
+      timing.ttfb_ms,
+      timing.download_ms,
+      timing.redirect_ms,
+      timing.total_ms,
+    ];
+    table(
+      html,
+      &["DNS", "Connect", "TLS", "Waiting", "Download", "Redirects", "Total"],
+      [phases.iter().map(|ms| millis(*ms)).collect()],
+    );
+  }
+  if let Some(request) = &endpoint.request {
+    html.push_str("<h4>Request</h4>\n");
+    let mut headers: Vec<(&String, &String)> = request.headers.iter().collect();
+    headers.sort();
+    let rows = headers.into_iter().map(|(name, value)| vec![escape(name), masker.header(name, value)]);
+    table(html, &["Header", "Value"], rows);
+    if let Some(body) = &request.body {
+      let _ = writeln!(html, "<pre>{}</pre>", masker.body(body));
+    }
+  }
+  if let Some(response) = &endpoint.response {
+    let _ = writeln!(
+      html,
+      "<h4>Response</h4>\n<p>{} {}</p>",
+      response.status,
+      escape(&response.status_text)
+    );
+    let rows = response.headers.iter().map(|(name, value)| {
+      let value = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+      vec![escape(name), masker.header(name, &value)]
+    });
+    table(html, &["Header", "Value"], rows);
+    let _ = writeln!(html, "<pre>{}</pre>", masker.body(&response.body));
+  }
+  if !endpoint.assertions.is_empty() {
+    html.push_str("<h4>Assertions</h4>\n");
+    let rows = endpoint.assertions.iter().map(|assertion| {
+      vec![
+        badge(if assertion.passed { "passed" } else { "failed" }),
+        masker.text(&assertion.message),
+        masker.value(&assertion.expected_value),
+        masker.value(&assertion.actual_value),
+      ]
+    });
+    table(html, &["Result", "Assertion", "Expected", "Actual"], rows);
+  }
+  if let Some(Value::Object(transformations)) = transformations {
+    if !transformations.is_empty() {
+      html.push_str("<h4>Transformations</h4>\n");
+      let rows = transformations
+        .iter()
+        .map(|(alias, value)| vec![escape(alias), masker.parameter(alias, value)]);
+      table(html, &["Alias", "Value"], rows);
+    }
+  }
+  html.push_str("</div>\n</details>\n");
+}
+
+/// A table of cells that are already escaped; nothing without rows.
+fn table(html: &mut String, headers: &[&str], rows: impl IntoIterator<Item = Vec<String>>) {
+  let mut rows = rows.into_iter().peekable();
+  if rows.peek().is_none() {
+    return;
+  }
+  html.push_str("<table>\n<tr>");
+  for header in headers {
+    let _ = write!(html, "<th>{header}</th>");
+  }
+  html.push_str("</tr>\n");
+  for row in rows {
+    html.push_str("<tr>");
+    for cell in row {
+      let _ = write!(html, "<td>{cell}</td>");
+    }
+    html.push_str("</tr>\n");
+  }
+  html.push_str("</table>\n");
+}
+
+fn badge(status: &str) -> String {
+  format!("<span class=\"badge {status}\">{status}</span>")
+}
+
+fn millis(ms: f64) -> String {
+  format!("{ms:.1} ms")
+}
+
+/// Port of `SENSITIVE_PARAMETER_NAME_PATTERN` in `parameter-manager.ts`.
+fn is_secret_parameter(name: &str) -> bool {
+  static PATTERN: OnceLock<Regex> = OnceLock::new();
+  PATTERN
+    .get_or_init(|| {
+      Regex::new(
+        r"(?i)(^|[_\-.])(auth|authorization|cookie|credential|key|pass|password|secret|session|token)([_\-.]|$)|api[_\-.]?key",
+      )
+      .expect("valid pattern")
+    })
+    .is_match(name)
+}
+
+/// Masks secrets and escapes whatever goes into the report.
+struct Masker {
+  /// Values of secret parameters, outputs and transformations, longest
+  /// first.
+  secrets: Vec<String>,
+}
+
+impl Masker {
+  fn new<'a>(runs: impl IntoIterator<Item = &'a FlowRunResult>) -> Self {
+    let mut secrets: Vec<String> = runs
+      .into_iter()
+      .flat_map(|run| {
+        let transformations = run.stored_transformations.values().filter_map(Value::as_object).flatten();
+        run.parameter_values.iter().chain(&run.flow_outputs).chain(transformations)
+      })
+      .filter(|(name, _)| is_secret_parameter(name))
+      .map(|(_, value)| plain(value))
+      .filter(|value| value.chars().count() >= MIN_MASKED_LENGTH)
+      .collect();
+    secrets.sort();
+    secrets.dedup();
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    Self { secrets }
+  }
+
+  fn text(&self, text: &str) -> String {
+    let mut masked = text.to_string();
+    for secret in &self.secrets {
+      if masked.contains(secret.as_str()) {
+        masked = masked.replace(secret.as_str(), REDACTED);
+      }
+    }
+    escape(&masked)
+  }
+
+  fn value(&self, value: &Value) -> String {
+    self.text(&plain(value))
+  }
+
+  fn body(&self, body: &Value) -> String {
+    let text = match body {
+      Value::String(text) => text.clone(),
+      value => serde_json::to_string_pretty(value).unwrap_or_default(),
+    };
+    match text.char_indices().nth(MAX_BODY_CHARS) {
+      Some((cut, _)) => format!(
+        "{}\n… ({} more characters)",
+        self.text(&text[..cut]),
+        text[cut..].chars().count()
+      ),
+      None => self.text(&text),
+    }
+  }
+
+  fn header(&self, name: &str, value: &str) -> String {
+    if is_sensitive_header(name) {
+      REDACTED.to_string()
+    } else {
+      self.text(value)
+    }
+  }
+
+  fn parameter(&self, name: &str, value: &Value) -> String {
+    if is_secret_parameter(name) {
+      REDACTED.to_string()
+    } else {
+      self.value(value)
+    }
+  }
+}
+
+/// Strings as they are, anything else as JSON.
+fn plain(value: &Value) -> String {
+  match value {
+    Value::String(text) => text.clone(),
+    value => value.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn details() -> ReportDetails {
+    ReportDetails {
+      name: "Login <v2>".to_string(),
+      environment: Some("Shop API".to_string()),
+      sub_environment: Some("sit".to_string()),
+    }
+  }
+
+  #[test]
+  fn renders_endpoints_with_secrets_masked() {
+    let result: FlowRunResult = serde_json::from_value(json!({
+      "success": false,
+      "status": "failed",
+      "endpoints": [{
+        "endpointId": "s1-0",
+        "stepId": "s1",
+        "status": "failed",
+        "request": {
+          "url": "https://api.test/login?token=tok-123456",
+          "method": "POST",
+          "headers": { "Authorization": "Bearer abc", "Accept": "application/json" },
+          "body": { "user": "ann", "password": "hunter22" }
+        },
+        "response": {
+          "status": 401,
+          "statusText": "Unauthorized",
+          "headers": { "set-cookie": "sid=1", "content-type": "application/json" },
+          "body": { "error": "bad <credentials>" }
+        },
+        "timing": {
+          "startedAt": 0, "dnsMs": 1.0, "connectMs": 2.0, "tlsMs": 0.0,
+          "ttfbMs": 30.0, "downloadMs": 1.0, "redirectMs": 0.0, "totalMs": 34.0
+        },
+        "assertions": [
+          { "passed": false, "actualValue": 401, "expectedValue": 200, "message": "status_code equals 200" }
+        ],
+        "error": "Request failed with status 401"
+      }],
+      "storedResponses": {},
+      "storedTransformations": { "s1-0": { "reason": "bad credentials", "access_token": "at-98765" } },
+      "parameterValues": { "api_token": "tok-123456", "password": "hunter22", "user": "ann" },
+      "flowOutputs": { "session": "sid=1" },
+      "missingParameters": [],
+      "error": null
+    }))
+    .unwrap();
+
+    let html = flow_report(&details(), &result);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Login &lt;v2&gt; · run report</title>"));
+    assert!(html.contains("<dt>Sub-environment</dt><dd>sit</dd>"));
+    assert!(html.contains("<dt>Endpoints</dt><dd>0/1 passed</dd>"));
+    assert!(html.contains("<details class=\"endpoint\" open>"));
+    assert!(html.contains("https://api.test/login?token=[redacted]"));
+    assert!(html.contains("<td>user</td><td>ann</td>"));
+    assert!(html.contains("bad &lt;credentials&gt;"));
+    assert!(html.contains("<td>reason</td><td>bad credentials</td>"));
+    assert!(html.contains("status_code equals 200"));
+    assert!(html.contains("<td>34.0 ms</td>"));
+    assert!(html.contains("<td>set-cookie</td><td>[redacted]</td>"));
+    assert!(html.contains("<td>session</td><td>[redacted]</td>"));
+    assert!(html.contains("<td>access_token</td><td>[redacted]</td>"));
+    for secret in ["tok-123456", "hunter22", "Bearer abc", "sid=1", "at-98765"] {
+      assert!(!html.contains(secret), "{secret} leaked");
+    }
+  }
+
+  #[test]
+  fn recognises_secret_parameter_names() {
+    for name in ["token", "api_key", "apiKey", "X-Auth-Token", "db.password", "session_id"] {
+      assert!(is_secret_parameter(name), "{name}");
+    }
+    for name in ["user", "keyboard", "monkey", "author"] {
+      assert!(!is_secret_parameter(name), "{name}");
+    }
+  }
+}
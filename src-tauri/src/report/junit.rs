@@ -0,0 +1,364 @@
+//! JUnit XML, in the dialect Jenkins, GitLab and most CI dashboards read.
+//!
+//! A flow run becomes a `<testsuite>` per step with a `<testcase>` per
+//! endpoint. Failed assertions and error responses are `<failure>`s; requests
+//! that never got a response are `<error>`s. A sequence run gets the suites
+//! of each of its flows, per loop iteration where a step loops, named after
+//! the flow's step order (`flow_2 / s1`). A flow that is expected to fail is
+//! a single testcase passing when it did.
+
+use std::fmt::Write;
+
+use super::{escape, RunReport};
+use crate::flow::runner::{EndpointResult, EndpointStatus, FlowRunResult, RunStatus};
+use crate::sequence::runner::SequenceFlowResult;
+
+/// The report for whichever kind of run `report` holds.
+pub fn render(name: &str, report: &RunReport) -> String {
+  match report {
+    RunReport::Flow(result) => flow_report(name, result),
+    RunReport::Sequence(results) => sequence_report(name, results),
+  }
+}
+
+pub fn flow_report(name: &str, result: &FlowRunResult) -> String {
+  to_xml(name, &flow_suites("", result))
+}
+
+pub fn sequence_report(name: &str, results: &[SequenceFlowResult]) -> String {
+  let mut suites = Vec::new();
+  for flow in results {
+    let flow_name = format!("flow_{}", flow.step_order);
+    let iterations = flow.loop_result.as_ref().map(|result| result.iterations.as_slice()).unwrap_or_default();
+    if !iterations.is_empty() {
+      for iteration in iterations {
+        let label = if iteration.label.is_empty() {
+          format!("iteration_{}", iteration.index)
+        } else {
+          iteration.label.clone()
+        };
+        suites.extend(sequence_flow_suites(
+          &format!("{flow_name} / {label}"),
+          flow.expects_error,
+          iteration.matched_expectation,
+          &iteration.flow,
+        ));
+      }
+    } else if let Some(run) = &flow.flow {
+      suites.extend(sequence_flow_suites(&flow_name, flow.expects_error, flow.matched_expectation, run));
+    } else {
+      // Failing before the flow ran still counts for a flow expected to fail.
+      let case = if flow.expects_error && flow.matched_expectation {
+        Case::new(&flow_name, "expects error", 0.0, Outcome::Passed)
+      } else {
+        let message = flow.error.clone().unwrap_or_else(|| "the flow did not run".to_string());
+        Case::new(&flow_name, "run", 0.0, Outcome::Error(message))
+      };
+      suites.push(Suite {
+        cases: vec![case],
+        name: flow_name,
+      });
+    }
+  }
+  to_xml(name, &suites)
+}
+
+struct Suite {
+  name: String,
+  cases: Vec<Case>,
+}
+
+struct Case {
+  classname: String,
+  name: String,
+  time_ms: f64,
+  outcome: Outcome,
+}
+
+impl Case {
+  fn new(classname: &str, name: &str, time_ms: f64, outcome: Outcome) -> Self {
+    Self {
+      classname: classname.to_string(),
+      name: name.to_string(),
+      time_ms,
+      outcome,
+    }
+  }
+}
+
+enum Outcome {
+  Passed,
+  /// A message and, for assertions, every failed assertion's message.
+  Failure(String, Option<String>),
+  Error(String),
+  Skipped(String),
+}
+
+/// One suite per step, in the order the steps ran, prefixed with `prefix`.
+fn flow_suites(prefix: &str, result: &FlowRunResult) -> Vec<Suite> {
+  let mut suites: Vec<Suite> = Vec::new();
+  for endpoint in &result.endpoints {
+    let name = format!("{prefix}{}", endpoint.step_id);
+    let case = endpoint_case(&name, endpoint);
+    match suites.last_mut() {
+      Some(suite) if suite.name == name => suite.cases.push(case),
+      _ => suites.push(Suite { name, cases: vec![case] }),
+    }
+  }
+  if suites.is_empty() {
+    if let Some(error) = &result.error {
+      let name = format!("{prefix}run");
+      suites.push(Suite {
+        cases: vec![Case::new(&name, "run", 0.0, Outcome::Error(error.clone()))],
+        name,
+      });
+    }
+  }
+  suites
+}
+
+fn sequence_flow_suites(name: &str, expects_error: bool, matched_expectation: bool, run: &FlowRunResult) -> Vec<Suite> {
+  if !expects_error {
+    return flow_suites(&format!("{name} / "), run);
+  }
+  let outcome = if matched_expectation {
+    Outcome::Passed
+  } else if run.status == RunStatus::Cancelled {
+    Outcome::Skipped("run cancelled".to_string())
+  } else {
+    Outcome::Failure("expected the flow to fail, but it succeeded".to_string(), None)
+  };
+  let time_ms = run.endpoints.iter().filter_map(|endpoint| endpoint.timing.as_ref()).map(|timing| timing.total_ms).sum();
+  vec![Suite {
+    name: name.to_string(),
+    cases: vec![Case::new(name, "expects error", time_ms, outcome)],
+  }]
+}
+
+fn endpoint_case(classname: &str, endpoint: &EndpointResult) -> Case {
+  let name = match &endpoint.request {
+    Some(request) => format!("{} {} {}", endpoint.endpoint_id, request.method, request.url),
+    None => endpoint.endpoint_id.clone(),
+  };
+  let error = || endpoint.error.clone().unwrap_or_else(|| "request failed".to_string());
+  let outcome = match endpoint.status {
+    EndpointStatus::Completed => Outcome::Passed,
+    EndpointStatus::Running | EndpointStatus::Cancelled => {
+      Outcome::Skipped(endpoint.error.clone().unwrap_or_else(|| "run cancelled".to_string()))
+    }
+    EndpointStatus::Failed => {
+      let failed: Vec<&str> = endpoint
+        .assertions
+        .iter()
+        .filter(|assertion| !assertion.passed)
+        .map(|assertion| assertion.message.as_str())
+        .collect();
+      if let Some(first) = failed.first() {
+        Outcome::Failure(first.to_string(), Some(failed.join("\n")))
+      } else if endpoint.response.is_some() {
+        Outcome::Failure(error(), None)
+      } else {
+        Outcome::Error(error())
+      }
+    }
+  };
+  let time_ms = endpoint.timing.as_ref().map_or(0.0, |timing| timing.total_ms);
+  Case::new(classname, &name, time_ms, outcome)
+}
+
+#[derive(Default)]
+struct Counts {
+  tests: usize,
+  failures: usize,
+  errors: usize,
+  skipped: usize,
+  time_ms: f64,
+}
+
+impl Counts {
+  fn add(&mut self, cases: &[Case]) {
+    for case in cases {
+      self.tests += 1;
+      self.time_ms += case.time_ms;
+      match case.outcome {
+        Outcome::Passed => {}
+        Outcome::Failure(..) => self.failures += 1,
+        Outcome::Error(_) => self.errors += 1,
+        Outcome::Skipped(_) => self.skipped += 1,
+      }
+    }
+  }
+
+  fn attributes(&self) -> String {
+    format!(
+      r#"tests="{}" failures="{}" errors="{}" skipped="{}" time="{}""#,
+      self.tests,
+      self.failures,
+      self.errors,
+      self.skipped,
+      seconds(self.time_ms)
+    )
+  }
+}
+
+fn to_xml(name: &str, suites: &[Suite]) -> String {
+  let mut total = Counts::default();
+  suites.iter().for_each(|suite| total.add(&suite.cases));
+
+  // Writing to a String cannot fail.
+  let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+  let _ = writeln!(xml, r#"<testsuites name="{}" {}>"#, escape(name), total.attributes());
+  for suite in suites {
+    let mut counts = Counts::default();
+    counts.add(&suite.cases);
+    let _ = writeln!(xml, r#"  <testsuite name="{}" {}>"#, escape(&suite.name), counts.attributes());
+    for case in &suite.cases {
+      let _ = write!(
+        xml,
+        r#"    <testcase classname="{}" name="{}" time="{}""#,
+        escape(&case.classname),
+        escape(&case.name),
+        seconds(case.time_ms)
+      );
+      match &case.outcome {
+        Outcome::Passed => xml.push_str("/>\n"),
+        Outcome::Failure(message, details) => {
+          let _ = write!(xml, ">\n      <failure message=\"{}\"", escape(message));
+          match details {
+            Some(details) => {
+              let _ = writeln!(xml, ">{}</failure>", escape(details));
+            }
+            None => xml.push_str("/>\n"),
+          }
+          xml.push_str("    </testcase>\n");
+        }
+        Outcome::Error(message) => {
+          let _ = writeln!(xml, ">\n      <error message=\"{}\"/>\n    </testcase>", escape(message));
+        }
+        Outcome::Skipped(message) => {
+          let _ = writeln!(xml, ">\n      <skipped message=\"{}\"/>\n    </testcase>", escape(message));
+        }
+      }
+    }
+    xml.push_str("  </testsuite>\n");
+  }
+  xml.push_str("</testsuites>\n");
+  xml
+}
+
+fn seconds(ms: f64) -> String {
+  format!("{:.3}", ms / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::{json, Value};
+
+  fn endpoint(endpoint_id: &str, status: &str, extra: Value) -> Value {
+    let mut endpoint = json!({
+      "endpointId": endpoint_id,
+      "stepId": endpoint_id.split('-').next().unwrap(),
+      "status": status,
+      "request": { "url": "https://api.test/users?a=1&b=2", "method": "GET", "headers": {}, "body": null },
+      "response": null,
+      "timing": null,
+      "assertions": [],
+      "error": null
+    });
+    endpoint.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    endpoint
+  }
+
+  fn flow(endpoints: Vec<Value>, status: &str) -> Value {
+    json!({
+      "success": status == "completed",
+      "status": status,
+      "endpoints": endpoints,
+      "storedResponses": {},
+      "storedTransformations": {},
+      "parameterValues": {},
+      "flowOutputs": {},
+      "missingParameters": [],
+      "error": null
+    })
+  }
+
+  #[test]
+  fn reports_steps_as_suites_and_endpoints_as_cases() {
+    let timing = json!({
+      "startedAt": 0, "dnsMs": 0.0, "connectMs": 0.0, "tlsMs": 0.0,
+      "ttfbMs": 0.0, "downloadMs": 0.0, "redirectMs": 0.0, "totalMs": 250.0
+    });
+    let result: FlowRunResult = serde_json::from_value(flow(
+      vec![
+        endpoint("s1-0", "completed", json!({ "timing": timing })),
+        endpoint(
+          "s2-0",
+          "failed",
+          json!({
+            "response": { "status": 200, "statusText": "OK", "headers": {}, "body": null },
+            "assertions": [
+              { "passed": false, "actualValue": 1, "expectedValue": 2, "message": "count <should> be 2" }
+            ],
+            "error": "Assertion failed"
+          }),
+        ),
+        endpoint("s2-1", "failed", json!({ "error": "connection failed: refused" })),
+      ],
+      "failed",
+    ))
+    .unwrap();
+
+    let xml = flow_report("Login & profile", &result);
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    assert!(xml.contains(
+      r#"<testsuites name="Login &amp; profile" tests="3" failures="1" errors="1" skipped="0" time="0.250">"#
+    ));
+    assert!(xml.contains(r#"<testsuite name="s2" tests="2" failures="1" errors="1" skipped="0" time="0.000">"#));
+    assert!(xml.contains(
+      r#"<testcase classname="s1" name="s1-0 GET https://api.test/users?a=1&amp;b=2" time="0.250"/>"#
+    ));
+    assert!(xml.contains(r#"<failure message="count &lt;should&gt; be 2">count &lt;should&gt; be 2</failure>"#));
+    assert!(xml.contains(r#"<error message="connection failed: refused"/>"#));
+  }
+
+  #[test]
+  fn reports_sequence_flows_and_loop_iterations() {
+    let passed = flow(vec![endpoint("s1-0", "completed", json!({}))], "completed");
+    let results: Vec<SequenceFlowResult> = serde_json::from_value(json!([
+      {
+        "testFlowId": 7, "stepOrder": 1, "success": true, "expectsError": false, "matchedExpectation": true,
+        "outputs": {}, "responses": {}, "parameterValues": {}, "error": null, "executionTimeMs": 5,
+        "flow": null,
+        "loop": {
+          "totalIterations": 1, "completedIterations": 1, "failedIterationIndex": null,
+          "loopNames": {}, "sourceAliases": {},
+          "iterations": [{
+            "index": 0, "label": "users[1]: id=1", "path": [], "valuesByLoopId": {},
+            "success": true, "matchedExpectation": true, "error": null, "outputs": {},
+            "executionTimeMs": 5, "flow": passed
+          }]
+        }
+      },
+      {
+        "testFlowId": 8, "stepOrder": 2, "success": true, "expectsError": true, "matchedExpectation": false,
+        "outputs": {}, "responses": {}, "parameterValues": {}, "error": null, "executionTimeMs": 5,
+        "flow": passed
+      },
+      {
+        "testFlowId": 9, "stepOrder": 3, "success": false, "expectsError": false, "matchedExpectation": false,
+        "outputs": {}, "responses": {}, "parameterValues": {}, "error": "Flow 9 not found", "executionTimeMs": 0,
+        "flow": null
+      }
+    ]))
+    .unwrap();
+
+    let xml = sequence_report("Checkout", &results);
+    assert!(xml.contains(r#"tests="3" failures="1" errors="1""#));
+    assert!(xml.contains(r#"<testsuite name="flow_1 / users[1]: id=1 / s1""#));
+    assert!(xml.contains(r#"<testcase classname="flow_2" name="expects error""#));
+    assert!(xml.contains(r#"<failure message="expected the flow to fail, but it succeeded"/>"#));
+    assert!(xml.contains(r#"<error message="Flow 9 not found"/>"#));
+  }
+}
@@ -0,0 +1,51 @@
+//! Reports on finished runs for tools outside the app, such as CI
+//! dashboards.
+//!
+//! Every format takes either a [`FlowRunResult`] or the
+//! [`SequenceFlowResult`]s of a sequence run, wrapped in [`RunReport`] when
+//! it comes from the webview.
+
+pub mod html;
+pub mod junit;
+
+use serde::Deserialize;
+
+use crate::flow::runner::FlowRunResult;
+use crate::sequence::runner::SequenceFlowResult;
+
+/// What a report is about, beyond the run itself.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportDetails {
+  /// The flow or sequence name.
+  pub name: String,
+  pub environment: Option<String>,
+  pub sub_environment: Option<String>,
+}
+
+/// A finished run to report on, as sent by the webview.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", content = "result", rename_all = "lowercase")]
+pub enum RunReport {
+  Flow(Box<FlowRunResult>),
+  Sequence(Vec<SequenceFlowResult>),
+}
+
+/// Escape text for XML or HTML attributes and content, dropping the control
+/// characters XML 1.0 does not allow.
+fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      '\t' | '\n' | '\r' => escaped.push(c),
+      c if c < ' ' => {}
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
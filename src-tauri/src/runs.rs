@@ -0,0 +1,112 @@
+//! Cancellation for native flow and sequence runs.
+//!
+//! Every run registers a [`CancellationToken`] under its run ID in
+//! [`RunRegistry`]. Cancelling the token drops the run's in-flight requests
+//! at once instead of waiting for them to time out.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::sync::watch;
+
+use crate::error::{Error, Result};
+
+/// A cheaply cloned flag that wakes every waiter once it is set.
+#[derive(Debug, Clone)]
+pub struct CancellationToken(Arc<watch::Sender<bool>>);
+
+impl Default for CancellationToken {
+  fn default() -> Self {
+    Self(Arc::new(watch::channel(false).0))
+  }
+}
+
+impl CancellationToken {
+  pub fn cancel(&self) {
+    self.0.send_replace(true);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    *self.0.borrow()
+  }
+
+  /// Resolves once the token is cancelled.
+  pub async fn cancelled(&self) {
+    let mut receiver = self.0.subscribe();
+    // The sender lives as long as `self`, so this only returns on cancel.
+    let _ = receiver.wait_for(|cancelled| *cancelled).await;
+  }
+
+  /// Drive `future` to completion, or drop it with [`Error::Cancelled`] as
+  /// soon as the token is cancelled.
+  pub async fn run<T>(&self, future: impl Future<Output = T>) -> Result<T> {
+    tokio::select! {
+      biased;
+      _ = self.cancelled() => Err(Error::Cancelled),
+      output = future => Ok(output),
+    }
+  }
+}
+
+/// Tokens of all active runs, held as Tauri managed state.
+#[derive(Default)]
+pub struct RunRegistry {
+  runs: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl RunRegistry {
+  /// Start tracking `run_id`, replacing any earlier run with the same ID.
+  pub fn register(&self, run_id: &str) -> CancellationToken {
+    let token = CancellationToken::default();
+    self.lock().insert(run_id.to_string(), token.clone());
+    token
+  }
+
+  /// Cancel a run; `false` if no such run is active.
+  pub fn cancel(&self, run_id: &str) -> bool {
+    match self.lock().get(run_id) {
+      Some(token) => {
+        log::debug!("[runs] cancelling run {run_id}");
+        token.cancel();
+        true
+      }
+      None => false,
+    }
+  }
+
+  pub fn remove(&self, run_id: &str) -> bool {
+    self.lock().remove(run_id).is_some()
+  }
+
+  fn lock(&self) -> MutexGuard<'_, HashMap<String, CancellationToken>> {
+    self.runs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  #[tokio::test]
+  async fn cancel_interrupts_pending_work() {
+    let registry = RunRegistry::default();
+    let token = registry.register("run-1");
+    assert!(!token.is_cancelled());
+
+    let pending = token.run(tokio::time::sleep(Duration::from_secs(30)));
+    let cancel = async {
+      tokio::time::sleep(Duration::from_millis(10)).await;
+      assert!(registry.cancel("run-1"));
+    };
+    let (outcome, ()) = tokio::join!(pending, cancel);
+    assert!(matches!(outcome, Err(Error::Cancelled)));
+    assert!(token.is_cancelled());
+
+    // Already cancelled: the work is not even started.
+    assert!(matches!(token.run(async { 1 }).await, Err(Error::Cancelled)));
+    assert!(registry.remove("run-1"));
+    assert!(!registry.cancel("run-1"));
+  }
+}
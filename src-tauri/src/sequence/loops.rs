@@ -0,0 +1,308 @@
+//! Loop plans for sequence steps, ported from `resolveLoopPlan` in
+//! `src/lib/sequence-runner/parameter-resolver.ts`.
+//!
+//! A loop's sources are zipped into rows, and each row runs every child loop
+//! in full, so a plan flattens depth-first into one [`LoopContext`] per flow
+//! run.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{FlowLoopDefinition, FlowLoopSource};
+use crate::error::{Error, Result};
+use crate::template::js_string;
+use crate::transform::to_number;
+
+/// Upper bound on the runs one plan may expand to.
+pub const MAX_LOOP_ITERATIONS: usize = 100_000;
+
+/// One loop's row within an iteration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopIterationPath {
+  pub loop_id: String,
+  pub loop_name: String,
+  pub index: usize,
+  pub values_by_source_id: Map<String, Value>,
+  pub source_aliases: BTreeMap<String, String>,
+  /// `name[n]: alias=value, ...`, in source order.
+  #[serde(skip)]
+  label: String,
+}
+
+/// The values one run of a looped step sees, outermost loop first.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopContext {
+  pub path: Vec<LoopIterationPath>,
+  pub values_by_loop_id: BTreeMap<String, LoopIterationPath>,
+}
+
+impl LoopContext {
+  pub fn label(&self) -> String {
+    let labels: Vec<&str> = self.path.iter().map(|row| row.label.as_str()).collect();
+    labels.join(" / ")
+  }
+
+  /// The value of one loop source. Without ids this falls back to the only
+  /// source of the only loop, as the TS resolver does.
+  pub fn value(&self, loop_id: Option<&str>, source_id: Option<&str>) -> Option<&Value> {
+    let (loop_id, source_id) = match (loop_id.filter(|id| !id.is_empty()), source_id.filter(|id| !id.is_empty())) {
+      (Some(loop_id), Some(source_id)) => (loop_id, source_id),
+      _ => {
+        let loops = &self.values_by_loop_id;
+        let (loop_id, row) = loops.iter().next().filter(|_| loops.len() == 1)?;
+        let sources = &row.values_by_source_id;
+        let (source_id, _) = sources.iter().next().filter(|_| sources.len() == 1)?;
+        (loop_id.as_str(), source_id.as_str())
+      }
+    };
+    self.values_by_loop_id.get(loop_id)?.values_by_source_id.get(source_id)
+  }
+}
+
+/// Expand a loop tree into the contexts to run, in order.
+///
+/// `outputs` are the sequence's outputs so far, keyed `flow_{step_order}`.
+pub fn expand(
+  root: &FlowLoopDefinition,
+  outputs: &Map<String, Value>,
+  environment: &Map<String, Value>,
+) -> Result<Vec<LoopContext>> {
+  let mut contexts = Vec::new();
+  expand_into(root, outputs, environment, &LoopContext::default(), &mut contexts)?;
+  Ok(contexts)
+}
+
+fn expand_into(
+  definition: &FlowLoopDefinition,
+  outputs: &Map<String, Value>,
+  environment: &Map<String, Value>,
+  parent: &LoopContext,
+  contexts: &mut Vec<LoopContext>,
+) -> Result<()> {
+  let rows = rows(definition, outputs, environment)?;
+  for row in rows {
+    let mut context = parent.clone();
+    context.values_by_loop_id.insert(row.loop_id.clone(), row.clone());
+    context.path.push(row);
+
+    if definition.children.is_empty() {
+      if contexts.len() == MAX_LOOP_ITERATIONS {
+        return Err(Error::Sequence(format!(
+          "Loop plan has more than {MAX_LOOP_ITERATIONS} iterations"
+        )));
+      }
+      contexts.push(context);
+    } else {
+      for child in &definition.children {
+        expand_into(child, outputs, environment, &context, contexts)?;
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Zip the loop's sources into rows; every source must be the same length.
+fn rows(
+  definition: &FlowLoopDefinition,
+  outputs: &Map<String, Value>,
+  environment: &Map<String, Value>,
+) -> Result<Vec<LoopIterationPath>> {
+  let name = &definition.name;
+  if definition.sources.is_empty() {
+    return Err(Error::Sequence(format!("Loop '{name}' must have at least one source")));
+  }
+
+  let mut columns = Vec::with_capacity(definition.sources.len());
+  for source in &definition.sources {
+    columns.push((source, source_values(source, outputs, environment)?));
+  }
+  let expected = columns[0].1.len();
+  if let Some((source, values)) = columns.iter().find(|(_, values)| values.len() != expected) {
+    return Err(Error::Sequence(format!(
+      "Loop '{name}' zip source '{}' has {} value(s), expected {expected}",
+      source.alias,
+      values.len()
+    )));
+  }
+
+  let source_aliases: BTreeMap<String, String> = definition
+    .sources
+    .iter()
+    .map(|source| (source.id.clone(), source.alias.clone()))
+    .collect();
+  let rows = (0..expected)
+    .map(|index| {
+      let mut values_by_source_id = Map::new();
+      let mut labels = Vec::with_capacity(columns.len());
+      for (source, values) in &columns {
+        let alias = if source.alias.is_empty() { &source.id } else { &source.alias };
+        labels.push(format!("{alias}={}", js_string(&values[index])));
+        values_by_source_id.insert(source.id.clone(), values[index].clone());
+      }
+      LoopIterationPath {
+        loop_id: definition.id.clone(),
+        loop_name: name.clone(),
+        index,
+        values_by_source_id,
+        source_aliases: source_aliases.clone(),
+        label: format!("{name}[{}]: {}", index + 1, labels.join(", ")),
+      }
+    })
+    .collect();
+  Ok(rows)
+}
+
+fn source_values(
+  source: &FlowLoopSource,
+  outputs: &Map<String, Value>,
+  environment: &Map<String, Value>,
+) -> Result<Vec<Value>> {
+  let alias = &source.alias;
+  let source_value = source.source_value.as_deref().filter(|value| !value.is_empty());
+  match source.source_type.as_str() {
+    "fixed_count" => {
+      let count = source.count.as_ref().map_or(f64::NAN, to_number);
+      if count.fract() != 0.0 || count < 1.0 {
+        return Err(Error::Sequence(format!(
+          "Loop source '{alias}' count must be a positive integer"
+        )));
+      }
+      if count > MAX_LOOP_ITERATIONS as f64 {
+        return Err(Error::Sequence(format!(
+          "Loop plan has more than {MAX_LOOP_ITERATIONS} iterations"
+        )));
+      }
+      Ok((0..count as u64).map(Value::from).collect())
+    }
+    "environment_variable_array" => {
+      let Some(name) = source_value else {
+        return Err(Error::Sequence(format!(
+          "Loop source '{alias}' environment variable is required"
+        )));
+      };
+      primitive_array(environment.get(name), &format!("environment variable '{name}'"))
+    }
+    "previous_output_array" => {
+      let Some(step) = source.source_flow_step.filter(|step| *step != 0) else {
+        return Err(Error::Sequence(format!("Loop source '{alias}' source flow step is required")));
+      };
+      let Some(flow_outputs) = outputs.get(&format!("flow_{step}")) else {
+        return Err(Error::Sequence(format!("No outputs found from flow step {step}")));
+      };
+      let Some(field) = source.source_output_field.as_deref().filter(|field| !field.is_empty()).or(source_value)
+      else {
+        return Err(Error::Sequence(format!("Loop source '{alias}' output field is required")));
+      };
+      primitive_array(flow_outputs.get(field), &format!("output '{field}' from step {step}"))
+    }
+    other => Err(Error::Sequence(format!("Unknown loop source type: {other}"))),
+  }
+}
+
+fn primitive_array(value: Option<&Value>, description: &str) -> Result<Vec<Value>> {
+  let Some(Value::Array(items)) = value else {
+    return Err(Error::Sequence(format!("Loop source {description} must be an array")));
+  };
+  if !items
+    .iter()
+    .all(|item| matches!(item, Value::String(_) | Value::Number(_) | Value::Bool(_)))
+  {
+    return Err(Error::Sequence(format!(
+      "Loop source {description} must contain only string, number, or boolean values"
+    )));
+  }
+  Ok(items.clone())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn definition(value: Value) -> FlowLoopDefinition {
+    serde_json::from_value(value).unwrap()
+  }
+
+  #[test]
+  fn zips_sources_and_nests_children() {
+    let root = definition(json!({
+      "id": "users",
+      "name": "users",
+      "sources": [
+        { "id": "ids", "alias": "id", "source_type": "previous_output_array", "source_flow_step": 1, "source_output_field": "ids" },
+        { "id": "names", "alias": "name", "source_type": "environment_variable_array", "source_value": "NAMES" }
+      ],
+      "children": [
+        { "id": "pages", "name": "page", "sources": [{ "id": "n", "alias": "n", "source_type": "fixed_count", "count": "2" }] }
+      ]
+    }));
+    let outputs = json!({ "flow_1": { "ids": [7, 8] } });
+    let environment = json!({ "NAMES": ["ann", "bob"] });
+
+    let contexts = expand(&root, outputs.as_object().unwrap(), environment.as_object().unwrap()).unwrap();
+    let labels: Vec<String> = contexts.iter().map(LoopContext::label).collect();
+    assert_eq!(
+      labels,
+      [
+        "users[1]: id=7, name=ann / page[1]: n=0",
+        "users[1]: id=7, name=ann / page[2]: n=1",
+        "users[2]: id=8, name=bob / page[1]: n=0",
+        "users[2]: id=8, name=bob / page[2]: n=1",
+      ]
+    );
+    assert_eq!(contexts[2].value(Some("users"), Some("names")), Some(&json!("bob")));
+    assert_eq!(contexts[3].value(Some("pages"), Some("n")), Some(&json!(1)));
+    // Two loops in play, so there is no single source to fall back to.
+    assert_eq!(contexts[3].value(None, None), None);
+  }
+
+  #[test]
+  fn falls_back_to_the_only_source() {
+    let root = definition(json!({
+      "id": "loop_legacy",
+      "name": "loop",
+      "sources": [{ "id": "source_legacy", "alias": "value", "source_type": "fixed_count", "count": 2 }]
+    }));
+    let contexts = expand(&root, &Map::new(), &Map::new()).unwrap();
+    assert_eq!(contexts.len(), 2);
+    assert_eq!(contexts[1].value(None, Some("")), Some(&json!(1)));
+  }
+
+  #[test]
+  fn rejects_bad_sources() {
+    let error = |value: Value| {
+      let environment = json!({ "LIST": [1, { "a": 1 }], "ONE": [1] });
+      expand(&definition(value), &Map::new(), environment.as_object().unwrap())
+        .unwrap_err()
+        .to_string()
+    };
+
+    assert_eq!(
+      error(json!({ "id": "l", "name": "l", "sources": [] })),
+      "sequence error: Loop 'l' must have at least one source"
+    );
+    assert_eq!(
+      error(json!({ "id": "l", "name": "l", "sources": [{ "id": "s", "alias": "n", "source_type": "fixed_count", "count": 1.5 }] })),
+      "sequence error: Loop source 'n' count must be a positive integer"
+    );
+    assert_eq!(
+      error(json!({ "id": "l", "name": "l", "sources": [{ "id": "s", "alias": "a", "source_type": "environment_variable_array", "source_value": "LIST" }] })),
+      "sequence error: Loop source environment variable 'LIST' must contain only string, number, or boolean values"
+    );
+    assert_eq!(
+      error(json!({ "id": "l", "name": "l", "sources": [
+        { "id": "s", "alias": "n", "source_type": "fixed_count", "count": 2 },
+        { "id": "t", "alias": "one", "source_type": "environment_variable_array", "source_value": "ONE" }
+      ] })),
+      "sequence error: Loop 'l' zip source 'one' has 1 value(s), expected 2"
+    );
+    assert_eq!(
+      error(json!({ "id": "l", "name": "l", "sources": [{ "id": "s", "alias": "p", "source_type": "previous_output_array", "source_flow_step": 3, "source_value": "ids" }] })),
+      "sequence error: No outputs found from flow step 3"
+    );
+  }
+}
@@ -0,0 +1,143 @@
+//! Flow sequences: several test flows run one after another, with parameters
+//! fed from the environment, fixed values, earlier flows' outputs or the
+//! values of the step's loops.
+//!
+//! Models mirror `FlowSequenceConfig` in `src/lib/types/flow_sequence.ts`.
+
+pub mod loops;
+pub mod runner;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::Result;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FlowSequenceConfig {
+  #[serde(default)]
+  pub steps: Vec<FlowSequenceStep>,
+  #[serde(default)]
+  pub global_settings: Option<GlobalSettings>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GlobalSettings {
+  #[serde(default)]
+  pub timeout: Option<u64>,
+  #[serde(default)]
+  pub continue_on_error: Option<bool>,
+  #[serde(default)]
+  pub parallel_execution: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlowSequenceStep {
+  #[serde(default)]
+  pub id: String,
+  pub test_flow_id: i64,
+  pub step_order: i64,
+  #[serde(default)]
+  pub parameter_mappings: Vec<FlowParameterMapping>,
+  #[serde(default)]
+  pub loop_config: Option<Value>,
+  #[serde(default)]
+  pub expects_error: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlowParameterMapping {
+  pub flow_parameter_name: String,
+  pub source_type: String,
+  #[serde(default)]
+  pub source_value: String,
+  #[serde(default)]
+  pub data_type: Option<String>,
+  #[serde(default)]
+  pub source_flow_step: Option<i64>,
+  #[serde(default)]
+  pub source_output_field: Option<String>,
+  /// For `loop_value`: which loop and which of its sources to read.
+  #[serde(default)]
+  pub loop_id: Option<String>,
+  #[serde(default)]
+  pub loop_source_id: Option<String>,
+}
+
+/// One level of a step's loop tree. Its sources are zipped together, and
+/// every row runs each child loop in full.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlowLoopDefinition {
+  pub id: String,
+  #[serde(default)]
+  pub name: String,
+  #[serde(default)]
+  pub sources: Vec<FlowLoopSource>,
+  #[serde(default)]
+  pub children: Vec<FlowLoopDefinition>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FlowLoopSource {
+  pub id: String,
+  #[serde(default)]
+  pub alias: String,
+  /// `fixed_count`, `environment_variable_array` or `previous_output_array`.
+  pub source_type: String,
+  #[serde(default)]
+  pub count: Option<Value>,
+  #[serde(default)]
+  pub source_value: Option<String>,
+  #[serde(default)]
+  pub source_flow_step: Option<i64>,
+  #[serde(default)]
+  pub source_output_field: Option<String>,
+}
+
+impl FlowSequenceConfig {
+  /// Parse either a bare config or a sequence record with `sequenceConfig`.
+  pub fn from_json(text: &str) -> Result<Self> {
+    let mut value: Value = serde_json::from_str(text)?;
+    if let Some(config) = value.get_mut("sequenceConfig").map(Value::take) {
+      value = config;
+    }
+    Ok(serde_json::from_value(value)?)
+  }
+}
+
+impl FlowSequenceStep {
+  pub fn loop_enabled(&self) -> bool {
+    self
+      .loop_config
+      .as_ref()
+      .and_then(|config| config.get("enabled"))
+      .and_then(Value::as_bool)
+      .unwrap_or(false)
+  }
+
+  /// The root of an enabled loop, as `normalizeFlowLoopConfig` reads it: a
+  /// legacy single-source config becomes a one-level loop named `loop`.
+  pub fn loop_definition(&self) -> Result<Option<FlowLoopDefinition>> {
+    let Some(config) = self.loop_config.as_ref().filter(|_| self.loop_enabled()) else {
+      return Ok(None);
+    };
+    if let Some(root) = config.get("root").filter(|root| !root.is_null()) {
+      return Ok(Some(serde_json::from_value(root.clone())?));
+    }
+    if config.get("source_type").map_or(true, Value::is_null) {
+      return Ok(None);
+    }
+
+    let mut source = config.clone();
+    if let Some(source) = source.as_object_mut() {
+      source.remove("enabled");
+      source.insert("id".to_string(), Value::from("source_legacy"));
+      source.insert("alias".to_string(), Value::from("value"));
+    }
+    Ok(Some(FlowLoopDefinition {
+      id: "loop_legacy".to_string(),
+      name: "loop".to_string(),
+      sources: vec![serde_json::from_value(source)?],
+      children: Vec::new(),
+    }))
+  }
+}
@@ -0,0 +1,705 @@
+//! Native counterpart of `SequenceRunner` in `src/lib/sequence-runner`.
+//!
+//! Steps run in `step_order`. A step "matches its expectation" when it fails
+//! exactly when `expects_error` says it should; the first mismatch stops the
+//! sequence unless `stop_on_error` is off. A looped step runs its flow once
+//! per [`LoopContext`] of its plan and stops at the first iteration that
+//! misses the expectation.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::loops::{self, LoopContext};
+use super::{FlowParameterMapping, FlowSequenceConfig, FlowSequenceStep};
+use crate::cookies::{CookieJar, SharedCookieJar};
+use crate::environment::ResolvedEnvironment;
+use crate::flow::runner::{FlowEvent, FlowRunResult, FlowRunner, RunPreferences, RunStatus};
+use crate::flow::TestFlow;
+use crate::http::HttpClient;
+use crate::runs::CancellationToken;
+use crate::template::{self, TemplateContext};
+use crate::transform::{json_path, truthy};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceFlowResult {
+  pub test_flow_id: i64,
+  pub step_order: i64,
+  pub success: bool,
+  pub expects_error: bool,
+  pub matched_expectation: bool,
+  /// Flow outputs; for a loop, every matched iteration's values per output.
+  pub outputs: Map<String, Value>,
+  /// Stored responses by endpoint id, under `iteration_{i}` for a loop.
+  pub responses: Map<String, Value>,
+  /// Parameter values used, under `iteration_{i}` for a loop.
+  pub parameter_values: Map<String, Value>,
+  pub error: Option<String>,
+  pub execution_time_ms: u64,
+  /// The flow run of a step without a loop.
+  pub flow: Option<FlowRunResult>,
+  #[serde(rename = "loop", skip_serializing_if = "Option::is_none")]
+  pub loop_result: Option<SequenceLoopResult>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceLoopResult {
+  pub total_iterations: usize,
+  pub completed_iterations: usize,
+  /// The iteration that missed its expectation and stopped the loop.
+  pub failed_iteration_index: Option<usize>,
+  pub loop_names: BTreeMap<String, String>,
+  /// Source aliases by loop id, then source id.
+  pub source_aliases: BTreeMap<String, BTreeMap<String, String>>,
+  pub iterations: Vec<SequenceLoopIteration>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceLoopIteration {
+  pub index: usize,
+  pub label: String,
+  #[serde(flatten)]
+  pub context: LoopContext,
+  pub success: bool,
+  pub matched_expectation: bool,
+  pub error: Option<String>,
+  pub outputs: Map<String, Value>,
+  pub execution_time_ms: u64,
+  pub flow: FlowRunResult,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceRunResult {
+  pub success: bool,
+  pub status: RunStatus,
+  pub flow_results: Vec<SequenceFlowResult>,
+  /// Outputs of every flow keyed `flow_{step_order}`.
+  pub sequence_outputs: Map<String, Value>,
+  /// Why the sequence was abandoned, e.g. a step's flow was not supplied.
+  pub error: Option<String>,
+}
+
+/// Progress reported to a [`SequenceRunner::on_event`] listener, after the
+/// TS runner's `onFlowStart` and `onFlowComplete` callbacks.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SequenceEvent {
+  #[serde(rename_all = "camelCase")]
+  FlowStarted { flow_index: usize, test_flow_id: i64, step_order: i64 },
+  #[serde(rename_all = "camelCase")]
+  IterationStarted {
+    flow_index: usize,
+    iteration_index: usize,
+    total_iterations: usize,
+    label: String,
+  },
+  /// Endpoint and step progress of the flow being run.
+  #[serde(rename_all = "camelCase")]
+  FlowProgress {
+    flow_index: usize,
+    iteration_index: Option<usize>,
+    event: FlowEvent,
+  },
+  #[serde(rename_all = "camelCase")]
+  FlowCompleted {
+    flow_index: usize,
+    step_order: i64,
+    success: bool,
+    matched_expectation: bool,
+    error: Option<String>,
+    flow_outputs: Map<String, Value>,
+  },
+}
+
+pub struct SequenceRunner<'a> {
+  client: &'a HttpClient,
+  sequence: &'a FlowSequenceConfig,
+  flows: &'a HashMap<i64, TestFlow>,
+  environment: Option<&'a ResolvedEnvironment>,
+  preferences: RunPreferences,
+  cookie_jar: SharedCookieJar,
+  cancellation: CancellationToken,
+  listener: Option<&'a (dyn Fn(SequenceEvent) + Sync)>,
+}
+
+impl<'a> SequenceRunner<'a> {
+  pub fn new(
+    client: &'a HttpClient,
+    sequence: &'a FlowSequenceConfig,
+    flows: &'a HashMap<i64, TestFlow>,
+    environment: Option<&'a ResolvedEnvironment>,
+  ) -> Self {
+    Self {
+      client,
+      sequence,
+      flows,
+      environment,
+      preferences: RunPreferences::default(),
+      cookie_jar: Arc::new(Mutex::new(CookieJar::default())),
+      cancellation: CancellationToken::default(),
+      listener: None,
+    }
+  }
+
+  pub fn preferences(mut self, preferences: RunPreferences) -> Self {
+    self.preferences = preferences;
+    self
+  }
+
+  /// The jar every flow of the sequence, and every loop iteration, shares;
+  /// a fresh one by default.
+  pub fn cookie_jar(mut self, cookie_jar: SharedCookieJar) -> Self {
+    self.cookie_jar = cookie_jar;
+    self
+  }
+
+  /// Stop between flows and abort the running one when `token` is
+  /// cancelled.
+  pub fn cancellation(mut self, token: CancellationToken) -> Self {
+    self.cancellation = token;
+    self
+  }
+
+  /// Report flow, iteration and endpoint progress to `listener`.
+  pub fn on_event(mut self, listener: &'a (dyn Fn(SequenceEvent) + Sync)) -> Self {
+    self.listener = Some(listener);
+    self
+  }
+
+  pub async fn run(&self) -> SequenceRunResult {
+    let mut steps: Vec<&FlowSequenceStep> = self.sequence.steps.iter().collect();
+    steps.sort_by_key(|step| step.step_order);
+
+    let mut result = SequenceRunResult::default();
+    // Stored responses of every flow so far, keyed like the outputs.
+    let mut responses = Map::new();
+    for (flow_index, step) in steps.into_iter().enumerate() {
+      if self.cancellation.is_cancelled() {
+        break;
+      }
+      self.emit(|| SequenceEvent::FlowStarted {
+        flow_index,
+        test_flow_id: step.test_flow_id,
+        step_order: step.step_order,
+      });
+
+      let Some(flow) = self.flows.get(&step.test_flow_id) else {
+        let error = format!("flow with ID {} not found", step.test_flow_id);
+        let flow_result = SequenceFlowResult::failed(step, error.clone());
+        self.emit_completed(flow_index, &flow_result);
+        result.flow_results.push(flow_result);
+        result.error = Some(error);
+        break;
+      };
+
+      let flow_result = self.run_step(flow_index, step, flow, &result.sequence_outputs, &responses).await;
+      self.emit_completed(flow_index, &flow_result);
+      let key = format!("flow_{}", step.step_order);
+      result.sequence_outputs.insert(key.clone(), Value::Object(flow_result.outputs.clone()));
+      responses.insert(key, Value::Object(flow_result.responses.clone()));
+      let matched = flow_result.matched_expectation;
+      result.flow_results.push(flow_result);
+
+      if !matched && self.preferences.stop_on_error {
+        log::debug!("[sequence] stopping after step {} did not match its expectation", step.step_order);
+        break;
+      }
+    }
+
+    result.success = result.error.is_none() && result.flow_results.iter().all(|flow| flow.matched_expectation);
+    result.status = if self.cancellation.is_cancelled() {
+      log::debug!("[sequence] run cancelled after {} flow(s)", result.flow_results.len());
+      result.success = false;
+      RunStatus::Cancelled
+    } else if result.success {
+      RunStatus::Completed
+    } else {
+      RunStatus::Failed
+    };
+    result
+  }
+
+  fn emit(&self, event: impl FnOnce() -> SequenceEvent) {
+    if let Some(listener) = self.listener {
+      listener(event());
+    }
+  }
+
+  fn emit_completed(&self, flow_index: usize, result: &SequenceFlowResult) {
+    self.emit(|| SequenceEvent::FlowCompleted {
+      flow_index,
+      step_order: result.step_order,
+      success: result.success,
+      matched_expectation: result.matched_expectation,
+      error: result.error.clone(),
+      flow_outputs: result.outputs.clone(),
+    });
+  }
+
+  async fn run_step(
+    &self,
+    flow_index: usize,
+    step: &FlowSequenceStep,
+    flow: &TestFlow,
+    outputs: &Map<String, Value>,
+    responses: &Map<String, Value>,
+  ) -> SequenceFlowResult {
+    let started = Instant::now();
+    // Sequence mappings replace the flow's own environment mappings.
+    let mut flow = flow.clone();
+    flow.settings.linked_environment = None;
+
+    let plan = step.loop_definition().and_then(|root| {
+      root
+        .map(|root| {
+          let environment = self.environment.map(|environment| environment.variables.clone());
+          loops::expand(&root, outputs, &environment.unwrap_or_default())
+        })
+        .transpose()
+    });
+    let mut result = match plan {
+      Ok(None) => {
+        let run = self.run_flow(flow_index, None, &flow, step, outputs, responses).await;
+        SequenceFlowResult::single(step, run)
+      }
+      Ok(Some(contexts)) => self.run_loop(flow_index, &flow, step, outputs, responses, contexts).await,
+      Err(err) => SequenceFlowResult::failed(step, err.to_string()),
+    };
+    result.execution_time_ms = started.elapsed().as_millis() as u64;
+    result
+  }
+
+  async fn run_loop(
+    &self,
+    flow_index: usize,
+    flow: &TestFlow,
+    step: &FlowSequenceStep,
+    outputs: &Map<String, Value>,
+    responses: &Map<String, Value>,
+    contexts: Vec<LoopContext>,
+  ) -> SequenceFlowResult {
+    let mut loop_result = SequenceLoopResult {
+      total_iterations: contexts.len(),
+      ..SequenceLoopResult::default()
+    };
+    for row in contexts.iter().flat_map(|context| &context.path) {
+      loop_result.loop_names.insert(row.loop_id.clone(), row.loop_name.clone());
+      loop_result.source_aliases.insert(row.loop_id.clone(), row.source_aliases.clone());
+    }
+
+    for (index, context) in contexts.into_iter().enumerate() {
+      let label = context.label();
+      log::debug!("[sequence] step {} iteration {index}: {label}", step.step_order);
+      self.emit(|| SequenceEvent::IterationStarted {
+        flow_index,
+        iteration_index: index,
+        total_iterations: loop_result.total_iterations,
+        label: label.clone(),
+      });
+
+      let started = Instant::now();
+      let run = self
+        .run_flow(flow_index, Some((index, &context)), flow, step, outputs, responses)
+        .await;
+      let cancelled = run.status == RunStatus::Cancelled;
+      let matched_expectation = !cancelled && run.success != step.expects_error;
+      loop_result.iterations.push(SequenceLoopIteration {
+        index,
+        label,
+        context,
+        success: run.success,
+        matched_expectation,
+        error: run.error.clone(),
+        outputs: run.flow_outputs.clone(),
+        execution_time_ms: started.elapsed().as_millis() as u64,
+        flow: run,
+      });
+      if cancelled || self.cancellation.is_cancelled() {
+        break;
+      }
+      if !matched_expectation {
+        loop_result.failed_iteration_index = Some(index);
+        break;
+      }
+    }
+    loop_result.completed_iterations = loop_result.iterations.len();
+
+    let mut result = SequenceFlowResult::new(step);
+    for iteration in &loop_result.iterations {
+      let key = format!("iteration_{}", iteration.index);
+      result.responses.insert(key.clone(), Value::Object(iteration.flow.stored_responses.clone()));
+      result.parameter_values.insert(key, Value::Object(iteration.flow.parameter_values.clone()));
+      if !iteration.matched_expectation {
+        continue;
+      }
+      for (name, value) in &iteration.outputs {
+        let Value::Array(values) = result.outputs.entry(name.clone()).or_insert_with(|| Value::Array(Vec::new())) else {
+          unreachable!("aggregated outputs are arrays");
+        };
+        match value {
+          Value::Array(items) => values.extend(items.iter().cloned()),
+          value => values.push(value.clone()),
+        }
+      }
+    }
+    result.success = loop_result.completed_iterations == loop_result.total_iterations
+      && loop_result.iterations.iter().all(|iteration| iteration.success);
+    // A cancelled loop ends early without any iteration having failed.
+    result.matched_expectation = loop_result.completed_iterations == loop_result.total_iterations
+      && loop_result.iterations.iter().all(|iteration| iteration.matched_expectation);
+    if !result.success {
+      result.error = loop_result.iterations.last().and_then(|iteration| iteration.error.clone());
+    }
+    result.loop_result = Some(loop_result);
+    result
+  }
+
+  /// Run the flow once with the step's mappings. A stored response that
+  /// reports an error fails the run even if every endpoint passed.
+  async fn run_flow(
+    &self,
+    flow_index: usize,
+    iteration: Option<(usize, &LoopContext)>,
+    flow: &TestFlow,
+    step: &FlowSequenceStep,
+    outputs: &Map<String, Value>,
+    responses: &Map<String, Value>,
+  ) -> FlowRunResult {
+    let loop_context = iteration.map(|(_, context)| context);
+    let parameters = self.resolve_parameters(flow, step, outputs, responses, loop_context);
+
+    let forward = |event: FlowEvent| {
+      self.emit(|| SequenceEvent::FlowProgress {
+        flow_index,
+        iteration_index: iteration.map(|(index, _)| index),
+        event,
+      })
+    };
+    let mut runner = FlowRunner::new(self.client, flow, self.environment)
+      .preferences(self.preferences.clone())
+      .cookie_jar(self.cookie_jar.clone())
+      .cancellation(self.cancellation.clone());
+    if self.listener.is_some() {
+      runner = runner.on_event(&forward);
+    }
+    let mut result = runner.run(&parameters).await;
+
+    if let Some(error) = result.stored_responses.values().find_map(response_error) {
+      result.success = false;
+      if result.status == RunStatus::Completed {
+        result.status = RunStatus::Failed;
+      }
+      result.error.get_or_insert(error);
+    }
+    result
+  }
+
+  fn resolve_parameters(
+    &self,
+    flow: &TestFlow,
+    step: &FlowSequenceStep,
+    outputs: &Map<String, Value>,
+    responses: &Map<String, Value>,
+    loop_context: Option<&LoopContext>,
+  ) -> Map<String, Value> {
+    let mut parameters = Map::new();
+    for parameter in &flow.parameters {
+      let Some(mapping) = step
+        .parameter_mappings
+        .iter()
+        .find(|mapping| mapping.flow_parameter_name == parameter.name)
+      else {
+        continue;
+      };
+      if let Some(value) = self.resolve_mapping(mapping, outputs, responses, loop_context) {
+        parameters.insert(parameter.name.clone(), value);
+      }
+    }
+    parameters
+  }
+
+  /// Resolve one mapping; both the sequence editor's source types and the
+  /// runner's older names are accepted.
+  fn resolve_mapping(
+    &self,
+    mapping: &FlowParameterMapping,
+    outputs: &Map<String, Value>,
+    responses: &Map<String, Value>,
+    loop_context: Option<&LoopContext>,
+  ) -> Option<Value> {
+    let name = &mapping.flow_parameter_name;
+    let value = match mapping.source_type.as_str() {
+      "environment_variable" | "environment" => self.environment?.variables.get(&mapping.source_value).cloned(),
+      "previous_output" | "previous_flow_output" => {
+        let flow_outputs = outputs.get(&format!("flow_{}", mapping.source_flow_step?))?;
+        let field = mapping
+          .source_output_field
+          .as_deref()
+          .filter(|field| !field.is_empty())
+          .unwrap_or(&mapping.source_value);
+        flow_outputs.get(field).cloned()
+      }
+      "previous_flow_response" => {
+        let flow_responses = responses.get(&format!("flow_{}", mapping.source_flow_step?))?;
+        Some(json_path::evaluate(&mapping.source_value, flow_responses)).filter(|value| !value.is_null())
+      }
+      "static_value" | "fixed_value" => Some(convert_static_value(&mapping.source_value, mapping.data_type.as_deref())),
+      "loop_value" => loop_context
+        .and_then(|context| context.value(mapping.loop_id.as_deref(), mapping.loop_source_id.as_deref()))
+        .cloned(),
+      "function" => {
+        let expression = format!("{{{{func:{}}}}}", mapping.source_value.trim());
+        match template::resolve_template(&expression, &TemplateContext::default()) {
+          Ok(value) => Some(value),
+          Err(err) => {
+            log::warn!("[sequence] function `{}` for `{name}` failed: {err}", mapping.source_value);
+            None
+          }
+        }
+      }
+      other => {
+        log::warn!("[sequence] unknown `{other}` mapping for `{name}`");
+        return None;
+      }
+    };
+    if value.is_none() {
+      log::warn!("[sequence] no value for parameter `{name}` from its `{}` mapping", mapping.source_type);
+    }
+    value
+  }
+}
+
+impl SequenceFlowResult {
+  fn new(step: &FlowSequenceStep) -> Self {
+    Self {
+      test_flow_id: step.test_flow_id,
+      step_order: step.step_order,
+      success: false,
+      expects_error: step.expects_error,
+      matched_expectation: false,
+      outputs: Map::new(),
+      responses: Map::new(),
+      parameter_values: Map::new(),
+      error: None,
+      execution_time_ms: 0,
+      flow: None,
+      loop_result: None,
+    }
+  }
+
+  /// A step that could not run for want of a flow or a valid loop plan. A
+  /// broken configuration is not the error a step expects, so it never
+  /// matches.
+  fn failed(step: &FlowSequenceStep, error: String) -> Self {
+    Self {
+      matched_expectation: false,
+      error: Some(error),
+      ..Self::new(step)
+    }
+  }
+
+  fn single(step: &FlowSequenceStep, flow: FlowRunResult) -> Self {
+    Self {
+      success: flow.success,
+      matched_expectation: flow.status != RunStatus::Cancelled && flow.success != step.expects_error,
+      outputs: flow.flow_outputs.clone(),
+      responses: flow.stored_responses.clone(),
+      parameter_values: flow.parameter_values.clone(),
+      error: flow.error.clone(),
+      flow: Some(flow),
+      ..Self::new(step)
+    }
+  }
+}
+
+/// `getErrorFromResponse`, for responses that carry a non-null `__error` or
+/// `error`, or `success: false`.
+fn response_error(response: &Value) -> Option<String> {
+  let response = response.as_object()?;
+  let field = |name: &str| response.get(name).filter(|value| !value.is_null());
+  if field("__error").is_none() && field("error").is_none() && response.get("success") != Some(&Value::Bool(false)) {
+    return None;
+  }
+  let message = ["__error", "error", "message"]
+    .into_iter()
+    .filter_map(field)
+    .find(|value| truthy(value))
+    .map(template::js_string)
+    .unwrap_or_else(|| "Unknown error from API response".to_string());
+  Some(message)
+}
+
+fn convert_static_value(value: &str, data_type: Option<&str>) -> Value {
+  match data_type {
+    Some("number") => match value.trim().parse::<i64>() {
+      Ok(integer) => Value::from(integer),
+      Err(_) => value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|number| serde_json::Number::from_f64(number).map(Value::Number))
+        .unwrap_or_else(|| Value::String(value.to_string())),
+    },
+    Some("boolean") => Value::Bool(matches!(value, "true" | "1" | "yes")),
+    _ => Value::String(value.to_string()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_support::serve_http;
+  use serde_json::json;
+
+  /// `/login` sets a session cookie, `/broken` answers with an error body
+  /// and any other path with the `Cookie` header it got.
+  async fn server() -> u16 {
+    serve_http(|request| {
+      let (cookie, body) = if request.starts_with("GET /login ") {
+        ("Set-Cookie: session=abc; Path=/\r\n", "{}".to_string())
+      } else if request.starts_with("GET /broken ") {
+        ("", json!({ "error": "boom" }).to_string())
+      } else {
+        let cookie = request.lines().find_map(|line| line.strip_prefix("cookie: ").or(line.strip_prefix("Cookie: ")));
+        ("", json!({ "cookie": cookie }).to_string())
+      };
+      format!(
+        "HTTP/1.1 200 OK\r\n{cookie}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+      )
+    })
+    .await
+  }
+
+  /// A flow of one `GET path` endpoint against the test server.
+  fn flow(port: u16, path: &str, assertions: Value) -> TestFlow {
+    serde_json::from_value(json!({
+      "settings": { "api_hosts": { "1": { "url": format!("http://127.0.0.1:{port}"), "name": "Shop" } } },
+      "endpoints": [{ "id": 1, "path": path, "method": "GET" }],
+      "steps": [{ "step_id": "step1", "endpoints": [{ "endpoint_id": 1, "api_id": 1, "assertions": assertions }] }]
+    }))
+    .unwrap()
+  }
+
+  #[tokio::test]
+  async fn error_responses_fail_the_flow() {
+    let port = server().await;
+    let flows = HashMap::from([(1, flow(port, "/broken", json!([])))]);
+    let sequence: FlowSequenceConfig = serde_json::from_value(json!({
+      "steps": [{ "test_flow_id": 1, "step_order": 1 }]
+    }))
+    .unwrap();
+
+    let client = HttpClient::new().unwrap();
+    let result = SequenceRunner::new(&client, &sequence, &flows, None).run().await;
+    let failed = result.flow_results[0].flow.as_ref().unwrap();
+    assert!(!failed.success);
+    assert_eq!(failed.status, RunStatus::Failed);
+    assert_eq!(failed.error.as_deref(), Some("boom"));
+    assert!(!result.flow_results[0].matched_expectation);
+  }
+
+  #[tokio::test]
+  async fn broken_loops_never_meet_an_expected_error() {
+    let flows = HashMap::from([(1, flow(1, "/broken", json!([])))]);
+    let sequence: FlowSequenceConfig = serde_json::from_value(json!({
+      "steps": [{
+        "test_flow_id": 1,
+        "step_order": 1,
+        "expects_error": true,
+        "loop_config": { "enabled": true, "source_type": "bogus" }
+      }]
+    }))
+    .unwrap();
+
+    let client = HttpClient::new().unwrap();
+    let result = SequenceRunner::new(&client, &sequence, &flows, None).run().await;
+    let step = &result.flow_results[0];
+    assert!(step.flow.is_none() && step.loop_result.is_none());
+    assert!(!step.matched_expectation);
+    assert!(!result.success);
+  }
+
+  #[tokio::test]
+  async fn shares_cookies_between_flows() {
+    let port = server().await;
+    let flows = HashMap::from([
+      (1, flow(port, "/login", json!([]))),
+      (
+        2,
+        flow(
+          port,
+          "/me",
+          json!([{ "assertion_type": "json_body", "data_id": "$.cookie", "operator": "equals", "expected_value": "session=abc" }]),
+        ),
+      ),
+    ]);
+    let sequence: FlowSequenceConfig = serde_json::from_value(json!({
+      "steps": [{ "test_flow_id": 1, "step_order": 1 }, { "test_flow_id": 2, "step_order": 2 }]
+    }))
+    .unwrap();
+
+    let client = HttpClient::new().unwrap();
+    let result = SequenceRunner::new(&client, &sequence, &flows, None).run().await;
+    assert!(result.success, "{:?}", result.flow_results.iter().map(|flow| &flow.error).collect::<Vec<_>>());
+    let me = result.flow_results[1].flow.as_ref().unwrap();
+    assert_eq!(me.stored_responses["step1-0"]["cookie"], json!("session=abc"));
+  }
+
+  fn step(value: Value) -> FlowSequenceStep {
+    serde_json::from_value(value).unwrap()
+  }
+
+  #[test]
+  fn reads_legacy_and_nested_loop_configs() {
+    let legacy = step(json!({
+      "test_flow_id": 1,
+      "step_order": 2,
+      "loop_config": { "enabled": true, "source_type": "fixed_count", "count": 3 }
+    }));
+    let root = legacy.loop_definition().unwrap().unwrap();
+    assert_eq!((root.id.as_str(), root.name.as_str()), ("loop_legacy", "loop"));
+    assert_eq!(root.sources[0].id, "source_legacy");
+    assert_eq!(root.sources[0].alias, "value");
+    assert_eq!(root.sources[0].count, Some(json!(3)));
+
+    let nested = step(json!({
+      "test_flow_id": 1,
+      "step_order": 2,
+      "loop_config": {
+        "enabled": true,
+        "root": { "id": "a", "name": "outer", "sources": [], "children": [{ "id": "b", "name": "inner" }] }
+      }
+    }));
+    assert_eq!(nested.loop_definition().unwrap().unwrap().children[0].name, "inner");
+
+    let disabled = step(json!({
+      "test_flow_id": 1,
+      "step_order": 2,
+      "loop_config": { "enabled": false, "source_type": "fixed_count", "count": 3 }
+    }));
+    assert!(disabled.loop_definition().unwrap().is_none());
+  }
+
+  #[test]
+  fn detects_errors_in_responses() {
+    assert_eq!(response_error(&json!({ "id": 1, "error": null })), None);
+    assert_eq!(response_error(&json!([{ "error": "x" }])), None);
+    assert_eq!(response_error(&json!({ "__error": "boom", "error": "x" })), Some("boom".to_string()));
+    assert_eq!(
+      response_error(&json!({ "success": false, "message": "nope" })),
+      Some("nope".to_string())
+    );
+    assert_eq!(
+      response_error(&json!({ "error": false })),
+      Some("Unknown error from API response".to_string())
+    );
+  }
+}
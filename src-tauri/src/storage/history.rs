@@ -0,0 +1,235 @@
+//! The `run_history` table behind [`crate::history`].
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, OptionalExtension, Row};
+use serde_json::Value;
+
+use super::Store;
+use crate::error::{Error, Result};
+use crate::history::{PruneOptions, RunEntry, RunQuery};
+
+const SUMMARY_COLUMNS: &str =
+  "id, run_id, kind, test_flow_id, sequence_id, status, started_at, duration_ms, total, failed, error";
+
+/// A row as read, before `kind` and `status` are parsed.
+struct StoredRun {
+  id: i64,
+  run_id: String,
+  kind: String,
+  test_flow_id: Option<i64>,
+  sequence_id: Option<i64>,
+  status: String,
+  started_at: String,
+  duration_ms: i64,
+  total: i64,
+  failed: i64,
+  error: Option<String>,
+  result: Option<String>,
+}
+
+impl StoredRun {
+  fn read(row: &Row<'_>, with_result: bool) -> rusqlite::Result<Self> {
+    Ok(Self {
+      id: row.get(0)?,
+      run_id: row.get(1)?,
+      kind: row.get(2)?,
+      test_flow_id: row.get(3)?,
+      sequence_id: row.get(4)?,
+      status: row.get(5)?,
+      started_at: row.get(6)?,
+      duration_ms: row.get(7)?,
+      total: row.get(8)?,
+      failed: row.get(9)?,
+      error: row.get(10)?,
+      result: if with_result { row.get(11)? } else { None },
+    })
+  }
+
+  fn into_entry(self) -> Result<RunEntry> {
+    Ok(RunEntry {
+      id: self.id,
+      run_id: self.run_id,
+      kind: serde_json::from_value(Value::String(self.kind))?,
+      test_flow_id: self.test_flow_id,
+      sequence_id: self.sequence_id,
+      status: serde_json::from_value(Value::String(self.status))?,
+      started_at: self.started_at,
+      duration_ms: self.duration_ms.max(0) as u64,
+      total: self.total.max(0) as usize,
+      failed: self.failed.max(0) as usize,
+      error: self.error,
+      result: self.result.map(|result| serde_json::from_str(&result)).transpose()?,
+    })
+  }
+}
+
+impl Store {
+  /// Append a run to the history and return its history id.
+  pub fn record_run(&self, entry: &RunEntry) -> Result<i64> {
+    let result = entry.result.as_ref().unwrap_or(&Value::Null).to_string();
+    let connection = self.lock();
+    connection.execute(
+      "INSERT INTO run_history \
+       (run_id, kind, test_flow_id, sequence_id, status, started_at, duration_ms, total, failed, error, result) \
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+      params![
+        entry.run_id,
+        entry.kind.as_str(),
+        entry.test_flow_id,
+        entry.sequence_id,
+        entry.status.as_str(),
+        entry.started_at,
+        entry.duration_ms as i64,
+        entry.total as i64,
+        entry.failed as i64,
+        entry.error,
+        result,
+      ],
+    )?;
+    let id = connection.last_insert_rowid();
+    log::debug!("[storage] recorded {} run {} as history entry {id}", entry.kind.as_str(), entry.run_id);
+    Ok(id)
+  }
+
+  /// Runs matching `query`, newest first and without their results.
+  pub fn list_runs(&self, query: &RunQuery) -> Result<Vec<RunEntry>> {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+    if let Some(kind) = query.kind {
+      conditions.push("kind = ?");
+      values.push(SqlValue::Text(kind.as_str().to_string()));
+    }
+    if let Some(test_flow_id) = query.test_flow_id {
+      conditions.push("test_flow_id = ?");
+      values.push(SqlValue::Integer(test_flow_id));
+    }
+    if let Some(sequence_id) = query.sequence_id {
+      conditions.push("sequence_id = ?");
+      values.push(SqlValue::Integer(sequence_id));
+    }
+    if let Some(status) = query.status {
+      conditions.push("status = ?");
+      values.push(SqlValue::Text(status.as_str().to_string()));
+    }
+    if let Some(before_id) = query.before_id {
+      conditions.push("id < ?");
+      values.push(SqlValue::Integer(before_id));
+    }
+
+    let mut sql = format!("SELECT {SUMMARY_COLUMNS} FROM run_history");
+    if !conditions.is_empty() {
+      sql.push_str(" WHERE ");
+      sql.push_str(&conditions.join(" AND "));
+    }
+    sql.push_str(" ORDER BY id DESC LIMIT ?");
+    values.push(SqlValue::Integer(query.limit as i64));
+
+    let connection = self.lock();
+    let mut statement = connection.prepare(&sql)?;
+    let rows = statement.query_map(params_from_iter(values), |row| StoredRun::read(row, false))?;
+    rows.map(|row| row?.into_entry()).collect()
+  }
+
+  /// A run with its full result.
+  pub fn get_run(&self, id: i64) -> Result<RunEntry> {
+    let sql = format!("SELECT {SUMMARY_COLUMNS}, result FROM run_history WHERE id = ?");
+    self
+      .lock()
+      .query_row(&sql, [id], |row| StoredRun::read(row, true))
+      .optional()?
+      .ok_or_else(|| Error::NotFound(format!("run history entry {id}")))?
+      .into_entry()
+  }
+
+  /// Delete runs as `options` say and return how many went.
+  pub fn prune_runs(&self, options: &PruneOptions) -> Result<usize> {
+    let connection = self.lock();
+    let mut deleted = 0;
+    if let Some(before) = &options.before {
+      deleted += connection.execute("DELETE FROM run_history WHERE started_at < ?", [before])?;
+    }
+    if let Some(keep_last) = options.keep_last {
+      deleted += connection.execute(
+        "DELETE FROM run_history WHERE id IN ( \
+           SELECT id FROM ( \
+             SELECT id, ROW_NUMBER() OVER ( \
+               PARTITION BY kind, test_flow_id, sequence_id ORDER BY id DESC \
+             ) AS position FROM run_history \
+           ) WHERE position > ? \
+         )",
+        [keep_last as i64],
+      )?;
+    }
+    log::debug!("[storage] pruned {deleted} run history entries");
+    Ok(deleted)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::flow::runner::RunStatus;
+  use crate::history::RunKind;
+  use serde_json::json;
+
+  fn entry(test_flow_id: i64, status: RunStatus, started_at: &str) -> RunEntry {
+    RunEntry {
+      id: 0,
+      run_id: format!("run-{started_at}"),
+      kind: RunKind::Flow,
+      test_flow_id: Some(test_flow_id),
+      sequence_id: None,
+      status,
+      started_at: started_at.to_string(),
+      duration_ms: 10,
+      total: 1,
+      failed: usize::from(status != RunStatus::Completed),
+      error: None,
+      result: Some(json!({ "endpoints": [], "flowOutputs": { "id": 1 } })),
+    }
+  }
+
+  #[test]
+  fn records_lists_and_prunes_runs() {
+    let store = Store::open_in_memory().unwrap();
+    for (flow, status, started_at) in [
+      (1, RunStatus::Completed, "2026-01-01T00:00:00.000Z"),
+      (1, RunStatus::Failed, "2026-02-01T00:00:00.000Z"),
+      (1, RunStatus::Completed, "2026-03-01T00:00:00.000Z"),
+      (2, RunStatus::Cancelled, "2026-03-02T00:00:00.000Z"),
+    ] {
+      store.record_run(&entry(flow, status, started_at)).unwrap();
+    }
+
+    let flow_runs = store
+      .list_runs(&RunQuery {
+        test_flow_id: Some(1),
+        ..RunQuery::default()
+      })
+      .unwrap();
+    assert_eq!(flow_runs.iter().map(|run| run.id).collect::<Vec<_>>(), [3, 2, 1]);
+    assert!(flow_runs[0].result.is_none());
+    let failed = store
+      .list_runs(&RunQuery {
+        status: Some(RunStatus::Failed),
+        ..RunQuery::default()
+      })
+      .unwrap();
+    assert_eq!(failed.len(), 1);
+
+    let run = store.get_run(2).unwrap();
+    assert_eq!(run.status, RunStatus::Failed);
+    assert_eq!(run.result.unwrap()["flowOutputs"]["id"], json!(1));
+    assert!(matches!(store.get_run(9), Err(Error::NotFound(_))));
+
+    let pruned = store
+      .prune_runs(&PruneOptions {
+        keep_last: Some(1),
+        before: Some("2026-01-15T00:00:00.000Z".to_string()),
+      })
+      .unwrap();
+    assert_eq!(pruned, 2);
+    let left: Vec<i64> = store.list_runs(&RunQuery::default()).unwrap().iter().map(|run| run.id).collect();
+    assert_eq!(left, [4, 3]);
+  }
+}
@@ -0,0 +1,309 @@
+//! Embedded SQLite store for offline desktop use.
+//!
+//! The tables mirror the hosted Postgres schema in
+//! `src/lib/server/db/schema.ts`, minus accounts: locally nothing is owned by
+//! a user. Records go in and out as JSON objects with the camelCase fields of
+//! the Drizzle models, and JSON columns (`flowJson`, `config`, ...) take any
+//! JSON value.
+
+mod history;
+pub mod schema;
+
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection, OptionalExtension, Row};
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+use schema::{Column, ColumnKind, Table};
+
+/// File name of the database inside the app data directory.
+pub const DATABASE_FILE: &str = "test-pilot.db";
+
+/// The local database, held as Tauri managed state.
+pub struct Store {
+  connection: Mutex<Connection>,
+}
+
+impl Store {
+  /// Open the database at `path`, creating it if needed, and bring its
+  /// schema up to date.
+  pub fn open(path: &Path) -> Result<Self> {
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    log::info!("[storage] opening {}", path.display());
+    Self::init(Connection::open(path)?)
+  }
+
+  pub fn open_in_memory() -> Result<Self> {
+    Self::init(Connection::open_in_memory()?)
+  }
+
+  fn init(mut connection: Connection) -> Result<Self> {
+    connection.pragma_update(None, "foreign_keys", true)?;
+    schema::migrate(&mut connection)?;
+    Ok(Self {
+      connection: Mutex::new(connection),
+    })
+  }
+
+  /// Records whose fields equal every value in `filter`, oldest first.
+  pub fn list(&self, table: &str, filter: &Map<String, Value>) -> Result<Vec<Value>> {
+    let table = schema::table(table)?;
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+    for (field, value) in filter {
+      let column = table.column(field)?;
+      if value.is_null() {
+        conditions.push(format!("{} IS NULL", column.name));
+      } else {
+        conditions.push(format!("{} = ?", column.name));
+        values.push(to_sql(column, value)?);
+      }
+    }
+
+    let mut sql = select(table);
+    if !conditions.is_empty() {
+      sql.push_str(" WHERE ");
+      sql.push_str(&conditions.join(" AND "));
+    }
+    sql.push_str(" ORDER BY id");
+
+    let connection = self.lock();
+    let mut statement = connection.prepare(&sql)?;
+    let rows = statement.query_map(params_from_iter(values), |row| read_row(table, row))?;
+    rows.map(|row| to_record(table, row?)).collect()
+  }
+
+  pub fn get(&self, table: &str, id: i64) -> Result<Value> {
+    let table = schema::table(table)?;
+    get(&self.lock(), table, id)
+  }
+
+  /// Insert a record and return it as stored. `id` and the timestamps are
+  /// assigned by the store and ignored if given.
+  pub fn create(&self, table: &str, record: &Map<String, Value>) -> Result<Value> {
+    create(&self.lock(), schema::table(table)?, record)
+  }
+
+  /// Run `write` in one transaction, committed only if it returns `Ok`; on
+  /// an error nothing it wrote is kept.
+  pub fn transaction<T>(&self, write: impl FnOnce(&Transaction<'_>) -> Result<T>) -> Result<T> {
+    let mut connection = self.lock();
+    let transaction = Transaction {
+      inner: connection.transaction()?,
+    };
+    let value = write(&transaction)?;
+    transaction.inner.commit()?;
+    Ok(value)
+  }
+
+  /// Change the given fields of a record, touching `updatedAt` where the
+  /// table has one, and return the updated record.
+  pub fn update(&self, table: &str, id: i64, changes: &Map<String, Value>) -> Result<Value> {
+    let table = schema::table(table)?;
+    let (columns, mut values) = writable_fields(table, changes)?;
+    let mut assignments: Vec<String> = columns.iter().map(|column| format!("{column} = ?")).collect();
+    if table.has_updated_at() {
+      assignments.push(format!("updated_at = {}", schema::NOW));
+    }
+
+    let connection = self.lock();
+    if !assignments.is_empty() {
+      let sql = format!("UPDATE {} SET {} WHERE id = ?", table.name, assignments.join(", "));
+      values.push(SqlValue::Integer(id));
+      connection.execute(&sql, params_from_iter(values))?;
+    }
+    get(&connection, table, id)
+  }
+
+  /// Delete a record; `false` if there was none. Dependent rows go with it
+  /// where the schema cascades.
+  pub fn delete(&self, table: &str, id: i64) -> Result<bool> {
+    let table = schema::table(table)?;
+    let sql = format!("DELETE FROM {} WHERE id = ?", table.name);
+    Ok(self.lock().execute(&sql, [id])? > 0)
+  }
+
+  fn lock(&self) -> MutexGuard<'_, Connection> {
+    self.connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+}
+
+/// Writes inside [`Store::transaction`].
+pub struct Transaction<'a> {
+  inner: rusqlite::Transaction<'a>,
+}
+
+impl Transaction<'_> {
+  /// [`Store::create`], as part of the transaction.
+  pub fn create(&self, table: &str, record: &Map<String, Value>) -> Result<Value> {
+    create(&self.inner, schema::table(table)?, record)
+  }
+}
+
+fn create(connection: &Connection, table: &Table, record: &Map<String, Value>) -> Result<Value> {
+  let (columns, values) = writable_fields(table, record)?;
+  let sql = if columns.is_empty() {
+    format!("INSERT INTO {} DEFAULT VALUES", table.name)
+  } else {
+    let placeholders = vec!["?"; columns.len()].join(", ");
+    format!("INSERT INTO {} ({}) VALUES ({placeholders})", table.name, columns.join(", "))
+  };
+  connection.execute(&sql, params_from_iter(values))?;
+  get(connection, table, connection.last_insert_rowid())
+}
+
+fn select(table: &Table) -> String {
+  let columns: Vec<&str> = table.columns.iter().map(|column| column.name).collect();
+  format!("SELECT {} FROM {}", columns.join(", "), table.name)
+}
+
+fn get(connection: &Connection, table: &Table, id: i64) -> Result<Value> {
+  let sql = format!("{} WHERE id = ?", select(table));
+  let row = connection
+    .query_row(&sql, [id], |row| read_row(table, row))
+    .optional()?
+    .ok_or_else(|| Error::NotFound(format!("{} record {id}", table.name)))?;
+  to_record(table, row)
+}
+
+/// The writable columns named in `record` and their values. Unknown fields
+/// are an error; read-only ones are skipped.
+fn writable_fields(table: &Table, record: &Map<String, Value>) -> Result<(Vec<&'static str>, Vec<SqlValue>)> {
+  let mut columns = Vec::new();
+  let mut values = Vec::new();
+  for (field, value) in record {
+    let column = table.column(field)?;
+    if column.writable() {
+      columns.push(column.name);
+      values.push(to_sql(column, value)?);
+    }
+  }
+  Ok((columns, values))
+}
+
+fn to_sql(column: &Column, value: &Value) -> Result<SqlValue> {
+  let invalid = |expected: &str| Error::InvalidRequest(format!("`{}` must be {expected}", column.field));
+  Ok(match (column.kind, value) {
+    (_, Value::Null) => SqlValue::Null,
+    (ColumnKind::Json, value) => SqlValue::Text(value.to_string()),
+    (ColumnKind::Id | ColumnKind::Integer, value) => SqlValue::Integer(value.as_i64().ok_or_else(|| invalid("an integer"))?),
+    (ColumnKind::Text | ColumnKind::Timestamp, value) => {
+      SqlValue::Text(value.as_str().ok_or_else(|| invalid("a string"))?.to_string())
+    }
+  })
+}
+
+fn read_row(table: &Table, row: &Row<'_>) -> rusqlite::Result<Vec<SqlValue>> {
+  (0..table.columns.len()).map(|index| row.get(index)).collect()
+}
+
+fn to_record(table: &Table, row: Vec<SqlValue>) -> Result<Value> {
+  let mut record = Map::new();
+  for (column, value) in table.columns.iter().zip(row) {
+    let value = match value {
+      SqlValue::Null => Value::Null,
+      SqlValue::Integer(integer) => Value::from(integer),
+      SqlValue::Real(real) => Value::from(real),
+      SqlValue::Text(text) if column.kind == ColumnKind::Json => serde_json::from_str(&text)?,
+      SqlValue::Text(text) => Value::String(text),
+      SqlValue::Blob(_) => Value::Null,
+    };
+    record.insert(column.field.to_string(), value);
+  }
+  Ok(Value::Object(record))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn object(value: Value) -> Map<String, Value> {
+    value.as_object().cloned().unwrap()
+  }
+
+  #[test]
+  fn round_trips_records_and_json_columns() {
+    let store = Store::open_in_memory().unwrap();
+    let project = store
+      .create("projects", &object(json!({ "name": "Shop", "projectJson": { "apis": [] } })))
+      .unwrap();
+    let project_id = project["id"].as_i64().unwrap();
+    assert_eq!(project["projectJson"], json!({ "apis": [] }));
+    assert!(project["createdAt"].as_str().unwrap().ends_with('Z'));
+
+    let flow = store
+      .create(
+        "test_flows",
+        &object(json!({ "id": 99, "name": "Login", "projectId": project_id, "flowJson": { "steps": [] } })),
+      )
+      .unwrap();
+    assert_eq!(flow["id"], json!(1));
+    assert_eq!(flow["draftOf"], Value::Null);
+
+    let updated = store
+      .update("test_flows", 1, &object(json!({ "name": "Login v2", "flowJson": { "steps": [1] } })))
+      .unwrap();
+    assert_eq!(updated["name"], json!("Login v2"));
+    assert_eq!(updated["flowJson"], json!({ "steps": [1] }));
+
+    let found = store.list("test_flows", &object(json!({ "projectId": project_id }))).unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(store.list("test_flows", &object(json!({ "environmentId": 5 }))).unwrap().is_empty());
+  }
+
+  #[test]
+  fn cascades_and_reports_missing_records() {
+    let store = Store::open_in_memory().unwrap();
+    store.create("projects", &object(json!({ "name": "P" }))).unwrap();
+    store
+      .create("project_modules", &object(json!({ "projectId": 1, "name": "Checkout" })))
+      .unwrap();
+    store
+      .create(
+        "flow_sequences",
+        &object(json!({ "moduleId": 1, "name": "Happy path", "sequenceConfig": { "steps": [] } })),
+      )
+      .unwrap();
+
+    assert!(store.delete("projects", 1).unwrap());
+    assert!(store.list("flow_sequences", &Map::new()).unwrap().is_empty());
+    assert!(!store.delete("projects", 1).unwrap());
+    assert!(matches!(store.get("projects", 1), Err(Error::NotFound(_))));
+    assert!(matches!(store.update("projects", 1, &Map::new()), Err(Error::NotFound(_))));
+  }
+
+  #[test]
+  fn failed_transactions_write_nothing() {
+    let store = Store::open_in_memory().unwrap();
+    let shop = object(json!({ "name": "Shop", "specFormat": "openapi", "specContent": "{}" }));
+    let written = store.transaction(|transaction| {
+      transaction.create("apis", &shop)?;
+      transaction.create("api_endpoints", &object(json!({ "apiId": 1, "path": "/a", "method": "GET", "owner": 1 })))
+    });
+    assert!(matches!(written, Err(Error::InvalidRequest(_))));
+    assert!(store.list("apis", &Map::new()).unwrap().is_empty());
+
+    let api = store.transaction(|transaction| transaction.create("apis", &shop)).unwrap();
+    assert_eq!(store.get("apis", 1).unwrap(), api);
+  }
+
+  #[test]
+  fn rejects_unknown_tables_and_fields() {
+    let store = Store::open_in_memory().unwrap();
+    assert!(matches!(store.list("users", &Map::new()), Err(Error::InvalidRequest(_))));
+    assert!(matches!(
+      store.create("projects", &object(json!({ "name": "P", "owner": 1 }))),
+      Err(Error::InvalidRequest(_))
+    ));
+    assert!(matches!(
+      store.create("apis", &object(json!({ "name": "P", "projectId": "one" }))),
+      Err(Error::InvalidRequest(_))
+    ));
+  }
+}
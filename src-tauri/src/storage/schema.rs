@@ -0,0 +1,392 @@
+//! Table layout and migrations of the local database.
+//!
+//! Migrations are applied in order and tracked with `PRAGMA user_version`;
+//! only ever append to [`MIGRATIONS`].
+
+use rusqlite::Connection;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+  /// The `INTEGER PRIMARY KEY`, assigned by SQLite.
+  Id,
+  Integer,
+  Text,
+  /// Any JSON value, stored as text.
+  Json,
+  /// An ISO 8601 timestamp maintained by the store.
+  Timestamp,
+}
+
+#[derive(Debug)]
+pub struct Column {
+  pub name: &'static str,
+  /// The camelCase field name records use, as in the Drizzle models.
+  pub field: &'static str,
+  pub kind: ColumnKind,
+}
+
+impl Column {
+  pub fn writable(&self) -> bool {
+    matches!(self.kind, ColumnKind::Integer | ColumnKind::Text | ColumnKind::Json)
+  }
+}
+
+#[derive(Debug)]
+pub struct Table {
+  pub name: &'static str,
+  pub columns: &'static [Column],
+}
+
+impl Table {
+  pub fn column(&self, field: &str) -> Result<&Column> {
+    self
+      .columns
+      .iter()
+      .find(|column| column.field == field)
+      .ok_or_else(|| Error::InvalidRequest(format!("`{}` has no field `{field}`", self.name)))
+  }
+
+  pub fn has_updated_at(&self) -> bool {
+    self.columns.iter().any(|column| column.name == "updated_at")
+  }
+}
+
+/// Look up a table by name.
+pub fn table(name: &str) -> Result<&'static Table> {
+  TABLES
+    .iter()
+    .find(|table| table.name == name)
+    .ok_or_else(|| Error::InvalidRequest(format!("unknown table `{name}`")))
+}
+
+/// SQLite expression for the current time, formatted like `Date.toISOString`.
+pub const NOW: &str = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+
+macro_rules! columns {
+  ($($name:literal $field:literal $kind:ident),* $(,)?) => {
+    &[$(Column { name: $name, field: $field, kind: ColumnKind::$kind }),*]
+  };
+}
+
+pub static TABLES: &[Table] = &[
+  Table {
+    name: "projects",
+    columns: columns![
+      "id" "id" Id,
+      "name" "name" Text,
+      "description" "description" Text,
+      "agent_context" "agentContext" Text,
+      "project_json" "projectJson" Json,
+      "created_at" "createdAt" Timestamp,
+      "updated_at" "updatedAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "apis",
+    columns: columns![
+      "id" "id" Id,
+      "name" "name" Text,
+      "description" "description" Text,
+      "spec_format" "specFormat" Text,
+      "spec_content" "specContent" Text,
+      "host" "host" Text,
+      "project_id" "projectId" Integer,
+      "created_at" "createdAt" Timestamp,
+      "updated_at" "updatedAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "api_endpoints",
+    columns: columns![
+      "id" "id" Id,
+      "api_id" "apiId" Integer,
+      "path" "path" Text,
+      "method" "method" Text,
+      "operation_id" "operationId" Text,
+      "summary" "summary" Text,
+      "description" "description" Text,
+      "request_schema" "requestSchema" Json,
+      "response_schema" "responseSchema" Json,
+      "parameters" "parameters" Json,
+      "tags" "tags" Json,
+      "created_at" "createdAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "test_flows",
+    columns: columns![
+      "id" "id" Id,
+      "name" "name" Text,
+      "description" "description" Text,
+      "project_id" "projectId" Integer,
+      "flow_json" "flowJson" Json,
+      "environment_id" "environmentId" Integer,
+      "draft_of" "draftOf" Integer,
+      "created_at" "createdAt" Timestamp,
+      "updated_at" "updatedAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "test_flow_apis",
+    columns: columns![
+      "id" "id" Id,
+      "test_flow_id" "testFlowId" Integer,
+      "api_id" "apiId" Integer,
+    ],
+  },
+  Table {
+    name: "project_test_flows",
+    columns: columns![
+      "id" "id" Id,
+      "project_id" "projectId" Integer,
+      "test_flow_id" "testFlowId" Integer,
+      "created_at" "createdAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "environments",
+    columns: columns![
+      "id" "id" Id,
+      "name" "name" Text,
+      "description" "description" Text,
+      "config" "config" Json,
+      "created_at" "createdAt" Timestamp,
+      "updated_at" "updatedAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "environment_apis",
+    columns: columns![
+      "id" "id" Id,
+      "environment_id" "environmentId" Integer,
+      "api_id" "apiId" Integer,
+      "created_at" "createdAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "project_apis",
+    columns: columns![
+      "id" "id" Id,
+      "project_id" "projectId" Integer,
+      "api_id" "apiId" Integer,
+      "default_host" "defaultHost" Text,
+      "created_at" "createdAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "project_modules",
+    columns: columns![
+      "id" "id" Id,
+      "project_id" "projectId" Integer,
+      "name" "name" Text,
+      "description" "description" Text,
+      "display_order" "displayOrder" Integer,
+      "created_at" "createdAt" Timestamp,
+      "updated_at" "updatedAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "flow_sequences",
+    columns: columns![
+      "id" "id" Id,
+      "module_id" "moduleId" Integer,
+      "name" "name" Text,
+      "description" "description" Text,
+      "sequence_config" "sequenceConfig" Json,
+      "display_order" "displayOrder" Integer,
+      "created_at" "createdAt" Timestamp,
+      "updated_at" "updatedAt" Timestamp,
+    ],
+  },
+  Table {
+    name: "project_environments",
+    columns: columns![
+      "id" "id" Id,
+      "project_id" "projectId" Integer,
+      "environment_id" "environmentId" Integer,
+      "variable_mappings" "variableMappings" Json,
+      "created_at" "createdAt" Timestamp,
+    ],
+  },
+];
+
+/// Schema changes, oldest first. Entry `n` moves the database to version
+/// `n + 1`.
+pub const MIGRATIONS: &[&str] = &[
+  // 1: the hosted schema without users, agent tokens or search vectors.
+  r#"
+  CREATE TABLE projects (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    agent_context TEXT,
+    project_json TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+  );
+
+  CREATE TABLE environments (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    config TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+  );
+
+  CREATE TABLE apis (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    spec_format TEXT NOT NULL,
+    spec_content TEXT NOT NULL,
+    host TEXT,
+    project_id INTEGER REFERENCES projects (id),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+  );
+  CREATE INDEX apis_project_id_idx ON apis (project_id);
+
+  CREATE TABLE api_endpoints (
+    id INTEGER PRIMARY KEY,
+    api_id INTEGER NOT NULL REFERENCES apis (id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    method TEXT NOT NULL,
+    operation_id TEXT,
+    summary TEXT,
+    description TEXT,
+    request_schema TEXT,
+    response_schema TEXT,
+    parameters TEXT,
+    tags TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+  );
+  CREATE INDEX api_endpoints_api_id_idx ON api_endpoints (api_id);
+
+  CREATE TABLE test_flows (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    project_id INTEGER REFERENCES projects (id),
+    flow_json TEXT NOT NULL,
+    environment_id INTEGER REFERENCES environments (id),
+    draft_of INTEGER REFERENCES test_flows (id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+  );
+  CREATE INDEX test_flows_project_id_idx ON test_flows (project_id);
+  CREATE INDEX test_flows_environment_id_idx ON test_flows (environment_id);
+  CREATE INDEX test_flows_draft_of_idx ON test_flows (draft_of);
+
+  CREATE TABLE test_flow_apis (
+    id INTEGER PRIMARY KEY,
+    test_flow_id INTEGER NOT NULL REFERENCES test_flows (id) ON DELETE CASCADE,
+    api_id INTEGER NOT NULL REFERENCES apis (id) ON DELETE CASCADE,
+    UNIQUE (test_flow_id, api_id)
+  );
+
+  CREATE TABLE project_test_flows (
+    id INTEGER PRIMARY KEY,
+    project_id INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    test_flow_id INTEGER NOT NULL REFERENCES test_flows (id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (project_id, test_flow_id)
+  );
+  CREATE INDEX project_test_flows_test_flow_id_idx ON project_test_flows (test_flow_id);
+
+  CREATE TABLE environment_apis (
+    id INTEGER PRIMARY KEY,
+    environment_id INTEGER NOT NULL REFERENCES environments (id) ON DELETE CASCADE,
+    api_id INTEGER NOT NULL REFERENCES apis (id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (environment_id, api_id)
+  );
+  CREATE INDEX environment_apis_api_id_idx ON environment_apis (api_id);
+
+  CREATE TABLE project_apis (
+    id INTEGER PRIMARY KEY,
+    project_id INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    api_id INTEGER NOT NULL REFERENCES apis (id) ON DELETE CASCADE,
+    default_host TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (project_id, api_id)
+  );
+
+  CREATE TABLE project_modules (
+    id INTEGER PRIMARY KEY,
+    project_id INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    display_order INTEGER DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+  );
+  CREATE INDEX project_modules_project_id_idx ON project_modules (project_id);
+
+  CREATE TABLE flow_sequences (
+    id INTEGER PRIMARY KEY,
+    module_id INTEGER NOT NULL REFERENCES project_modules (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    sequence_config TEXT NOT NULL,
+    display_order INTEGER DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+  );
+  CREATE INDEX flow_sequences_module_id_idx ON flow_sequences (module_id);
+
+  CREATE TABLE project_environments (
+    id INTEGER PRIMARY KEY,
+    project_id INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    environment_id INTEGER NOT NULL REFERENCES environments (id) ON DELETE CASCADE,
+    variable_mappings TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (project_id, environment_id)
+  );
+  "#,
+  // 2: run history, see `crate::history`. Not tied to flows by foreign key,
+  // so runs outlive the flows they ran.
+  r#"
+  CREATE TABLE run_history (
+    id INTEGER PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    test_flow_id INTEGER,
+    sequence_id INTEGER,
+    status TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    total INTEGER NOT NULL,
+    failed INTEGER NOT NULL,
+    error TEXT,
+    result TEXT NOT NULL
+  );
+  CREATE INDEX run_history_test_flow_id_idx ON run_history (test_flow_id);
+  CREATE INDEX run_history_sequence_id_idx ON run_history (sequence_id);
+  CREATE INDEX run_history_started_at_idx ON run_history (started_at);
+  "#,
+];
+
+/// Apply every migration the database has not seen yet, each in its own
+/// transaction.
+pub fn migrate(connection: &mut Connection) -> Result<()> {
+  let applied: i64 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+  if applied > MIGRATIONS.len() as i64 {
+    log::warn!(
+      "[storage] database is at version {applied}, newer than this build's {}",
+      MIGRATIONS.len()
+    );
+  }
+
+  for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied.max(0) as usize) {
+    let version = index as i64 + 1;
+    log::info!("[storage] applying migration {version}");
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(migration)?;
+    transaction.pragma_update(None, "user_version", version)?;
+    transaction.commit()?;
+  }
+  Ok(())
+}
@@ -0,0 +1,533 @@
+//! `defaultTemplateFunctions` from `src/lib/template/functions.ts`.
+//!
+//! Arguments arrive as parsed JSON values; like the TS versions, a function
+//! falls back to its default whenever an argument has the wrong type.
+//! Date functions work in the local time zone and accept Luxon format tokens.
+
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, Months, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rand::Rng;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+pub type TemplateFunction = fn(&[Value]) -> Result<Value>;
+
+/// Characters `encodeURIComponent` leaves alone.
+pub(crate) const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+  .remove(b'-')
+  .remove(b'_')
+  .remove(b'.')
+  .remove(b'!')
+  .remove(b'~')
+  .remove(b'*')
+  .remove(b'\'')
+  .remove(b'(')
+  .remove(b')');
+
+/// `atob` tolerates missing padding.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+  &alphabet::STANDARD,
+  GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+const DEFAULT_FUNCTIONS: &[(&str, TemplateFunction)] = &[
+  ("jsonPath", json_path_fn),
+  ("uuid", uuid),
+  ("timestamp", timestamp),
+  ("isoDate", iso_date),
+  ("dateFormat", date_format),
+  ("dateISO", date_iso),
+  ("dateRFC3339", date_rfc3339),
+  ("randomInt", random_int),
+  ("randomString", random_string),
+  ("base64Encode", base64_encode),
+  ("base64Decode", base64_decode),
+  ("urlEncode", url_encode),
+  ("urlDecode", url_decode),
+  ("dateInTimezone", date_in_timezone),
+  ("dateAdd", date_add),
+  ("dateSubtract", date_subtract),
+  ("dateStartOf", date_start_of),
+  ("dateEndOf", date_end_of),
+];
+
+pub fn lookup(name: &str) -> Option<TemplateFunction> {
+  DEFAULT_FUNCTIONS
+    .iter()
+    .find(|(function_name, _)| *function_name == name)
+    .map(|(_, function)| *function)
+}
+
+pub fn names() -> impl Iterator<Item = &'static str> {
+  DEFAULT_FUNCTIONS.iter().map(|(name, _)| *name)
+}
+
+/// Simplified JSONPath: dotted property access with an optional trailing
+/// `[index]` per segment. Anything that does not resolve yields `None`.
+pub fn extract_json_path<'v>(data: &'v Value, path: &str) -> Option<&'v Value> {
+  let clean = path
+    .strip_prefix("$.")
+    .or_else(|| path.strip_prefix('$'))
+    .unwrap_or(path);
+  if clean.is_empty() {
+    return Some(data);
+  }
+
+  let mut current = data;
+  for part in clean.split('.') {
+    current = match indexed_segment(part) {
+      Some((property, index)) => {
+        let container = if property.is_empty() { current } else { property_of(current, property)? };
+        container.as_array()?.get(index)?
+      }
+      None => property_of(current, part)?,
+    };
+  }
+  Some(current)
+}
+
+/// Split `name[3]` (or `[3]`) into its property and index.
+fn indexed_segment(part: &str) -> Option<(&str, usize)> {
+  let inner = part.strip_suffix(']')?;
+  let bracket = inner.rfind('[')?;
+  let digits = &inner[bracket + 1..];
+  if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+    return None;
+  }
+  Some((&inner[..bracket], digits.parse().ok()?))
+}
+
+fn property_of<'v>(value: &'v Value, property: &str) -> Option<&'v Value> {
+  match value {
+    Value::Object(map) => map.get(property),
+    Value::Array(items) => property.parse::<usize>().ok().and_then(|index| items.get(index)),
+    _ => None,
+  }
+}
+
+fn number_arg(args: &[Value], index: usize) -> Option<f64> {
+  args.get(index).filter(|value| value.is_number()).and_then(Value::as_f64)
+}
+
+fn string_arg(args: &[Value], index: usize) -> Option<&str> {
+  args.get(index).and_then(Value::as_str)
+}
+
+/// `String(args[index])`, or `""` when absent.
+fn text_arg(args: &[Value], index: usize) -> String {
+  args.get(index).map(super::js_string).unwrap_or_default()
+}
+
+fn json_number(number: f64) -> Value {
+  if number.fract() == 0.0 && number.abs() < 9_007_199_254_740_992.0 {
+    Value::from(number as i64)
+  } else {
+    serde_json::Number::from_f64(number).map(Value::Number).unwrap_or(Value::Null)
+  }
+}
+
+fn json_path_fn(args: &[Value]) -> Result<Value> {
+  let data = args.first().unwrap_or(&Value::Null);
+  Ok(extract_json_path(data, &text_arg(args, 1)).cloned().unwrap_or(Value::Null))
+}
+
+fn uuid(_: &[Value]) -> Result<Value> {
+  Ok(Value::String(uuid::Uuid::new_v4().to_string()))
+}
+
+fn timestamp(_: &[Value]) -> Result<Value> {
+  Ok(Value::from(Utc::now().timestamp_millis()))
+}
+
+fn iso_date(_: &[Value]) -> Result<Value> {
+  Ok(Value::String(to_iso(&local_now())))
+}
+
+fn date_format(args: &[Value]) -> Result<Value> {
+  let offset = number_arg(args, 0).unwrap_or(0.0);
+  let format = string_arg(args, 1).unwrap_or("yyyy-MM-dd");
+  Ok(Value::String(to_format(&plus_days(local_now(), offset), format, None)))
+}
+
+fn date_iso(args: &[Value]) -> Result<Value> {
+  let offset = number_arg(args, 0).unwrap_or(0.0);
+  Ok(Value::String(plus_days(local_now(), offset).format("%Y-%m-%d").to_string()))
+}
+
+fn date_rfc3339(args: &[Value]) -> Result<Value> {
+  let offset = number_arg(args, 0).unwrap_or(0.0);
+  Ok(Value::String(to_iso(&plus_days(local_now(), offset))))
+}
+
+fn random_int(args: &[Value]) -> Result<Value> {
+  let min = number_arg(args, 0).unwrap_or(0.0);
+  let max = number_arg(args, 1).unwrap_or(100.0);
+  let random: f64 = rand::thread_rng().gen();
+  Ok(json_number((random * (max - min + 1.0)).floor() + min))
+}
+
+fn random_string(args: &[Value]) -> Result<Value> {
+  const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+  let length = number_arg(args, 0).unwrap_or(10.0).max(0.0).ceil() as usize;
+  let mut rng = rand::thread_rng();
+  let text = (0..length).map(|_| CHARS[rng.gen_range(0..CHARS.len())] as char).collect();
+  Ok(Value::String(text))
+}
+
+/// `btoa`: only Latin-1 input can be encoded; anything else yields `""`.
+fn base64_encode(args: &[Value]) -> Result<Value> {
+  let input = text_arg(args, 0);
+  let bytes: Option<Vec<u8>> = input.chars().map(|c| u8::try_from(u32::from(c)).ok()).collect();
+  Ok(Value::String(bytes.map(|bytes| BASE64.encode(bytes)).unwrap_or_default()))
+}
+
+/// `atob`: decoded bytes become Latin-1 characters; invalid input yields `""`.
+fn base64_decode(args: &[Value]) -> Result<Value> {
+  let input: String = text_arg(args, 0).chars().filter(|c| !c.is_ascii_whitespace()).collect();
+  let decoded = BASE64
+    .decode(input)
+    .map(|bytes| bytes.into_iter().map(char::from).collect())
+    .unwrap_or_default();
+  Ok(Value::String(decoded))
+}
+
+fn url_encode(args: &[Value]) -> Result<Value> {
+  Ok(Value::String(utf8_percent_encode(&text_arg(args, 0), URI_COMPONENT).to_string()))
+}
+
+/// `decodeURIComponent`: malformed escapes yield `""`.
+fn url_decode(args: &[Value]) -> Result<Value> {
+  let input = text_arg(args, 0);
+  let bytes = input.as_bytes();
+  let well_formed = bytes.iter().enumerate().all(|(i, b)| {
+    *b != b'%' || (bytes.len() > i + 2 && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit())
+  });
+  let decoded = well_formed
+    .then(|| percent_decode_str(&input).decode_utf8().ok().map(|text| text.into_owned()))
+    .flatten()
+    .unwrap_or_default();
+  Ok(Value::String(decoded))
+}
+
+fn date_in_timezone(args: &[Value]) -> Result<Value> {
+  let zone = string_arg(args, 0).unwrap_or("UTC");
+  let format = string_arg(args, 1).unwrap_or("");
+  let Some((now, zone_name)) = now_in_zone(zone) else {
+    // Luxon yields an invalid DateTime for unknown zones.
+    return Ok(Value::String(if format.is_empty() { String::new() } else { "Invalid DateTime".to_string() }));
+  };
+  let text = if format.is_empty() { to_iso(&now) } else { to_format(&now, format, Some(&zone_name)) };
+  Ok(Value::String(text))
+}
+
+fn date_add(args: &[Value]) -> Result<Value> {
+  shift_now(args, 1.0)
+}
+
+fn date_subtract(args: &[Value]) -> Result<Value> {
+  shift_now(args, -1.0)
+}
+
+fn shift_now(args: &[Value], sign: f64) -> Result<Value> {
+  let amount = number_arg(args, 0).unwrap_or(0.0) * sign;
+  let unit = string_arg(args, 1).unwrap_or("days");
+  Ok(Value::String(to_iso(&plus(local_now(), amount, normalize_unit(unit)?))))
+}
+
+fn date_start_of(args: &[Value]) -> Result<Value> {
+  let unit = normalize_unit(string_arg(args, 0).unwrap_or("day"))?;
+  let offset = number_arg(args, 1).unwrap_or(0.0);
+  Ok(Value::String(to_iso(&start_of(plus_days(local_now(), offset), unit))))
+}
+
+fn date_end_of(args: &[Value]) -> Result<Value> {
+  let unit = normalize_unit(string_arg(args, 0).unwrap_or("day"))?;
+  let offset = number_arg(args, 1).unwrap_or(0.0);
+  let start = start_of(plus_days(local_now(), offset), unit);
+  let end = plus(start, 1.0, unit) - Duration::milliseconds(1);
+  Ok(Value::String(to_iso(&end)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+  Year,
+  Quarter,
+  Month,
+  Week,
+  Day,
+  Hour,
+  Minute,
+  Second,
+  Millisecond,
+}
+
+/// Luxon accepts singular and plural unit names.
+fn normalize_unit(unit: &str) -> Result<Unit> {
+  Ok(match unit.to_ascii_lowercase().trim_end_matches('s') {
+    "year" => Unit::Year,
+    "quarter" => Unit::Quarter,
+    "month" => Unit::Month,
+    "week" => Unit::Week,
+    "day" => Unit::Day,
+    "hour" => Unit::Hour,
+    "minute" => Unit::Minute,
+    "second" => Unit::Second,
+    "millisecond" => Unit::Millisecond,
+    _ => return Err(Error::Template(format!("Invalid unit {unit}"))),
+  })
+}
+
+fn local_now() -> DateTime<FixedOffset> {
+  Local::now().fixed_offset()
+}
+
+fn now_in_zone(zone: &str) -> Option<(DateTime<FixedOffset>, String)> {
+  let now = Utc::now();
+  if zone.eq_ignore_ascii_case("utc") || zone.eq_ignore_ascii_case("z") {
+    return Some((now.fixed_offset(), "UTC".to_string()));
+  }
+  if zone.eq_ignore_ascii_case("local") || zone.eq_ignore_ascii_case("system") {
+    return Some((now.with_timezone(&Local).fixed_offset(), zone.to_string()));
+  }
+  let tz: chrono_tz::Tz = zone.parse().ok()?;
+  Some((now.with_timezone(&tz).fixed_offset(), tz.name().to_string()))
+}
+
+/// Re-anchor a wall-clock time in the zone of `reference`, keeping local
+/// time across DST changes like Luxon's calendar math does.
+fn at_local(reference: &DateTime<FixedOffset>, naive: NaiveDateTime) -> DateTime<FixedOffset> {
+  if reference.offset().local_minus_utc() == Local::now().offset().local_minus_utc() {
+    if let Some(local) = Local.from_local_datetime(&naive).earliest() {
+      return local.fixed_offset();
+    }
+  }
+  reference.offset().from_local_datetime(&naive).single().unwrap_or(*reference)
+}
+
+fn plus_days(date: DateTime<FixedOffset>, days: f64) -> DateTime<FixedOffset> {
+  plus(date, days, Unit::Day)
+}
+
+fn plus(date: DateTime<FixedOffset>, amount: f64, unit: Unit) -> DateTime<FixedOffset> {
+  let whole = amount.trunc() as i64;
+  let calendar = |months: i64| {
+    let shifted = if months >= 0 {
+      date.naive_local().checked_add_months(Months::new(months as u32))
+    } else {
+      date.naive_local().checked_sub_months(Months::new(months.unsigned_abs() as u32))
+    };
+    shifted.map(|naive| at_local(&date, naive)).unwrap_or(date)
+  };
+  let exact = |millis: f64| date + Duration::milliseconds(millis.round() as i64);
+
+  match unit {
+    Unit::Year => calendar(whole * 12),
+    Unit::Quarter => calendar(whole * 3),
+    Unit::Month => calendar(whole),
+    Unit::Week | Unit::Day => {
+      let days = if unit == Unit::Week { amount * 7.0 } else { amount };
+      let naive = date.naive_local() + Duration::days(days.trunc() as i64);
+      at_local(&date, naive) + Duration::milliseconds((days.fract() * 86_400_000.0).round() as i64)
+    }
+    Unit::Hour => exact(amount * 3_600_000.0),
+    Unit::Minute => exact(amount * 60_000.0),
+    Unit::Second => exact(amount * 1_000.0),
+    Unit::Millisecond => exact(amount),
+  }
+}
+
+fn start_of(date: DateTime<FixedOffset>, unit: Unit) -> DateTime<FixedOffset> {
+  let naive = date.naive_local();
+  let day = naive.date();
+  let midnight = |d: NaiveDate| d.and_hms_opt(0, 0, 0).unwrap_or(naive);
+  let truncated = match unit {
+    Unit::Year => midnight(NaiveDate::from_ymd_opt(day.year(), 1, 1).unwrap_or(day)),
+    Unit::Quarter => {
+      let month = (day.month0() / 3) * 3 + 1;
+      midnight(NaiveDate::from_ymd_opt(day.year(), month, 1).unwrap_or(day))
+    }
+    Unit::Month => midnight(NaiveDate::from_ymd_opt(day.year(), day.month(), 1).unwrap_or(day)),
+    Unit::Week => midnight(day - Duration::days(i64::from(day.weekday().num_days_from_monday()))),
+    Unit::Day => midnight(day),
+    Unit::Hour => day.and_hms_opt(naive.hour(), 0, 0).unwrap_or(naive),
+    Unit::Minute => day.and_hms_opt(naive.hour(), naive.minute(), 0).unwrap_or(naive),
+    Unit::Second => day.and_hms_opt(naive.hour(), naive.minute(), naive.second()).unwrap_or(naive),
+    Unit::Millisecond => naive,
+  };
+  at_local(&date, truncated)
+}
+
+/// Luxon's `toISO()`: millisecond precision with a `+hh:mm` offset.
+fn to_iso(date: &DateTime<FixedOffset>) -> String {
+  date.format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string()
+}
+
+const MONTHS: [&str; 12] = [
+  "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December",
+];
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+/// Format with Luxon `toFormat` tokens. Text in single quotes is literal and
+/// unknown tokens are copied through, as Luxon does.
+pub fn to_format(date: &DateTime<FixedOffset>, format: &str, zone_name: Option<&str>) -> String {
+  let chars: Vec<char> = format.chars().collect();
+  let mut out = String::new();
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    if c == '\'' {
+      let end = chars[i + 1..].iter().position(|&ch| ch == '\'').map(|p| i + 1 + p);
+      match end {
+        Some(end) if end == i + 1 => out.push('\''),
+        Some(end) => out.extend(&chars[i + 1..end]),
+        None => out.extend(&chars[i + 1..]),
+      }
+      i = end.map_or(chars.len(), |end| end + 1);
+      continue;
+    }
+    let mut run = 1;
+    while i + run < chars.len() && chars[i + run] == c {
+      run += 1;
+    }
+    let token: String = chars[i..i + run].iter().collect();
+    out.push_str(&format_token(date, &token, zone_name).unwrap_or(token));
+    i += run;
+  }
+  out
+}
+
+fn format_token(date: &DateTime<FixedOffset>, token: &str, zone_name: Option<&str>) -> Option<String> {
+  let month = date.month0() as usize;
+  let weekday = date.weekday().num_days_from_monday() as usize;
+  let hour12 = match date.hour() % 12 {
+    0 => 12,
+    h => h,
+  };
+  let offset_minutes = date.offset().local_minus_utc() / 60;
+  let sign = if offset_minutes < 0 { '-' } else { '+' };
+  let (offset_h, offset_m) = (offset_minutes.abs() / 60, offset_minutes.abs() % 60);
+
+  Some(match token {
+    "S" => date.timestamp_subsec_millis().to_string(),
+    "SSS" => format!("{:03}", date.timestamp_subsec_millis()),
+    "s" => date.second().to_string(),
+    "ss" => format!("{:02}", date.second()),
+    "m" => date.minute().to_string(),
+    "mm" => format!("{:02}", date.minute()),
+    "h" => hour12.to_string(),
+    "hh" => format!("{hour12:02}"),
+    "H" => date.hour().to_string(),
+    "HH" => format!("{:02}", date.hour()),
+    "a" => if date.hour() < 12 { "AM" } else { "PM" }.to_string(),
+    "Z" if offset_m == 0 => format!("{sign}{offset_h}"),
+    "Z" => format!("{sign}{offset_h}:{offset_m:02}"),
+    "ZZ" => format!("{sign}{offset_h:02}:{offset_m:02}"),
+    "ZZZ" => format!("{sign}{offset_h:02}{offset_m:02}"),
+    "z" => zone_name.map(str::to_string).unwrap_or_else(|| format!("UTC{sign}{offset_h}")),
+    "d" => date.day().to_string(),
+    "dd" => format!("{:02}", date.day()),
+    "o" => date.ordinal().to_string(),
+    "ooo" => format!("{:03}", date.ordinal()),
+    "c" | "E" => (weekday + 1).to_string(),
+    "ccc" | "EEE" => WEEKDAYS[weekday][..3].to_string(),
+    "cccc" | "EEEE" => WEEKDAYS[weekday].to_string(),
+    "ccccc" | "EEEEE" => WEEKDAYS[weekday][..1].to_string(),
+    "L" | "M" => (month + 1).to_string(),
+    "LL" | "MM" => format!("{:02}", month + 1),
+    "LLL" | "MMM" => MONTHS[month][..3].to_string(),
+    "LLLL" | "MMMM" => MONTHS[month].to_string(),
+    "LLLLL" | "MMMMM" => MONTHS[month][..1].to_string(),
+    "q" => (month / 3 + 1).to_string(),
+    "qq" => format!("{:02}", month / 3 + 1),
+    "y" => date.year().to_string(),
+    "yy" => format!("{:02}", date.year().rem_euclid(100)),
+    "yyyy" => format!("{:04}", date.year()),
+    "yyyyyy" => format!("{:06}", date.year()),
+    "W" => date.iso_week().week().to_string(),
+    "WW" => format!("{:02}", date.iso_week().week()),
+    "kk" => format!("{:02}", date.iso_week().year().rem_euclid(100)),
+    "kkkk" => format!("{:04}", date.iso_week().year()),
+    "G" => if date.year() > 0 { "AD" } else { "BC" }.to_string(),
+    "X" => date.timestamp().to_string(),
+    "x" => date.timestamp_millis().to_string(),
+    _ => return None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn call(name: &str, args: &[Value]) -> Value {
+    lookup(name).unwrap()(args).unwrap()
+  }
+
+  #[test]
+  fn json_path_supports_properties_and_indexes() {
+    let data = json!({ "items": [{ "id": 1 }, { "id": 2 }], "name": "x" });
+    assert_eq!(extract_json_path(&data, "$.items[1].id"), Some(&json!(2)));
+    assert_eq!(extract_json_path(&data, "items.0.id"), Some(&json!(1)));
+    assert_eq!(extract_json_path(&data, "$"), Some(&data));
+    assert_eq!(extract_json_path(&data, "$.name.first"), None);
+  }
+
+  #[test]
+  fn uuid_is_v4() {
+    let id = call("uuid", &[]);
+    let id = id.as_str().unwrap();
+    assert_eq!(id.len(), 36);
+    assert_eq!(&id[14..15], "4");
+  }
+
+  #[test]
+  fn random_int_respects_bounds() {
+    for _ in 0..100 {
+      let value = call("randomInt", &[json!(10), json!(20)]).as_i64().unwrap();
+      assert!((10..=20).contains(&value));
+    }
+    assert_eq!(call("randomString", &[json!(5)]).as_str().unwrap().len(), 5);
+  }
+
+  #[test]
+  fn base64_and_url_round_trip() {
+    assert_eq!(call("base64Encode", &[json!("user:pass")]), json!("dXNlcjpwYXNz"));
+    assert_eq!(call("base64Decode", &[json!("dXNlcjpwYXNz")]), json!("user:pass"));
+    assert_eq!(call("base64Encode", &[json!("€")]), json!(""));
+    assert_eq!(call("urlEncode", &[json!("a b&c/d")]), json!("a%20b%26c%2Fd"));
+    assert_eq!(call("urlDecode", &[json!("a%20b%26c")]), json!("a b&c"));
+    assert_eq!(call("urlDecode", &[json!("%zz")]), json!(""));
+  }
+
+  #[test]
+  fn formats_luxon_tokens() {
+    let date = FixedOffset::east_opt(7 * 3600)
+      .unwrap()
+      .with_ymd_and_hms(2024, 3, 5, 14, 7, 9)
+      .unwrap();
+    assert_eq!(to_format(&date, "yyyy-MM-dd", None), "2024-03-05");
+    assert_eq!(to_format(&date, "dd/LL/yy HH:mm:ss", None), "05/03/24 14:07:09");
+    assert_eq!(to_format(&date, "cccc, LLLL d 'at' h a", None), "Tuesday, March 5 at 2 PM");
+    assert_eq!(to_format(&date, "ZZ", None), "+07:00");
+    assert_eq!(to_iso(&date), "2024-03-05T14:07:09.000+07:00");
+  }
+
+  #[test]
+  fn start_and_end_of_periods() {
+    let date = FixedOffset::east_opt(0).unwrap().with_ymd_and_hms(2024, 5, 15, 10, 30, 0).unwrap();
+    assert_eq!(to_iso(&start_of(date, Unit::Month)), "2024-05-01T00:00:00.000+00:00");
+    assert_eq!(to_iso(&start_of(date, Unit::Week)), "2024-05-13T00:00:00.000+00:00");
+    let end = plus(start_of(date, Unit::Year), 1.0, Unit::Year) - Duration::milliseconds(1);
+    assert_eq!(to_iso(&end), "2024-12-31T23:59:59.999+00:00");
+  }
+
+  #[test]
+  fn unknown_units_are_errors() {
+    assert!(lookup("dateAdd").unwrap()(&[json!(1), json!("fortnights")]).is_err());
+    assert!(call("dateInTimezone", &[json!("Asia/Ho_Chi_Minh")]).as_str().unwrap().ends_with("+07:00"));
+  }
+}
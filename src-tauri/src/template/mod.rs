@@ -0,0 +1,599 @@
+//! Template expression engine, ported from `src/lib/template/engine.ts`.
+//!
+//! Expressions take the form `{{source:path}}`, where source is `res`, `proc`,
+//! `param`, `func` or `env` (or one of their aliases). A template that is a
+//! single unquoted expression keeps the resolved value's type. Inside larger
+//! templates, `{{...}}` is stringified and `"{{{...}}}"` is replaced by the
+//! value's JSON, so a JSON body template keeps numbers and booleans intact.
+
+pub mod functions;
+
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+
+/// Values a template can draw from.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateContext {
+  /// Response bodies keyed by `{step_id}-{index}`.
+  #[serde(default)]
+  pub responses: Map<String, Value>,
+  /// Transformation results keyed by `{step_id}-{index}`, then by alias.
+  #[serde(default)]
+  pub transformed_data: Map<String, Value>,
+  #[serde(default)]
+  pub parameters: Map<String, Value>,
+  /// `None` means no environment is selected, which `{{env:...}}` reports.
+  #[serde(default)]
+  pub environment: Option<Map<String, Value>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+  Res,
+  Proc,
+  Param,
+  Func,
+  Env,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateExpression {
+  pub source: Source,
+  pub path: String,
+  /// Written with triple braces.
+  pub preserve_type: bool,
+}
+
+/// Mirrors `TemplateResolutionResult`: on failure `value` is the original template.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateResolution {
+  pub value: Value,
+  pub success: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+impl TemplateResolution {
+  fn ok(value: Value) -> Self {
+    Self {
+      value,
+      success: true,
+      error: None,
+    }
+  }
+
+  fn failed(template: &str, error: Error) -> Self {
+    Self {
+      value: Value::String(template.to_string()),
+      success: false,
+      error: Some(error.to_string()),
+    }
+  }
+}
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+  cell.get_or_init(|| Regex::new(pattern).expect("valid template regex"))
+}
+
+fn double_brace() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  regex(&RE, r"\{\{[^}]+\}\}")
+}
+
+fn quoted_triple_brace() -> &'static Regex {
+  static RE: OnceLock<Regex> = OnceLock::new();
+  regex(&RE, r#""\{\{\{[^}]+\}\}\}""#)
+}
+
+pub fn has_template_expressions(value: &str) -> bool {
+  double_brace().is_match(value)
+}
+
+pub fn parse_template_expression(expression: &str) -> Option<TemplateExpression> {
+  static TRIPLE: OnceLock<Regex> = OnceLock::new();
+  static DOUBLE: OnceLock<Regex> = OnceLock::new();
+
+  let (captures, preserve_type) = match regex(&TRIPLE, r"^\{\{\{([^:]+):(.+)\}\}\}$").captures(expression) {
+    Some(captures) => (captures, true),
+    None => (regex(&DOUBLE, r"^\{\{([^:]+):(.+)\}\}$").captures(expression)?, false),
+  };
+  Some(TemplateExpression {
+    source: parse_source(&captures[1])?,
+    path: captures[2].trim().to_string(),
+    preserve_type,
+  })
+}
+
+fn parse_source(source: &str) -> Option<Source> {
+  Some(match source.trim().to_lowercase().as_str() {
+    "res" | "response" => Source::Res,
+    "proc" | "process" | "transform" => Source::Proc,
+    "param" | "parameter" | "var" => Source::Param,
+    "func" | "function" => Source::Func,
+    "env" | "environment" => Source::Env,
+    _ => return None,
+  })
+}
+
+/// Resolve a template, reporting failure in the result instead of erroring.
+pub fn resolve_template_expression(template: &str, context: &TemplateContext) -> TemplateResolution {
+  if !has_template_expressions(template) {
+    return TemplateResolution::ok(Value::String(template.to_string()));
+  }
+  resolve_single_expression(template, context).unwrap_or_else(|| resolve_multiple_expressions(template, context))
+}
+
+/// Resolve a template, failing on the first expression that cannot be resolved.
+pub fn resolve_template(template: &str, context: &TemplateContext) -> Result<Value> {
+  let resolution = resolve_template_expression(template, context);
+  if resolution.success {
+    Ok(resolution.value)
+  } else {
+    Err(Error::Template(
+      resolution.error.unwrap_or_else(|| "Template resolution failed".to_string()),
+    ))
+  }
+}
+
+/// Resolve a header, path or query value: the result as a string, `""` for null.
+pub fn resolve_template_string(template: &str, context: &TemplateContext) -> Result<String> {
+  Ok(match resolve_template(template, context)? {
+    Value::Null => String::new(),
+    value => js_string(&value),
+  })
+}
+
+/// Resolve every expression inside a JSON value, e.g. a request body, by
+/// templating its serialized form and parsing the result back.
+pub fn resolve_template_value(value: &Value, context: &TemplateContext) -> Result<Value> {
+  if value.is_null() {
+    return Ok(Value::Null);
+  }
+  match resolve_template(&value.to_string(), context)? {
+    Value::String(text) => Ok(serde_json::from_str(&text)?),
+    resolved => Ok(resolved),
+  }
+}
+
+/// A template that is exactly one expression: `{{x}}`, `"{{x}}"` or `"{{{x}}}"`.
+fn resolve_single_expression(template: &str, context: &TemplateContext) -> Option<TemplateResolution> {
+  static QUOTED: OnceLock<Regex> = OnceLock::new();
+  static UNQUOTED: OnceLock<Regex> = OnceLock::new();
+  static QUOTED_TRIPLE: OnceLock<Regex> = OnceLock::new();
+
+  let (expression, force_string) = if regex(&QUOTED, r#"^"\{\{[^}]+\}\}"$"#).is_match(template) {
+    (&template[1..template.len() - 1], true)
+  } else if regex(&UNQUOTED, r"^\{\{[^}]+\}\}$").is_match(template) {
+    (template, false)
+  } else if regex(&QUOTED_TRIPLE, r#"^"\{\{\{[^}]+\}\}\}"$"#).is_match(template) {
+    (&template[1..template.len() - 1], false)
+  } else {
+    return None;
+  };
+
+  let Some(expression) = parse_template_expression(expression) else {
+    return Some(TemplateResolution::ok(Value::String(template.to_string())));
+  };
+  Some(match resolve_by_source(&expression, context) {
+    Ok(Value::String(text)) => TemplateResolution::ok(Value::String(text)),
+    Ok(value) if force_string => TemplateResolution::ok(Value::String(js_string(&value))),
+    Ok(value) => TemplateResolution::ok(value),
+    Err(err) => TemplateResolution::failed(template, err),
+  })
+}
+
+fn resolve_multiple_expressions(template: &str, context: &TemplateContext) -> TemplateResolution {
+  let mut error = None;
+
+  // Quoted triple braces become the value's JSON, quotes included.
+  let processed = quoted_triple_brace().replace_all(template, |captures: &Captures| {
+    let matched = &captures[0];
+    let Some(expression) = parse_template_expression(&matched[1..matched.len() - 1]).filter(|e| e.preserve_type) else {
+      return matched.to_string();
+    };
+    match resolve_by_source(&expression, context) {
+      Ok(value) => value.to_string(),
+      Err(err) => {
+        error = Some(err);
+        matched.to_string()
+      }
+    }
+  });
+  if let Some(err) = error {
+    return TemplateResolution::failed(template, err);
+  }
+
+  // Everything else is stringified in place.
+  let processed = double_brace().replace_all(&processed, |captures: &Captures| {
+    let matched = &captures[0];
+    let Some(expression) = parse_template_expression(matched) else {
+      log::warn!("[template] invalid template expression: {matched}");
+      return matched.to_string();
+    };
+    match resolve_by_source(&expression, context) {
+      Ok(Value::Null) => String::new(),
+      Ok(value) => js_string(&value),
+      Err(err) => {
+        error = Some(err);
+        matched.to_string()
+      }
+    }
+  });
+  if let Some(err) = error {
+    return TemplateResolution::failed(template, err);
+  }
+
+  // A template that was one quoted triple-brace plus surrounding text may
+  // now be a JSON string literal.
+  if processed != template && processed.len() >= 2 && processed.starts_with('"') && processed.ends_with('"') {
+    if let Ok(value) = serde_json::from_str(&processed) {
+      return TemplateResolution::ok(value);
+    }
+  }
+  TemplateResolution::ok(Value::String(processed.into_owned()))
+}
+
+fn resolve_by_source(expression: &TemplateExpression, context: &TemplateContext) -> Result<Value> {
+  match expression.source {
+    Source::Res => resolve_response(&expression.path, context),
+    Source::Proc => resolve_transformation(&expression.path, context),
+    Source::Param => resolve_parameter(&expression.path, context),
+    Source::Func => resolve_function(&expression.path, context),
+    Source::Env => resolve_environment(&expression.path, context),
+  }
+}
+
+fn keys(map: &Map<String, Value>) -> String {
+  map.keys().map(String::as_str).collect::<Vec<_>>().join(", ")
+}
+
+/// `stepId-endpointIndex.$.path`
+fn resolve_response(path: &str, context: &TemplateContext) -> Result<Value> {
+  let (step_endpoint_id, json_path) = match path.find('.') {
+    Some(dot) if dot > 0 => (&path[..dot], &path[dot + 1..]),
+    _ => (path, "$"),
+  };
+  let data = context.responses.get(step_endpoint_id).ok_or_else(|| {
+    Error::Template(format!(
+      "Response data not found for: {step_endpoint_id}. Available keys: {}",
+      keys(&context.responses)
+    ))
+  })?;
+  Ok(functions::extract_json_path(data, json_path).cloned().unwrap_or(Value::Null))
+}
+
+/// `stepId-endpointIndex.$.alias.path`
+fn resolve_transformation(path: &str, context: &TemplateContext) -> Result<Value> {
+  let parts: Vec<&str> = path.split('.').collect();
+  if parts.len() < 3 {
+    return Err(Error::Template(format!(
+      "Invalid transformation template: {path}. Format should be stepId-endpointIndex.$.alias.path"
+    )));
+  }
+  if parts[1] != "$" {
+    return Err(Error::Template(format!(
+      "Invalid transformation template: {path}. Format should include $ to indicate JSON path"
+    )));
+  }
+
+  let (step_endpoint_id, alias) = (parts[0], parts[2]);
+  let aliases = context
+    .transformed_data
+    .get(step_endpoint_id)
+    .and_then(Value::as_object)
+    .ok_or_else(|| {
+      Error::Template(format!(
+        "Transformation data not found for: {step_endpoint_id}. Available keys: {}",
+        keys(&context.transformed_data)
+      ))
+    })?;
+  let data = aliases.get(alias).ok_or_else(|| {
+    Error::Template(format!(
+      "Transformation alias not found: {alias} for step {step_endpoint_id}. Available aliases: {}",
+      keys(aliases)
+    ))
+  })?;
+
+  if parts.len() > 3 {
+    return Ok(functions::extract_json_path(data, &parts[3..].join(".")).cloned().unwrap_or(Value::Null));
+  }
+  Ok(data.clone())
+}
+
+fn resolve_parameter(name: &str, context: &TemplateContext) -> Result<Value> {
+  context.parameters.get(name).cloned().ok_or_else(|| {
+    Error::Template(format!(
+      "Parameter not found: {name}. Available parameters: {}",
+      keys(&context.parameters)
+    ))
+  })
+}
+
+fn resolve_environment(name: &str, context: &TemplateContext) -> Result<Value> {
+  let environment = context.environment.as_ref().ok_or_else(|| {
+    Error::Template("Environment context not available for environment variable resolution".to_string())
+  })?;
+  environment.get(name).cloned().ok_or_else(|| {
+    Error::Template(format!(
+      "Environment variable not found: {name}. Available variables: {}",
+      keys(environment)
+    ))
+  })
+}
+
+/// `functionName(arg1, arg2, ...)`; arguments are JSON, or bare strings.
+fn resolve_function(expression: &str, _context: &TemplateContext) -> Result<Value> {
+  static CALL: OnceLock<Regex> = OnceLock::new();
+  let captures = regex(&CALL, r"^([a-zA-Z0-9_]+)\s*\((.*)\)$")
+    .captures(expression)
+    .ok_or_else(|| Error::Template(format!("Invalid function template format: {expression}")))?;
+  let name = &captures[1];
+  let function = functions::lookup(name).ok_or_else(|| {
+    Error::Template(format!(
+      "Function not found: {name}. Available functions: {}",
+      functions::names().collect::<Vec<_>>().join(", ")
+    ))
+  })?;
+
+  let arguments = captures[2].trim();
+  let args: Vec<Value> = if arguments.is_empty() {
+    Vec::new()
+  } else {
+    arguments
+      .split(',')
+      .map(|arg| {
+        let arg = arg.trim();
+        serde_json::from_str(arg).unwrap_or_else(|_| {
+          let unquoted = arg
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+            .unwrap_or(arg);
+          Value::String(unquoted.to_string())
+        })
+      })
+      .collect()
+  };
+  function(&args)
+}
+
+/// JavaScript's `String(value)` for JSON values.
+pub(crate) fn js_string(value: &Value) -> String {
+  match value {
+    Value::Null => "null".to_string(),
+    Value::Bool(flag) => flag.to_string(),
+    Value::Number(number) => match number.as_f64() {
+      Some(float) if number.is_f64() && float.fract() == 0.0 && float.abs() < 1e21 => format!("{float:.0}"),
+      _ => number.to_string(),
+    },
+    Value::String(text) => text.clone(),
+    Value::Array(items) => items
+      .iter()
+      .map(|item| if item.is_null() { String::new() } else { js_string(item) })
+      .collect::<Vec<_>>()
+      .join(","),
+    Value::Object(_) => "[object Object]".to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn context() -> TemplateContext {
+    serde_json::from_value(json!({
+      "responses": {
+        "step1-0": { "id": 123, "name": "John", "active": true, "items": [1, 2, 3], "score": 85.5 },
+        "step2-0": { "status": "success", "count": 0, "data": null }
+      },
+      "transformedData": {
+        "step1-0": { "userInfo": { "fullName": "John Doe", "age": 30, "isAdmin": false } }
+      },
+      "parameters": { "userId": 456, "name": "Test User", "isEnabled": true, "version": 2.1 }
+    }))
+    .unwrap()
+  }
+
+  fn resolve(template: &str) -> TemplateResolution {
+    resolve_template_expression(template, &context())
+  }
+
+  #[test]
+  fn detects_expressions() {
+    assert!(has_template_expressions("{{res:step1.data}}"));
+    assert!(has_template_expressions("Hello {{param:name}}"));
+    assert!(!has_template_expressions("No templates here"));
+    assert!(has_template_expressions("{{{res:step1.data}}}"));
+    assert!(has_template_expressions("Value: {{{param:count}}}"));
+  }
+
+  #[test]
+  fn parses_expressions() {
+    assert_eq!(
+      parse_template_expression("{{res:step1-0.$.data}}"),
+      Some(TemplateExpression {
+        source: Source::Res,
+        path: "step1-0.$.data".to_string(),
+        preserve_type: false
+      })
+    );
+    assert_eq!(
+      parse_template_expression("{{{param:userId}}}"),
+      Some(TemplateExpression {
+        source: Source::Param,
+        path: "userId".to_string(),
+        preserve_type: true
+      })
+    );
+  }
+
+  #[test]
+  fn normalizes_source_aliases() {
+    let source = |template: &str| parse_template_expression(template).map(|expression| expression.source);
+    assert_eq!(source("{{response:step1.data}}"), Some(Source::Res));
+    assert_eq!(source("{{var:userId}}"), Some(Source::Param));
+    assert_eq!(source("{{function:uuid()}}"), Some(Source::Func));
+    assert_eq!(source("{{transform:step1.$.alias}}"), Some(Source::Proc));
+  }
+
+  #[test]
+  fn rejects_invalid_expressions() {
+    assert_eq!(parse_template_expression("{{invalid}}"), None);
+    assert_eq!(parse_template_expression("{{unknown:path}}"), None);
+    assert_eq!(parse_template_expression("not a template"), None);
+  }
+
+  #[test]
+  fn quoted_triple_braces_preserve_types() {
+    assert_eq!(resolve(r#""{{{res:step1-0.$.id}}}""#).value, json!(123));
+    assert_eq!(resolve(r#""{{{res:step1-0.$.active}}}""#).value, json!(true));
+    assert_eq!(resolve(r#""{{{res:step1-0.$.items}}}""#).value, json!([1, 2, 3]));
+    assert_eq!(resolve(r#""{{{res:step2-0.$.data}}}""#).value, json!(null));
+    assert_eq!(resolve(r#""{{{res:step1-0.$.score}}}""#).value, json!(85.5));
+    assert_eq!(resolve(r#""{{{param:userId}}}""#).value, json!(456));
+    assert_eq!(resolve(r#""{{{proc:step1-0.$.userInfo.age}}}""#).value, json!(30));
+    assert_eq!(resolve(r#""{{{param:name}}}""#).value, json!("Test User"));
+  }
+
+  #[test]
+  fn multiple_quoted_triple_braces_produce_json() {
+    let result = resolve(r#"{"count": "{{{res:step1-0.$.id}}}", "active": "{{{res:step1-0.$.active}}}"}"#);
+    assert!(result.success);
+    let parsed: Value = serde_json::from_str(result.value.as_str().unwrap()).unwrap();
+    assert_eq!(parsed, json!({ "count": 123, "active": true }));
+  }
+
+  #[test]
+  fn failed_quoted_triple_brace_returns_the_template() {
+    let result = resolve(r#""{{{res:nonexistent.data}}}""#);
+    assert!(!result.success);
+    assert!(result.error.is_some());
+    assert_eq!(result.value, json!(r#""{{{res:nonexistent.data}}}""#));
+  }
+
+  #[test]
+  fn double_braces_stringify() {
+    assert_eq!(resolve("User ID: {{res:step1-0.$.id}}").value, json!("User ID: 123"));
+    assert_eq!(
+      resolve("Hello {{param:name}}, your ID is {{res:step1-0.$.id}}").value,
+      json!("Hello Test User, your ID is 123")
+    );
+    assert_eq!(resolve("Active: {{res:step1-0.$.active}}").value, json!("Active: true"));
+    assert_eq!(resolve("Data: {{res:step2-0.$.data}}").value, json!("Data: "));
+  }
+
+  #[test]
+  fn mixed_templates() {
+    let result = resolve(
+      r#"{"id": "{{{res:step1-0.$.id}}}", "name": "{{res:step1-0.$.name}}", "items": "{{{res:step1-0.$.items}}}"}"#,
+    );
+    let parsed: Value = serde_json::from_str(result.value.as_str().unwrap()).unwrap();
+    assert_eq!(parsed, json!({ "id": 123, "name": "John", "items": [1, 2, 3] }));
+
+    let template = r#"{
+      "user": {
+        "id": "{{{param:userId}}}",
+        "name": "{{param:name}}",
+        "active": "{{{res:step1-0.$.active}}}"
+      },
+      "score": "{{{res:step1-0.$.score}}}",
+      "message": "User {{param:name}} has score {{res:step1-0.$.score}}"
+    }"#;
+    let parsed: Value = serde_json::from_str(resolve(template).value.as_str().unwrap()).unwrap();
+    assert_eq!(
+      parsed,
+      json!({
+        "user": { "id": 456, "name": "Test User", "active": true },
+        "score": 85.5,
+        "message": "User Test User has score 85.5"
+      })
+    );
+  }
+
+  #[test]
+  fn edge_cases() {
+    assert_eq!(resolve("Plain text with no templates").value, json!("Plain text with no templates"));
+    assert_eq!(resolve("").value, json!(""));
+    let malformed = resolve("{{invalid}}");
+    assert!(malformed.success);
+    assert_eq!(malformed.value, json!("{{invalid}}"));
+    let unknown_source = resolve("{{invalidSource:step1-0.data}}");
+    assert!(unknown_source.success);
+    assert_eq!(unknown_source.value, json!("{{invalidSource:step1-0.data}}"));
+  }
+
+  #[test]
+  fn reports_missing_data() {
+    let missing_response = resolve("{{res:missing.data}}");
+    assert!(!missing_response.success);
+    assert!(missing_response.error.unwrap().contains("Response data not found"));
+    assert_eq!(missing_response.value, json!("{{res:missing.data}}"));
+
+    let missing_parameter = resolve("{{param:missing}}");
+    assert!(!missing_parameter.success);
+    assert!(missing_parameter.error.unwrap().contains("Parameter not found"));
+  }
+
+  #[test]
+  fn single_expressions_keep_their_type_unless_quoted() {
+    let context = context();
+    let resolve = |template: &str| resolve_template(template, &context).unwrap();
+    assert_eq!(resolve("{{res:step1-0.$.id}}"), json!(123));
+    assert_eq!(resolve("{{res:step1-0.$.name}}"), json!("John"));
+    assert_eq!(resolve(r#""{{res:step1-0.$.id}}""#), json!("123"));
+    assert_eq!(resolve("{{res:step1-0.$.items[2]}}"), json!(3));
+    assert_eq!(resolve(r#""{{res:step1-0.$.items[0]}}""#), json!("1"));
+    assert_eq!(resolve("{{proc:step1-0.$.userInfo.fullName}}"), json!("John Doe"));
+    assert_eq!(resolve("{{param:userId}}"), json!(456));
+    assert_eq!(resolve(r#""{{param:userId}}""#), json!("456"));
+    assert_eq!(resolve("User {{param:name}} has ID {{res:step1-0.$.id}}"), json!("User Test User has ID 123"));
+  }
+
+  #[test]
+  fn calls_functions() {
+    let context = context();
+    assert_eq!(resolve_template("{{func:uuid()}}", &context).unwrap().as_str().unwrap().len(), 36);
+    assert!(resolve_template("{{func:randomInt(10, 20)}}", &context).unwrap().is_number());
+    assert!(resolve_template("{{func:randomString(5)}}", &context).is_ok());
+    assert!(resolve_template("{{func:nonexistentFunction()}}", &context).is_err());
+    assert!(resolve_template("{{res:nonexistent.data}}", &context).is_err());
+  }
+
+  #[test]
+  fn resolves_environment_variables() {
+    let context = TemplateContext {
+      parameters: serde_json::from_value(json!({ "username": "param_user" })).unwrap(),
+      environment: serde_json::from_value(json!({ "username": "env_user", "api_url": "https://api.dev.com" })).unwrap(),
+      ..TemplateContext::default()
+    };
+    assert_eq!(resolve_template("{{env:username}}", &context).unwrap(), json!("env_user"));
+    assert_eq!(resolve_template(r#""{{{env:api_url}}}""#, &context).unwrap(), json!("https://api.dev.com"));
+    assert_eq!(
+      resolve_template("User: {{env:username}}, Param: {{param:username}}", &context).unwrap(),
+      json!("User: env_user, Param: param_user")
+    );
+    let missing = resolve_template("{{env:missing_var}}", &context).unwrap_err();
+    assert!(missing.to_string().contains("Environment variable not found: missing_var"));
+
+    let without_env = resolve_template("{{env:username}}", &TemplateContext::default()).unwrap_err();
+    assert!(without_env.to_string().contains("Environment context not available"));
+  }
+
+  #[test]
+  fn resolves_json_values() {
+    let body = json!({ "id": "{{{param:userId}}}", "label": "user-{{param:name}}", "keep": 1 });
+    assert_eq!(
+      resolve_template_value(&body, &context()).unwrap(),
+      json!({ "id": 456, "label": "user-Test User", "keep": 1 })
+    );
+  }
+}
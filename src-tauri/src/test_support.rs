@@ -0,0 +1,42 @@
+//! Loopback servers for tests that drive the clients end to end.
+
+use std::future::Future;
+use std::net::Ipv4Addr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Listen on a free loopback port and hand every connection to `handle` on
+/// a task of its own. Returns the port.
+pub(crate) async fn serve<F, T>(handle: F) -> u16
+where
+  F: Fn(TcpStream) -> T + Send + 'static,
+  T: Future<Output = ()> + Send + 'static,
+{
+  let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+  let port = listener.local_addr().unwrap().port();
+  tokio::spawn(async move {
+    while let Ok((stream, _)) = listener.accept().await {
+      tokio::spawn(handle(stream));
+    }
+  });
+  port
+}
+
+/// [`serve`] with canned answers: `respond` gets each request as text and
+/// returns the whole HTTP/1.1 response.
+pub(crate) async fn serve_http(respond: fn(&str) -> String) -> u16 {
+  serve(move |mut stream| async move {
+    let request = read_request(&mut stream).await;
+    stream.write_all(respond(&request).as_bytes()).await.unwrap();
+  })
+  .await
+}
+
+/// The request on `stream`, as far as one read gets it; the tests' requests
+/// are small enough to arrive whole.
+pub(crate) async fn read_request(stream: &mut TcpStream) -> String {
+  let mut request = [0; 4096];
+  let read = stream.read(&mut request).await.unwrap();
+  String::from_utf8_lossy(&request[..read]).into_owned()
+}
@@ -0,0 +1,378 @@
+//! Evaluates a parsed [`Expression`], following `ASTEvaluator` in
+//! `ExpressionParser.ts`.
+
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+use serde_json::{Map, Value};
+
+use super::functions::{self, compare_equal, divide};
+use super::parser::{BinaryOperator, Expression, FunctionCall, UnaryOperator};
+use super::{number, to_number, truthy};
+use crate::error::{Error, Result};
+use crate::template::{self, Source, TemplateContext};
+
+/// Work allowed per evaluation, counted in evaluated nodes plus the size of
+/// values copied out of the input. Nested pipelines over `data` can
+/// otherwise grow exponentially.
+const MAX_STEPS: usize = 5_000_000;
+
+const TRIPLE_BRACE_ERROR: &str =
+  "Transformations use {{...}} templates only; {{{...}}} is only for JSON request bodies.";
+
+#[derive(Clone, Copy)]
+struct Scope<'v> {
+  root: &'v Value,
+  current: &'v Value,
+}
+
+pub(super) struct Evaluator<'c> {
+  context: &'c TemplateContext,
+  steps: Cell<usize>,
+}
+
+impl<'c> Evaluator<'c> {
+  pub(super) fn new(context: &'c TemplateContext) -> Self {
+    Self {
+      context,
+      steps: Cell::new(0),
+    }
+  }
+
+  pub(super) fn evaluate(&self, expression: &Expression, data: &Value) -> Result<Value> {
+    self.evaluate_node(
+      expression,
+      Scope {
+        root: data,
+        current: data,
+      },
+    )
+  }
+
+  fn charge(&self, steps: usize) -> Result<()> {
+    let total = self.steps.get().saturating_add(steps);
+    self.steps.set(total);
+    if total > MAX_STEPS {
+      return Err(Error::Transform(format!(
+        "Expression exceeded the evaluation limit of {MAX_STEPS} steps."
+      )));
+    }
+    Ok(())
+  }
+
+  /// Charge for a value copied out of the input.
+  fn copied(&self, value: Value) -> Result<Value> {
+    self.charge(size(&value))?;
+    Ok(value)
+  }
+
+  fn evaluate_node(&self, node: &Expression, scope: Scope<'_>) -> Result<Value> {
+    self.charge(1)?;
+    match node {
+      Expression::Literal(value) => Ok(value.clone()),
+      Expression::Identifier(name) => self.copied(match (name.as_str(), scope.current) {
+        ("data", _) => scope.root.clone(),
+        (_, Value::Object(map)) => map.get(name).cloned().unwrap_or(Value::Null),
+        _ => Value::Null,
+      }),
+      Expression::JsonPath(path) => self.copied(path.evaluate(scope.current)),
+      Expression::Template(expression) => self.evaluate_template(expression),
+      Expression::TemplateString(value) => self.evaluate_template_string(value),
+      Expression::Member { object, property } => Ok(match self.evaluate_node(object, scope)? {
+        Value::Object(mut map) => map.remove(property).unwrap_or(Value::Null),
+        _ => Value::Null,
+      }),
+      Expression::Array(elements) => elements
+        .iter()
+        .map(|element| self.evaluate_node(element, scope))
+        .collect::<Result<_>>()
+        .map(Value::Array),
+      Expression::Object(properties) => properties
+        .iter()
+        .map(|(key, value)| Ok((key.clone(), self.evaluate_node(value, scope)?)))
+        .collect::<Result<Map<_, _>>>()
+        .map(Value::Object),
+      Expression::Unary { operator, operand } => {
+        let value = self.evaluate_node(operand, scope)?;
+        Ok(match operator {
+          UnaryOperator::Not => Value::Bool(!truthy(&value)),
+          UnaryOperator::Negate => number(-to_number(&value)),
+        })
+      }
+      Expression::Binary { operator, left, right } => self.evaluate_binary(*operator, left, right, scope),
+      Expression::Pipeline { input, calls } => {
+        let mut value = self.evaluate_node(input, scope)?;
+        for call in calls {
+          value = self.evaluate_call(call, value, scope)?;
+        }
+        Ok(value)
+      }
+    }
+  }
+
+  fn evaluate_binary(
+    &self,
+    operator: BinaryOperator,
+    left: &Expression,
+    right: &Expression,
+    scope: Scope<'_>,
+  ) -> Result<Value> {
+    let left = self.evaluate_node(left, scope)?;
+    match operator {
+      BinaryOperator::And if !truthy(&left) => return Ok(Value::Bool(false)),
+      BinaryOperator::Or if truthy(&left) => return Ok(Value::Bool(true)),
+      BinaryOperator::And | BinaryOperator::Or => {
+        return Ok(Value::Bool(truthy(&self.evaluate_node(right, scope)?)));
+      }
+      _ => {}
+    }
+
+    let right = self.evaluate_node(right, scope)?;
+    let compare = |op: fn(f64, f64) -> bool| {
+      let (a, b) = (to_number(&left), to_number(&right));
+      Value::Bool(!a.is_nan() && !b.is_nan() && op(a, b))
+    };
+    let arithmetic = |op: fn(f64, f64) -> f64| number(op(to_number(&left), to_number(&right)));
+
+    Ok(match operator {
+      BinaryOperator::Equal => Value::Bool(compare_equal(&left, &right)),
+      BinaryOperator::NotEqual => Value::Bool(!compare_equal(&left, &right)),
+      BinaryOperator::Greater => compare(|a, b| a > b),
+      BinaryOperator::Less => compare(|a, b| a < b),
+      BinaryOperator::GreaterOrEqual => compare(|a, b| a >= b),
+      BinaryOperator::LessOrEqual => compare(|a, b| a <= b),
+      BinaryOperator::Add => arithmetic(|a, b| a + b),
+      BinaryOperator::Subtract => arithmetic(|a, b| a - b),
+      BinaryOperator::Multiply => arithmetic(|a, b| a * b),
+      BinaryOperator::Divide => arithmetic(divide),
+      BinaryOperator::Remainder => arithmetic(|a, b| if b == 0.0 { f64::NAN } else { a % b }),
+      BinaryOperator::And | BinaryOperator::Or => unreachable!("short-circuited above"),
+    })
+  }
+
+  fn evaluate_template(&self, expression: &str) -> Result<Value> {
+    let parsed = template::parse_template_expression(expression)
+      .ok_or_else(|| Error::Transform(format!("Invalid template expression \"{expression}\".")))?;
+    if parsed.preserve_type {
+      return Err(Error::Transform(TRIPLE_BRACE_ERROR.to_string()));
+    }
+    check_source(parsed.source)?;
+    resolve(expression, self.context)
+  }
+
+  fn evaluate_template_string(&self, value: &str) -> Result<Value> {
+    if value.contains("{{{") {
+      return Err(Error::Transform(TRIPLE_BRACE_ERROR.to_string()));
+    }
+
+    let mut resolved = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("{{") {
+      let Some(length) = rest[start..].find("}}").filter(|&end| end > 2 && !rest[start..start + end].contains('}')) else {
+        resolved.push_str(&rest[..start + 2]);
+        rest = &rest[start + 2..];
+        continue;
+      };
+      let expression = &rest[start..start + length + 2];
+      let parsed = template::parse_template_expression(expression)
+        .ok_or_else(|| Error::Transform(format!("Invalid template expression \"{expression}\".")))?;
+      check_source(parsed.source)?;
+
+      resolved.push_str(&rest[..start]);
+      match resolve(expression, self.context)? {
+        Value::Null => {}
+        value => resolved.push_str(&template::js_string(&value)),
+      }
+      rest = &rest[start + length + 2..];
+    }
+    resolved.push_str(rest);
+    Ok(Value::String(resolved))
+  }
+
+  fn evaluate_call(&self, call: &FunctionCall, input: Value, scope: Scope<'_>) -> Result<Value> {
+    self.charge(1)?;
+    let first_arg = call.args.first();
+    match call.name.as_str() {
+      "where" | "select" => {
+        let condition = first_arg
+          .ok_or_else(|| Error::Transform("where() requires a condition expression.".to_string()))?;
+        let Value::Array(items) = input else {
+          return Ok(Value::Array(Vec::new()));
+        };
+        let mut kept = Vec::new();
+        for item in items {
+          if truthy(&self.evaluate_node(condition, scope.with_current(&item))?) {
+            kept.push(item);
+          }
+        }
+        Ok(Value::Array(kept))
+      }
+      "map" if !input.is_array() => Ok(Value::Array(Vec::new())),
+      "map" | "transform" => {
+        let named = named_args_object(call);
+        let mapper = first_arg.or(named.as_ref()).ok_or_else(|| {
+          Error::Transform(format!(
+            "{}() requires an expression or named object mapping.",
+            call.name
+          ))
+        })?;
+        match input {
+          Value::Array(items) => items
+            .iter()
+            .map(|item| self.evaluate_node(mapper, scope.with_current(item)))
+            .collect::<Result<_>>()
+            .map(Value::Array),
+          other => self.evaluate_node(mapper, scope.with_current(&other)),
+        }
+      }
+      "sort" => self.sort(call, input, scope),
+      "sum" => {
+        let Value::Array(items) = input else {
+          return Ok(Value::from(0));
+        };
+        let mut total = 0.0;
+        for item in &items {
+          let value = match first_arg {
+            Some(field) => self.evaluate_node(field, scope.with_current(item))?,
+            None => item.clone(),
+          };
+          let value = to_number(&value);
+          if !value.is_nan() {
+            total += value;
+          }
+        }
+        Ok(number(total))
+      }
+      name => {
+        let args = call
+          .args
+          .iter()
+          .map(|arg| self.evaluate_node(arg, scope))
+          .collect::<Result<Vec<_>>>()?;
+        for (_, value) in &call.named_args {
+          self.evaluate_node(value, scope)?;
+        }
+        functions::call(name, &input, &args)
+          .ok_or_else(|| Error::Transform(format!("Unknown function \"{name}\".")))
+      }
+    }
+  }
+
+  /// Sort by `by:` (a key name or an expression per item), optionally `desc: true`.
+  fn sort(&self, call: &FunctionCall, input: Value, scope: Scope<'_>) -> Result<Value> {
+    let Value::Array(items) = input else {
+      return Ok(Value::Array(Vec::new()));
+    };
+    let by = call.named_arg("by").or(call.args.first());
+    let descending = match call.named_arg("desc") {
+      Some(desc) => truthy(&self.evaluate_node(desc, scope)?),
+      None => false,
+    };
+
+    let mut keyed = Vec::with_capacity(items.len());
+    for item in items {
+      let key = match by {
+        Some(Expression::Literal(Value::String(key))) => item.get(key).cloned().unwrap_or(Value::Null),
+        Some(by) => self.evaluate_node(by, scope.with_current(&item))?,
+        None => item.clone(),
+      };
+      keyed.push((SortKey::from(&key), item));
+    }
+    self.charge(keyed.len())?;
+
+    keyed.sort_by(|(a, _), (b, _)| if descending { b.cmp(a) } else { a.cmp(b) });
+    Ok(Value::Array(keyed.into_iter().map(|(_, item)| item).collect()))
+  }
+}
+
+impl<'v> Scope<'v> {
+  fn with_current<'a>(self, current: &'a Value) -> Scope<'a>
+  where
+    'v: 'a,
+  {
+    Scope {
+      root: self.root,
+      current,
+    }
+  }
+}
+
+/// A total order approximating `compareSortValues`: nulls first, then
+/// anything numeric, then everything else by its string form.
+#[derive(Debug, PartialEq)]
+enum SortKey {
+  Null,
+  Number(f64),
+  Text(String),
+}
+
+impl From<&Value> for SortKey {
+  fn from(value: &Value) -> Self {
+    if value.is_null() {
+      return Self::Null;
+    }
+    match to_number(value) {
+      n if n.is_nan() => Self::Text(template::js_string(value)),
+      n => Self::Number(n),
+    }
+  }
+}
+
+impl Eq for SortKey {}
+
+impl PartialOrd for SortKey {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for SortKey {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (Self::Null, Self::Null) => Ordering::Equal,
+      (Self::Null, _) => Ordering::Less,
+      (_, Self::Null) => Ordering::Greater,
+      (Self::Number(a), Self::Number(b)) => a.total_cmp(b),
+      (Self::Number(_), Self::Text(_)) => Ordering::Less,
+      (Self::Text(_), Self::Number(_)) => Ordering::Greater,
+      (Self::Text(a), Self::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b)),
+    }
+  }
+}
+
+/// `env` templates are left to flow parameters, as in the webview.
+fn check_source(source: Source) -> Result<()> {
+  if source == Source::Env {
+    return Err(Error::Transform(
+      "Template source \"env\" is not supported in transformations. Use a flow parameter instead.".to_string(),
+    ));
+  }
+  Ok(())
+}
+
+fn resolve(expression: &str, context: &TemplateContext) -> Result<Value> {
+  let resolution = template::resolve_template_expression(expression, context);
+  if resolution.success {
+    Ok(resolution.value)
+  } else {
+    Err(Error::Transform(
+      resolution
+        .error
+        .unwrap_or_else(|| format!("Failed to resolve template \"{expression}\".")),
+    ))
+  }
+}
+
+/// `map(id: $.id, name: $.name)` is shorthand for `map({ id: $.id, name: $.name })`.
+fn named_args_object(call: &FunctionCall) -> Option<Expression> {
+  (!call.named_args.is_empty()).then(|| Expression::Object(call.named_args.clone()))
+}
+
+/// Node count of a value, for the step budget.
+fn size(value: &Value) -> usize {
+  match value {
+    Value::Array(items) => 1 + items.iter().map(size).sum::<usize>(),
+    Value::Object(map) => 1 + map.values().map(size).sum::<usize>(),
+    _ => 1,
+  }
+}
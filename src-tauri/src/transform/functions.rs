@@ -0,0 +1,410 @@
+//! Pipeline functions that only need their evaluated input and arguments,
+//! ported from `PipelineFunctions.ts` and the helpers in `ExpressionParser.ts`.
+//! Functions that evaluate an argument per element (`where`, `map`, `sort`,
+//! ...) live in the evaluator.
+
+use regex::RegexBuilder;
+use serde_json::{Map, Value};
+
+use super::{number, strict_equals, to_number, truthy};
+use crate::template::js_string;
+
+/// Compiled size limit for `matches()` patterns.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Call a function that takes the piped value followed by its arguments,
+/// or `None` if there is no such function.
+pub fn call(name: &str, input: &Value, args: &[Value]) -> Option<Value> {
+  let arg = |index: usize| args.get(index);
+  let value = match name {
+    "count" => Value::from(input.as_array().map_or(0, Vec::len)),
+    "first" => input.as_array().and_then(|items| items.first()).cloned().unwrap_or(Value::Null),
+    "last" => input.as_array().and_then(|items| items.last()).cloned().unwrap_or(Value::Null),
+    "take" => take(input, arg(0)),
+    "skip" => skip(input, arg(0)),
+    "at" => at(input, arg(0)),
+    "flatten" => flatten(input, arg(0)),
+    "pick" => pick(input, arg(0)),
+    "add" => math(input, arg(0), |a, b| a + b),
+    "sub" => math(input, arg(0), |a, b| a - b),
+    "mul" => math(input, arg(0), |a, b| a * b),
+    "div" => math(input, arg(0), divide),
+    "mod" => match arg(0) {
+      Some(divisor) if to_number(divisor) == 0.0 => Value::Null,
+      divisor => math(input, divisor, |a, b| a % b),
+    },
+    "int" => cast_each(input, arg(0), cast_to_int),
+    "float" => cast_each(input, arg(0), cast_to_float),
+    "string" => cast_each(input, arg(0), cast_to_string),
+    "bool" => cast_to_bool(input, arg(0)),
+    "contains" => Value::Bool(js_string(input).contains(&display(arg(0)))),
+    "startsWith" => Value::Bool(js_string(input).starts_with(&display(arg(0)))),
+    "endsWith" => Value::Bool(js_string(input).ends_with(&display(arg(0)))),
+    "matches" => Value::Bool(regex_match(input, arg(0))),
+    "empty" => Value::Bool(empty(input)),
+    "length" => Value::from(match input {
+      Value::Array(items) => items.len(),
+      Value::String(text) => text.encode_utf16().count(),
+      _ => 0,
+    }),
+    "abs" => number(to_number(input).abs()),
+    "round" => round(input, arg(0)),
+    "ceil" => number(to_number(input).ceil()),
+    "floor" => number(to_number(input).floor()),
+    "min" => number(binary(input, arg(0), f64::min)),
+    "max" => number(binary(input, arg(0), f64::max)),
+    "pow" => number(binary(input, arg(0), f64::powf)),
+    _ => return None,
+  };
+  Some(value)
+}
+
+/// Cast an output to one of the flow parameter types, as `FlowOutputEvaluator`
+/// does when `castToType` is set. Unknown types leave the value unchanged.
+pub fn cast_to_type(value: Value, kind: &str) -> Value {
+  match kind {
+    "string" => cast_to_string(&value, None),
+    "number" => cast_to_float(&value, None),
+    "boolean" => cast_to_bool(&value, None),
+    "object" => match &value {
+      Value::String(text) => serde_json::from_str(text).unwrap_or(value),
+      _ => value,
+    },
+    "array" => match value {
+      Value::String(text) => match serde_json::from_str(&text) {
+        Ok(Value::Array(items)) => Value::Array(items),
+        _ => Value::Array(vec![Value::String(text)]),
+      },
+      Value::Array(items) => Value::Array(items),
+      other => Value::Array(vec![other]),
+    },
+    "null" => Value::Null,
+    other => {
+      log::warn!("[transform] unknown type \"{other}\" for casting, returning value as-is");
+      value
+    }
+  }
+}
+
+/// `String(value)`, with a missing argument reading as `"undefined"`.
+fn display(value: Option<&Value>) -> String {
+  value.map_or_else(|| "undefined".to_string(), js_string)
+}
+
+/// JavaScript `Array.prototype.slice` bounds for `[start, end)`.
+pub(crate) fn slice_bounds(len: usize, start: f64, end: f64) -> (usize, usize) {
+  let clamp = |position: f64| {
+    let position = if position.is_nan() { 0.0 } else { position.trunc() };
+    let position = if position < 0.0 { len as f64 + position } else { position };
+    position.clamp(0.0, len as f64) as usize
+  };
+  let (start, end) = (clamp(start), clamp(end));
+  (start, end.max(start))
+}
+
+fn take(input: &Value, count: Option<&Value>) -> Value {
+  let Some(items) = input.as_array() else {
+    return Value::Array(Vec::new());
+  };
+  let count = count.map_or(f64::NAN, to_number);
+  if count.is_nan() {
+    return input.clone();
+  }
+  let (start, end) = slice_bounds(items.len(), 0.0, count);
+  Value::Array(items[start..end].to_vec())
+}
+
+fn skip(input: &Value, count: Option<&Value>) -> Value {
+  let Some(items) = input.as_array() else {
+    return Value::Array(Vec::new());
+  };
+  let count = count.map_or(f64::NAN, to_number);
+  if count.is_nan() {
+    return input.clone();
+  }
+  let (start, end) = slice_bounds(items.len(), count, f64::INFINITY);
+  Value::Array(items[start..end].to_vec())
+}
+
+fn at(input: &Value, index: Option<&Value>) -> Value {
+  let (Some(items), Some(index)) = (input.as_array(), index.filter(|index| !index.is_null())) else {
+    return Value::Null;
+  };
+  let index = to_number(index).floor();
+  if index.is_nan() {
+    return Value::Null;
+  }
+  let index = if index < 0.0 { items.len() as f64 + index } else { index };
+  if index < 0.0 {
+    return Value::Null;
+  }
+  items.get(index as usize).cloned().unwrap_or(Value::Null)
+}
+
+fn flatten(input: &Value, depth: Option<&Value>) -> Value {
+  let Some(items) = input.as_array() else {
+    return Value::Array(Vec::new());
+  };
+  let depth = depth.map_or(1.0, to_number);
+  if depth.is_nan() || depth <= 0.0 {
+    return input.clone();
+  }
+
+  fn flatten_into(items: &[Value], depth: f64, out: &mut Vec<Value>) {
+    for item in items {
+      match item {
+        Value::Array(inner) if depth > 0.0 => flatten_into(inner, depth - 1.0, out),
+        other => out.push(other.clone()),
+      }
+    }
+  }
+  let mut out = Vec::new();
+  flatten_into(items, depth, &mut out);
+  Value::Array(out)
+}
+
+fn pick(input: &Value, keys: Option<&Value>) -> Value {
+  let (Some(map), Some(keys)) = (input.as_object(), keys.and_then(Value::as_array)) else {
+    return Value::Object(Map::new());
+  };
+  Value::Object(
+    keys
+      .iter()
+      .map(js_string)
+      .filter_map(|key| map.get(&key).map(|value| (key, value.clone())))
+      .collect(),
+  )
+}
+
+/// `add`/`sub`/`mul`/`div`/`mod` only work on actual numbers.
+fn math(input: &Value, operand: Option<&Value>, operation: fn(f64, f64) -> f64) -> Value {
+  match (input.as_f64(), operand.and_then(Value::as_f64)) {
+    (Some(a), Some(b)) => number(operation(a, b)),
+    _ => Value::Null,
+  }
+}
+
+/// Division where `x / 0` is infinite with the sign of `x`, as in the webview.
+pub(crate) fn divide(a: f64, b: f64) -> f64 {
+  if b != 0.0 {
+    a / b
+  } else if a >= 0.0 {
+    f64::INFINITY
+  } else {
+    f64::NEG_INFINITY
+  }
+}
+
+fn binary(input: &Value, operand: Option<&Value>, operation: fn(f64, f64) -> f64) -> f64 {
+  let (a, b) = (to_number(input), operand.map_or(f64::NAN, to_number));
+  if a.is_nan() || b.is_nan() {
+    return f64::NAN;
+  }
+  operation(a, b)
+}
+
+/// `Math.round`, which rounds halves towards positive infinity.
+fn js_round(value: f64) -> f64 {
+  (value + 0.5).floor()
+}
+
+fn round(input: &Value, digits: Option<&Value>) -> Value {
+  let value = to_number(input);
+  let digits = digits.map_or(f64::NAN, to_number);
+  if digits.is_nan() {
+    return number(js_round(value));
+  }
+  let factor = 10f64.powf(digits.floor());
+  number(js_round(value * factor) / factor)
+}
+
+fn regex_match(input: &Value, pattern: Option<&Value>) -> bool {
+  let pattern = display(pattern);
+  if is_unsafe_regex(&pattern) {
+    return false;
+  }
+  RegexBuilder::new(&pattern)
+    .size_limit(REGEX_SIZE_LIMIT)
+    .build()
+    .is_ok_and(|regex| regex.is_match(&js_string(input)))
+}
+
+/// The same screen `PipelineHelpers.isUnsafeRegex` applies, kept so that
+/// patterns behave alike in both runners.
+fn is_unsafe_regex(pattern: &str) -> bool {
+  let complex_repetition = pattern.match_indices('{').any(|(start, _)| {
+    let rest = pattern[start + 1..].strip_prefix(',').unwrap_or(&pattern[start + 1..]);
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let Some(upper) = rest[digits..].strip_prefix(',') else {
+      return false;
+    };
+    digits > 0 && upper.trim_start_matches(|c: char| c.is_ascii_digit()).starts_with('}')
+  });
+  pattern.contains("(?=") || pattern.contains("**") || complex_repetition
+}
+
+fn empty(value: &Value) -> bool {
+  match value {
+    Value::Null => true,
+    Value::String(text) => text.is_empty(),
+    Value::Array(items) => items.is_empty(),
+    Value::Object(map) => map.is_empty(),
+    _ => false,
+  }
+}
+
+/// Casts map over arrays, except `bool` which judges the value as a whole.
+fn cast_each(input: &Value, default: Option<&Value>, cast: fn(&Value, Option<&Value>) -> Value) -> Value {
+  match input {
+    Value::Array(items) => Value::Array(items.iter().map(|item| cast(item, default)).collect()),
+    other => cast(other, default),
+  }
+}
+
+fn numeric_default(default: Option<&Value>) -> Value {
+  default.map_or(Value::Null, |default| number(to_number(default)))
+}
+
+pub fn cast_to_int(value: &Value, default: Option<&Value>) -> Value {
+  match value {
+    Value::Number(n) => number(n.as_f64().unwrap_or(f64::NAN).floor()),
+    Value::Bool(flag) => Value::from(u8::from(*flag)),
+    Value::String(text) => match parse_int(text) {
+      Some(parsed) => number(parsed),
+      None => numeric_default(default),
+    },
+    _ => numeric_default(default),
+  }
+}
+
+pub fn cast_to_float(value: &Value, default: Option<&Value>) -> Value {
+  match value {
+    Value::Number(_) => value.clone(),
+    Value::Bool(flag) => Value::from(u8::from(*flag)),
+    Value::String(text) => match parse_float(text) {
+      Some(parsed) => number(parsed),
+      None => numeric_default(default),
+    },
+    _ => numeric_default(default),
+  }
+}
+
+pub fn cast_to_string(value: &Value, default: Option<&Value>) -> Value {
+  match value {
+    Value::Null => default.map_or(Value::Null, |default| Value::String(js_string(default))),
+    Value::String(_) => value.clone(),
+    Value::Array(_) | Value::Object(_) => Value::String(value.to_string()),
+    other => Value::String(js_string(other)),
+  }
+}
+
+pub fn cast_to_bool(value: &Value, default: Option<&Value>) -> Value {
+  match value {
+    Value::Null => default.map_or(Value::Null, |default| Value::Bool(truthy(default))),
+    Value::String(text) => {
+      let normalized = text.trim().to_lowercase();
+      Value::Bool(!matches!(normalized.as_str(), "false" | "0" | ""))
+    }
+    other => Value::Bool(truthy(other)),
+  }
+}
+
+/// `parseInt(text, 10)`: the leading integer, ignoring whatever follows.
+fn parse_int(text: &str) -> Option<f64> {
+  let text = text.trim_start();
+  let unsigned = text.trim_start_matches(['+', '-']);
+  if text.len() - unsigned.len() > 1 {
+    return None;
+  }
+  let digits = unsigned.bytes().take_while(u8::is_ascii_digit).count();
+  if digits == 0 {
+    return None;
+  }
+  let magnitude: f64 = unsigned[..digits].parse().ok()?;
+  Some(if text.starts_with('-') { -magnitude } else { magnitude })
+}
+
+/// `parseFloat(text)`: the longest leading decimal literal.
+fn parse_float(text: &str) -> Option<f64> {
+  let text = text.trim_start();
+  let bytes = text.as_bytes();
+  let mut end = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+  if text[end..].starts_with("Infinity") {
+    return Some(if text.starts_with('-') { f64::NEG_INFINITY } else { f64::INFINITY });
+  }
+
+  let digits_from = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+  let integer = digits_from(end);
+  end += integer;
+  let mut fraction = 0;
+  if bytes.get(end) == Some(&b'.') {
+    fraction = digits_from(end + 1);
+    if integer > 0 || fraction > 0 {
+      end += 1 + fraction;
+    }
+  }
+  if integer == 0 && fraction == 0 {
+    return None;
+  }
+  if matches!(bytes.get(end), Some(b'e' | b'E')) {
+    let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+    let exponent = digits_from(end + 1 + sign);
+    if exponent > 0 {
+      end += 1 + sign + exponent;
+    }
+  }
+  text[..end].parse().ok()
+}
+
+/// `==` in expressions: an array on one side matches if it contains the other.
+pub(crate) fn compare_equal(left: &Value, right: &Value) -> bool {
+  match (left, right) {
+    (Value::Array(items), other) | (other, Value::Array(items)) if !other.is_array() => {
+      items.iter().any(|item| strict_equals(item, other))
+    }
+    _ => strict_equals(left, right),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn parses_number_prefixes() {
+    assert_eq!(parse_int(" 42abc"), Some(42.0));
+    assert_eq!(parse_int("-3.9"), Some(-3.0));
+    assert_eq!(parse_int("abc"), None);
+    assert_eq!(parse_float("2.5xyz"), Some(2.5));
+    assert_eq!(parse_float(".5"), Some(0.5));
+    assert_eq!(parse_float("1e3px"), Some(1000.0));
+    assert_eq!(parse_float("."), None);
+  }
+
+  #[test]
+  fn slices_like_javascript() {
+    assert_eq!(slice_bounds(5, 0.0, -1.0), (0, 4));
+    assert_eq!(slice_bounds(5, -2.0, f64::INFINITY), (3, 5));
+    assert_eq!(slice_bounds(5, 4.0, 2.0), (4, 4));
+    assert_eq!(slice_bounds(5, 0.0, 1e300), (0, 5));
+  }
+
+  #[test]
+  fn casts_output_types() {
+    assert_eq!(cast_to_type(json!("42.5"), "number"), json!(42.5));
+    assert_eq!(cast_to_type(json!("[1,2]"), "array"), json!([1, 2]));
+    assert_eq!(cast_to_type(json!("x"), "array"), json!(["x"]));
+    assert_eq!(cast_to_type(json!({ "a": 1 }), "string"), json!("{\"a\":1}"));
+    assert_eq!(cast_to_type(json!("no"), "boolean"), json!(true));
+  }
+
+  #[test]
+  fn screens_unsafe_patterns() {
+    assert!(is_unsafe_regex("a(?=b)"));
+    assert!(is_unsafe_regex("a{1,3}"));
+    assert!(!is_unsafe_regex("a{3}"));
+    assert!(!regex_match(&json!("aaa"), Some(&json!("a{1,2}"))));
+    assert!(regex_match(&json!("abc"), Some(&json!("^a.c$"))));
+  }
+}
@@ -0,0 +1,177 @@
+//! The JSONPath subset transformations use, ported from `JSONPathEvaluator.ts`:
+//! `$`, `.key`, `[0]`, `['key']`, `[*]` and `[start:end]`. A property step
+//! after one that produced an array is applied to each element, so
+//! `$.items[*].id` collects every id.
+
+use serde_json::Value;
+
+use super::functions::slice_bounds;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+  Root,
+  Property(String),
+  Index(Index),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Index {
+  Wildcard,
+  Numeric(Option<usize>),
+  Key(String),
+  Slice { start: f64, end: Option<f64> },
+}
+
+/// A compiled path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+  steps: Vec<Step>,
+}
+
+impl JsonPath {
+  pub fn compile(path: &str) -> Self {
+    let mut steps = Vec::new();
+    let mut rest = path;
+    if let Some(stripped) = path.strip_prefix('$') {
+      steps.push(Step::Root);
+      rest = stripped;
+    }
+
+    let mut current = String::new();
+    let mut in_brackets = false;
+    for c in rest.chars() {
+      match c {
+        '[' if !in_brackets => {
+          if !current.is_empty() {
+            steps.push(Step::Property(std::mem::take(&mut current)));
+          }
+          in_brackets = true;
+        }
+        ']' if in_brackets => {
+          if !current.is_empty() {
+            steps.push(Step::Index(parse_index(std::mem::take(&mut current).trim())));
+          }
+          in_brackets = false;
+        }
+        '.' if !in_brackets => {
+          if !current.is_empty() {
+            steps.push(Step::Property(std::mem::take(&mut current)));
+          }
+        }
+        _ => current.push(c),
+      }
+    }
+    if !current.is_empty() {
+      steps.push(Step::Property(current));
+    }
+
+    Self { steps }
+  }
+
+  /// The value at this path, or `null` when there is none.
+  pub fn evaluate(&self, data: &Value) -> Value {
+    let mut current = data.clone();
+    let mut steps = self.steps.iter().peekable();
+
+    while let Some(step) = steps.next() {
+      if current.is_null() {
+        return Value::Null;
+      }
+      current = execute_step(step, &current);
+
+      if let (Value::Array(items), Some(Step::Property(key))) = (&current, steps.peek()) {
+        current = Value::Array(items.iter().map(|item| property(item, key)).collect());
+        steps.next();
+      }
+    }
+    current
+  }
+}
+
+/// Evaluate `path` against `data` without keeping the compiled form.
+pub fn evaluate(path: &str, data: &Value) -> Value {
+  JsonPath::compile(path).evaluate(data)
+}
+
+fn parse_index(index: &str) -> Index {
+  if index == "*" {
+    return Index::Wildcard;
+  }
+  if let Some((start, end)) = index.split_once(':') {
+    let end = end.split(':').next().unwrap_or_default().trim();
+    return Index::Slice {
+      start: start.trim().parse::<i64>().map_or(0.0, |start| start as f64),
+      end: (!end.is_empty()).then(|| end.parse::<i64>().map_or(0.0, |end| end as f64)),
+    };
+  }
+  if !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) {
+    return Index::Numeric(index.parse().ok());
+  }
+  let quoted = index.len() >= 2
+    && ((index.starts_with('\'') && index.ends_with('\'')) || (index.starts_with('"') && index.ends_with('"')));
+  if quoted {
+    return Index::Key(index[1..index.len() - 1].to_string());
+  }
+  Index::Key(index.to_string())
+}
+
+fn execute_step(step: &Step, data: &Value) -> Value {
+  match step {
+    Step::Root => data.clone(),
+    Step::Property(key) => property(data, key),
+    Step::Index(index) => execute_index(index, data),
+  }
+}
+
+fn execute_index(index: &Index, data: &Value) -> Value {
+  match (data, index) {
+    (Value::Array(items), Index::Numeric(position)) => {
+      position.and_then(|position| items.get(position)).cloned().unwrap_or(Value::Null)
+    }
+    (Value::Array(items), Index::Slice { start, end }) => {
+      let (start, end) = slice_bounds(items.len(), *start, end.unwrap_or(f64::INFINITY));
+      Value::Array(items[start..end].to_vec())
+    }
+    (Value::Array(_), Index::Wildcard) => data.clone(),
+    (Value::Object(map), Index::Key(key)) => map.get(key).cloned().unwrap_or(Value::Null),
+    (Value::Object(map), Index::Wildcard) => Value::Array(map.values().cloned().collect()),
+    _ => Value::Null,
+  }
+}
+
+/// `value[key]` for objects, and for arrays given a numeric key.
+fn property(value: &Value, key: &str) -> Value {
+  match value {
+    Value::Object(map) => map.get(key).cloned(),
+    Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)).cloned(),
+    _ => None,
+  }
+  .unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn walks_keys_indexes_and_slices() {
+    let data = json!({ "users": [{ "name": "a" }, { "name": "b" }, { "name": "c" }], "map": { "x": 1, "y": 2 } });
+    assert_eq!(evaluate("$.users[1].name", &data), json!("b"));
+    assert_eq!(evaluate("$['map']['y']", &data), json!(2));
+    assert_eq!(evaluate("$.users[0:2].name", &data), json!(["a", "b"]));
+    assert_eq!(evaluate("$.users[-1:].name", &data), json!(["c"]));
+    assert_eq!(evaluate("$.map[*]", &data), json!([1, 2]));
+    assert_eq!(evaluate("$.users.name", &data), json!(["a", "b", "c"]));
+    assert_eq!(evaluate("$.users[99]", &data), Value::Null);
+    assert_eq!(evaluate("$", &data), data);
+  }
+
+  #[test]
+  fn wildcards_map_over_elements() {
+    let data = json!({ "items": [{ "id": 1, "data": null }, null, { "id": 3, "data": { "v": 1 } }] });
+    assert_eq!(evaluate("$.items[*].id", &data), json!([1, null, 3]));
+    assert_eq!(evaluate("$.items[*].data", &data), json!([null, null, { "v": 1 }]));
+    assert_eq!(evaluate("$.missing[*].id", &data), Value::Null);
+  }
+}
@@ -0,0 +1,303 @@
+//! Transformation expressions, ported from `src/lib/transform`.
+//!
+//! An expression is a JSONPath or literal input followed by pipeline stages,
+//! e.g. `$.users | where($.age > 18) | map(id: $.id, name: $.name)`, and may
+//! reference `{{param:...}}`, `{{res:...}}`, `{{proc:...}}` and `{{func:...}}`
+//! templates.
+//!
+//! Values are plain JSON, so JavaScript's `undefined` is represented as
+//! `null`, and results such as `NaN` or `Infinity` come back as `null`.
+//! Expressions are limited in length and nesting depth so that hostile input
+//! fails with an error instead of exhausting the stack.
+
+pub mod functions;
+pub mod json_path;
+
+mod evaluator;
+mod parser;
+
+use serde_json::{Number, Value};
+
+use crate::error::Result;
+use crate::template::TemplateContext;
+
+pub use parser::{parse, Expression};
+
+/// Evaluate `expression` against `data`. An empty expression returns `data`.
+pub fn evaluate(expression: &str, data: &Value, context: &TemplateContext) -> Result<Value> {
+  let expression = expression.trim();
+  if expression.is_empty() {
+    return Ok(data.clone());
+  }
+  evaluator::Evaluator::new(context).evaluate(&parse(expression)?, data)
+}
+
+/// JavaScript's `Number(value)`, except that `null` (standing in for
+/// `undefined`) is `NaN`.
+pub(crate) fn to_number(value: &Value) -> f64 {
+  match value {
+    Value::Null | Value::Object(_) => f64::NAN,
+    Value::Bool(flag) => f64::from(u8::from(*flag)),
+    Value::Number(number) => number.as_f64().unwrap_or(f64::NAN),
+    Value::String(text) => parse_number(text),
+    Value::Array(items) => match items.as_slice() {
+      [] => 0.0,
+      [item] if !item.is_null() => parse_number(&crate::template::js_string(item)),
+      _ => f64::NAN,
+    },
+  }
+}
+
+fn parse_number(text: &str) -> f64 {
+  let text = text.trim();
+  if text.is_empty() {
+    return 0.0;
+  }
+  match text {
+    "Infinity" | "+Infinity" => f64::INFINITY,
+    "-Infinity" => f64::NEG_INFINITY,
+    // Rust also accepts "inf" and "NaN", which JavaScript does not.
+    _ if text.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')) => {
+      text.parse().unwrap_or(f64::NAN)
+    }
+    _ => f64::NAN,
+  }
+}
+
+/// A number as JSON: integral values become integers so they compare equal
+/// to integer literals, and non-finite values become `null`.
+pub(crate) fn number(value: f64) -> Value {
+  const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+  if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER {
+    // Normalizes -0 to 0 as well.
+    return Value::from(value as i64);
+  }
+  Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+/// JavaScript truthiness.
+pub(crate) fn truthy(value: &Value) -> bool {
+  match value {
+    Value::Null => false,
+    Value::Bool(flag) => *flag,
+    Value::Number(number) => number.as_f64().is_some_and(|n| n != 0.0 && !n.is_nan()),
+    Value::String(text) => !text.is_empty(),
+    Value::Array(_) | Value::Object(_) => true,
+  }
+}
+
+/// JavaScript `===` on JSON values, with containers compared structurally.
+pub(crate) fn strict_equals(left: &Value, right: &Value) -> bool {
+  match (left, right) {
+    (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+    _ => left == right,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn eval(expression: &str, data: Value) -> Value {
+    evaluate(expression, &data, &TemplateContext::default()).unwrap()
+  }
+
+  fn eval_with(expression: &str, data: Value, parameters: Value) -> Result<Value> {
+    let context = TemplateContext {
+      parameters: serde_json::from_value(parameters).unwrap(),
+      ..TemplateContext::default()
+    };
+    evaluate(expression, &data, &context)
+  }
+
+  fn users() -> Value {
+    json!({
+      "data": [
+        { "name": "Alice", "age": 25, "active": true },
+        { "name": "Bob", "age": 30, "active": false },
+        { "name": "Charlie", "age": 17, "active": true },
+        { "name": "Diana", "age": 22, "active": true }
+      ]
+    })
+  }
+
+  #[test]
+  fn empty_expression_returns_data() {
+    assert_eq!(eval("  ", json!({ "a": 1 })), json!({ "a": 1 }));
+  }
+
+  #[test]
+  fn json_paths_with_wildcards() {
+    let data = json!({
+      "data": [
+        { "code": "fusion-cafe", "terminals": [{ "id": 1, "name": "T1" }, { "id": 2, "name": "T2" }] },
+        { "code": "other", "terminals": [{ "id": 3, "name": "T3" }] }
+      ]
+    });
+    assert_eq!(eval("$.data[0].terminals[*].id", data.clone()), json!([1, 2]));
+    assert_eq!(eval("$.data[0].terminals[*].nonexistent", data.clone()), json!([null, null]));
+    assert_eq!(eval("$.data | map($.terminals[*].id)", data.clone()), json!([[1, 2], [3]]));
+    assert_eq!(
+      eval(
+        "$.data | where($.code == 'fusion-cafe') | map($.terminals[*].id) | flatten() | sort() | first()",
+        data
+      ),
+      json!(1)
+    );
+  }
+
+  #[test]
+  fn where_filters_with_comparisons() {
+    let names = |expression: &str| eval(&format!("{expression} | map($.name)"), users());
+    assert_eq!(names("$.data | where($.age > 18 && $.active == true)"), json!(["Alice", "Diana"]));
+    assert_eq!(names("$.data | where($.age < 18 || $.age > 29)"), json!(["Bob", "Charlie"]));
+    assert_eq!(names("$.data | select($.name == \"Bob\")"), json!(["Bob"]));
+    assert_eq!(names("$.data | where($.nonexistent > 0)"), json!([]));
+    assert_eq!(names("$.data | where($.age > 18) | sort(by: \"age\")"), json!(["Diana", "Alice", "Bob"]));
+  }
+
+  #[test]
+  fn where_uses_template_parameters() {
+    let data = json!({ "data": [{ "value": null, "name": "match" }, { "value": "x", "name": "no match" }] });
+    assert_eq!(
+      eval_with("$.data | where($.value == {{param:null_val}})", data, json!({ "null_val": null })).unwrap(),
+      json!([{ "value": null, "name": "match" }])
+    );
+
+    let data = json!({ "data": [{ "key": "a" }, { "key": "b" }] });
+    assert_eq!(
+      eval_with(
+        "$.data | where($.key == {{param:object_val}}.key)",
+        data,
+        json!({ "object_val": { "key": "b" } })
+      )
+      .unwrap(),
+      json!([{ "key": "b" }])
+    );
+  }
+
+  #[test]
+  fn map_with_named_arguments_and_arithmetic() {
+    let data = json!({
+      "users": [
+        { "id": 1, "balance": 1000, "init_credit": 500, "used_credit": 100 },
+        { "id": 2, "balance": 2550, "init_credit": 800, "used_credit": 200 }
+      ]
+    });
+    assert_eq!(eval("$.users | map($.init_credit - $.used_credit)", data.clone()), json!([400, 600]));
+    assert_eq!(
+      eval("$.users | map(balance: $.balance | div(100), status: \"active\")", data.clone()),
+      json!([{ "balance": 10, "status": "active" }, { "balance": 25.5, "status": "active" }])
+    );
+    assert_eq!(
+      eval_with(
+        "$.users | map({ id: $.id | string(), user: \"{{param:username}}\" })",
+        data,
+        json!({ "username": "neo" })
+      )
+      .unwrap(),
+      json!([{ "id": "1", "user": "neo" }, { "id": "2", "user": "neo" }])
+    );
+  }
+
+  #[test]
+  fn math_operators_and_functions() {
+    assert_eq!(eval("((2 + 3) * 4) - (10 / 2)", json!({})), json!(15));
+    assert_eq!(eval("10 % 3", json!({})), json!(1));
+    assert_eq!(eval("10 / 0", json!({})), Value::Null);
+    assert_eq!(eval("\"hello\" + \"world\"", json!({})), Value::Null);
+    assert_eq!(eval("-10.6 | round()", json!({})), json!(-11));
+    assert_eq!(eval("1.23456 | round(2)", json!({})), json!(1.23));
+    assert_eq!(eval("-10 | mod(3)", json!({})), json!(-1));
+    assert_eq!(eval("2 | pow(10)", json!({})), json!(1024));
+    assert_eq!(eval("\"10\" | add(5)", json!({})), Value::Null);
+    assert_eq!(
+      eval_with("$.values | first() | add({{param:increment}})", json!({ "values": [10, 20] }), json!({ "increment": 5 }))
+        .unwrap(),
+      json!(15)
+    );
+  }
+
+  #[test]
+  fn casts() {
+    let data = json!({ "data": [2.75, "42", true, false, "invalid", null] });
+    assert_eq!(eval("$.data | int()", data.clone()), json!([2, 42, 1, 0, null, null]));
+    assert_eq!(eval("$.data | map($ | int(0)) | sum()", data.clone()), json!(45));
+    assert_eq!(eval("$.data | string(\"N/A\")", data), json!(["2.75", "42", "true", "false", "invalid", "N/A"]));
+    assert_eq!(eval("$.value | bool()", json!({ "value": "hello" })), json!(true));
+    assert_eq!(eval("$.value | bool()", json!({ "value": "0" })), json!(false));
+    assert_eq!(eval("$.value | float() | int()", json!({ "value": "12.7abc" })), json!(12));
+  }
+
+  #[test]
+  fn array_helpers() {
+    let data = json!({ "numbers": [5, 3, 9, 1], "nested": [[1, [2]], [3]] });
+    assert_eq!(eval("$.numbers | sort(desc: true) | take(2)", data.clone()), json!([9, 5]));
+    assert_eq!(eval("$.numbers | skip(3)", data.clone()), json!([1]));
+    assert_eq!(eval("$.numbers | at(-1)", data.clone()), json!(1));
+    assert_eq!(eval("$.numbers | at(10)", data.clone()), Value::Null);
+    assert_eq!(eval("$.nested | flatten()", data.clone()), json!([1, [2], 3]));
+    assert_eq!(eval("$.nested | flatten(2)", data.clone()), json!([1, 2, 3]));
+    assert_eq!(eval("$.numbers | count()", data.clone()), json!(4));
+    assert_eq!(eval("$ | pick([\"numbers\"])", data), json!({ "numbers": [5, 3, 9, 1] }));
+  }
+
+  #[test]
+  fn string_operators() {
+    let data = json!({ "name": "test-pilot" });
+    assert_eq!(eval("$.name | contains(\"pilot\")", data.clone()), json!(true));
+    assert_eq!(eval("$.name | startsWith(\"test\")", data.clone()), json!(true));
+    assert_eq!(eval("$.name | matches(\"^t.*t$\")", data.clone()), json!(true));
+    assert_eq!(eval("$.name | length()", data.clone()), json!(10));
+    assert_eq!(eval("$.missing | empty()", data), json!(true));
+  }
+
+  #[test]
+  fn rejects_invalid_expressions() {
+    let error = |expression: &str| {
+      evaluate(expression, &json!({ "data": [] }), &TemplateContext::default())
+        .unwrap_err()
+        .to_string()
+    };
+    assert!(error("$.data | map(int())").contains("Direct function call"));
+    assert!(error("$.data | map({ id: $.id ").contains("Expected"));
+    assert!(error("$.data | map(id: $.id, id: $.status)").contains("Duplicate named argument"));
+    assert!(error("$.data | | map($.id)").contains("Expected identifier"));
+    assert!(error("$.data | take({{{param:limit}}})").contains("{{...}} templates only"));
+    assert!(error("$.data | take({{env:LIMIT}})").contains("not supported in transformations"));
+    assert!(error("$.data | nope()").contains("Unknown function"));
+    assert!(error("$.data # 1").contains("Unexpected character"));
+  }
+
+  #[test]
+  fn hostile_expressions_fail_cleanly() {
+    let context = TemplateContext::default();
+    let data = json!({});
+    for expression in [
+      "(".repeat(10_000),
+      "!".repeat(10_000) + "1",
+      "1".to_string() + &" + 1".repeat(5_000),
+      "[".repeat(5_000) + &"]".repeat(5_000),
+      "{a:".repeat(5_000),
+      "$ | map(".repeat(2_000),
+      "x".to_string() + &".y".repeat(5_000),
+      "\"unterminated".to_string(),
+      "{{param:".to_string(),
+    ] {
+      assert!(evaluate(&expression, &data, &context).is_err(), "{}", &expression[..20.min(expression.len())]);
+    }
+  }
+
+  #[test]
+  fn coerces_like_javascript() {
+    assert_eq!(to_number(&json!(" 42 ")), 42.0);
+    assert_eq!(to_number(&json!("")), 0.0);
+    assert!(to_number(&json!("inf")).is_nan());
+    assert!(to_number(&json!("12px")).is_nan());
+    assert_eq!(to_number(&json!([7])), 7.0);
+    assert_eq!(number(40.0), json!(40));
+    assert_eq!(number(f64::NAN), Value::Null);
+    assert!(!truthy(&json!(0)) && truthy(&json!("0")) && truthy(&json!([])));
+  }
+}
@@ -0,0 +1,658 @@
+//! Tokenizer and recursive-descent parser, ported from `ExpressionParser.ts`.
+
+use serde_json::Value;
+
+use super::json_path::JsonPath;
+use super::number;
+use crate::error::{Error, Result};
+
+/// Longest expression accepted, in characters.
+const MAX_EXPRESSION_LENGTH: usize = 64 * 1024;
+/// Deepest nesting accepted; keeps parsing and evaluation off the end of the stack.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+  Not,
+  Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+  Or,
+  And,
+  Equal,
+  NotEqual,
+  Less,
+  LessOrEqual,
+  Greater,
+  GreaterOrEqual,
+  Add,
+  Subtract,
+  Multiply,
+  Divide,
+  Remainder,
+}
+
+impl BinaryOperator {
+  fn from_token(token: &str) -> Option<Self> {
+    Some(match token {
+      "||" => Self::Or,
+      "&&" => Self::And,
+      "==" => Self::Equal,
+      "!=" => Self::NotEqual,
+      "<" => Self::Less,
+      "<=" => Self::LessOrEqual,
+      ">" => Self::Greater,
+      ">=" => Self::GreaterOrEqual,
+      "+" => Self::Add,
+      "-" => Self::Subtract,
+      "*" => Self::Multiply,
+      "/" => Self::Divide,
+      "%" => Self::Remainder,
+      _ => return None,
+    })
+  }
+
+  fn precedence(self) -> u8 {
+    match self {
+      Self::Or => 1,
+      Self::And => 2,
+      Self::Equal | Self::NotEqual => 3,
+      Self::Less | Self::LessOrEqual | Self::Greater | Self::GreaterOrEqual => 4,
+      Self::Add | Self::Subtract => 5,
+      Self::Multiply | Self::Divide | Self::Remainder => 6,
+    }
+  }
+}
+
+/// A parsed transformation expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+  Literal(Value),
+  /// `data` for the root input, otherwise a key of the current item.
+  Identifier(String),
+  JsonPath(JsonPath),
+  /// A bare `{{source:path}}` that keeps the resolved value's type.
+  Template(String),
+  /// A string literal with templates inside, always resolved to a string.
+  TemplateString(String),
+  Member {
+    object: Box<Expression>,
+    property: String,
+  },
+  Array(Vec<Expression>),
+  Object(Vec<(String, Expression)>),
+  Unary {
+    operator: UnaryOperator,
+    operand: Box<Expression>,
+  },
+  Binary {
+    operator: BinaryOperator,
+    left: Box<Expression>,
+    right: Box<Expression>,
+  },
+  Pipeline {
+    input: Box<Expression>,
+    calls: Vec<FunctionCall>,
+  },
+}
+
+/// One `| name(args)` pipeline stage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+  pub name: String,
+  pub args: Vec<Expression>,
+  pub named_args: Vec<(String, Expression)>,
+}
+
+impl FunctionCall {
+  pub fn named_arg(&self, name: &str) -> Option<&Expression> {
+    self
+      .named_args
+      .iter()
+      .find(|(arg, _)| arg == name)
+      .map(|(_, value)| value)
+  }
+}
+
+/// Parse a whole expression.
+pub fn parse(expression: &str) -> Result<Expression> {
+  let length = expression.chars().count();
+  if length > MAX_EXPRESSION_LENGTH {
+    return Err(Error::Transform(format!(
+      "Expression is {length} characters long; the limit is {MAX_EXPRESSION_LENGTH}."
+    )));
+  }
+
+  let mut parser = Parser {
+    tokens: tokenize(expression)?,
+    current: 0,
+    depth: 0,
+  };
+  let node = parser.parse_pipeline()?;
+  parser.expect(TokenKind::Eof)?;
+  Ok(node)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+  Template,
+  JsonPath,
+  Identifier,
+  Number,
+  String,
+  Operator,
+  Paren,
+  Brace,
+  Bracket,
+  Comma,
+  Colon,
+  Dot,
+  Pipe,
+  Eof,
+}
+
+impl TokenKind {
+  fn name(self) -> &'static str {
+    match self {
+      Self::Template => "template",
+      Self::JsonPath => "jsonPath",
+      Self::Identifier => "identifier",
+      Self::Number => "number",
+      Self::String => "string",
+      Self::Operator => "operator",
+      Self::Paren => "paren",
+      Self::Brace => "brace",
+      Self::Bracket => "bracket",
+      Self::Comma => "comma",
+      Self::Colon => "colon",
+      Self::Dot => "dot",
+      Self::Pipe => "pipe",
+      Self::Eof => "eof",
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+  kind: TokenKind,
+  value: String,
+  position: usize,
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  current: usize,
+  depth: usize,
+}
+
+impl Parser {
+  fn parse_pipeline(&mut self) -> Result<Expression> {
+    self.enter(1)?;
+    let input = self.parse_binary_expression(0)?;
+    let mut calls = Vec::new();
+    while self.matches(TokenKind::Pipe) {
+      calls.push(self.parse_pipeline_stage()?);
+    }
+    self.depth -= 1;
+
+    Ok(match input {
+      _ if calls.is_empty() => input,
+      Expression::Pipeline {
+        input,
+        calls: mut earlier,
+      } => {
+        earlier.extend(calls);
+        Expression::Pipeline { input, calls: earlier }
+      }
+      input => Expression::Pipeline {
+        input: Box::new(input),
+        calls,
+      },
+    })
+  }
+
+  fn parse_pipeline_stage(&mut self) -> Result<FunctionCall> {
+    let name = self.expect(TokenKind::Identifier)?.value;
+    self.expect_value(TokenKind::Paren, "(")?;
+    self.parse_function_call(name)
+  }
+
+  fn parse_binary_expression(&mut self, min_precedence: u8) -> Result<Expression> {
+    let mut left = self.parse_unary()?;
+    let mut chained = 0;
+
+    while self.peek().kind == TokenKind::Operator {
+      let Some(operator) = BinaryOperator::from_token(&self.peek().value) else {
+        break;
+      };
+      if operator.precedence() < min_precedence {
+        break;
+      }
+
+      self.advance();
+      // Each operator nests the tree one level deeper.
+      self.enter(1)?;
+      chained += 1;
+      let right = self.parse_binary_expression(operator.precedence() + 1)?;
+      left = Expression::Binary {
+        operator,
+        left: Box::new(left),
+        right: Box::new(right),
+      };
+    }
+
+    self.depth -= chained;
+    Ok(left)
+  }
+
+  fn parse_unary(&mut self) -> Result<Expression> {
+    let operator = match (self.peek().kind, self.peek().value.as_str()) {
+      (TokenKind::Operator, "!") => UnaryOperator::Not,
+      (TokenKind::Operator, "-") => UnaryOperator::Negate,
+      _ => return self.parse_postfix(),
+    };
+    self.advance();
+    self.enter(1)?;
+    let operand = self.parse_unary()?;
+    self.depth -= 1;
+    Ok(Expression::Unary {
+      operator,
+      operand: Box::new(operand),
+    })
+  }
+
+  fn parse_postfix(&mut self) -> Result<Expression> {
+    let mut node = self.parse_primary()?;
+    let mut chained = 0;
+    while self.matches(TokenKind::Dot) {
+      let property = self.expect(TokenKind::Identifier)?.value;
+      self.enter(1)?;
+      chained += 1;
+      node = Expression::Member {
+        object: Box::new(node),
+        property,
+      };
+    }
+    self.depth -= chained;
+    Ok(node)
+  }
+
+  fn parse_primary(&mut self) -> Result<Expression> {
+    let token = self.peek().clone();
+
+    if self.matches_value(TokenKind::Paren, "(") {
+      let node = self.parse_pipeline()?;
+      self.expect_value(TokenKind::Paren, ")")?;
+      return Ok(node);
+    }
+    if self.matches_value(TokenKind::Bracket, "[") {
+      return self.parse_array();
+    }
+    if self.matches_value(TokenKind::Brace, "{") {
+      return self.parse_object();
+    }
+
+    match token.kind {
+      TokenKind::Number => {
+        self.advance();
+        Ok(Expression::Literal(token.value.parse().map_or(Value::Null, number)))
+      }
+      TokenKind::String => {
+        self.advance();
+        let value = unquote(&token.value);
+        if crate::template::has_template_expressions(&value) {
+          Ok(Expression::TemplateString(value))
+        } else {
+          Ok(Expression::Literal(Value::String(value)))
+        }
+      }
+      TokenKind::Template => {
+        self.advance();
+        Ok(Expression::Template(token.value))
+      }
+      TokenKind::JsonPath => {
+        self.advance();
+        Ok(Expression::JsonPath(JsonPath::compile(&token.value)))
+      }
+      TokenKind::Identifier => {
+        self.advance();
+        match token.value.as_str() {
+          "true" => return Ok(Expression::Literal(Value::Bool(true))),
+          "false" => return Ok(Expression::Literal(Value::Bool(false))),
+          "null" | "undefined" => return Ok(Expression::Literal(Value::Null)),
+          _ => {}
+        }
+        if self.peek().kind == TokenKind::Paren && self.peek().value == "(" {
+          return Err(self.error(&format!(
+            "Direct function call \"{name}(...)\" is not valid in transformations. Use pipeline syntax: value | {name}(...).",
+            name = token.value
+          )));
+        }
+        Ok(Expression::Identifier(token.value))
+      }
+      _ => Err(self.error(&format!("Unexpected token \"{}\".", token.value))),
+    }
+  }
+
+  fn parse_array(&mut self) -> Result<Expression> {
+    let mut elements = Vec::new();
+    if self.matches_value(TokenKind::Bracket, "]") {
+      return Ok(Expression::Array(elements));
+    }
+    loop {
+      elements.push(self.parse_pipeline()?);
+      if !self.matches(TokenKind::Comma) {
+        break;
+      }
+    }
+    self.expect_value(TokenKind::Bracket, "]")?;
+    Ok(Expression::Array(elements))
+  }
+
+  fn parse_object(&mut self) -> Result<Expression> {
+    let mut properties: Vec<(String, Expression)> = Vec::new();
+    if self.matches_value(TokenKind::Brace, "}") {
+      return Ok(Expression::Object(properties));
+    }
+    loop {
+      let key_token = self.peek().clone();
+      let key = match key_token.kind {
+        TokenKind::Identifier => key_token.value,
+        TokenKind::String => unquote(&key_token.value),
+        _ => return Err(self.error("Object literal keys must be identifiers or strings.")),
+      };
+      if properties.iter().any(|(existing, _)| *existing == key) {
+        return Err(self.error(&format!("Duplicate object key \"{key}\".")));
+      }
+      self.advance();
+
+      self.expect(TokenKind::Colon)?;
+      properties.push((key, self.parse_pipeline()?));
+      if !self.matches(TokenKind::Comma) {
+        break;
+      }
+    }
+    self.expect_value(TokenKind::Brace, "}")?;
+    Ok(Expression::Object(properties))
+  }
+
+  fn parse_function_call(&mut self, name: String) -> Result<FunctionCall> {
+    let mut call = FunctionCall {
+      name,
+      args: Vec::new(),
+      named_args: Vec::new(),
+    };
+    if self.matches_value(TokenKind::Paren, ")") {
+      return Ok(call);
+    }
+    loop {
+      if self.peek().kind == TokenKind::Identifier && self.peek_next().kind == TokenKind::Colon {
+        let arg_name = self.advance().value;
+        if call.named_arg(&arg_name).is_some() {
+          return Err(self.error(&format!("Duplicate named argument \"{arg_name}\".")));
+        }
+        self.expect(TokenKind::Colon)?;
+        let value = self.parse_pipeline()?;
+        call.named_args.push((arg_name, value));
+      } else {
+        call.args.push(self.parse_pipeline()?);
+      }
+      if !self.matches(TokenKind::Comma) {
+        break;
+      }
+    }
+    self.expect_value(TokenKind::Paren, ")")?;
+    Ok(call)
+  }
+
+  fn enter(&mut self, levels: usize) -> Result<()> {
+    self.depth += levels;
+    if self.depth > MAX_DEPTH {
+      return Err(self.error(&format!("Expression is nested too deeply (limit {MAX_DEPTH}).")));
+    }
+    Ok(())
+  }
+
+  fn peek(&self) -> &Token {
+    &self.tokens[self.current]
+  }
+
+  fn peek_next(&self) -> &Token {
+    self.tokens.get(self.current + 1).unwrap_or(&self.tokens[self.tokens.len() - 1])
+  }
+
+  fn advance(&mut self) -> Token {
+    let token = self.peek().clone();
+    if token.kind != TokenKind::Eof {
+      self.current += 1;
+    }
+    token
+  }
+
+  fn matches(&mut self, kind: TokenKind) -> bool {
+    if self.peek().kind != kind {
+      return false;
+    }
+    self.advance();
+    true
+  }
+
+  fn matches_value(&mut self, kind: TokenKind, value: &str) -> bool {
+    if self.peek().kind != kind || self.peek().value != value {
+      return false;
+    }
+    self.advance();
+    true
+  }
+
+  fn expect(&mut self, kind: TokenKind) -> Result<Token> {
+    if self.peek().kind != kind {
+      return Err(self.error(&format!("Expected {} but found \"{}\".", kind.name(), self.peek().value)));
+    }
+    Ok(self.advance())
+  }
+
+  fn expect_value(&mut self, kind: TokenKind, value: &str) -> Result<Token> {
+    if self.peek().kind != kind || self.peek().value != value {
+      return Err(self.error(&format!("Expected \"{value}\" but found \"{}\".", self.peek().value)));
+    }
+    Ok(self.advance())
+  }
+
+  fn error(&self, message: &str) -> Error {
+    Error::Transform(format!("{message} Position {}.", self.peek().position))
+  }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+  let chars: Vec<char> = expression.chars().collect();
+  let text = |from: usize, to: usize| chars[from..to].iter().collect::<String>();
+  let starts_with = |index: usize, pattern: &str| {
+    pattern.chars().enumerate().all(|(offset, c)| chars.get(index + offset) == Some(&c))
+  };
+  let find = |from: usize, pattern: &str| (from..chars.len()).find(|&index| starts_with(index, pattern));
+
+  let mut tokens = Vec::new();
+  let mut push = |kind: TokenKind, value: String, position: usize| tokens.push(Token { kind, value, position });
+  let mut index = 0;
+
+  while index < chars.len() {
+    let c = chars[index];
+    let start = index;
+
+    if c.is_whitespace() {
+      index += 1;
+      continue;
+    }
+
+    if starts_with(index, "{{{") {
+      let end = find(index + 3, "}}}")
+        .ok_or_else(|| Error::Transform("Unclosed triple-brace template expression.".to_string()))?;
+      index = end + 3;
+      push(TokenKind::Template, text(start, index), start);
+      continue;
+    }
+    if starts_with(index, "{{") {
+      let end = find(index + 2, "}}").ok_or_else(|| Error::Transform("Unclosed template expression.".to_string()))?;
+      index = end + 2;
+      push(TokenKind::Template, text(start, index), start);
+      continue;
+    }
+
+    if c == '"' || c == '\'' {
+      index = read_string(&chars, index)?;
+      push(TokenKind::String, text(start, index), start);
+      continue;
+    }
+
+    if c == '$' {
+      index = read_json_path(&chars, index);
+      push(TokenKind::JsonPath, text(start, index), start);
+      continue;
+    }
+
+    let two = text(index, (index + 2).min(chars.len()));
+    if ["||", "&&", "==", "!=", ">=", "<="].contains(&two.as_str()) {
+      index += 2;
+      push(TokenKind::Operator, two, start);
+      continue;
+    }
+
+    let single = match c {
+      '|' => Some(TokenKind::Pipe),
+      '>' | '<' | '!' | '+' | '-' | '*' | '/' | '%' => Some(TokenKind::Operator),
+      '(' | ')' => Some(TokenKind::Paren),
+      '{' | '}' => Some(TokenKind::Brace),
+      '[' | ']' => Some(TokenKind::Bracket),
+      ',' => Some(TokenKind::Comma),
+      ':' => Some(TokenKind::Colon),
+      '.' if !chars.get(index + 1).is_some_and(char::is_ascii_digit) => Some(TokenKind::Dot),
+      _ => None,
+    };
+    if let Some(kind) = single {
+      index += 1;
+      push(kind, c.to_string(), start);
+      continue;
+    }
+
+    if c == '.' || c.is_ascii_digit() {
+      index += 1;
+      while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.') {
+        index += 1;
+      }
+      push(TokenKind::Number, text(start, index), start);
+      continue;
+    }
+
+    if c.is_ascii_alphabetic() || c == '_' {
+      index += 1;
+      while index < chars.len() && (chars[index].is_ascii_alphanumeric() || chars[index] == '_') {
+        index += 1;
+      }
+      push(TokenKind::Identifier, text(start, index), start);
+      continue;
+    }
+
+    return Err(Error::Transform(format!(
+      "Unexpected character \"{c}\" at position {index}."
+    )));
+  }
+
+  push(TokenKind::Eof, "<eof>".to_string(), chars.len());
+  Ok(tokens)
+}
+
+/// The index just past the closing quote of the string starting at `start`.
+fn read_string(chars: &[char], start: usize) -> Result<usize> {
+  let quote = chars[start];
+  let mut index = start + 1;
+  let mut escaped = false;
+  while index < chars.len() {
+    match chars[index] {
+      _ if escaped => escaped = false,
+      '\\' => escaped = true,
+      c if c == quote => return Ok(index + 1),
+      _ => {}
+    }
+    index += 1;
+  }
+  Err(Error::Transform("Unclosed string literal.".to_string()))
+}
+
+/// The index just past a `$...` path, which ends at the first operator or
+/// delimiter outside brackets and quotes.
+fn read_json_path(chars: &[char], start: usize) -> usize {
+  let mut index = start + 1;
+  let mut bracket_depth = 0i64;
+  let mut quote = None;
+
+  while index < chars.len() {
+    let c = chars[index];
+    if let Some(open) = quote {
+      if c == open && chars[index - 1] != '\\' {
+        quote = None;
+      }
+    } else {
+      match c {
+        '"' | '\'' => quote = Some(c),
+        '[' => bracket_depth += 1,
+        ']' => bracket_depth -= 1,
+        c if bracket_depth == 0 && (c.is_whitespace() || ",|):}<>=!+-*/%".contains(c)) => break,
+        _ => {}
+      }
+    }
+    index += 1;
+  }
+  index
+}
+
+fn unquote(token: &str) -> String {
+  let raw = &token[1..token.len() - 1];
+  if token.starts_with('"') {
+    serde_json::from_str(token).unwrap_or_else(|_| raw.to_string())
+  } else {
+    raw.replace("\\'", "'")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn folds_pipelines_and_precedence() {
+    let Expression::Pipeline { input, calls } = parse("($.a | first()) | count()").unwrap() else {
+      panic!("expected a pipeline");
+    };
+    assert!(matches!(*input, Expression::JsonPath(_)));
+    assert_eq!(calls.iter().map(|call| call.name.as_str()).collect::<Vec<_>>(), ["first", "count"]);
+
+    let Expression::Binary { operator, right, .. } = parse("1 + 2 * 3").unwrap() else {
+      panic!("expected a binary expression");
+    };
+    assert_eq!(operator, BinaryOperator::Add);
+    assert!(matches!(*right, Expression::Binary { operator: BinaryOperator::Multiply, .. }));
+  }
+
+  #[test]
+  fn json_paths_stop_at_operators() {
+    let tokens = tokenize("$.items[?x == 'a b'].id>=2").unwrap();
+    assert_eq!(tokens[0].value, "$.items[?x == 'a b'].id");
+    assert_eq!(tokens[1].value, ">=");
+  }
+
+  #[test]
+  fn strings_unescape() {
+    assert_eq!(unquote(r#""a\"b""#), "a\"b");
+    assert_eq!(unquote(r"'it\'s'"), "it's");
+  }
+
+  #[test]
+  fn limits_depth_and_length() {
+    assert!(parse(&format!("{}1", "-".repeat(MAX_DEPTH))).is_err());
+    assert!(parse(&format!("{}1{}", "(".repeat(MAX_DEPTH - 1), ")".repeat(MAX_DEPTH - 1))).is_ok());
+    assert!(parse(&"1".repeat(MAX_EXPRESSION_LENGTH + 1)).unwrap_err().to_string().contains("limit"));
+  }
+}
@@ -0,0 +1,281 @@
+//! WebSocket client for flow steps.
+//!
+//! A connection is opened the way an HTTP request is, on a fresh TCP (and
+//! for `wss:` TLS) connection with the client's root store, carrying the
+//! run's cookies. Text frames that parse as JSON are read as JSON, other
+//! text as strings and binary frames as base64 strings. Everything sent and
+//! received is kept in a transcript for the endpoint result.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_tungstenite::WebSocketStream;
+use url::Url;
+
+use crate::cookies::{lock_jar, SharedCookieJar};
+use crate::error::{Error, Result};
+use crate::http::{self, HttpClient, HttpResponse, RequestTiming};
+
+/// How long [`WebSocket::close`] waits for the server's close frame.
+const CLOSE_TIMEOUT_MS: u64 = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FrameDirection {
+  Sent,
+  Received,
+}
+
+/// One message of a connection's transcript.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketFrame {
+  pub direction: FrameDirection,
+  pub data: Value,
+  /// Since the connection opened.
+  pub elapsed_ms: f64,
+}
+
+pub struct WebSocket {
+  stream: WebSocketStream<Box<dyn http::Io>>,
+  opened: Instant,
+  transcript: Vec<WebSocketFrame>,
+}
+
+impl WebSocket {
+  /// Connect to `url`, given as `ws:`/`wss:` or as the `http:`/`https:`
+  /// URL of the endpoint, with `headers` and the jar's cookies for it.
+  /// Returns the socket and the handshake response, whose `Set-Cookie`
+  /// headers go into the jar.
+  pub async fn connect(
+    client: &HttpClient,
+    url: &str,
+    headers: &HashMap<String, String>,
+    cookie_jar: Option<&SharedCookieJar>,
+    timeout_ms: u64,
+  ) -> Result<(Self, HttpResponse)> {
+    let url = Url::parse(url).map_err(|err| Error::InvalidRequest(format!("invalid URL `{url}`: {err}")))?;
+    let (socket_url, http_url) = match url.scheme() {
+      "ws" | "http" => (with_scheme(&url, "ws")?, with_scheme(&url, "http")?),
+      "wss" | "https" => (with_scheme(&url, "wss")?, with_scheme(&url, "https")?),
+      scheme => return Err(Error::InvalidRequest(format!("unsupported WebSocket URL scheme `{scheme}`"))),
+    };
+
+    let mut request = socket_url
+      .as_str()
+      .into_client_request()
+      .map_err(|err| Error::InvalidRequest(err.to_string()))?;
+    request.headers_mut().extend(http::build_header_map(headers)?);
+    if let Some(jar) = cookie_jar {
+      http::add_jar_cookies(request.headers_mut(), jar, &http_url);
+    }
+
+    let started_at = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_millis() as u64)
+      .unwrap_or_default();
+    let start = Instant::now();
+    let handshake = async {
+      let (stream, timing) = client.open_stream(&socket_url).await?;
+      let request_start = Instant::now();
+      let (stream, response) = tokio_tungstenite::client_async(request, stream).await.map_err(websocket_error)?;
+      Ok::<_, Error>((stream, response, RequestTiming {
+        ttfb_ms: elapsed_ms(request_start),
+        ..timing
+      }))
+    };
+    let (stream, response, timing) = tokio::time::timeout(Duration::from_millis(timeout_ms), handshake)
+      .await
+      .map_err(|_| Error::Timeout(timeout_ms))??;
+
+    let set_cookies: Vec<String> = response
+      .headers()
+      .get_all(::http::header::SET_COOKIE)
+      .iter()
+      .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+      .collect();
+    if let Some(jar) = cookie_jar {
+      lock_jar(jar).store_response_cookies(&http_url, &set_cookies);
+    }
+    log::debug!("[websocket] connected to {socket_url}");
+
+    let response = HttpResponse {
+      status: response.status().as_u16(),
+      status_text: response.status().canonical_reason().unwrap_or_default().to_string(),
+      url: socket_url.to_string(),
+      headers: response
+        .headers()
+        .iter()
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+        .collect(),
+      set_cookies,
+      body: Vec::new(),
+      timing: RequestTiming {
+        started_at,
+        total_ms: elapsed_ms(start),
+        ..timing
+      },
+    };
+    let socket = Self {
+      stream,
+      opened: Instant::now(),
+      transcript: Vec::new(),
+    };
+    Ok((socket, response))
+  }
+
+  /// Send `message`: a string as it is, anything else as JSON text.
+  pub async fn send(&mut self, message: &Value) -> Result<()> {
+    let text = match message {
+      Value::String(text) => text.clone(),
+      other => other.to_string(),
+    };
+    self.stream.send(Message::Text(text)).await.map_err(websocket_error)?;
+    self.record(FrameDirection::Sent, message.clone());
+    Ok(())
+  }
+
+  /// Wait up to `timeout_ms` for a message `matches` holds for, reading
+  /// past the others. An error from `matches` ends the wait.
+  pub async fn expect(&mut self, timeout_ms: u64, mut matches: impl FnMut(&Value) -> Result<bool>) -> Result<Value> {
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(timeout_ms);
+    loop {
+      let message = tokio::time::timeout_at(deadline, self.stream.next())
+        .await
+        .map_err(|_| Error::WebSocket(format!("no matching message within {timeout_ms} ms")))?;
+      let data = match message {
+        Some(Ok(Message::Text(text))) => serde_json::from_str(&text).unwrap_or(Value::String(text)),
+        Some(Ok(Message::Binary(bytes))) => Value::String(base64::engine::general_purpose::STANDARD.encode(bytes)),
+        Some(Ok(Message::Close(_))) | None => {
+          return Err(Error::WebSocket("connection closed before a matching message arrived".to_string()))
+        }
+        // Pings are answered by the protocol layer.
+        Some(Ok(_)) => continue,
+        Some(Err(err)) => return Err(websocket_error(err)),
+      };
+      self.record(FrameDirection::Received, data.clone());
+      if matches(&data)? {
+        return Ok(data);
+      }
+    }
+  }
+
+  /// Close the connection, not waiting long for the server to agree.
+  pub async fn close(mut self) -> Vec<WebSocketFrame> {
+    let closing = async {
+      self.stream.close(None).await?;
+      while self.stream.next().await.is_some() {}
+      Ok::<_, tokio_tungstenite::tungstenite::Error>(())
+    };
+    if let Ok(Err(err)) = tokio::time::timeout(Duration::from_millis(CLOSE_TIMEOUT_MS), closing).await {
+      log::debug!("[websocket] close failed: {err}");
+    }
+    self.transcript
+  }
+
+  fn record(&mut self, direction: FrameDirection, data: Value) {
+    self.transcript.push(WebSocketFrame {
+      direction,
+      data,
+      elapsed_ms: elapsed_ms(self.opened),
+    });
+  }
+}
+
+fn with_scheme(url: &Url, scheme: &str) -> Result<Url> {
+  let mut url = url.clone();
+  url
+    .set_scheme(scheme)
+    .map_err(|()| Error::InvalidRequest(format!("cannot use `{url}` as a {scheme} URL")))?;
+  Ok(url)
+}
+
+fn websocket_error(err: tokio_tungstenite::tungstenite::Error) -> Error {
+  Error::WebSocket(err.to_string())
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+  start.elapsed().as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use serde_json::json;
+  use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+  use super::*;
+  use crate::cookies::CookieJar;
+  use crate::flow::runner::{EndpointStatus, FlowRunner};
+  use crate::flow::TestFlow;
+  use crate::test_support::serve;
+
+  /// Greets with the handshake's cookie and token, then answers every
+  /// message with a heartbeat and an echo.
+  async fn server() -> u16 {
+    serve(|stream| async move {
+      let mut greeting = Value::Null;
+      // The callback's signature is tungstenite's.
+      #[allow(clippy::result_large_err)]
+      let callback = |request: &Request, mut response: Response| {
+        let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+        greeting = json!({ "type": "hello", "cookie": header("cookie"), "token": header("x-token") });
+        response.headers_mut().insert("set-cookie", "session=next; Path=/".parse().unwrap());
+        Ok(response)
+      };
+      let mut socket = tokio_tungstenite::accept_hdr_async(stream, callback).await.unwrap();
+      socket.send(Message::Text(greeting.to_string())).await.unwrap();
+      while let Some(Ok(Message::Text(text))) = socket.next().await {
+        let echo = json!({ "type": "echo", "message": serde_json::from_str::<Value>(&text).unwrap() });
+        socket.send(Message::Text(r#"{"type":"heartbeat"}"#.to_string())).await.unwrap();
+        socket.send(Message::Text(echo.to_string())).await.unwrap();
+      }
+    })
+    .await
+  }
+
+  #[tokio::test]
+  async fn runs_a_websocket_step() {
+    let port = server().await;
+    let flow: TestFlow = serde_json::from_value(json!({
+      "settings": { "api_hosts": { "1": { "url": format!("http://127.0.0.1:{port}"), "name": "Live" } } },
+      "parameters": [{ "name": "token", "defaultValue": "abc" }],
+      "endpoints": [{ "id": 1, "path": "/live", "method": "GET" }],
+      "steps": [{ "step_id": "step1", "endpoints": [{
+        "endpoint_id": 1,
+        "api_id": 1,
+        "headers": [{ "name": "X-Token", "value": "{{param:token}}" }],
+        "websocket": { "actions": [
+          { "type": "expect", "predicate": "$.type == \"hello\"" },
+          { "type": "send", "message": { "op": "subscribe", "token": "{{param:token}}" } },
+          { "type": "expect", "predicate": "$.type == \"echo\"", "timeoutMs": 5000 }
+        ] },
+        "assertions": [{ "assertion_type": "json_body", "data_id": "$[1].message.op", "operator": "equals", "expected_value": "subscribe" }]
+      }] }]
+    }))
+    .unwrap();
+    let jar = Arc::new(Mutex::new(CookieJar::default()));
+    let origin = Url::parse("http://127.0.0.1/").unwrap();
+    lock_jar(&jar).store_response_cookies(&origin, &["session=first; Path=/".to_string()]);
+
+    let client = HttpClient::new().unwrap();
+    let result = FlowRunner::new(&client, &flow, None).cookie_jar(jar.clone()).run(&Default::default()).await;
+    let endpoint = &result.endpoints[0];
+    assert_eq!(endpoint.status, EndpointStatus::Completed, "{:?}", endpoint.error);
+    assert_eq!(endpoint.response.as_ref().unwrap().status, 101);
+    let matched = &result.stored_responses["step1-0"];
+    assert_eq!(matched[0], json!({ "type": "hello", "cookie": "session=first", "token": "abc" }));
+    assert_eq!(matched[1]["message"], json!({ "op": "subscribe", "token": "abc" }));
+    let directions: Vec<FrameDirection> = endpoint.websocket_frames.iter().map(|frame| frame.direction).collect();
+    use FrameDirection::{Received, Sent};
+    assert_eq!(directions, [Received, Sent, Received, Received]);
+    assert_eq!(lock_jar(&jar).cookie_header(&origin).as_deref(), Some("session=next"));
+  }
+}
@@ -28,6 +28,7 @@ describe('BrowserFlowHttpTransport', () => {
       body: null,
       timeout: 30000,
       cookieStore: new Map(),
+      cookieJarId: 'run-1',
       endpointId: 'users-0',
       useServerCookieHandling: false,
       addLog: vi.fn()
@@ -40,8 +41,7 @@ describe('BrowserFlowHttpTransport', () => {
       {},
       null,
       30000,
-      expect.any(Map),
-      'users-0'
+      'run-1'
     );
   });
 
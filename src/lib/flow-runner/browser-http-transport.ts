@@ -60,19 +60,14 @@ export class BrowserFlowHttpTransport implements FlowHttpTransport {
       request.headers,
       request.body,
       request.timeout,
-      request.cookieStore,
-      request.endpointId
+      request.cookieJarId
     );
 
     if (isDesktop) {
       request.addLog(
         'debug',
         'Desktop mode: cookies managed via Tauri HTTP client',
-        `Cookies for ${request.endpointId}: ${
-          request.cookieStore.has(request.endpointId)
-            ? request.cookieStore.get(request.endpointId)?.length
-            : 0
-        } cookies`
+        `Cookie jar: ${request.cookieJarId ?? 'none'}`
       );
     }
 
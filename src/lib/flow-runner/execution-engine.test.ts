@@ -1,6 +1,7 @@
 import { describe, it, expect, vi, beforeEach } from 'vitest';
 import { FlowExecutionEngine, type ExecutionContext } from './execution-engine';
 import { resolveTemplate, createTemplateContextFromFlowRunner } from '$lib/template';
+import { clearCookieJar } from '$lib/http_client/tauri/cookie-jar';
 import type { TestFlowData, StepEndpoint } from '$lib/components/test-flows/types';
 
 // Mock the dependencies
@@ -19,6 +20,10 @@ vi.mock('$lib/http_client/test-flow', () => ({
   executeProxiedEndpoint: vi.fn()
 }));
 
+vi.mock('$lib/http_client/tauri/cookie-jar', () => ({
+  clearCookieJar: vi.fn()
+}));
+
 vi.mock('$lib/environment', () => ({
   isDesktop: false
 }));
@@ -683,6 +688,19 @@ describe('FlowExecutionEngine Template Resolution', () => {
       );
     });
 
+    it('should clear the native cookie jar of the run', async () => {
+      mockContext.cookieJarId = 'run-1';
+
+      await engine.executeStep({
+        step_id: 'step3',
+        label: 'Login as different user',
+        endpoints: [],
+        clearCookiesBeforeExecution: true
+      });
+
+      expect(clearCookieJar).toHaveBeenCalledWith('run-1');
+    });
+
     it('should not clear cookies when step has clearCookiesBeforeExecution flag disabled', async () => {
       // Setup: Add some cookies to the store
       mockContext.cookieStore.set('step1-0', [
@@ -7,6 +7,7 @@ import type {
 import { createTemplateContextFromFlowRunner, resolveTemplate } from '$lib/template';
 import { createTemplateFunctions, defaultTemplateFunctions } from '$lib/template';
 import type { FlowHttpTransport, RequestCookie } from './http-transport';
+import { clearCookieJar } from '$lib/http_client/tauri/cookie-jar';
 import { resolveEndpointApiHost } from './api-hosts';
 
 export interface ExecutionPreferences {
@@ -25,6 +26,8 @@ export interface ExecutionContext {
   parameterValues: Record<string, unknown>;
   environmentVariables: Record<string, unknown>;
   cookieStore: Map<string, Array<RequestCookie>>;
+  /** Run ID of the native cookie jar, in the desktop app */
+  cookieJarId?: string | null;
   httpTransport: FlowHttpTransport;
   selectedEnvironment: import('$lib/types/environment').Environment | null;
   shouldStopExecution: boolean;
@@ -69,6 +72,9 @@ export class FlowExecutionEngine {
     // Clear cookies if the step has this flag enabled (do this before checking endpoints)
     if (step.clearCookiesBeforeExecution === true) {
       this.context.cookieStore.clear();
+      if (this.context.cookieJarId) {
+        await clearCookieJar(this.context.cookieJarId);
+      }
       this.context.addLog(
         'info',
         `🍪 Cookies cleared before step ${step.step_id}`,
@@ -334,6 +340,7 @@ export class FlowExecutionEngine {
       body,
       timeout: this.context.preferences.timeout,
       cookieStore: this.context.cookieStore,
+      cookieJarId: this.context.cookieJarId,
       endpointId,
       useServerCookieHandling: this.context.preferences.serverCookieHandling,
       addLog: this.context.addLog
@@ -13,6 +13,7 @@ import { ParameterManager, type ParameterManagerContext } from './parameter-mana
 import { FlowOutputEvaluator, type OutputEvaluatorContext } from './output-evaluator';
 import { FlowValidator } from './validator';
 import type { FlowHttpTransport, RequestCookie } from './http-transport';
+import { createCookieJar, deleteCookieJar } from '$lib/http_client/tauri/cookie-jar';
 
 export interface FlowRunnerOptions {
   flowData: TestFlowData;
@@ -20,6 +21,11 @@ export interface FlowRunnerOptions {
   httpTransport: FlowHttpTransport;
   selectedEnvironment: import('$lib/types/environment').Environment | null;
   environmentVariables: Record<string, unknown>;
+  /**
+   * Native cookie jar to run with, owned by the caller, e.g. shared by the
+   * flows of a sequence. Without one the runner creates a jar per run.
+   */
+  cookieJarId?: string | null;
   onLog: (level: 'info' | 'debug' | 'error' | 'warning', message: string, details?: string) => void;
   onExecutionStateUpdate: (state: ExecutionState) => void;
   onEndpointStateUpdate: (data: { endpointId: string; state: any }) => void;
@@ -53,6 +59,7 @@ export interface FlowRunnerState {
   parameterValues: Record<string, unknown>;
   executionState: ExecutionState;
   cookieStore: Map<string, Array<RequestCookie>>;
+  cookieJarId: string | null;
 }
 
 export class FlowRunner {
@@ -80,7 +87,8 @@ export class FlowRunner {
       storedTransformations: {},
       parameterValues: {},
       executionState: {},
-      cookieStore: new Map()
+      cookieStore: new Map(),
+      cookieJarId: this.options.cookieJarId ?? null
     };
   }
 
@@ -105,6 +113,9 @@ export class FlowRunner {
       parameterValues: this.state.parameterValues,
       environmentVariables: this.options.environmentVariables,
       cookieStore: this.state.cookieStore,
+      get cookieJarId() {
+        return state.cookieJarId;
+      },
       httpTransport: this.options.httpTransport,
       selectedEnvironment: this.options.selectedEnvironment,
       get shouldStopExecution() {
@@ -188,6 +199,8 @@ export class FlowRunner {
     this.state.currentStep = 0;
 
     try {
+      await this.ensureCookieJar();
+
       for (let i = 0; i < this.options.flowData.steps.length; i++) {
         this.state.currentStep = i;
         this.state.progress = Math.floor((i / this.state.totalSteps) * 100);
@@ -292,6 +305,7 @@ export class FlowRunner {
     this.state.isRunning = true;
 
     try {
+      await this.ensureCookieJar();
       this.options.onLog(
         'info',
         `Executing step ${step.step_id} individually`,
@@ -400,6 +414,7 @@ export class FlowRunner {
     this.state.isRunning = false;
     this.state.shouldStopExecution = false;
     this.state.cookieStore.clear();
+    this.releaseCookieJar();
     this.setupManagers();
     this.options.onExecutionStateUpdate(this.state.executionState);
   }
@@ -422,9 +437,41 @@ export class FlowRunner {
     this.state.parameterValues = {};
     this.state.shouldStopExecution = false;
     this.state.cookieStore.clear();
+    this.releaseCookieJar();
     this.setupManagers();
   }
 
+  /**
+   * Drop the runner's own cookie jar, e.g. when its editor closes
+   */
+  dispose(): void {
+    this.releaseCookieJar();
+  }
+
+  /**
+   * Create the run's native cookie jar unless it has one (desktop only)
+   */
+  private async ensureCookieJar(): Promise<void> {
+    if (this.state.cookieJarId === null) {
+      this.state.cookieJarId = await createCookieJar();
+    }
+  }
+
+  /**
+   * Forget the runner's own cookie jar so the next run starts without cookies.
+   * A jar given in the options belongs to the caller and is kept.
+   */
+  private releaseCookieJar(): void {
+    const cookieJarId = this.state.cookieJarId;
+    if (cookieJarId === null || this.options.cookieJarId) {
+      return;
+    }
+    this.state.cookieJarId = null;
+    deleteCookieJar(cookieJarId).catch((err) =>
+      this.options.onLog('warning', 'Failed to delete cookie jar', String(err))
+    );
+  }
+
   updateParameterValues(parametersWithMissingValues: FlowParameter[]): void {
     this.state.parameterValues = this.parameterManager.updateParameterValues(
       parametersWithMissingValues,
@@ -13,7 +13,10 @@ export interface FlowHttpRequest {
   headers: Record<string, string>;
   body: unknown;
   timeout: number;
+  /** Cookies of server cookie handling, sent through the proxy */
   cookieStore: Map<string, RequestCookie[]>;
+  /** Run ID of the native cookie jar used by direct requests in the desktop app */
+  cookieJarId?: string | null;
   endpointId: string;
   useServerCookieHandling: boolean;
   addLog: (level: 'info' | 'debug' | 'error' | 'warning', message: string, details?: string) => void;
@@ -0,0 +1,35 @@
+import { isDesktop } from '$lib/environment';
+
+/**
+ * Native cookie jars, one per run, which `execute_request` reads and fills
+ * when given the jar's run ID. They follow RFC 6265, so there is no cookie
+ * handling left to do in the webview.
+ */
+
+/**
+ * Create an empty jar and return its run ID, or null outside the desktop app.
+ * Passing an existing run ID empties that jar.
+ */
+export async function createCookieJar(runId?: string): Promise<string | null> {
+  if (!isDesktop) {
+    return null;
+  }
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<string>('create_cookie_jar', { runId: runId ?? null });
+}
+
+/**
+ * Remove every cookie of a run's jar
+ */
+export async function clearCookieJar(runId: string): Promise<void> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  await invoke('clear_cookie_jar', { runId });
+}
+
+/**
+ * Drop a run's jar once nothing will run with it again
+ */
+export async function deleteCookieJar(runId: string): Promise<void> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  await invoke('delete_cookie_jar', { runId });
+}
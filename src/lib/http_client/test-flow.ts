@@ -176,7 +176,9 @@ export async function runTestFlowSync(
 import { isDesktop } from '$lib/environment';
 
 /**
- * Execute a direct HTTP request to an endpoint, handling cookies and timeouts
+ * Execute a direct HTTP request to an endpoint, handling timeouts. In the
+ * desktop app cookies live in the native jar of `cookieJarId`; in a browser
+ * they are left to the browser.
  */
 export async function executeDirectEndpoint(
     endpointDef: Endpoint,
@@ -184,25 +186,12 @@ export async function executeDirectEndpoint(
     headers: Record<string, string>,
     body: any,
     timeout: number,
-    cookieStore?: Map<string, Array<RequestCookie>>,
-    endpointId?: string
+    cookieJarId?: string | null
 ): Promise<Response> {
-    const debugMode = true; // Set to false to disable verbose logging
     const controller = new AbortController();
     const timeoutId = setTimeout(() => controller.abort(), timeout);
-    const requestDomain = new URL(url).hostname;
     
     try {
-        // Process cookie headers if needed
-        let mergedHeaders = { ...headers };
-        if (cookieStore && cookieStore.size > 0) {
-            const cookieHeader = buildCookieHeaderFromStore(cookieStore, requestDomain);
-            if (cookieHeader) {
-                debugMode && console.log(`Adding Cookie header: ${cookieHeader}`);
-                mergedHeaders = { ...mergedHeaders, 'Cookie': cookieHeader };
-            }
-        }
-        
         // Choose between Tauri HTTP client and browser fetch
         let response: Response;
         
@@ -210,25 +199,21 @@ export async function executeDirectEndpoint(
             response = await executeTauriRequest(
                 endpointDef.method,
                 url,
-                mergedHeaders,
+                headers,
                 body,
-                cookieStore,
-                endpointId
+                timeout,
+                cookieJarId
             );
         } else {
             // Browser mode with standard fetch
             response = await fetch(url, {
                 method: endpointDef.method,
-                headers: mergedHeaders,
+                headers,
                 body: body ? JSON.stringify(body) : null,
                 signal: controller.signal,
                 mode: 'cors',
                 credentials: 'include'
             });
-
-            if (cookieStore) {
-                processResponseCookies(response, cookieStore, requestDomain, endpointId);
-            }
         }
         
         return response;
@@ -238,271 +223,109 @@ export async function executeDirectEndpoint(
 }
 
 /**
- * Execute a request using Tauri's HTTP client
+ * Response shape returned by the native `execute_request` command
+ */
+interface NativeHttpResponse {
+    status: number;
+    statusText: string;
+    url: string;
+    headers: Array<[string, string]>;
+    setCookies: string[];
+    /** Base64-encoded body bytes */
+    body: string;
+    timing: {
+        startedAt: number;
+        dnsMs: number;
+        connectMs: number;
+        tlsMs: number;
+        ttfbMs: number;
+        downloadMs: number;
+        redirectMs: number;
+        totalMs: number;
+    };
+}
+
+// Statuses for which the Response constructor rejects a body
+const NULL_BODY_STATUSES = [101, 103, 204, 205, 304];
+
+/**
+ * Decode a base64 response body into its bytes
  */
-function isTauriScopeError(error: unknown): boolean {
-    return (error instanceof Error && error.message.includes('url not allowed on the configured scope')) ||
-        (typeof error === 'string' && error.includes('url not allowed on the configured scope'));
+function decodeBase64(encoded: string): Uint8Array {
+    const binary = atob(encoded);
+    const bytes = new Uint8Array(binary.length);
+    for (let i = 0; i < binary.length; i++) {
+        bytes[i] = binary.charCodeAt(i);
+    }
+    return bytes;
 }
 
+/**
+ * Execute a request through the native `execute_request` command
+ */
 async function executeTauriRequest(
     method: string,
     url: string,
     headers: Record<string, string>,
     body: any,
-    cookieStore?: Map<string, Array<RequestCookie>>,
-    endpointId?: string
+    timeout: number,
+    cookieJarId?: string | null
 ): Promise<Response> {
     const debugMode = true; // Set to false to disable verbose logging
-    const requestDomain = new URL(url).hostname;
-    
-    try {
-        // Dynamic import to avoid issues in browser environments
-        const { fetch: tauriFetch } = await import('@tauri-apps/plugin-http');
-        
-        // Log request details if in debug mode
-        if (debugMode) {
-            console.log(`[Tauri HTTP Request] ${method} ${url}`);
-            console.log('[Tauri HTTP Request Headers]', headers);
-            if (body) console.log('[Tauri HTTP Request Body]', body);
-        }
-        
-        // Execute the request
-        const response = await tauriFetch(url, {
+
+    // Dynamic import to avoid issues in browser environments
+    const { invoke } = await import('@tauri-apps/api/core');
+
+    // Log request details if in debug mode
+    if (debugMode) {
+        console.log(`[Tauri HTTP Request] ${method} ${url}`);
+        console.log('[Tauri HTTP Request Headers]', headers);
+        if (body) console.log('[Tauri HTTP Request Body]', body);
+    }
+
+    // Execute the request
+    const nativeResponse = await invoke<NativeHttpResponse>('execute_request', {
+        request: {
             method,
+            url,
             headers,
             body: body ? JSON.stringify(body) : null,
-            credentials: 'include'
-        });
-        
-        // Log response details if in debug mode
-        if (debugMode) {
-            console.log(`[Tauri HTTP Response] ${method} ${url} ${response.status}`);
-            console.log('[Tauri HTTP Response Status]', response.status, response.statusText);
-            console.log('[Tauri HTTP Response Headers]:');
-            response.headers.forEach((value, key) => console.log(`${key}: ${value}`));
-        }
-        
-        // Process cookies if cookie store is provided
-        if (cookieStore) {
-            processResponseCookies(response, cookieStore, requestDomain, endpointId);
-        }
-        
-        return response;
-    } catch (error) {
-        console.error('Error using Tauri HTTP client:', error);
-        if (isTauriScopeError(error)) {
-            throw error;
+            timeoutMs: timeout,
+            runId: cookieJarId ?? null
         }
-        // Fall back to browser fetch
-        return fetch(url, {
-            method,
-            headers,
-            body: body ? JSON.stringify(body) : null,
-            mode: 'cors',
-            credentials: 'include'
-        });
-    }
-}
+    });
 
-/**
- * Process and store cookies from a Tauri response
- */
-function processResponseCookies(
-    response: Response, 
-    cookieStore: Map<string, Array<RequestCookie>>, 
-    domain: string,
-    endpointId?: string
-): void {
-    const debugMode = true; // Set to false to disable verbose logging
-    
-    const extractedCookies = extractCookiesFromResponse(response, domain);
-    if (extractedCookies.length === 0) return;
-    
-    // Use endpoint ID as storage key if provided, otherwise use the domain
-    const storageKey = endpointId || `domain:${domain}`;
-    
-    // Get existing cookies and merge with new ones
-    const existingCookies = cookieStore.get(storageKey) || [];
-    const mergedCookies = [...existingCookies];
-    
-    for (const newCookie of extractedCookies) {
-        // Find existing cookie with same name, domain, and path
-        const existingIndex = mergedCookies.findIndex(c => 
-            c.name === newCookie.name && 
-            c.domain === newCookie.domain && 
-            (c.path === newCookie.path || (!c.path && !newCookie.path))
-        );
-        
-        // Replace or add the cookie
-        if (existingIndex !== -1) {
-            mergedCookies[existingIndex] = newCookie;
-        } else {
-            mergedCookies.push(newCookie);
-        }
-    }
-    
-    // Update the cookie store
-    cookieStore.set(storageKey, mergedCookies);
-    
+    // Log response details if in debug mode
     if (debugMode) {
+        console.log(`[Tauri HTTP Response] ${method} ${url} ${nativeResponse.status}`);
+        console.log('[Tauri HTTP Response Status]', nativeResponse.status, nativeResponse.statusText);
+        const { dnsMs, connectMs, tlsMs, ttfbMs, downloadMs, redirectMs, totalMs } = nativeResponse.timing;
         console.log(
-            `Stored ${extractedCookies.length} cookies for ${endpointId ? `endpoint ${endpointId}` : `domain ${domain}`}`
+            `[Tauri HTTP Response Timing] total=${totalMs.toFixed(1)}ms dns=${dnsMs.toFixed(1)}ms ` +
+            `connect=${connectMs.toFixed(1)}ms tls=${tlsMs.toFixed(1)}ms ttfb=${ttfbMs.toFixed(1)}ms ` +
+            `download=${downloadMs.toFixed(1)}ms redirects=${redirectMs.toFixed(1)}ms`
         );
-        console.log(`Cookie store now contains ${mergedCookies.length} cookies for this key`);
+        console.log('[Tauri HTTP Response Headers]:');
+        nativeResponse.headers.forEach(([key, value]) => console.log(`${key}: ${value}`));
     }
-}
 
-/**
- * Parse cookie string into name and value
- * @param cookieStr Cookie string in name=value format
- * @returns Object with name and value properties, or null if invalid
- */
-function parseCookieNameValue(cookieStr: string): { name: string, value: string } | null {
-    const nameValueSplit = cookieStr.indexOf('=');
-    if (nameValueSplit === -1) return null;
-    
-    const name = cookieStr.slice(0, nameValueSplit).trim();
-    let value = cookieStr.slice(nameValueSplit + 1).trim();
-    
-    // Handle quoted values
-    if (value.startsWith('"') && value.endsWith('"')) {
-        value = decodeURIComponent(value.slice(1, -1));
-    } else {
-        value = decodeURIComponent(value);
-    }
-    
-    return name ? { name, value } : null;
-}
-
-/**
- * Parse Set-Cookie headers from a Tauri response and convert them to RequestCookie objects
- * @param response The response from Tauri HTTP client
- * @param domain The domain to associate with the cookie
- * @returns Array of RequestCookie objects
- */
-function extractCookiesFromResponse(response: Response, domain: string): RequestCookie[] {
-    const cookies: RequestCookie[] = [];
-    const debugMode = true; // Set to false to disable verbose logging
-    
-    debugMode && console.log(`Extracting cookies for domain: ${domain}`);
-
-    // Process Set-Cookie headers
-    const getSetCookie = (response.headers as Headers & { getSetCookie?: () => string[] }).getSetCookie;
-    const rawSetCookieHeaders = typeof getSetCookie === 'function'
-        ? getSetCookie.call(response.headers)
-        : response.headers.has('set-cookie')
-            ? [response.headers.get('set-cookie')].filter((value): value is string => Boolean(value))
-            : [];
-
-    for (const setCookieHeader of rawSetCookieHeaders) {
-        debugMode && console.log(`Raw Set-Cookie header: ${setCookieHeader}`);
-        
-        // Split by comma followed by space and a word with an equals sign
-        const rawCookies = setCookieHeader.split(/,(?=\s*[A-Za-z0-9_-]+=)/);
-        debugMode && console.log(`Parsed ${rawCookies.length} cookies from header`);
-        
-        for (const rawCookie of rawCookies) {
-            const parts = rawCookie.split(';');
-            const cookieNameValue = parseCookieNameValue(parts[0].trim());
-            if (!cookieNameValue) continue;
-            
-            // Create cookie with defaults
-            const cookie: RequestCookie = {
-                name: cookieNameValue.name,
-                value: cookieNameValue.value,
-                domain: domain
-            };
-            
-            // Process attributes
-            for (const part of parts.slice(1)) {
-                const partTrimmed = part.trim().toLowerCase();
-                if (partTrimmed.startsWith('domain=')) {
-                    let cookieDomain = part.split('=')[1].trim();
-                    // Remove leading dot
-                    if (cookieDomain.startsWith('.')) cookieDomain = cookieDomain.slice(1);
-                    cookie.domain = cookieDomain;
-                } else if (partTrimmed.startsWith('path=')) {
-                    cookie.path = part.split('=')[1].trim();
-                }
-            }
-            
-            debugMode && console.log(`Extracted cookie: ${cookie.name}=${cookie.value}, domain=${cookie.domain}, path=${cookie.path || '/'}`);
-            cookies.push(cookie);
+    // Set-Cookie is a forbidden response header name; the native jar has already stored them
+    const responseHeaders = new Headers();
+    for (const [key, value] of nativeResponse.headers) {
+        if (key.toLowerCase() !== 'set-cookie') {
+            responseHeaders.append(key, value);
         }
     }
-    
-    // Process non-standard cookie headers
-    const nonStandardHeaders = Array.from(response.headers.keys())
-        .filter(key => key.toLowerCase().includes('cookie') && key.toLowerCase() !== 'set-cookie');
-    
-    for (const key of nonStandardHeaders) {
-        const value = response.headers.get(key);
-        if (!value) continue;
-        
-        debugMode && console.log(`Found non-standard cookie header: ${key}=${value}`);
-        
-        try {
-            for (const part of value.split(';')) {
-                const cookieNameValue = parseCookieNameValue(part);
-                if (cookieNameValue) {
-                    const cookie = {
-                        name: cookieNameValue.name,
-                        value: cookieNameValue.value,
-                        domain: domain
-                    };
-                    debugMode && console.log(`Extracted non-standard cookie: ${cookie.name}=${cookie.value}`);
-                    cookies.push(cookie);
-                }
-            }
-        } catch (e) {
-            console.error('Error parsing non-standard cookie header:', e);
-        }
-    }
-    
-    // Filter out empty cookies
-    const validCookies = cookies.filter(cookie => cookie.value !== '');
-    
-    if (debugMode && validCookies.length !== cookies.length) {
-        console.log(`Filtered out ${cookies.length - validCookies.length} expired or empty cookies`);
-    }
-    
-    return validCookies;
-}
 
-/**
- * Build a Cookie header value from the cookieStore
- * @param cookieStore The map of cookies by endpoint ID
- * @param requestDomain Optional domain to filter cookies by
- * @returns Cookie header string or undefined if no cookies
- */
-function buildCookieHeaderFromStore(
-    cookieStore: Map<string, Array<RequestCookie>>, 
-    requestDomain?: string
-): string | undefined {
-    const debugMode = true; // Set to false to disable verbose logging
-    
-    // Get all cookies and filter by domain if needed
-    const matchedCookies = Array.from(cookieStore.values())
-        .flat()
-        .filter(cookie => {
-            if (!requestDomain) return true;
-            
-            return (
-                cookie.domain === requestDomain || // Exact domain match
-                requestDomain.endsWith(`.${cookie.domain}`) || // Parent domain match
-                cookie.domain === 'localhost' // Always include localhost cookies
-            );
-        });
-    
-    if (matchedCookies.length === 0) {
-        return undefined;
-    }
-    
-    debugMode && console.log(`Found ${matchedCookies.length} matching cookies for domain: ${requestDomain || 'any'}`);
-    
-    // Build the cookie header string
-    return matchedCookies.map(cookie => `${cookie.name}=${cookie.value}`).join('; ');
+    const responseBody = NULL_BODY_STATUSES.includes(nativeResponse.status)
+        ? null
+        : decodeBase64(nativeResponse.body);
+
+    return new Response(responseBody, {
+        status: nativeResponse.status,
+        statusText: nativeResponse.statusText,
+        headers: responseHeaders
+    });
 }
 
 /**
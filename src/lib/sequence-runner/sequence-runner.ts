@@ -10,10 +10,13 @@ import type { TestFlow } from '$lib/types/test-flow';
 import type { FlowSequenceStep } from '$lib/types/flow_sequence';
 import { FlowRunner, type FlowRunnerOptions } from '$lib/flow-runner';
 import { SequenceParameterResolver } from './parameter-resolver';
+import { createCookieJar, deleteCookieJar } from '$lib/http_client/tauri/cookie-jar';
 
 export class SequenceRunner {
   private options: SequenceRunnerOptions;
   private state: SequenceExecutionState;
+  /** Native cookie jar every flow of the run shares, in the desktop app */
+  private cookieJarId: string | null = null;
 
   constructor(options: SequenceRunnerOptions) {
     this.options = options;
@@ -40,6 +43,8 @@ export class SequenceRunner {
     this.state.isRunning = true;
 
     try {
+      this.cookieJarId = await createCookieJar();
+
       // Get the sequence steps ordered by step_order
       const orderedSteps = [...this.options.sequence.sequenceConfig.steps].sort(
         (a, b) => a.step_order - b.step_order
@@ -186,6 +191,12 @@ export class SequenceRunner {
       return { success: false, error: this.state.error };
     } finally {
       this.state.isRunning = false;
+      if (this.cookieJarId) {
+        await deleteCookieJar(this.cookieJarId).catch((err) =>
+          this.options.onLog('warning', 'Failed to delete cookie jar', String(err))
+        );
+        this.cookieJarId = null;
+      }
     }
   }
 
@@ -312,6 +323,7 @@ export class SequenceRunner {
         httpTransport: this.options.httpTransport,
         selectedEnvironment: this.options.selectedEnvironment,
         environmentVariables: resolvedExecution.environmentVariables,
+        cookieJarId: this.cookieJarId,
         onLog: this.options.onLog,
         onExecutionStateUpdate: () => {}, // We handle this at sequence level
         onEndpointStateUpdate: () => {}, // We handle this at sequence level